@@ -17,7 +17,27 @@ fn run_cmd(args: &[&str]) -> Option<String> {
     })
 }
 
+/// Writes the C header of the `capi` feature to `include/gitripper.h`.
+#[cfg(feature = "capi")]
+fn write_header() {
+    println!("cargo:rerun-if-changed=src/capi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    let crate_dir = var("CARGO_MANIFEST_DIR").unwrap();
+    let config =
+        cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir))
+            .expect("Unable to read `cbindgen.toml`");
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(format!("{}/src/capi.rs", crate_dir))
+        .generate()
+        .expect("Unable to generate the C header")
+        .write_to_file(format!("{}/include/gitripper.h", crate_dir));
+}
+
 fn main() {
+    #[cfg(feature = "capi")]
+    write_header();
+
     println!("cargo:rerun-if-changed=.git/HEAD");
     println!("cargo:rerun-if-changed=.git/refs/heads");
     println!("cargo:rerun-if-env-changed=MY_BUILD_FLAG");
@@ -55,11 +75,7 @@ fn main() {
     let mut features: Vec<String> = env::vars()
         .filter_map(|(k, _)| {
             const PREFIX: &str = "CARGO_FEATURE_";
-            if k.starts_with(PREFIX) {
-                Some(k[PREFIX.len()..].to_lowercase())
-            } else {
-                None
-            }
+            k.strip_prefix(PREFIX).map(|f| f.to_lowercase())
         })
         .collect();
     features.sort();
@@ -0,0 +1,126 @@
+/* Generated by cbindgen from src/capi.rs with the `capi` feature. */
+
+#ifndef GITRIPPER_H
+#define GITRIPPER_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Outcome of a call, numbered like the exit codes of `gitripper`.
+ */
+typedef enum GitripperStatus {
+  GITRIPPER_STATUS_OK = 0,
+  /**
+   * A required argument was null or not UTF-8.
+   */
+  GITRIPPER_STATUS_INVALID_ARGUMENT = 1,
+  GITRIPPER_STATUS_INVALID_URL = 2,
+  GITRIPPER_STATUS_DESTINATION_EXISTS = 3,
+  GITRIPPER_STATUS_CLEANUP_FAILED = 4,
+  GITRIPPER_STATUS_GIT_NOT_FOUND = 5,
+  GITRIPPER_STATUS_DOWNLOAD_FAILED = 6,
+  GITRIPPER_STATUS_EXTRACTION_FAILED = 7,
+  GITRIPPER_STATUS_INIT_FAILED = 8,
+  GITRIPPER_STATUS_IO = 9,
+  GITRIPPER_STATUS_RATE_LIMITED = 10,
+  GITRIPPER_STATUS_NOT_A_RIP = 12,
+  GITRIPPER_STATUS_CHECKSUM_MISMATCH = 14,
+  GITRIPPER_STATUS_INVALID_TEMPLATE = 15,
+  GITRIPPER_STATUS_HOOK_FAILED = 16,
+  GITRIPPER_STATUS_PUSH_FAILED = 17,
+  GITRIPPER_STATUS_SECRETS_FOUND = 18,
+  GITRIPPER_STATUS_ATTESTATION_FAILED = 19,
+  GITRIPPER_STATUS_TOO_LARGE = 20,
+  GITRIPPER_STATUS_CANCELLED = 130,
+} GitripperStatus;
+
+/**
+ * Settings of a rip. Start from [`gitripper_options_default`]; null
+ * strings are left unset.
+ */
+typedef struct GitripperOptions {
+  /**
+   * Branch, tag or commit to rip instead of the default branch.
+   */
+  const char *reference;
+  /**
+   * Token for the host. Without one, its environment variable is read,
+   * such as `GITHUB_TOKEN`.
+   */
+  const char *token;
+  /**
+   * Directory of the repository to keep instead of all of it.
+   */
+  const char *subpath;
+  /**
+   * Clones the history instead of taking a snapshot.
+   */
+  bool keep_history;
+  /**
+   * Leaves the files without creating a repository.
+   */
+  bool no_init;
+  /**
+   * Replaces an existing destination.
+   */
+  bool force;
+  /**
+   * Only uses the download cache.
+   */
+  bool offline;
+} GitripperOptions;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * The options of a default rip.
+ */
+struct GitripperOptions gitripper_options_default(void);
+
+/**
+ * Rips `url` into `dest`, or a directory named after the repository in the
+ * working directory if `dest` is null. `options` may be null for the
+ * defaults.
+ *
+ * # Safety
+ *
+ * `url` and `dest` are null or NUL-terminated strings, and `options` is
+ * null or points to a [`GitripperOptions`] whose strings are.
+ */
+enum GitripperStatus gitripper_rip(const char *url,
+                                   const char *dest,
+                                   const struct GitripperOptions *options);
+
+/**
+ * Rips `url` into a new archive at `path`, in the format its extension
+ * names, instead of a directory.
+ *
+ * # Safety
+ *
+ * As for [`gitripper_rip`], with `path` not null.
+ */
+enum GitripperStatus gitripper_rip_to_archive(const char *url,
+                                              const char *path,
+                                              const struct GitripperOptions *options);
+
+/**
+ * Message of the last call on this thread that failed, or null if it
+ * succeeded. Valid until the next call on the thread.
+ */
+const char *gitripper_last_error(void);
+
+/**
+ * Version of the library, such as `1.2.0`.
+ */
+const char *gitripper_version(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* GITRIPPER_H */
@@ -0,0 +1,283 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::anyhow;
+use flate2::{write::GzEncoder, Compression};
+use tar::{EntryType, Header};
+#[cfg(feature = "zip")]
+use zip::{write::SimpleFileOptions, CompressionMethod, DateTime, ZipWriter};
+
+use crate::{extract::is_symlink, ExtractTarget, MemEntry};
+
+/// Kind of archive [`ArchiveTarget`] writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    TarZst,
+}
+
+impl ArchiveFormat {
+    /// Format named by the extension of `path`: `.zip`, `.tar`, `.tar.gz`
+    /// or `.tgz`, and `.tar.zst` or `.tzst`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        split_extension(path.file_name()?.to_str()?).map(|(_, format)| format)
+    }
+
+    /// Extension of archives in this format, without the leading dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::Tar => "tar",
+            ArchiveFormat::TarGz => "tar.gz",
+            ArchiveFormat::TarZst => "tar.zst",
+        }
+    }
+}
+
+/// `name` without the archive extension [`ArchiveFormat::from_path`]
+/// recognizes, and the format it names.
+pub(crate) fn split_extension(name: &str) -> Option<(&str, ArchiveFormat)> {
+    let lower = name.to_ascii_lowercase();
+    [
+        (".zip", ArchiveFormat::Zip),
+        (".tar", ArchiveFormat::Tar),
+        (".tar.gz", ArchiveFormat::TarGz),
+        (".tgz", ArchiveFormat::TarGz),
+        (".tar.zst", ArchiveFormat::TarZst),
+        (".tzst", ArchiveFormat::TarZst),
+    ]
+    .into_iter()
+    .find(|(ext, _)| lower.ends_with(ext))
+    .map(|(ext, format)| (&name[..name.len() - ext.len()], format))
+}
+
+/// Repackages extracted entries into a new archive instead of writing them
+/// to disk. Timestamps are fixed, so the same snapshot always gives the same
+/// bytes. Call [`ArchiveTarget::finish`] once every entry is written.
+pub struct ArchiveTarget {
+    writer: Writer,
+}
+
+enum Writer {
+    #[cfg(feature = "zip")]
+    Zip(ZipWriter<BufWriter<File>>),
+    Tar(tar::Builder<TarSink>),
+}
+
+/// Where a tarball goes, through the compressor if there is one.
+enum TarSink {
+    Plain(BufWriter<File>),
+    Gz(GzEncoder<BufWriter<File>>),
+    #[cfg(feature = "zstd")]
+    Zst(zstd::Encoder<'static, BufWriter<File>>),
+}
+
+impl Write for TarSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(w) => w.write(buf),
+            Self::Gz(w) => w.write(buf),
+            #[cfg(feature = "zstd")]
+            Self::Zst(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(w) => w.flush(),
+            Self::Gz(w) => w.flush(),
+            #[cfg(feature = "zstd")]
+            Self::Zst(w) => w.flush(),
+        }
+    }
+}
+
+impl TarSink {
+    fn finish(self) -> io::Result<()> {
+        let mut file = match self {
+            Self::Plain(w) => w,
+            Self::Gz(w) => w.finish()?,
+            #[cfg(feature = "zstd")]
+            Self::Zst(w) => w.finish()?,
+        };
+        file.flush()
+    }
+}
+
+impl ArchiveTarget {
+    /// Creates `path` to write a `format` archive into.
+    pub fn create(path: &Path, format: ArchiveFormat) -> io::Result<Self> {
+        let file = BufWriter::new(File::create(path)?);
+        let writer = match format {
+            #[cfg(feature = "zip")]
+            ArchiveFormat::Zip => Writer::Zip(ZipWriter::new(file)),
+            #[cfg(not(feature = "zip"))]
+            ArchiveFormat::Zip => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "writing zip archives needs the zip feature",
+                ));
+            },
+            ArchiveFormat::Tar => {
+                Writer::Tar(tar::Builder::new(TarSink::Plain(file)))
+            },
+            ArchiveFormat::TarGz => Writer::Tar(tar::Builder::new(
+                TarSink::Gz(GzEncoder::new(file, Compression::default())),
+            )),
+            #[cfg(feature = "zstd")]
+            ArchiveFormat::TarZst => Writer::Tar(tar::Builder::new(
+                TarSink::Zst(zstd::Encoder::new(file, 0)?),
+            )),
+            #[cfg(not(feature = "zstd"))]
+            ArchiveFormat::TarZst => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "writing .tar.zst archives needs the zstd feature",
+                ));
+            },
+        };
+        Ok(Self { writer })
+    }
+
+    /// Writes the end of the archive and flushes it to disk.
+    pub fn finish(self) -> anyhow::Result<()> {
+        match self.writer {
+            #[cfg(feature = "zip")]
+            Writer::Zip(zip) => {
+                zip.finish()?.flush()?;
+            },
+            Writer::Tar(tar) => tar.into_inner()?.finish()?,
+        }
+        Ok(())
+    }
+}
+
+impl ExtractTarget for ArchiveTarget {
+    fn write(&mut self, entry: MemEntry) -> anyhow::Result<()> {
+        let name = entry
+            .rel_path
+            .components()
+            .map(|c| c.as_os_str().to_str())
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| {
+                anyhow!("non UTF-8 path: {}", entry.rel_path.display())
+            })?
+            .join("/");
+        let mode = entry.unix_mode.map(|m| m & 0o7777);
+
+        match &mut self.writer {
+            #[cfg(feature = "zip")]
+            Writer::Zip(zip) => {
+                let opts = SimpleFileOptions::default()
+                    .compression_method(CompressionMethod::Deflated)
+                    .last_modified_time(DateTime::default())
+                    .large_file(entry.data.len() as u64 >= u32::MAX as u64);
+                if entry.is_dir {
+                    zip.add_directory(name, opts)?;
+                } else if is_symlink(entry.unix_mode) {
+                    let target = String::from_utf8_lossy(&entry.data);
+                    zip.add_symlink(name, target, opts)?;
+                } else {
+                    zip.start_file(
+                        name,
+                        opts.unix_permissions(mode.unwrap_or(0o644)),
+                    )?;
+                    zip.write_all(&entry.data)?;
+                }
+            },
+            Writer::Tar(tar) => {
+                let mut header = Header::new_gnu();
+                if entry.is_dir {
+                    header.set_entry_type(EntryType::Directory);
+                    header.set_mode(mode.unwrap_or(0o755));
+                    header.set_size(0);
+                    tar.append_data(&mut header, name, io::empty())?;
+                } else if is_symlink(entry.unix_mode) {
+                    header.set_entry_type(EntryType::Symlink);
+                    header.set_mode(0o777);
+                    header.set_size(0);
+                    let target = String::from_utf8_lossy(&entry.data);
+                    tar.append_link(&mut header, name, target.as_ref())?;
+                } else {
+                    header.set_entry_type(EntryType::Regular);
+                    header.set_mode(mode.unwrap_or(0o644));
+                    header.set_size(entry.data.len() as u64);
+                    tar.append_data(&mut header, name, &entry.data[..])?;
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Read, path::PathBuf};
+
+    use super::*;
+
+    fn file(path: &str, data: &str) -> MemEntry {
+        MemEntry {
+            rel_path:   PathBuf::from(path),
+            is_dir:     false,
+            _data_size: data.len() as u64,
+            unix_mode:  Some(0o100644),
+            _file_idx:  0,
+            data:       data.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        let format = |p: &str| ArchiveFormat::from_path(Path::new(p));
+        assert_eq!(format("out.tar.zst"), Some(ArchiveFormat::TarZst));
+        assert_eq!(format("out.TGZ"), Some(ArchiveFormat::TarGz));
+        assert_eq!(format("dir/out.zip"), Some(ArchiveFormat::Zip));
+        assert_eq!(format("out.rar"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "zstd")]
+    fn test_tar_zst_round_trips() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("out.tar.zst");
+        let mut target =
+            ArchiveTarget::create(&path, ArchiveFormat::TarZst).unwrap();
+        target.write(file("src/lib.rs", "fn main() {}")).unwrap();
+        target.finish().unwrap();
+
+        let decoder = zstd::Decoder::new(File::open(&path).unwrap()).unwrap();
+        let mut archive = tar::Archive::new(decoder);
+        let mut entry = archive.entries().unwrap().next().unwrap().unwrap();
+        assert_eq!(entry.path().unwrap(), Path::new("src/lib.rs"));
+        let mut text = String::new();
+        entry.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "fn main() {}");
+    }
+
+    #[test]
+    #[cfg(feature = "zip")]
+    fn test_zip_round_trips() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("out.zip");
+        let mut target =
+            ArchiveTarget::create(&path, ArchiveFormat::Zip).unwrap();
+        target.write(file("README.md", "hi")).unwrap();
+        target.finish().unwrap();
+
+        let mut archive =
+            zip::ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        let mut text = String::new();
+        archive
+            .by_name("README.md")
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "hi");
+    }
+}
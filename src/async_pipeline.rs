@@ -0,0 +1,187 @@
+//! Overlapped download and extraction, enabled by the `async` feature.
+//!
+//! The zipball is downloaded in chunks on a tokio runtime. Each chunk is both
+//! appended to a local copy of the archive and forwarded to a blocking task
+//! that extracts entries from their local headers as soon as they arrive, so
+//! most of the extraction work is done by the time the last byte lands.
+
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use bytes::Bytes;
+use reqwest::Client;
+use tokio::{sync::mpsc, task};
+
+use crate::{
+    cancel::cancelled,
+    extract_archive_with, extract_zip_stream,
+    provider::{archive_path, archive_status_error, rate_limit_error},
+    ExtractOptions, GitripperError, HttpOptions, Provider, RepoLocation,
+    Result,
+};
+
+const CHANNEL_CAPACITY: usize = 64;
+
+/// Blocking [`Read`] adapter over the chunks received by the downloader.
+struct ChannelReader {
+    rx:      mpsc::Receiver<Bytes>,
+    current: Bytes,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.current.is_empty() {
+            match self.rx.blocking_recv() {
+                Some(chunk) => self.current = chunk,
+                None => return Ok(0),
+            }
+        }
+
+        let n = buf.len().min(self.current.len());
+        buf[..n].copy_from_slice(&self.current.split_to(n));
+        Ok(n)
+    }
+}
+
+/// Downloads the archive of `location` at `reference` into `work_dir` while
+/// extracting it into `dest_dir`, returning the path of the saved archive.
+///
+/// If the archive can't be extracted incrementally (for example because its
+/// entries use trailing data descriptors), extraction is redone from the
+/// saved archive once the download completes.
+#[allow(clippy::too_many_arguments)]
+pub async fn download_and_extract(
+    client: &Client,
+    provider: &dyn Provider,
+    location: &RepoLocation,
+    reference: &str,
+    token: Option<&str>,
+    work_dir: &Path,
+    dest_dir: &Path,
+    options: &ExtractOptions,
+) -> Result<PathBuf> {
+    let url = provider.archive_url(location, reference);
+    let mut req = client.get(&url);
+
+    if let Some(accept) = provider.accept_header() {
+        req = req.header("Accept", accept);
+    }
+    if let Some(t) = token {
+        let (name, value) = provider.auth_header(t);
+        req = req.header(name, value);
+    }
+
+    let mut resp = req.send().await?;
+    let status = resp.status();
+
+    if let Some(e) = rate_limit_error(status, resp.headers()) {
+        return Err(e);
+    }
+    if !status.is_success() {
+        return Err(archive_status_error(status, location, reference));
+    }
+
+    let path = archive_path(work_dir);
+    let mut outfile = File::create(&path)?;
+    let progress = options.progress.clone();
+
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+    let stream_dest = dest_dir.to_path_buf();
+    let stream_options = options.clone();
+    let extractor = task::spawn_blocking(move || {
+        let reader = ChannelReader {
+            rx,
+            current: Bytes::new(),
+        };
+        extract_zip_stream(reader, &stream_dest, &stream_options)
+    });
+
+    progress.download_started(resp.content_length());
+    let mut tx = Some(tx);
+
+    while let Some(chunk) = resp.chunk().await? {
+        if progress.is_cancelled() {
+            return Err(cancelled().into());
+        }
+        outfile.write_all(&chunk)?;
+        progress.download_progress(chunk.len() as u64);
+
+        // A closed channel means the extractor gave up; keep downloading so
+        // the fallback below has a complete archive to work with.
+        if let Some(sender) = &tx
+            && sender.send(chunk).await.is_err()
+        {
+            tx = None;
+        }
+    }
+    drop(tx);
+    outfile.flush()?;
+    progress.download_finished();
+
+    let streamed =
+        extractor.await.map_err(|e| GitripperError::Extraction(e.into()))?;
+
+    if streamed.is_err() {
+        let archive = path.clone();
+        let dest = dest_dir.to_path_buf();
+        let options = options.clone();
+
+        task::spawn_blocking(move || {
+            extract_archive_with(&archive, &dest, &options)
+        })
+        .await
+        .map_err(|e| GitripperError::Extraction(e.into()))?
+        .map_err(GitripperError::Extraction)?;
+    }
+
+    Ok(path)
+}
+
+/// Runs [`download_and_extract`] to completion on a dedicated runtime, for
+/// callers that aren't async themselves.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn download_and_extract_blocking(
+    http: &HttpOptions,
+    user_agent: &str,
+    provider: &dyn Provider,
+    location: &RepoLocation,
+    reference: &str,
+    token: Option<&str>,
+    work_dir: &Path,
+    dest_dir: &Path,
+    options: &ExtractOptions,
+) -> Result<PathBuf> {
+    let runtime =
+        tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
+    let client = http.build_async_client(user_agent)?;
+
+    runtime.block_on(download_and_extract(
+        &client, provider, location, reference, token, work_dir, dest_dir,
+        options,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_reader_spans_chunks() {
+        let (tx, rx) = mpsc::channel(4);
+        tx.try_send(Bytes::from_static(b"hel")).unwrap();
+        tx.try_send(Bytes::from_static(b"lo")).unwrap();
+        drop(tx);
+
+        let mut reader = ChannelReader {
+            rx,
+            current: Bytes::new(),
+        };
+        let mut out = String::new();
+        reader.read_to_string(&mut out).unwrap();
+
+        assert_eq!(out, "hello");
+    }
+}
@@ -0,0 +1,240 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rayon::prelude::*;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    manifest::{collect_files, manifest_path},
+    GitripperError, Result, RipReport,
+};
+
+/// In-toto statement written at the top of a rip by [`write_attestation`].
+pub const ATTESTATION_FILE: &str = ".gitripper-attestation.intoto.json";
+/// Sigstore bundle [`sign_attestation`] writes next to the statement.
+pub const ATTESTATION_BUNDLE_FILE: &str =
+    ".gitripper-attestation.sigstore.json";
+
+const STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v1";
+const PROVENANCE_TYPE: &str = "https://slsa.dev/provenance/v1";
+const BUILD_TYPE: &str = "urn:gitripper:rip:v1";
+
+/// The program that made a rip, as named in its attestation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuilderInfo {
+    /// URI identifying the builder.
+    pub id:      String,
+    /// Versions of the builder and what it was built with, by name.
+    pub version: BTreeMap<String, String>,
+}
+
+/// Where a rip came from, for [`provenance_statement`].
+#[derive(Debug, Clone, Copy)]
+pub struct RipSource<'a> {
+    /// URL the rip was made from.
+    pub url:     &'a str,
+    /// Subdirectory of the repository that was ripped.
+    pub subpath: Option<&'a Path>,
+    pub started: SystemTime,
+}
+
+/// An in-toto statement with an SLSA provenance predicate for the rip of
+/// `report`: every file under its destination with its SHA-256 is a
+/// subject, and the upstream repository at the resolved commit is the one
+/// dependency.
+pub fn provenance_statement(
+    report: &RipReport,
+    source: &RipSource,
+    builder: &BuilderInfo,
+) -> io::Result<Value> {
+    let mut upstream = json!({
+        "uri": format!("git+{}@{}", source.url.trim(), report.reference),
+    });
+    if let Some(sha) = &report.commit {
+        upstream["digest"] = json!({ "gitCommit": sha });
+    }
+
+    Ok(json!({
+        "_type": STATEMENT_TYPE,
+        "subject": subjects(&report.dest)?,
+        "predicateType": PROVENANCE_TYPE,
+        "predicate": {
+            "buildDefinition": {
+                "buildType": BUILD_TYPE,
+                "externalParameters": {
+                    "url": source.url.trim(),
+                    "reference": report.reference,
+                    "path": source
+                        .subpath
+                        .map(|p| p.to_string_lossy().into_owned()),
+                },
+                "resolvedDependencies": [upstream],
+            },
+            "runDetails": {
+                "builder": {
+                    "id": builder.id,
+                    "version": builder.version,
+                },
+                "metadata": {
+                    "startedOn": rfc3339(source.started),
+                    "finishedOn": rfc3339(SystemTime::now()),
+                },
+            },
+        },
+    }))
+}
+
+/// Every file under `dest` with its SHA-256, sorted by path. Symlinks are
+/// hashed by their target, as in the manifest.
+fn subjects(dest: &Path) -> io::Result<Vec<Value>> {
+    let mut files = Vec::new();
+    collect_files(dest, Path::new(""), &mut files)?;
+    files.retain(|rel| {
+        rel != Path::new(ATTESTATION_FILE)
+            && rel != Path::new(ATTESTATION_BUNDLE_FILE)
+    });
+    files.sort();
+
+    files
+        .into_par_iter()
+        .map(|rel| {
+            let path = dest.join(&rel);
+            let mut hasher = Sha256::new();
+            if fs::symlink_metadata(&path)?.is_symlink() {
+                let target = fs::read_link(&path)?;
+                hasher.update(target.to_string_lossy().as_bytes());
+            } else {
+                io::copy(&mut fs::File::open(&path)?, &mut hasher)?;
+            }
+            Ok(json!({
+                "name": manifest_path(&rel),
+                "digest": { "sha256": format!("{:x}", hasher.finalize()) },
+            }))
+        })
+        .collect()
+}
+
+/// Writes `statement` to the [`ATTESTATION_FILE`] in `dest`.
+pub fn write_attestation(
+    dest: &Path,
+    statement: &Value,
+) -> io::Result<PathBuf> {
+    let path = dest.join(ATTESTATION_FILE);
+    fs::write(&path, serde_json::to_string_pretty(statement)? + "\n")?;
+    Ok(path)
+}
+
+/// Signs the statement at `path` with `cosign sign-blob`, keyless unless
+/// cosign is configured otherwise, and writes the Sigstore bundle to the
+/// [`ATTESTATION_BUNDLE_FILE`] next to it.
+pub fn sign_attestation(path: &Path) -> Result<PathBuf> {
+    let bundle = path.with_file_name(ATTESTATION_BUNDLE_FILE);
+    let status = Command::new("cosign")
+        .args(["sign-blob", "--yes", "--bundle"])
+        .arg(&bundle)
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()
+        .map_err(|e| {
+            GitripperError::Attestation(format!("could not run cosign: {}", e))
+        })?;
+    if !status.success() {
+        return Err(GitripperError::Attestation(format!(
+            "cosign sign-blob {}",
+            status
+        )));
+    }
+    Ok(bundle)
+}
+
+/// `time` as an RFC 3339 UTC timestamp.
+fn rfc3339(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let (days, rest) = (secs / 86_400, secs % 86_400);
+
+    // Civil date from days since the epoch, after Howard Hinnant.
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rest / 3600,
+        rest / 60 % 60,
+        rest % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::write, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn test_rfc3339() {
+        assert_eq!(rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            rfc3339(UNIX_EPOCH + Duration::from_secs(1_709_251_199)),
+            "2024-02-29T23:59:59Z"
+        );
+    }
+
+    #[test]
+    fn test_statement_lists_files_and_commit() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dest = temp_dir.path();
+        fs::create_dir(dest.join("src")).unwrap();
+        write(dest.join("src/lib.rs"), "abc").unwrap();
+        write(dest.join(ATTESTATION_FILE), "{}").unwrap();
+        let report = RipReport {
+            provider:  "GitHub",
+            owner:     "o".to_string(),
+            repo:      "r".to_string(),
+            reference: "main".to_string(),
+            commit:    Some("abc123".to_string()),
+            dest:      dest.to_path_buf(),
+        };
+        let source = RipSource {
+            url:     "https://github.com/o/r",
+            subpath: None,
+            started: SystemTime::now(),
+        };
+        let builder = BuilderInfo {
+            id: "https://example.com/gitripper".to_string(),
+            ..Default::default()
+        };
+
+        let statement =
+            provenance_statement(&report, &source, &builder).unwrap();
+        assert_eq!(
+            statement["subject"],
+            json!([{
+                "name": "src/lib.rs",
+                "digest": {
+                    "sha256": "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+                },
+            }])
+        );
+        let upstream = &statement["predicate"]["buildDefinition"]
+            ["resolvedDependencies"][0];
+        assert_eq!(upstream["uri"], "git+https://github.com/o/r@main");
+        assert_eq!(upstream["digest"]["gitCommit"], "abc123");
+    }
+}
@@ -0,0 +1,161 @@
+use std::{
+    fmt,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use keyring::Entry;
+use reqwest::Url;
+
+use crate::{GitripperError, Provider, RepoLocation, Result};
+
+/// Service name tokens are stored under in the OS keyring, one entry per
+/// host.
+pub const KEYRING_SERVICE: &str = "gitripper";
+
+/// Where [`find_token`] found a token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenSource {
+    /// Stored with [`store_token`], e.g. by `gitripper auth login`.
+    Keyring,
+    /// `gh auth token`.
+    GhCli,
+    /// `git credential fill`.
+    GitCredential,
+}
+
+impl fmt::Display for TokenSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TokenSource::Keyring => "OS keyring",
+            TokenSource::GhCli => "gh CLI",
+            TokenSource::GitCredential => "git credential helper",
+        })
+    }
+}
+
+/// Host credentials for `location` are looked up under, taken from its
+/// clone URL.
+pub fn credential_host(
+    provider: &dyn Provider,
+    location: &RepoLocation,
+) -> Option<String> {
+    let url = Url::parse(&provider.clone_url(location)).ok()?;
+    url.host_str().map(str::to_string)
+}
+
+/// Looks for a token for `host` in the OS keyring, then, for GitHub hosts,
+/// the gh CLI, then git's credential helpers. The keyring comes first so a
+/// token stored for gitripper wins over broader ones.
+pub fn find_token(
+    provider: &dyn Provider,
+    host: &str,
+) -> Option<(String, TokenSource)> {
+    if let Some(token) = token_from_keyring(host) {
+        return Some((token, TokenSource::Keyring));
+    }
+    if provider.name() == "GitHub"
+        && let Some(token) = token_from_gh(host)
+    {
+        return Some((token, TokenSource::GhCli));
+    }
+    token_from_git_credential(host).map(|t| (t, TokenSource::GitCredential))
+}
+
+pub fn token_from_keyring(host: &str) -> Option<String> {
+    Entry::new(KEYRING_SERVICE, host).ok()?.get_password().ok()
+}
+
+/// Saves `token` for `host` in the OS keyring, replacing any earlier one.
+pub fn store_token(host: &str, token: &str) -> Result<()> {
+    Entry::new(KEYRING_SERVICE, host)
+        .and_then(|e| e.set_password(token))
+        .map_err(GitripperError::Keyring)
+}
+
+/// Removes the token for `host` from the OS keyring. Returns whether there
+/// was one.
+pub fn delete_token(host: &str) -> Result<bool> {
+    let entry =
+        Entry::new(KEYRING_SERVICE, host).map_err(GitripperError::Keyring)?;
+    match entry.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(GitripperError::Keyring(e)),
+    }
+}
+
+/// The token the gh CLI is logged in with for `host`.
+pub fn token_from_gh(host: &str) -> Option<String> {
+    let output = Command::new("gh")
+        .args(["auth", "token", "--hostname", host])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    non_empty(String::from_utf8(output.stdout).ok()?.trim())
+}
+
+/// The password git's credential helpers have for `https://<host>`. Never
+/// prompts.
+pub fn token_from_git_credential(host: &str) -> Option<String> {
+    let mut child = Command::new("git")
+        .args(["credential", "fill"])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GCM_INTERACTIVE", "never")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    let request = format!("protocol=https\nhost={}\n\n", host);
+    child.stdin.take()?.write_all(request.as_bytes()).ok()?;
+    let output = child.wait_with_output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_credential_password(&String::from_utf8(output.stdout).ok()?)
+}
+
+/// `password` attribute of `git credential fill` output.
+fn parse_credential_password(output: &str) -> Option<String> {
+    output.lines().find_map(|l| l.strip_prefix("password=")).and_then(non_empty)
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GitHub, GitLab};
+
+    #[test]
+    fn test_credential_host_from_clone_url() {
+        let location = RepoLocation::new("o", "r");
+        assert_eq!(
+            credential_host(&GitHub::default(), &location).as_deref(),
+            Some("github.com")
+        );
+        assert_eq!(
+            credential_host(&GitLab::new("gitlab.example.com"), &location)
+                .as_deref(),
+            Some("gitlab.example.com")
+        );
+    }
+
+    #[test]
+    fn test_parse_credential_password() {
+        let output = "host=github.com\nusername=me\npassword=ghp_secret\n";
+        assert_eq!(
+            parse_credential_password(output).as_deref(),
+            Some("ghp_secret")
+        );
+        assert_eq!(parse_credential_password("protocol=https\n"), None);
+    }
+}
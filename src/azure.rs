@@ -0,0 +1,307 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+#[cfg(feature = "native")]
+use reqwest::blocking::Client;
+#[cfg(feature = "native")]
+use serde_json::Value;
+use url::Url;
+
+use crate::provider::{is_commit_sha, strip_host, Provider, RepoLocation};
+#[cfg(feature = "native")]
+use crate::{
+    provider::{
+        fetch_json, fetch_raw, join_url_path, not_found_as, RefType, RemoteRef,
+    },
+    Result, DEFAULT_BRANCH,
+};
+
+pub const AZURE_HOST: &str = "dev.azure.com";
+
+const AZURE_SSH_HOST: &str = "ssh.dev.azure.com";
+const LEGACY_HOST_SUFFIX: &str = ".visualstudio.com";
+const API_VERSION: &str = "7.1";
+
+/// Azure DevOps Services (Azure Repos), through the Git REST API.
+///
+/// The owner of a location is `organization/project`. Tokens are personal
+/// access tokens, sent with basic auth and an empty username.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AzureDevOps;
+
+/// Parses `dev.azure.com/{org}/{project}/_git/{repo}` URLs, their
+/// `{org}.visualstudio.com` and SSH forms, and the `path` and `version` query
+/// of links to a folder at a branch, tag or commit.
+pub fn parse_azure_url(url: &str) -> Option<RepoLocation> {
+    let url = url.trim();
+    if let Some(rest) = strip_host(url, AZURE_SSH_HOST) {
+        let mut parts = rest.strip_prefix("v3/")?.split('/');
+        let (org, project, repo) =
+            (parts.next()?, parts.next()?, parts.next()?);
+        let repo = repo.strip_suffix(".git").unwrap_or(repo);
+        if [org, project, repo].iter().any(|s| s.is_empty()) {
+            return None;
+        }
+        return Some(RepoLocation::new(format!("{}/{}", org, project), repo));
+    }
+
+    let parsed = Url::parse(url).ok()?;
+    let host = parsed.host_str()?.to_ascii_lowercase();
+    let mut segments = parsed.path_segments()?.filter(|s| !s.is_empty());
+    let org = if host == AZURE_HOST {
+        segments.next()?.to_string()
+    } else {
+        host.strip_suffix(LEGACY_HOST_SUFFIX)?.to_string()
+    };
+    // A repository named like its project may leave the project out.
+    let (project, repo) = match (segments.next()?, segments.next()?) {
+        ("_git", repo) => (repo, repo),
+        (project, "_git") => (project, segments.next()?),
+        _ => return None,
+    };
+    if segments.next().is_some() {
+        return None;
+    }
+
+    let mut location =
+        RepoLocation::new(format!("{}/{}", org, project), repo.to_string());
+    for (key, value) in parsed.query_pairs() {
+        match &*key {
+            "path" => {
+                let path = value.trim_matches('/');
+                location.path = (!path.is_empty()).then(|| path.to_string());
+            },
+            // `GB`, `GT` or `GC` and a branch, tag or commit.
+            "version" if value.len() > 2 => {
+                location.reference = Some(value[2..].to_string());
+            },
+            _ => {},
+        }
+    }
+    Some(location)
+}
+
+impl AzureDevOps {
+    fn repo_url(&self, location: &RepoLocation) -> String {
+        format!(
+            "https://{}/{}/_apis/git/repositories/{}",
+            AZURE_HOST, location.owner, location.repo
+        )
+    }
+
+    /// The refs of `location` starting with `filter`, such as `heads/`,
+    /// with annotated tags peeled to their commits.
+    #[cfg(feature = "native")]
+    fn refs(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        filter: &str,
+        token: Option<&str>,
+    ) -> Result<Vec<RemoteRef>> {
+        let url = format!(
+            "{}/refs?filter={}&peelTags=true&api-version={}",
+            self.repo_url(location),
+            filter,
+            API_VERSION
+        );
+        let v = fetch_json(client, self, &url, token).map_err(|e| {
+            not_found_as(e, || {
+                format!("Repository {} not found (404).", location)
+            })
+        })?;
+
+        Ok(v.get("value")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|r| {
+                let name = r.get("name")?.as_str()?.strip_prefix("refs/")?;
+                let commit = r
+                    .get("peeledObjectId")
+                    .or_else(|| r.get("objectId"))?
+                    .as_str()?;
+                Some(RemoteRef {
+                    name:   name.strip_prefix(filter)?.to_string(),
+                    commit: commit.to_string(),
+                    date:   None,
+                })
+            })
+            .collect())
+    }
+}
+
+/// `versionDescriptor` query parameters selecting `reference`, taken as a
+/// commit if it is a full SHA and as a branch otherwise.
+fn version_query(reference: &str) -> [(&'static str, &str); 2] {
+    let version_type =
+        if is_commit_sha(reference) { "commit" } else { "branch" };
+    [
+        ("versionDescriptor.version", reference),
+        ("versionDescriptor.versionType", version_type),
+    ]
+}
+
+impl Provider for AzureDevOps {
+    fn name(&self) -> &'static str { "Azure DevOps" }
+
+    fn shorthand(&self) -> Option<&'static str> { Some("azure") }
+
+    fn parse_url(&self, url: &str) -> Option<RepoLocation> {
+        parse_azure_url(url)
+    }
+
+    #[cfg(feature = "native")]
+    fn default_branch(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        token: Option<&str>,
+    ) -> Result<String> {
+        let url =
+            format!("{}?api-version={}", self.repo_url(location), API_VERSION);
+        let v = fetch_json(client, self, &url, token).map_err(|e| {
+            not_found_as(e, || {
+                format!("Repository {} not found (404).", location)
+            })
+        })?;
+
+        Ok(v.get("defaultBranch")
+            .and_then(Value::as_str)
+            .map(|b| b.strip_prefix("refs/heads/").unwrap_or(b))
+            .unwrap_or(DEFAULT_BRANCH)
+            .to_string())
+    }
+
+    #[cfg(feature = "native")]
+    fn resolve_commit(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        reference: &str,
+        token: Option<&str>,
+    ) -> Result<Option<String>> {
+        for filter in ["heads/", "tags/"] {
+            let prefix = format!("{}{}", filter, reference);
+            let refs = self.refs(client, location, &prefix, token)?;
+            if let Some(r) = refs.into_iter().find(|r| r.name == reference) {
+                return Ok(Some(r.commit));
+            }
+        }
+        Ok(None)
+    }
+
+    #[cfg(feature = "native")]
+    fn list_refs(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        ref_type: RefType,
+        token: Option<&str>,
+    ) -> Result<Vec<RemoteRef>> {
+        let filter = match ref_type {
+            RefType::Branch => "heads/",
+            RefType::Tag => "tags/",
+        };
+        self.refs(client, location, filter, token)
+    }
+
+    #[cfg(feature = "native")]
+    fn fetch_file(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        reference: &str,
+        path: &str,
+        token: Option<&str>,
+    ) -> Result<Vec<u8>> {
+        let base = format!("{}/items", self.repo_url(location));
+        let mut url = join_url_path(&base, "")?;
+        url.query_pairs_mut()
+            .append_pair("path", &format!("/{}", path.trim_matches('/')))
+            .extend_pairs(version_query(reference))
+            .append_pair("download", "true")
+            .append_pair("api-version", API_VERSION);
+        fetch_raw(client, self, url.as_str(), None, token, path)
+    }
+
+    /// The Items API's zip of the repository's root folder.
+    fn archive_url(&self, location: &RepoLocation, reference: &str) -> String {
+        let base = format!("{}/items", self.repo_url(location));
+        let Ok(mut url) = Url::parse(&base) else {
+            return base;
+        };
+        url.query_pairs_mut()
+            .append_pair("path", "/")
+            .extend_pairs(version_query(reference))
+            .append_pair("$format", "zip")
+            .append_pair("download", "true")
+            .append_pair("api-version", API_VERSION);
+        url.into()
+    }
+
+    fn clone_url(&self, location: &RepoLocation) -> String {
+        let (org, project) =
+            location.owner.split_once('/').unwrap_or((&location.owner, ""));
+        format!(
+            "https://{}/{}/{}/_git/{}",
+            AZURE_HOST, org, project, location.repo
+        )
+    }
+
+    fn git_credentials(&self, token: &str) -> (String, String) {
+        ("pat".to_string(), token.to_string())
+    }
+
+    fn auth_header(&self, token: &str) -> (&'static str, String) {
+        let credentials = STANDARD.encode(format!(":{}", token));
+        ("Authorization", format!("Basic {}", credentials))
+    }
+
+    fn token_env(&self) -> Option<&'static str> { Some("AZURE_DEVOPS_EXT_PAT") }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_azure_url_forms() {
+        for url in [
+            "https://dev.azure.com/org/proj/_git/repo",
+            "https://org@dev.azure.com/org/proj/_git/repo/",
+            "https://org.visualstudio.com/proj/_git/repo",
+            "git@ssh.dev.azure.com:v3/org/proj/repo",
+        ] {
+            let loc = parse_azure_url(url).unwrap();
+            assert_eq!(loc, RepoLocation::new("org/proj", "repo"), "{}", url);
+        }
+
+        let loc =
+            parse_azure_url("https://dev.azure.com/org/_git/same").unwrap();
+        assert_eq!(loc, RepoLocation::new("org/same", "same"));
+        assert!(parse_azure_url("https://dev.azure.com/org/proj").is_none());
+        assert!(parse_azure_url("https://github.com/a/_git/b").is_none());
+
+        let url = "https://dev.azure.com/org/proj/_git/repo?path=/src/lib&\
+                   version=GBrelease/1.0";
+        let loc = parse_azure_url(url).unwrap();
+        assert_eq!(loc.reference.as_deref(), Some("release/1.0"));
+        assert_eq!(loc.path.as_deref(), Some("src/lib"));
+    }
+
+    #[test]
+    fn test_azure_api_urls_and_auth() {
+        let loc = RepoLocation::new("org/proj", "repo");
+        assert_eq!(
+            AzureDevOps.archive_url(&loc, "main"),
+            "https://dev.azure.com/org/proj/_apis/git/repositories/repo/items\
+             ?path=%2F&versionDescriptor.version=main&versionDescriptor.\
+             versionType=branch&%24format=zip&download=true&api-version=7.1"
+        );
+        assert_eq!(
+            AzureDevOps.clone_url(&loc),
+            "https://dev.azure.com/org/proj/_git/repo"
+        );
+        let (_, basic) = AzureDevOps.auth_header("secret");
+        assert_eq!(basic, "Basic OnNlY3JldA==");
+    }
+}
@@ -0,0 +1,188 @@
+use std::{fs::read_to_string, io, iter, path::Path};
+
+use rayon::{iter::ParallelBridge, prelude::*, ThreadPoolBuilder};
+
+use crate::{
+    DestLayout, GitHub, GitripperError, JobQueue, OrgRepo, RepoFilter,
+    RepoMatch, Result, RipReport, Ripper, UserRepos,
+};
+
+/// Default number of repositories [`Ripper::rip_batch`] works on at once.
+pub const DEFAULT_BATCH_PARALLELISM: usize = 4;
+
+/// Outcome of one URL in [`Ripper::rip_batch`].
+#[derive(Debug)]
+pub struct BatchResult {
+    pub url:    String,
+    pub result: Result<RipReport>,
+}
+
+impl Ripper {
+    /// Rips every URL in `urls` into `<root>/<owner>/<repo>`, or the
+    /// configured [`crate::DestLayout`] under `root`, at most `parallelism` at
+    /// a time and within the [`crate::RipperBuilder::batch_limits`]. One
+    /// failure doesn't stop the others; results come back in the order of
+    /// `urls`.
+    pub fn rip_batch(
+        &self,
+        urls: &[String],
+        root: &Path,
+        parallelism: usize,
+    ) -> Result<Vec<BatchResult>> {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(parallelism.max(1))
+            .build()
+            .map_err(|e| GitripperError::Io(io::Error::other(e)))?;
+
+        let default = DestLayout::default();
+        let layout = self.layout().unwrap_or(&default);
+
+        Ok(pool.install(|| {
+            urls.par_iter()
+                .map(|url| BatchResult {
+                    url:    url.clone(),
+                    result: self.rip_under(url, root, layout),
+                })
+                .collect()
+        }))
+    }
+
+    /// Rips the queued jobs of `queue` like [`Ripper::rip_batch`], recording
+    /// each outcome in the queue as it comes. Results come back in the order
+    /// of the jobs; jobs that weren't queued are left alone.
+    pub fn rip_jobs(
+        &self,
+        queue: &JobQueue,
+        root: &Path,
+        parallelism: usize,
+    ) -> Result<Vec<BatchResult>> {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(parallelism.max(1))
+            .build()
+            .map_err(|e| GitripperError::Io(io::Error::other(e)))?;
+
+        let default = DestLayout::default();
+        let layout = self.layout().unwrap_or(&default);
+
+        let mut results = pool.install(|| {
+            iter::from_fn(|| queue.try_next())
+                .par_bridge()
+                .map(|job| {
+                    let result = self.rip_under(&job.url, root, layout);
+                    queue.finish(
+                        job.id,
+                        match &result {
+                            Ok(report) => Ok(Some(report.dest.clone())),
+                            Err(e) => Err(e.to_string()),
+                        },
+                    )?;
+                    Ok((
+                        job.id,
+                        BatchResult {
+                            url: job.url,
+                            result,
+                        },
+                    ))
+                })
+                .collect::<Result<Vec<_>>>()
+        })?;
+        results.sort_by_key(|(id, _)| *id);
+        Ok(results.into_iter().map(|(_, result)| result).collect())
+    }
+
+    /// Lists the repositories of the GitHub organization `org` on `github`
+    /// that pass `filter`, with this ripper's client, token and retries.
+    pub fn list_org_repos(
+        &self,
+        github: &GitHub,
+        org: &str,
+        filter: &RepoFilter,
+    ) -> Result<Vec<OrgRepo>> {
+        self.with_retry(|| {
+            github.list_org_repos(self.client(), org, filter, self.token())
+        })
+    }
+
+    /// Lists the repositories `user` starred or owns on `github` that pass
+    /// `filter`, with this ripper's client, token and retries.
+    pub fn list_user_repos(
+        &self,
+        github: &GitHub,
+        user: &str,
+        which: UserRepos,
+        filter: &RepoFilter,
+    ) -> Result<Vec<OrgRepo>> {
+        self.with_retry(|| {
+            github.list_user_repos(
+                self.client(),
+                user,
+                which,
+                filter,
+                self.token(),
+            )
+        })
+    }
+
+    /// Searches `github` for the first `limit` repositories matching
+    /// `query`, with this ripper's client, token and retries.
+    pub fn search_repos(
+        &self,
+        github: &GitHub,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<RepoMatch>> {
+        self.with_retry(|| {
+            github.search_repos(self.client(), query, limit, self.token())
+        })
+    }
+}
+
+/// Reads repository URLs from a file, one per line. Blank lines and lines
+/// starting with `#` are skipped.
+pub fn read_url_list(path: &Path) -> io::Result<Vec<String>> {
+    Ok(read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+
+    use super::*;
+
+    #[test]
+    fn test_read_url_list_skips_comments() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("repos.txt");
+        write(
+            &path,
+            "# mirrors\nhttps://github.com/a/b\n\n  https://gitlab.com/c/d  \n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            read_url_list(&path).unwrap(),
+            ["https://github.com/a/b", "https://gitlab.com/c/d"]
+        );
+    }
+
+    #[test]
+    fn test_rip_batch_reports_each_url() {
+        let ripper = Ripper::builder().build().unwrap();
+        let urls = vec!["not a url".to_string(), "also bad".to_string()];
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let results = ripper.rip_batch(&urls, temp_dir.path(), 2).unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].url, "not a url");
+        assert!(matches!(
+            results[1].result,
+            Err(GitripperError::InvalidUrl(_))
+        ));
+    }
+}
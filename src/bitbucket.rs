@@ -0,0 +1,195 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+#[cfg(feature = "native")]
+use reqwest::blocking::Client;
+#[cfg(feature = "native")]
+use serde_json::Value;
+
+use crate::provider::{parse_repo_path, strip_host, Provider, RepoLocation};
+#[cfg(feature = "native")]
+use crate::{
+    provider::{
+        fetch_json, fetch_raw, join_url_path, non_empty_str, not_found_as,
+    },
+    GitripperError, RepoMetadata, Result, DEFAULT_BRANCH,
+};
+
+pub const BITBUCKET_API: &str = "https://api.bitbucket.org/2.0";
+
+const BITBUCKET_HOST: &str = "bitbucket.org";
+
+/// Bitbucket Cloud, through the 2.0 REST API.
+///
+/// Tokens of the form `username:app_password` are sent with basic auth;
+/// anything else is treated as an OAuth or repository access token.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Bitbucket;
+
+/// Parses `bitbucket.org` repository URLs, including
+/// `/src/<ref>/<path>` links.
+pub fn parse_bitbucket_url(url: &str) -> Option<RepoLocation> {
+    parse_repo_path(strip_host(url.trim(), BITBUCKET_HOST)?, "src/")
+}
+
+impl Provider for Bitbucket {
+    fn name(&self) -> &'static str { "Bitbucket" }
+
+    fn shorthand(&self) -> Option<&'static str> { Some("bitbucket") }
+
+    fn parse_url(&self, url: &str) -> Option<RepoLocation> {
+        parse_bitbucket_url(url)
+    }
+
+    #[cfg(feature = "native")]
+    fn default_branch(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        token: Option<&str>,
+    ) -> Result<String> {
+        let url = format!(
+            "{}/repositories/{}/{}",
+            BITBUCKET_API, location.owner, location.repo
+        );
+        let v = fetch_json(client, self, &url, token).map_err(|e| {
+            not_found_as(e, || {
+                format!("Repository {} not found (404).", location)
+            })
+        })?;
+
+        Ok(v.pointer("/mainbranch/name")
+            .and_then(|b| b.as_str())
+            .unwrap_or(DEFAULT_BRANCH)
+            .to_string())
+    }
+
+    #[cfg(feature = "native")]
+    fn metadata(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        token: Option<&str>,
+    ) -> Result<RepoMetadata> {
+        let url = format!(
+            "{}/repositories/{}/{}",
+            BITBUCKET_API, location.owner, location.repo
+        );
+        let v = fetch_json(client, self, &url, token)?;
+        Ok(RepoMetadata {
+            description: non_empty_str(&v, "/description"),
+            homepage:    non_empty_str(&v, "/website"),
+            topics:      Vec::new(),
+        })
+    }
+
+    #[cfg(feature = "native")]
+    fn repo_size(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        token: Option<&str>,
+    ) -> Result<Option<u64>> {
+        let url = format!(
+            "{}/repositories/{}/{}",
+            BITBUCKET_API, location.owner, location.repo
+        );
+        let v = fetch_json(client, self, &url, token)?;
+        Ok(v.get("size").and_then(Value::as_u64))
+    }
+
+    #[cfg(feature = "native")]
+    fn resolve_commit(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        reference: &str,
+        token: Option<&str>,
+    ) -> Result<Option<String>> {
+        let url = format!(
+            "{}/repositories/{}/{}/commit/{}",
+            BITBUCKET_API, location.owner, location.repo, reference
+        );
+        let v = fetch_json(client, self, &url, token)?;
+        Ok(v.get("hash").and_then(|s| s.as_str()).map(str::to_string))
+    }
+
+    #[cfg(feature = "native")]
+    fn fetch_file(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        reference: &str,
+        path: &str,
+        token: Option<&str>,
+    ) -> Result<Vec<u8>> {
+        let base = format!(
+            "{}/repositories/{}/{}/src",
+            BITBUCKET_API, location.owner, location.repo
+        );
+        let mut url = join_url_path(&base, "")?;
+        url.path_segments_mut()
+            .map_err(|_| GitripperError::InvalidUrl(base.clone()))?
+            .push(reference)
+            .extend(path.split('/').filter(|s| !s.is_empty()));
+        fetch_raw(client, self, url.as_str(), None, token, path)
+    }
+
+    fn archive_url(&self, location: &RepoLocation, reference: &str) -> String {
+        format!(
+            "https://{}/{}/{}/get/{}.zip",
+            BITBUCKET_HOST, location.owner, location.repo, reference
+        )
+    }
+
+    fn clone_url(&self, location: &RepoLocation) -> String {
+        format!("https://{}/{}.git", BITBUCKET_HOST, location)
+    }
+
+    fn git_credentials(&self, token: &str) -> (String, String) {
+        match token.split_once(':') {
+            Some((user, password)) => (user.to_string(), password.to_string()),
+            None => ("x-token-auth".to_string(), token.to_string()),
+        }
+    }
+
+    fn auth_header(&self, token: &str) -> (&'static str, String) {
+        let value = if token.contains(':') {
+            format!("Basic {}", STANDARD.encode(token))
+        } else {
+            format!("Bearer {}", token)
+        };
+        ("Authorization", value)
+    }
+
+    fn token_env(&self) -> Option<&'static str> { Some("BITBUCKET_TOKEN") }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bitbucket_url_forms() {
+        for url in [
+            "https://bitbucket.org/team/repo",
+            "https://bitbucket.org/team/repo.git",
+            "git@bitbucket.org:team/repo.git",
+        ] {
+            let loc = parse_bitbucket_url(url).unwrap();
+            assert_eq!(loc, RepoLocation::new("team", "repo"), "{}", url);
+        }
+
+        let url = "https://bitbucket.org/team/repo/src/dev/lib/core/";
+        let loc = parse_bitbucket_url(url).unwrap();
+        assert_eq!(loc.reference.as_deref(), Some("dev"));
+        assert_eq!(loc.path.as_deref(), Some("lib/core"));
+    }
+
+    #[test]
+    fn test_bitbucket_auth_header() {
+        let (_, basic) = Bitbucket.auth_header("me:secret");
+        assert_eq!(basic, "Basic bWU6c2VjcmV0");
+
+        let (_, bearer) = Bitbucket.auth_header("tok");
+        assert_eq!(bearer, "Bearer tok");
+    }
+}
@@ -0,0 +1,479 @@
+use std::{
+    env::var_os,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tempfile::NamedTempFile;
+
+use crate::{Provider, RepoLocation};
+
+const ARCHIVE_EXT: &str = "zip";
+const CHECKSUM_EXT: &str = "sha256";
+/// Per-repository index of the refs seen resolving to a cached commit.
+const REFS_FILE: &str = "refs.json";
+/// Directory of the responses kept for conditional requests.
+const RESPONSES_DIR: &str = "responses";
+const RESPONSE_EXT: &str = "body";
+const ETAG_EXT: &str = "etag";
+
+/// On-disk cache of downloaded archives, keyed by provider, repository and
+/// commit SHA.
+///
+/// Archives live at `<root>/<provider>/<owner>/<repo>/<sha>.zip` next to a
+/// `.sha256` file used to detect corrupted entries. A `refs.json` in the same
+/// directory remembers which commit each ref pointed at, and the default
+/// branch, so [offline](crate::RipperBuilder::offline) rips can find them.
+///
+/// Responses that came with an ETag are kept under `<root>/responses`, keyed
+/// by URL, so the next request for them can be made conditional.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    root: PathBuf,
+}
+
+/// A cached archive, as listed by [`Cache::entries`].
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    pub path:      PathBuf,
+    pub size:      u64,
+    /// Last time the entry was stored or used.
+    pub last_used: SystemTime,
+}
+
+/// What [`Cache::clean`] should evict.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CleanPolicy {
+    /// Evict entries unused for longer than this.
+    pub max_age:  Option<Duration>,
+    /// Evict least recently used entries until the cache fits.
+    pub max_size: Option<u64>,
+    /// Evict everything.
+    pub all:      bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CleanReport {
+    pub removed:     usize,
+    pub freed_bytes: u64,
+}
+
+impl Cache {
+    pub fn new(root: impl Into<PathBuf>) -> Self { Self { root: root.into() } }
+
+    /// `$GITRIPPER_CACHE_DIR`, or `gitripper` under the platform cache
+    /// directory (`~/.cache/gitripper` on Linux).
+    pub fn default_dir() -> Option<PathBuf> {
+        var_os("GITRIPPER_CACHE_DIR")
+            .map(PathBuf::from)
+            .or_else(|| dirs::cache_dir().map(|d| d.join("gitripper")))
+    }
+
+    pub fn open_default() -> Option<Self> { Self::default_dir().map(Self::new) }
+
+    pub fn root(&self) -> &Path { &self.root }
+
+    /// Where the archive for `location` at commit `sha` is stored.
+    pub fn archive_path(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        sha: &str,
+    ) -> PathBuf {
+        self.repo_dir(provider, location)
+            .join(format!("{}.{}", sha, ARCHIVE_EXT))
+    }
+
+    fn repo_dir(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+    ) -> PathBuf {
+        self.root
+            .join(provider.name().to_lowercase())
+            .join(&location.owner)
+            .join(&location.repo)
+    }
+
+    /// Remembers that `reference` of `location` resolved to `sha`.
+    pub fn record_ref(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        reference: &str,
+        sha: &str,
+    ) -> io::Result<()> {
+        self.update_refs(provider, location, |refs| {
+            refs["refs"][reference] = json!(sha);
+        })
+    }
+
+    /// Remembers `branch` as the default branch of `location`.
+    pub fn record_default_branch(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        branch: &str,
+    ) -> io::Result<()> {
+        self.update_refs(provider, location, |refs| {
+            refs["default_branch"] = json!(branch);
+        })
+    }
+
+    /// Commit `reference` of `location` last resolved to, per
+    /// [`Cache::record_ref`].
+    pub fn lookup_ref(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        reference: &str,
+    ) -> Option<String> {
+        let refs = self.read_refs(provider, location);
+        Some(refs.get("refs")?.get(reference)?.as_str()?.to_string())
+    }
+
+    /// Default branch of `location`, per [`Cache::record_default_branch`].
+    pub fn default_branch(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+    ) -> Option<String> {
+        let refs = self.read_refs(provider, location);
+        Some(refs.get("default_branch")?.as_str()?.to_string())
+    }
+
+    fn read_refs(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+    ) -> Value {
+        fs::read_to_string(self.repo_dir(provider, location).join(REFS_FILE))
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .filter(Value::is_object)
+            .unwrap_or_else(|| json!({}))
+    }
+
+    fn update_refs(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        update: impl FnOnce(&mut Value),
+    ) -> io::Result<()> {
+        let dir = self.repo_dir(provider, location);
+        fs::create_dir_all(&dir)?;
+        let mut refs = self.read_refs(provider, location);
+        if !refs["refs"].is_object() {
+            refs["refs"] = json!({});
+        }
+        update(&mut refs);
+        fs::write(dir.join(REFS_FILE), serde_json::to_string_pretty(&refs)?)
+    }
+
+    /// Returns the cached archive if present and intact, marking it as
+    /// recently used. Corrupted entries are removed.
+    pub fn get(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        sha: &str,
+    ) -> Option<PathBuf> {
+        let path = self.archive_path(provider, location, sha);
+        let expected = fs::read_to_string(checksum_path(&path)).ok()?;
+
+        match compute_sha256_hex(&path) {
+            Ok(actual) if actual == expected.trim() => {
+                let _ = File::options()
+                    .append(true)
+                    .open(&path)
+                    .and_then(|f| f.set_modified(SystemTime::now()));
+                Some(path)
+            },
+            _ => {
+                let _ = remove_entry(&path);
+                None
+            },
+        }
+    }
+
+    /// Copies `archive` into the cache, returning the cached path.
+    pub fn put(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        sha: &str,
+        archive: &Path,
+    ) -> io::Result<PathBuf> {
+        let path = self.archive_path(provider, location, sha);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Copy under a temporary name first so a crash never leaves a
+        // truncated archive behind a valid-looking name.
+        let partial = path.with_extension("partial");
+        fs::copy(archive, &partial)?;
+        let checksum = compute_sha256_hex(&partial)?;
+        fs::rename(&partial, &path)?;
+        fs::write(checksum_path(&path), checksum)?;
+
+        Ok(path)
+    }
+
+    /// ETag and body of the last response to `url` kept by
+    /// [`Cache::store_response`], if any.
+    pub fn stored_response(&self, url: &str) -> Option<(String, PathBuf)> {
+        let body = self.response_path(url);
+        let etag = fs::read_to_string(body.with_extension(ETAG_EXT)).ok()?;
+        body.is_file().then_some((etag, body))
+    }
+
+    /// Keeps `body`, the response to `url` tagged `etag`, replacing an
+    /// earlier one. `body` is moved, so it should come from
+    /// [`Cache::response_tempfile`].
+    pub fn store_response(
+        &self,
+        url: &str,
+        etag: &str,
+        body: NamedTempFile,
+    ) -> io::Result<PathBuf> {
+        let path = self.response_path(url);
+        // Write the ETag last so it never pairs with a half-stored body.
+        let _ = fs::remove_file(path.with_extension(ETAG_EXT));
+        body.persist(&path).map_err(|e| e.error)?;
+        fs::write(path.with_extension(ETAG_EXT), etag)?;
+        Ok(path)
+    }
+
+    /// A temporary file next to the stored responses, to write a response
+    /// body to before [`Cache::store_response`].
+    pub fn response_tempfile(&self) -> io::Result<NamedTempFile> {
+        let dir = self.root.join(RESPONSES_DIR);
+        fs::create_dir_all(&dir)?;
+        NamedTempFile::new_in(dir)
+    }
+
+    fn response_path(&self, url: &str) -> PathBuf {
+        let key = format!("{:x}", Sha256::digest(url.as_bytes()));
+        self.root.join(RESPONSES_DIR).join(format!("{}.{}", key, RESPONSE_EXT))
+    }
+
+    /// Every cached archive and stored response.
+    pub fn entries(&self) -> io::Result<Vec<CacheEntry>> {
+        let mut entries = Vec::new();
+        if self.root.exists() {
+            collect_entries(&self.root, &mut entries)?;
+        }
+        Ok(entries)
+    }
+
+    pub fn clean(&self, policy: &CleanPolicy) -> io::Result<CleanReport> {
+        let mut entries = self.entries()?;
+        entries.sort_by_key(|e| e.last_used);
+
+        let now = SystemTime::now();
+        let mut total: u64 = entries.iter().map(|e| e.size).sum();
+        let mut report = CleanReport::default();
+
+        for entry in entries {
+            let expired = policy.max_age.is_some_and(|max| {
+                now.duration_since(entry.last_used).unwrap_or_default() > max
+            });
+            let over_size = policy.max_size.is_some_and(|max| total > max);
+
+            if policy.all || expired || over_size {
+                remove_entry(&entry.path)?;
+                total -= entry.size;
+                report.removed += 1;
+                report.freed_bytes += entry.size;
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+fn checksum_path(archive: &Path) -> PathBuf {
+    archive.with_extension(format!("{}.{}", ARCHIVE_EXT, CHECKSUM_EXT))
+}
+
+fn remove_entry(path: &Path) -> io::Result<()> {
+    let sidecar = if path.extension().is_some_and(|e| e == RESPONSE_EXT) {
+        path.with_extension(ETAG_EXT)
+    } else {
+        checksum_path(path)
+    };
+    match fs::remove_file(sidecar) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {},
+    }
+    fs::remove_file(path)
+}
+
+fn collect_entries(dir: &Path, out: &mut Vec<CacheEntry>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let meta = entry.metadata()?;
+
+        if meta.is_dir() {
+            collect_entries(&path, out)?;
+        } else if path
+            .extension()
+            .is_some_and(|e| e == ARCHIVE_EXT || e == RESPONSE_EXT)
+        {
+            out.push(CacheEntry {
+                path,
+                size: meta.len(),
+                last_used: meta.modified()?,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Hex-encoded SHA-256 of the file at `path`.
+pub fn compute_sha256_hex(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Hex-encoded BLAKE3 hash of the file at `path`. Faster than
+/// [`compute_sha256_hex`] for comparing whole trees.
+pub fn compute_blake3_hex(path: &Path) -> io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(File::open(path)?)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::GitHub;
+
+    #[test]
+    fn test_put_then_get_round_trips() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new(temp_dir.path().join("cache"));
+        let archive = temp_dir.path().join("a.zip");
+        fs::write(&archive, b"zip bytes").unwrap();
+        let loc = RepoLocation::new("o", "r");
+
+        assert!(cache.get(&GitHub::default(), &loc, "abc").is_none());
+        let cached =
+            cache.put(&GitHub::default(), &loc, "abc", &archive).unwrap();
+
+        assert_eq!(cache.get(&GitHub::default(), &loc, "abc"), Some(cached));
+    }
+
+    #[test]
+    fn test_refs_are_remembered() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new(temp_dir.path());
+        let github = GitHub::default();
+        let loc = RepoLocation::new("o", "r");
+
+        assert_eq!(cache.lookup_ref(&github, &loc, "main"), None);
+        cache.record_ref(&github, &loc, "main", "abc").unwrap();
+        cache.record_ref(&github, &loc, "v1", "def").unwrap();
+        cache.record_default_branch(&github, &loc, "main").unwrap();
+
+        assert_eq!(cache.lookup_ref(&github, &loc, "main").unwrap(), "abc");
+        assert_eq!(cache.lookup_ref(&github, &loc, "v1").unwrap(), "def");
+        assert_eq!(cache.default_branch(&github, &loc).unwrap(), "main");
+        assert!(cache.entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_corrupted_entry_is_dropped() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new(temp_dir.path());
+        let archive = temp_dir.path().join("a.zip");
+        fs::write(&archive, b"zip bytes").unwrap();
+        let loc = RepoLocation::new("o", "r");
+
+        let cached =
+            cache.put(&GitHub::default(), &loc, "abc", &archive).unwrap();
+        fs::write(&cached, b"tampered").unwrap();
+
+        assert!(cache.get(&GitHub::default(), &loc, "abc").is_none());
+        assert!(!cached.exists());
+    }
+
+    #[test]
+    fn test_clean_evicts_to_max_size() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new(temp_dir.path().join("cache"));
+        let archive = temp_dir.path().join("a.zip");
+        fs::write(&archive, vec![0u8; 100]).unwrap();
+        let loc = RepoLocation::new("o", "r");
+
+        for sha in ["a", "b", "c"] {
+            cache.put(&GitHub::default(), &loc, sha, &archive).unwrap();
+        }
+
+        let report = cache
+            .clean(&CleanPolicy {
+                max_size: Some(150),
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(report.removed, 2);
+        assert_eq!(cache.entries().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_stored_responses_are_replaced_and_cleaned() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new(temp_dir.path());
+        let url = "https://api.github.com/repos/o/r";
+        assert!(cache.stored_response(url).is_none());
+
+        for (etag, body) in [("\"a\"", "{}"), ("\"b\"", "[]")] {
+            let mut file = cache.response_tempfile().unwrap();
+            file.write_all(body.as_bytes()).unwrap();
+            cache.store_response(url, etag, file).unwrap();
+        }
+        let (etag, body) = cache.stored_response(url).unwrap();
+        assert_eq!(etag, "\"b\"");
+        assert_eq!(fs::read_to_string(&body).unwrap(), "[]");
+
+        cache
+            .clean(&CleanPolicy {
+                all: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(cache.stored_response(url).is_none());
+        assert_eq!(fs::read_dir(body.parent().unwrap()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_blake3_hex_of_empty_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("empty");
+        fs::write(&path, b"").unwrap();
+        assert_eq!(
+            compute_blake3_hex(&path).unwrap(),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+    }
+}
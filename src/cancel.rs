@@ -0,0 +1,119 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+#[cfg(feature = "native")]
+use std::{
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "native")]
+use crate::{GitripperError, ProgressSink, Result, SkippedFile};
+
+/// Longest a [`StopSignal::sleep`] goes without checking the signal.
+#[cfg(feature = "native")]
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Asks rips to stop, e.g. from a Ctrl-C handler. Clones share their state,
+/// so one can be handed to [`crate::RipperBuilder::cancel_token`] and
+/// another kept to cancel with.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self { Self::default() }
+
+    /// Makes downloads and extraction polling this token stop with
+    /// [`crate::GitripperError::Cancelled`]. Can't be undone.
+    pub fn cancel(&self) { self.0.store(true, Ordering::SeqCst); }
+
+    pub fn is_cancelled(&self) -> bool { self.0.load(Ordering::SeqCst) }
+}
+
+/// When a [`crate::Ripper`] gives up: once its token is cancelled or its
+/// deadline passes.
+#[cfg(feature = "native")]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct StopSignal {
+    pub(crate) token:    Option<CancellationToken>,
+    pub(crate) deadline: Option<Instant>,
+}
+
+#[cfg(feature = "native")]
+impl StopSignal {
+    pub(crate) fn is_armed(&self) -> bool {
+        self.token.is_some() || self.deadline.is_some()
+    }
+
+    /// What to stop with, if it's time to.
+    pub(crate) fn error(&self) -> Option<GitripperError> {
+        if self.token.as_ref().is_some_and(CancellationToken::is_cancelled) {
+            Some(GitripperError::Cancelled)
+        } else if self.deadline.is_some_and(|d| Instant::now() >= d) {
+            Some(GitripperError::DeadlineExceeded)
+        } else {
+            None
+        }
+    }
+
+    /// Sleeps for `duration`, failing as soon as it's time to stop.
+    pub(crate) fn sleep(&self, duration: Duration) -> Result<()> {
+        let until = Instant::now() + duration;
+        loop {
+            if let Some(e) = self.error() {
+                return Err(e);
+            }
+            let left = until.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Ok(());
+            }
+            sleep(left.min(POLL_INTERVAL));
+        }
+    }
+}
+
+/// The error workers stop with when [`ProgressSink::is_cancelled`].
+pub(crate) fn cancelled() -> io::Error { io::Error::other("cancelled") }
+
+/// Passes progress on to `inner` and reports `stop` as its cancellation, so
+/// everything that reports progress also stops on it.
+#[cfg(feature = "native")]
+pub(crate) struct Cancellable {
+    pub(crate) inner: Arc<dyn ProgressSink>,
+    pub(crate) stop:  StopSignal,
+}
+
+#[cfg(feature = "native")]
+impl ProgressSink for Cancellable {
+    fn download_started(&self, total_bytes: Option<u64>) {
+        self.inner.download_started(total_bytes);
+    }
+
+    fn download_progress(&self, bytes: u64) {
+        self.inner.download_progress(bytes);
+    }
+
+    fn download_finished(&self) { self.inner.download_finished(); }
+
+    fn extract_started(&self, total_entries: u64) {
+        self.inner.extract_started(total_entries);
+    }
+
+    fn extract_progress(&self, entries: u64) {
+        self.inner.extract_progress(entries);
+    }
+
+    fn extract_finished(&self) { self.inner.extract_finished(); }
+
+    fn file_skipped(&self, file: &SkippedFile) {
+        self.inner.file_skipped(file);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.stop.error().is_some() || self.inner.is_cancelled()
+    }
+}
@@ -0,0 +1,342 @@
+//! A C API over [`Ripper`], declared in `include/gitripper.h`.
+//!
+//! Every function returns a [`GitripperStatus`], whose values are the exit
+//! codes of the command-line tool, and leaves the message of a failure for
+//! [`gitripper_last_error`]. Strings are NUL-terminated UTF-8 and borrowed
+//! for the duration of the call only.
+
+use std::{
+    cell::RefCell,
+    env::var,
+    ffi::{c_char, CStr, CString},
+    path::Path,
+    ptr,
+};
+
+use crate::{detect_provider, GitripperError, Ripper};
+
+thread_local! {
+    /// Message of the last failure on this thread.
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Outcome of a call, numbered like the exit codes of `gitripper`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GitripperStatus {
+    Ok = 0,
+    /// A required argument was null or not UTF-8.
+    InvalidArgument = 1,
+    InvalidUrl = 2,
+    DestinationExists = 3,
+    CleanupFailed = 4,
+    GitNotFound = 5,
+    DownloadFailed = 6,
+    ExtractionFailed = 7,
+    InitFailed = 8,
+    Io = 9,
+    RateLimited = 10,
+    NotARip = 12,
+    ChecksumMismatch = 14,
+    InvalidTemplate = 15,
+    HookFailed = 16,
+    PushFailed = 17,
+    SecretsFound = 18,
+    AttestationFailed = 19,
+    TooLarge = 20,
+    Cancelled = 130,
+}
+
+impl From<&GitripperError> for GitripperStatus {
+    fn from(e: &GitripperError) -> Self {
+        match e {
+            GitripperError::InvalidUrl(_) => Self::InvalidUrl,
+            GitripperError::Cancelled => Self::Cancelled,
+            GitripperError::DestinationExists(_) => Self::DestinationExists,
+            GitripperError::Cleanup { .. } => Self::CleanupFailed,
+            GitripperError::NotARip(_) => Self::NotARip,
+            GitripperError::ChecksumMismatch { .. }
+            | GitripperError::CommitMismatch { .. } => Self::ChecksumMismatch,
+            GitripperError::InvalidTemplate(_)
+            | GitripperError::Template(_) => Self::InvalidTemplate,
+            GitripperError::Hook { .. } => Self::HookFailed,
+            GitripperError::Push(_) => Self::PushFailed,
+            GitripperError::SecretsFound { .. } => Self::SecretsFound,
+            GitripperError::Attestation(_) => Self::AttestationFailed,
+            GitripperError::Keyring(_) | GitripperError::Io(_) => Self::Io,
+            GitripperError::GitNotFound => Self::GitNotFound,
+            GitripperError::RateLimited { .. } => Self::RateLimited,
+            GitripperError::Extraction(_) => Self::ExtractionFailed,
+            GitripperError::TooLarge { .. } => Self::TooLarge,
+            GitripperError::GitInit(_) => Self::InitFailed,
+            GitripperError::Unsupported(_)
+            | GitripperError::AppAuth(_)
+            | GitripperError::Http(_)
+            | GitripperError::HttpStatus { .. }
+            | GitripperError::Clone(_)
+            | GitripperError::Offline
+            | GitripperError::DeadlineExceeded
+            | GitripperError::NotCached { .. }
+            | GitripperError::EmptyRepository(_) => Self::DownloadFailed,
+        }
+    }
+}
+
+/// Settings of a rip. Start from [`gitripper_options_default`]; null
+/// strings are left unset.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct GitripperOptions {
+    /// Branch, tag or commit to rip instead of the default branch.
+    pub reference:    *const c_char,
+    /// Token for the host. Without one, its environment variable is read,
+    /// such as `GITHUB_TOKEN`.
+    pub token:        *const c_char,
+    /// Directory of the repository to keep instead of all of it.
+    pub subpath:      *const c_char,
+    /// Clones the history instead of taking a snapshot.
+    pub keep_history: bool,
+    /// Leaves the files without creating a repository.
+    pub no_init:      bool,
+    /// Replaces an existing destination.
+    pub force:        bool,
+    /// Only uses the download cache.
+    pub offline:      bool,
+}
+
+impl Default for GitripperOptions {
+    fn default() -> Self {
+        Self {
+            reference:    ptr::null(),
+            token:        ptr::null(),
+            subpath:      ptr::null(),
+            keep_history: false,
+            no_init:      false,
+            force:        false,
+            offline:      false,
+        }
+    }
+}
+
+/// An error for an argument that isn't usable as a string.
+struct InvalidArgument(&'static str);
+
+/// `ptr` as a string, `None` if it is null.
+///
+/// # Safety
+///
+/// `ptr` is null or points to a NUL-terminated string.
+unsafe fn optional_str<'a>(
+    ptr: *const c_char,
+    name: &'static str,
+) -> Result<Option<&'a str>, InvalidArgument> {
+    if ptr.is_null() {
+        return Ok(None);
+    }
+    // SAFETY: guaranteed by the caller.
+    let s = unsafe { CStr::from_ptr(ptr) };
+    s.to_str().map(Some).map_err(|_| InvalidArgument(name))
+}
+
+/// # Safety
+///
+/// As for [`optional_str`].
+unsafe fn required_str<'a>(
+    ptr: *const c_char,
+    name: &'static str,
+) -> Result<&'a str, InvalidArgument> {
+    unsafe { optional_str(ptr, name) }?.ok_or(InvalidArgument(name))
+}
+
+fn set_last_error(message: Option<String>) {
+    let message =
+        message.map(|m| CString::new(m.replace('\0', " ")).unwrap_or_default());
+    LAST_ERROR.set(message);
+}
+
+/// Records the outcome of `f` for [`gitripper_last_error`].
+fn status_of(
+    f: impl FnOnce() -> Result<Result<(), GitripperError>, InvalidArgument>,
+) -> GitripperStatus {
+    let (status, message) = match f() {
+        Ok(Ok(())) => (GitripperStatus::Ok, None),
+        Ok(Err(e)) => ((&e).into(), Some(e.to_string())),
+        Err(InvalidArgument(name)) => (
+            GitripperStatus::InvalidArgument,
+            Some(format!("'{}' is null or not UTF-8.", name)),
+        ),
+    };
+    set_last_error(message);
+    status
+}
+
+/// A ripper for `url` configured by `options`.
+///
+/// # Safety
+///
+/// `options` is null or points to a valid [`GitripperOptions`].
+unsafe fn ripper(
+    url: &str,
+    options: *const GitripperOptions,
+) -> Result<Result<Ripper, GitripperError>, InvalidArgument> {
+    // SAFETY: guaranteed by the caller.
+    let options = unsafe { options.as_ref() }.copied().unwrap_or_default();
+    let mut builder = Ripper::builder()
+        .keep_history(options.keep_history)
+        .no_init(options.no_init)
+        .force(options.force)
+        .offline(options.offline);
+    if let Some(reference) =
+        unsafe { optional_str(options.reference, "reference") }?
+    {
+        builder = builder.reference(reference);
+    }
+    if let Some(subpath) = unsafe { optional_str(options.subpath, "subpath") }?
+    {
+        builder = builder.subpath(subpath);
+    }
+    let token = match unsafe { optional_str(options.token, "token") }? {
+        Some(token) => Some(token.to_string()),
+        None => detect_provider(url)
+            .and_then(|(provider, _)| provider.token_env())
+            .and_then(|name| var(name).ok()),
+    };
+    if let Some(token) = token {
+        builder = builder.token(token);
+    }
+    Ok(builder.build())
+}
+
+/// The options of a default rip.
+#[unsafe(no_mangle)]
+pub extern "C" fn gitripper_options_default() -> GitripperOptions {
+    GitripperOptions::default()
+}
+
+/// Rips `url` into `dest`, or a directory named after the repository in the
+/// working directory if `dest` is null. `options` may be null for the
+/// defaults.
+///
+/// # Safety
+///
+/// `url` and `dest` are null or NUL-terminated strings, and `options` is
+/// null or points to a [`GitripperOptions`] whose strings are.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gitripper_rip(
+    url: *const c_char,
+    dest: *const c_char,
+    options: *const GitripperOptions,
+) -> GitripperStatus {
+    status_of(|| {
+        let url = unsafe { required_str(url, "url") }?;
+        let dest = unsafe { optional_str(dest, "dest") }?;
+        Ok(unsafe { ripper(url, options) }?.and_then(|ripper| {
+            match dest {
+                Some(dest) => ripper.rip_to(url, Path::new(dest)),
+                None => ripper.rip(url),
+            }
+            .map(drop)
+        }))
+    })
+}
+
+/// Rips `url` into a new archive at `path`, in the format its extension
+/// names, instead of a directory.
+///
+/// # Safety
+///
+/// As for [`gitripper_rip`], with `path` not null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gitripper_rip_to_archive(
+    url: *const c_char,
+    path: *const c_char,
+    options: *const GitripperOptions,
+) -> GitripperStatus {
+    status_of(|| {
+        let url = unsafe { required_str(url, "url") }?;
+        let path = unsafe { required_str(path, "path") }?;
+        Ok(unsafe { ripper(url, options) }?.and_then(|ripper| {
+            ripper.rip_to_archive(url, Path::new(path)).map(drop)
+        }))
+    })
+}
+
+/// Message of the last call on this thread that failed, or null if it
+/// succeeded. Valid until the next call on the thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn gitripper_last_error() -> *const c_char {
+    LAST_ERROR.with_borrow(|message| {
+        message.as_ref().map_or(ptr::null(), |m| m.as_ptr())
+    })
+}
+
+/// Version of the library, such as `1.2.0`.
+#[unsafe(no_mangle)]
+pub extern "C" fn gitripper_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Write};
+
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    use super::*;
+
+    fn last_error() -> Option<String> {
+        let message = gitripper_last_error();
+        (!message.is_null()).then(|| {
+            unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned()
+        })
+    }
+
+    #[test]
+    fn test_rip_local_archive_through_c_api() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let archive = temp_dir.path().join("widget.zip");
+        let mut zip = ZipWriter::new(fs::File::create(&archive).unwrap());
+        zip.start_file("widget-main/README.md", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"# widget\n").unwrap();
+        zip.finish().unwrap();
+
+        let url = CString::new(archive.to_str().unwrap()).unwrap();
+        let dest = temp_dir.path().join("out");
+        let dest = CString::new(dest.to_str().unwrap()).unwrap();
+        let options = GitripperOptions {
+            no_init: true,
+            ..gitripper_options_default()
+        };
+
+        let status =
+            unsafe { gitripper_rip(url.as_ptr(), dest.as_ptr(), &options) };
+        assert_eq!(status, GitripperStatus::Ok, "{:?}", last_error());
+        assert_eq!(last_error(), None);
+        assert_eq!(
+            fs::read(temp_dir.path().join("out/README.md")).unwrap(),
+            b"# widget\n"
+        );
+
+        let status =
+            unsafe { gitripper_rip(url.as_ptr(), dest.as_ptr(), &options) };
+        assert_eq!(status, GitripperStatus::DestinationExists);
+        assert!(last_error().is_some());
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        let status =
+            unsafe { gitripper_rip(ptr::null(), ptr::null(), ptr::null()) };
+        assert_eq!(status, GitripperStatus::InvalidArgument);
+        assert_eq!(last_error().unwrap(), "'url' is null or not UTF-8.");
+
+        let url = CString::new("not a repository").unwrap();
+        let status =
+            unsafe { gitripper_rip(url.as_ptr(), ptr::null(), ptr::null()) };
+        assert_eq!(status, GitripperStatus::InvalidUrl);
+
+        let version = unsafe { CStr::from_ptr(gitripper_version()) };
+        assert_eq!(version.to_str().unwrap(), env!("CARGO_PKG_VERSION"));
+    }
+}
@@ -0,0 +1,210 @@
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use unicode_normalization::UnicodeNormalization;
+
+/// What extraction does with paths that differ, but name the same file on
+/// the destination filesystem, like `README.md` and `Readme.md` on a
+/// case-insensitive one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CollisionPolicy {
+    /// Fail before the second path is written.
+    #[default]
+    Error,
+    /// Write the second path under a new name, e.g. `Readme~1.md`.
+    Rename,
+    /// Let the second path overwrite the first.
+    Overwrite,
+}
+
+/// How a filesystem compares file names.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FsSemantics {
+    pub case_insensitive:   bool,
+    /// NFC and NFD spellings of a name are the same file, as on macOS.
+    pub normalizes_unicode: bool,
+}
+
+impl FsSemantics {
+    /// Finds out how the filesystem `dir` is on compares names, by creating
+    /// a probe file in it and looking it up under other spellings.
+    pub fn probe(dir: &Path) -> io::Result<Self> {
+        let probe = tempfile::Builder::new()
+            .prefix(".gitripper-probe-")
+            .tempdir_in(dir)?;
+        File::create(probe.path().join("case-\u{e9}"))?;
+        Ok(Self {
+            case_insensitive:   probe.path().join("CASE-\u{e9}").exists(),
+            normalizes_unicode: probe.path().join("case-e\u{301}").exists(),
+        })
+    }
+
+    /// Whether two different names can ever be the same file.
+    fn folds(&self) -> bool { self.case_insensitive || self.normalizes_unicode }
+
+    /// `name` as the filesystem compares it.
+    fn fold(&self, name: &OsString) -> String {
+        let name = name.to_string_lossy();
+        let name = if self.normalizes_unicode {
+            name.nfc().collect()
+        } else {
+            name.into_owned()
+        };
+        if self.case_insensitive {
+            name.to_lowercase()
+        } else {
+            name
+        }
+    }
+}
+
+/// Tracks the paths written by an extraction and catches those that collide
+/// with an earlier one, following a [`CollisionPolicy`].
+#[derive(Debug)]
+pub(crate) struct CollisionGuard {
+    semantics: FsSemantics,
+    policy:    CollisionPolicy,
+    /// Names written in each directory, by folded name.
+    taken:     HashMap<(PathBuf, String), OsString>,
+    /// Names that were renamed, by directory and name in the archive.
+    renamed:   HashMap<(PathBuf, OsString), OsString>,
+}
+
+impl CollisionGuard {
+    pub(crate) fn new(semantics: FsSemantics, policy: CollisionPolicy) -> Self {
+        Self {
+            semantics,
+            policy,
+            taken: HashMap::new(),
+            renamed: HashMap::new(),
+        }
+    }
+
+    /// A guard for extracting into `dir`, `None` if nothing can collide
+    /// there or collisions are let through.
+    pub(crate) fn for_dir(
+        dir: &Path,
+        policy: CollisionPolicy,
+    ) -> io::Result<Option<Self>> {
+        if policy == CollisionPolicy::Overwrite {
+            return Ok(None);
+        }
+        let semantics = FsSemantics::probe(dir)?;
+        Ok(semantics.folds().then(|| Self::new(semantics, policy)))
+    }
+
+    /// The path to write `rel_path` at: itself, or with components renamed
+    /// where they collide with an earlier path.
+    pub(crate) fn check(&mut self, rel_path: &Path) -> anyhow::Result<PathBuf> {
+        let mut chosen = PathBuf::new();
+        for component in rel_path.iter() {
+            let name = component.to_os_string();
+            if let Some(written) =
+                self.renamed.get(&(chosen.clone(), name.clone()))
+            {
+                chosen.push(written);
+                continue;
+            }
+            let key = (chosen.clone(), self.semantics.fold(&name));
+            let written = match self.taken.get(&key) {
+                Some(taken) if *taken == name => name,
+                Some(taken) => {
+                    if self.policy == CollisionPolicy::Error {
+                        return Err(anyhow!(
+                            "'{}' collides with '{}', which is the same file \
+                             on this filesystem",
+                            rel_path.display(),
+                            chosen.join(taken).display()
+                        ));
+                    }
+                    let renamed = self.free_name(&chosen, &name);
+                    self.taken.insert(
+                        (chosen.clone(), self.semantics.fold(&renamed)),
+                        renamed.clone(),
+                    );
+                    self.renamed
+                        .insert((chosen.clone(), name), renamed.clone());
+                    renamed
+                },
+                None => {
+                    self.taken.insert(key, name.clone());
+                    name
+                },
+            };
+            chosen.push(written);
+        }
+        Ok(chosen)
+    }
+
+    /// The first of `stem~1.ext`, `stem~2.ext`, ... not taken in `dir`.
+    fn free_name(&self, dir: &Path, name: &OsString) -> OsString {
+        let path = Path::new(name);
+        let stem = path.file_stem().unwrap_or(name).to_string_lossy();
+        let ext = path
+            .extension()
+            .map(|e| format!(".{}", e.to_string_lossy()))
+            .unwrap_or_default();
+        (1..)
+            .map(|n| OsString::from(format!("{}~{}{}", stem, n, ext)))
+            .find(|candidate| {
+                let key = (dir.to_path_buf(), self.semantics.fold(candidate));
+                !self.taken.contains_key(&key)
+            })
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MACOS: FsSemantics = FsSemantics {
+        case_insensitive:   true,
+        normalizes_unicode: true,
+    };
+
+    #[test]
+    fn test_case_and_normalization_collisions_fail() {
+        let mut guard = CollisionGuard::new(MACOS, CollisionPolicy::Error);
+        guard.check(Path::new("README.md")).unwrap();
+        guard.check(Path::new("caf\u{e9}.txt")).unwrap();
+        assert_eq!(
+            guard.check(Path::new("README.md")).unwrap(),
+            Path::new("README.md")
+        );
+
+        assert!(guard.check(Path::new("Readme.md")).is_err());
+        assert!(guard.check(Path::new("cafe\u{301}.txt")).is_err());
+    }
+
+    #[test]
+    fn test_renames_colliding_files_and_directories() {
+        let mut guard = CollisionGuard::new(MACOS, CollisionPolicy::Rename);
+        guard.check(Path::new("docs/README.md")).unwrap();
+        assert_eq!(
+            guard.check(Path::new("docs/Readme.md")).unwrap(),
+            Path::new("docs/Readme~1.md")
+        );
+        assert_eq!(
+            guard.check(Path::new("Docs/guide.md")).unwrap(),
+            Path::new("Docs~1/guide.md")
+        );
+        assert_eq!(
+            guard.check(Path::new("Docs/index.md")).unwrap(),
+            Path::new("Docs~1/index.md")
+        );
+    }
+
+    #[test]
+    fn test_probe_on_temp_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        FsSemantics::probe(temp_dir.path()).unwrap();
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,102 @@
+use std::{
+    env::var_os,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use toml::Table;
+
+use crate::Result;
+
+/// Name of the config file in [`Config::default_dir`].
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Settings of the command-line tool that apply to every rip:
+///
+/// ```toml
+/// [hooks]
+/// post = ["cargo fmt --all", "scrub-licenses"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    /// Commands run after extraction, before any given with `--post-hook`.
+    pub post_hooks: Vec<String>,
+}
+
+impl Config {
+    /// `$GITRIPPER_CONFIG_DIR`, or `gitripper` under the platform config
+    /// directory (`~/.config/gitripper` on Linux).
+    pub fn default_dir() -> Option<PathBuf> {
+        var_os("GITRIPPER_CONFIG_DIR")
+            .map(PathBuf::from)
+            .or_else(|| dirs::config_dir().map(|d| d.join("gitripper")))
+    }
+
+    /// Reads the [`CONFIG_FILE`] in [`Config::default_dir`], empty if there
+    /// is none.
+    pub fn open_default() -> Result<Self> {
+        match Self::default_dir() {
+            Some(dir) => Self::read(&dir.join(CONFIG_FILE)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Reads the config at `path`, empty if there is none.
+    pub fn read(path: &Path) -> Result<Self> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Ok(Self::default())
+            },
+            Err(e) => return Err(e.into()),
+        };
+        let invalid = |e: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid {}: {}", path.display(), e),
+            )
+        };
+        let table: Table =
+            text.parse().map_err(|e| invalid(format!("{}", e)))?;
+
+        let mut config = Self::default();
+        if let Some(post) = table.get("hooks").and_then(|h| h.get("post")) {
+            config.post_hooks = post
+                .as_array()
+                .ok_or_else(|| invalid("hooks.post isn't a list".to_string()))?
+                .iter()
+                .map(|hook| hook.as_str().map(str::to_string))
+                .collect::<Option<_>>()
+                .ok_or_else(|| {
+                    invalid("hooks.post holds a non-string".to_string())
+                })?;
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+
+    use super::*;
+
+    #[test]
+    fn test_read_config_hooks() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join(CONFIG_FILE);
+        assert_eq!(Config::read(&path).unwrap(), Config::default());
+
+        write(&path, "[hooks]\npost = [\"cargo fmt --all\", \"true\"]\n")
+            .unwrap();
+        assert_eq!(
+            Config::read(&path).unwrap().post_hooks,
+            ["cargo fmt --all", "true"]
+        );
+
+        for bad in ["[hooks", "[hooks]\npost = \"true\"", "hooks.post = [1]"] {
+            write(&path, bad).unwrap();
+            assert!(Config::read(&path).is_err(), "{}", bad);
+        }
+    }
+}
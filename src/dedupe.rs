@@ -0,0 +1,159 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use rayon::prelude::*;
+
+use crate::{compute_blake3_hex, manifest::collect_files};
+
+/// What [`dedupe_tree`] saved.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DedupeReport {
+    /// Files replaced by a hardlink to an identical one.
+    pub files: usize,
+    /// Bytes no longer stored twice.
+    pub bytes: u64,
+}
+
+/// Replaces files under `root` whose contents and permissions are identical
+/// with hardlinks to a single copy. Files are grouped by size and
+/// permissions first, so only candidates are BLAKE3-hashed. Symlinks, empty
+/// files and the top-level repository are left alone.
+///
+/// Linked files share their contents: writing to one in place changes all
+/// of them, although most editors and git replace files instead.
+pub fn dedupe_tree(root: &Path) -> io::Result<DedupeReport> {
+    let mut files = Vec::new();
+    collect_files(root, Path::new(""), &mut files)?;
+
+    // Links share their permissions too, so an executable is only ever
+    // linked to another one.
+    let mut by_size: HashMap<(u64, u32), Vec<PathBuf>> = HashMap::new();
+    for rel in files {
+        let meta = fs::symlink_metadata(root.join(&rel))?;
+        if meta.is_file() && meta.len() > 0 {
+            by_size.entry((meta.len(), mode(&meta))).or_default().push(rel);
+        }
+    }
+
+    let hashed = by_size
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .flat_map(|(key, paths)| paths.into_iter().map(move |p| (key, p)))
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|((size, mode), rel)| {
+            let hash = compute_blake3_hex(&root.join(&rel))?;
+            Ok(((size, mode, hash), rel))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let mut groups: HashMap<(u64, u32, String), Vec<PathBuf>> = HashMap::new();
+    for (key, rel) in hashed {
+        groups.entry(key).or_default().push(rel);
+    }
+
+    let mut report = DedupeReport::default();
+    for ((size, ..), mut paths) in groups {
+        paths.sort();
+        let original = root.join(&paths[0]);
+        for rel in &paths[1..] {
+            let path = root.join(rel);
+            if same_file(&original, &path)? {
+                continue;
+            }
+            link_over(&original, &path)?;
+            report.files += 1;
+            report.bytes += size;
+        }
+    }
+    Ok(report)
+}
+
+/// Points `path` at `original` by linking to a sibling name and renaming it
+/// over `path`, so a failure never leaves `path` missing.
+fn link_over(original: &Path, path: &Path) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".gitripper-link");
+    let tmp = PathBuf::from(tmp);
+    fs::hard_link(original, &tmp)?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+/// Permission bits of a file, or just whether it is read-only where there
+/// are none.
+#[cfg(unix)]
+fn mode(meta: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+
+    meta.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn mode(meta: &fs::Metadata) -> u32 { meta.permissions().readonly().into() }
+
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> io::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+
+    let (a, b) = (fs::metadata(a)?, fs::metadata(b)?);
+    Ok(a.dev() == b.dev() && a.ino() == b.ino())
+}
+
+#[cfg(not(unix))]
+fn same_file(_: &Path, _: &Path) -> io::Result<bool> { Ok(false) }
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, write};
+
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_dedupe_links_identical_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        create_dir_all(root.join("a")).unwrap();
+        write(root.join("a/fixture.json"), "{}\n").unwrap();
+        write(root.join("b.json"), "{}\n").unwrap();
+        write(root.join("c.json"), "[]\n").unwrap();
+
+        let report = dedupe_tree(root).unwrap();
+        assert_eq!(report, DedupeReport { files: 1, bytes: 3 });
+        assert_eq!(fs::read(root.join("b.json")).unwrap(), b"{}\n");
+        assert!(
+            same_file(&root.join("a/fixture.json"), &root.join("b.json"))
+                .unwrap()
+        );
+
+        assert_eq!(dedupe_tree(root).unwrap(), DedupeReport::default());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_dedupe_keeps_files_with_other_permissions_apart() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        write(root.join("build.sh"), "make\n").unwrap();
+        write(root.join("notes.txt"), "make\n").unwrap();
+        for (name, mode) in [("build.sh", 0o755), ("notes.txt", 0o644)] {
+            let permissions = fs::Permissions::from_mode(mode);
+            fs::set_permissions(root.join(name), permissions).unwrap();
+        }
+
+        assert_eq!(dedupe_tree(root).unwrap(), DedupeReport::default());
+        let mode = |name: &str| {
+            fs::metadata(root.join(name)).unwrap().permissions().mode() & 0o777
+        };
+        assert_eq!((mode("build.sh"), mode("notes.txt")), (0o755, 0o644));
+        assert!(!same_file(&root.join("build.sh"), &root.join("notes.txt"))
+            .unwrap());
+    }
+}
@@ -0,0 +1,223 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use similar::TextDiff;
+use tempfile::{tempdir, TempDir};
+
+use crate::{
+    manifest::hash_tree, GitripperError, Provenance, Result, RipReport, Ripper,
+};
+
+/// How a file differs between a rip and its upstream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// Only upstream has the file.
+    Added,
+    /// Only the rip has the file.
+    Removed,
+    Modified,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    /// Path relative to the top of the rip.
+    pub path: PathBuf,
+    pub kind: ChangeKind,
+}
+
+/// Result of [`Ripper::diff`]. Holds on to the fetched upstream snapshot so
+/// individual changes can be rendered with [`SnapshotDiff::unified_diff`].
+#[derive(Debug)]
+pub struct SnapshotDiff {
+    /// The upstream snapshot that was compared against.
+    pub upstream: RipReport,
+    /// Changes from the rip to upstream, sorted by path.
+    pub changes:  Vec<FileChange>,
+    local:        PathBuf,
+    snapshot:     TempDir,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool { self.changes.is_empty() }
+
+    /// Unified diff of one change, or `None` if either side is not text.
+    pub fn unified_diff(
+        &self,
+        change: &FileChange,
+    ) -> io::Result<Option<String>> {
+        let read = |root: &Path, present: bool| -> io::Result<Option<String>> {
+            if !present {
+                return Ok(Some(String::new()));
+            }
+            Ok(String::from_utf8(fs::read(root.join(&change.path))?).ok())
+        };
+        let old = read(&self.local, change.kind != ChangeKind::Added)?;
+        let new =
+            read(self.snapshot.path(), change.kind != ChangeKind::Removed)?;
+        let (Some(old), Some(new)) = (old, new) else {
+            return Ok(None);
+        };
+
+        let path = change.path.to_string_lossy();
+        let old_header = match change.kind {
+            ChangeKind::Added => "/dev/null".to_string(),
+            _ => format!("a/{}", path),
+        };
+        let new_header = match change.kind {
+            ChangeKind::Removed => "/dev/null".to_string(),
+            _ => format!("b/{}", path),
+        };
+
+        Ok(Some(
+            TextDiff::from_lines(&old, &new)
+                .unified_diff()
+                .header(&old_header, &new_header)
+                .to_string(),
+        ))
+    }
+}
+
+impl Ripper {
+    /// Fetches the current upstream snapshot of the rip at `dest` and
+    /// compares it file by file. Nothing in `dest` is modified.
+    pub fn diff(&self, dest: &Path) -> Result<SnapshotDiff> {
+        let previous = Provenance::read(dest)?
+            .ok_or_else(|| GitripperError::NotARip(dest.to_path_buf()))?;
+        let (provider, location, upstream) =
+            self.upstream_of(&previous, dest)?;
+
+        let snapshot = tempdir()?;
+        self.fetch_upstream(
+            provider.as_ref(),
+            &location,
+            &upstream,
+            &previous,
+            snapshot.path(),
+        )?;
+        let changes = diff_trees(dest, snapshot.path())?;
+
+        Ok(SnapshotDiff {
+            upstream,
+            changes,
+            local: dest.to_path_buf(),
+            snapshot,
+        })
+    }
+}
+
+/// Compares the files under `local` and `upstream` by content hash. The
+/// repository and rip metadata at the top of `local` are ignored.
+pub fn diff_trees(
+    local: &Path,
+    upstream: &Path,
+) -> io::Result<Vec<FileChange>> {
+    Ok(compare_hashes(hash_tree(local)?, hash_tree(upstream)?))
+}
+
+/// Changes from the files hashed in `old` to those in `new`, sorted by path.
+pub(crate) fn compare_hashes(
+    old: BTreeMap<PathBuf, String>,
+    mut new: BTreeMap<PathBuf, String>,
+) -> Vec<FileChange> {
+    let mut changes = Vec::new();
+
+    for (path, hash) in old {
+        match new.remove(&path) {
+            Some(h) if h == hash => {},
+            Some(_) => changes.push(FileChange {
+                path,
+                kind: ChangeKind::Modified,
+            }),
+            None => changes.push(FileChange {
+                path,
+                kind: ChangeKind::Removed,
+            }),
+        }
+    }
+    changes.extend(new.into_keys().map(|path| FileChange {
+        path,
+        kind: ChangeKind::Added,
+    }));
+
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, write};
+
+    use super::*;
+
+    #[test]
+    fn test_diff_trees_classifies_changes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let local = temp_dir.path().join("local");
+        let upstream = temp_dir.path().join("upstream");
+        create_dir_all(local.join(".git")).unwrap();
+        create_dir_all(local.join("src")).unwrap();
+        create_dir_all(upstream.join("src")).unwrap();
+        write(local.join(".git/HEAD"), "ref").unwrap();
+        write(local.join("same.txt"), "same").unwrap();
+        write(upstream.join("same.txt"), "same").unwrap();
+        write(local.join("src/lib.rs"), "old").unwrap();
+        write(upstream.join("src/lib.rs"), "new").unwrap();
+        write(local.join("gone.txt"), "x").unwrap();
+        write(upstream.join("new.txt"), "y").unwrap();
+
+        let changes = diff_trees(&local, &upstream).unwrap();
+
+        assert_eq!(
+            changes,
+            [
+                FileChange {
+                    path: "gone.txt".into(),
+                    kind: ChangeKind::Removed,
+                },
+                FileChange {
+                    path: "new.txt".into(),
+                    kind: ChangeKind::Added,
+                },
+                FileChange {
+                    path: PathBuf::from("src").join("lib.rs"),
+                    kind: ChangeKind::Modified,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_of_modified_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let snapshot = tempfile::tempdir().unwrap();
+        write(temp_dir.path().join("a.txt"), "one\ntwo\n").unwrap();
+        write(snapshot.path().join("a.txt"), "one\nthree\n").unwrap();
+        let diff = SnapshotDiff {
+            upstream: RipReport {
+                provider:  "GitHub",
+                owner:     "o".to_string(),
+                repo:      "r".to_string(),
+                reference: "main".to_string(),
+                commit:    None,
+                dest:      temp_dir.path().to_path_buf(),
+            },
+            changes: Vec::new(),
+            local: temp_dir.path().to_path_buf(),
+            snapshot,
+        };
+
+        let patch = diff
+            .unified_diff(&FileChange {
+                path: "a.txt".into(),
+                kind: ChangeKind::Modified,
+            })
+            .unwrap()
+            .unwrap();
+
+        assert!(patch.starts_with("--- a/a.txt\n+++ b/a.txt\n"));
+        assert!(patch.contains("-two\n+three\n"));
+    }
+}
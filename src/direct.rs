@@ -0,0 +1,123 @@
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "native")]
+use reqwest::blocking::Client;
+use url::Url;
+
+use crate::{
+    archive::split_extension,
+    provider::{Provider, RepoLocation},
+};
+#[cfg(feature = "native")]
+use crate::{GitripperError, Result};
+
+/// Archives that are not a repository on any provider: a direct `http(s)`
+/// link to a `.zip`, `.tar`, `.tar.gz` or `.tar.zst`, or such a file already
+/// on disk. Nothing but the archive itself is fetched.
+///
+/// The location's owner is the host (or the directory holding the file),
+/// its repository the file name without the extension, and its reference
+/// the whole URL or absolute path, which [`Provider::archive_url`] returns
+/// whatever reference it is given.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DirectArchive;
+
+/// Parses a direct archive URL, or the path of an existing archive file.
+pub fn parse_archive_url(url: &str) -> Option<RepoLocation> {
+    let url = url.trim();
+    let is_http = ["http://", "https://"].iter().any(|scheme| {
+        url.get(..scheme.len())
+            .is_some_and(|head| head.eq_ignore_ascii_case(scheme))
+    });
+
+    let (owner, name, reference) = if is_http {
+        let parsed = Url::parse(url).ok()?;
+        let name = parsed.path_segments()?.next_back()?.to_string();
+        (parsed.host_str()?.to_string(), name, url.to_string())
+    } else {
+        let path = Path::new(url);
+        if !path.is_file() {
+            return None;
+        }
+        let path = path.canonicalize().ok()?;
+        let dir = path.parent().and_then(Path::file_name);
+        (
+            dir.map_or("local".into(), |d| d.to_string_lossy().into_owned()),
+            path.file_name()?.to_string_lossy().into_owned(),
+            path.to_string_lossy().into_owned(),
+        )
+    };
+
+    let (stem, _) = split_extension(&name).filter(|(s, _)| !s.is_empty())?;
+    let mut location = RepoLocation::new(owner, stem);
+    location.reference = Some(reference);
+    Some(location)
+}
+
+impl Provider for DirectArchive {
+    fn name(&self) -> &'static str { "archive" }
+
+    fn parse_url(&self, url: &str) -> Option<RepoLocation> {
+        parse_archive_url(url)
+    }
+
+    #[cfg(feature = "native")]
+    fn default_branch(
+        &self,
+        _client: &Client,
+        location: &RepoLocation,
+        _token: Option<&str>,
+    ) -> Result<String> {
+        location.reference.clone().ok_or_else(|| {
+            GitripperError::Unsupported(format!(
+                "refs of the archive {}",
+                location
+            ))
+        })
+    }
+
+    fn local_archive(&self, location: &RepoLocation) -> Option<PathBuf> {
+        location
+            .reference
+            .as_deref()
+            .filter(|r| Path::new(r).is_absolute())
+            .map(PathBuf::from)
+    }
+
+    fn archive_url(&self, location: &RepoLocation, reference: &str) -> String {
+        location.reference.as_deref().unwrap_or(reference).to_string()
+    }
+
+    fn clone_url(&self, location: &RepoLocation) -> String {
+        self.archive_url(location, "")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_archive_urls_and_files() {
+        let url = "https://example.com/dl/proj-1.2.tar.gz?sig=a/b";
+        let location = parse_archive_url(url).unwrap();
+        assert_eq!(location.owner, "example.com");
+        assert_eq!(location.repo, "proj-1.2");
+        assert_eq!(DirectArchive.archive_url(&location, "main"), url);
+        assert_eq!(DirectArchive.local_archive(&location), None);
+
+        assert!(parse_archive_url("https://example.com/dl/proj").is_none());
+        assert!(parse_archive_url("https://example.com/.zip").is_none());
+        assert!(parse_archive_url("missing.zip").is_none());
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("Proj.ZIP");
+        std::fs::write(&file, b"PK").unwrap();
+        let location = parse_archive_url(file.to_str().unwrap()).unwrap();
+        assert_eq!(location.repo, "Proj");
+        assert_eq!(
+            DirectArchive.local_archive(&location),
+            Some(file.canonicalize().unwrap())
+        );
+    }
+}
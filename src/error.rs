@@ -0,0 +1,149 @@
+use std::{io, path::PathBuf, time::Duration};
+
+use thiserror::Error;
+
+pub type Result<T, E = GitripperError> = std::result::Result<T, E>;
+
+/// Errors produced by the rip pipeline.
+#[derive(Debug, Error)]
+pub enum GitripperError {
+    #[error("invalid repository URL '{0}'")]
+    InvalidUrl(String),
+
+    #[error(
+        "destination '{}' exists and is not empty. Use --force to overwrite.",
+        .0.display()
+    )]
+    DestinationExists(PathBuf),
+
+    #[error("failed to clean up '{}': {source}", path.display())]
+    Cleanup {
+        path:   PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error(
+        "'{}' has no gitripper metadata; it was not ripped or was ripped with \
+         history",
+        .0.display()
+    )]
+    NotARip(PathBuf),
+
+    #[error("{0}")]
+    Unsupported(String),
+
+    #[error("invalid destination template: {0}")]
+    InvalidTemplate(String),
+
+    #[error("GitHub App authentication failed: {0}")]
+    AppAuth(String),
+
+    #[cfg(feature = "native")]
+    #[error("OS keyring: {0}")]
+    Keyring(#[source] keyring::Error),
+
+    #[error(
+        "checksum mismatch for '{name}': expected {expected}, got {actual}"
+    )]
+    ChecksumMismatch {
+        name:     String,
+        expected: String,
+        actual:   String,
+    },
+
+    #[error(
+        "archive of '{reference}' is of commit {actual}, but it resolved to \
+         {expected}; the ref may have moved during the download"
+    )]
+    CommitMismatch {
+        reference: String,
+        expected:  String,
+        actual:    String,
+    },
+
+    #[error("cancelled")]
+    Cancelled,
+
+    #[error("deadline exceeded")]
+    DeadlineExceeded,
+
+    #[error("network access is disabled in offline mode")]
+    Offline,
+
+    #[error(
+        "{location} at '{reference}' is not in the archive cache; rip it once \
+         while online first"
+    )]
+    NotCached {
+        location:  String,
+        reference: String,
+    },
+
+    #[error(
+        "found {count} likely secret(s) in {}; nothing was committed",
+        dest.display()
+    )]
+    SecretsFound { count: usize, dest: PathBuf },
+
+    #[error(
+        "{location} is {} MiB, more than the {} MiB to rip without asking",
+        size.div_ceil(1024 * 1024),
+        limit / (1024 * 1024)
+    )]
+    TooLarge {
+        location: String,
+        size:     u64,
+        limit:    u64,
+    },
+
+    #[error("repository {0} is empty")]
+    EmptyRepository(String),
+
+    #[error("attestation: {0}")]
+    Attestation(String),
+
+    #[error("project template: {0}")]
+    Template(String),
+
+    #[error("post-hook '{command}' failed: {reason}")]
+    Hook { command: String, reason: String },
+
+    #[error("git executable not found on PATH")]
+    GitNotFound,
+
+    #[cfg(feature = "native")]
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("{message}")]
+    HttpStatus { status: u16, message: String },
+
+    #[error("API rate limit exceeded{}", format_reset(.reset_in))]
+    RateLimited { reset_in: Option<Duration> },
+
+    #[error("failed to extract archive: {0}")]
+    Extraction(#[source] anyhow::Error),
+
+    #[error("failed to clone repository: {0}")]
+    Clone(#[source] anyhow::Error),
+
+    #[error("failed to initialize repository: {0}")]
+    GitInit(#[source] anyhow::Error),
+
+    #[error("failed to push: {0}")]
+    Push(#[source] anyhow::Error),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+fn format_reset(reset_in: &Option<Duration>) -> String {
+    match reset_in {
+        Some(d) => {
+            let secs = d.as_secs();
+            format!("; resets in {}m {}s", secs / 60, secs % 60)
+        },
+        None => String::new(),
+    }
+}
@@ -0,0 +1,2303 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{create_dir_all, File},
+    io::{self, BufReader, Read, Seek, Write},
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+#[cfg(all(unix, feature = "zip"))]
+use std::{ffi::CString, mem::MaybeUninit, os::unix::ffi::OsStrExt};
+#[cfg(feature = "native")]
+use std::{
+    fs::{read_dir, symlink_metadata, FileTimes},
+    time::{Duration, UNIX_EPOCH},
+};
+#[cfg(unix)]
+use std::{
+    fs::{remove_file, set_permissions, Permissions},
+    os::unix::fs::PermissionsExt,
+};
+
+use anyhow::anyhow;
+use flate2::read::GzDecoder;
+use globset::GlobSet;
+#[cfg(feature = "native")]
+use globset::{Glob, GlobSetBuilder};
+#[cfg(feature = "mmap")]
+use memmap2::MmapOptions;
+#[cfg(feature = "zip")]
+use once_cell::sync::Lazy;
+#[cfg(all(feature = "zip", feature = "parallel"))]
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+#[cfg(feature = "zip")]
+use zip::{
+    read::{read_zipfile_from_stream, ZipFile},
+    ZipArchive,
+};
+
+use crate::{
+    cancel::cancelled, collision::CollisionGuard, CollisionPolicy, NoProgress,
+    ProgressSink,
+};
+
+#[cfg(all(feature = "zip", feature = "parallel"))]
+const PARALLEL_THRESHOLD_BYTES: u64 = 10_485_760; // 10 MB
+const STREAM_BUFFER_BYTES: usize = 64 * 1024;
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+/// Leading bytes [`looks_binary`] checks, as many as Git does.
+const BINARY_SNIFF_BYTES: usize = 8000;
+
+/// CI configuration and hosting metadata left out by
+/// [`crate::RipperBuilder::strip_meta`].
+pub const STRIP_META_PATTERNS: &[&str] = &[
+    ".github",
+    ".gitlab",
+    ".gitlab-ci.yml",
+    ".circleci",
+    ".travis.yml",
+    ".drone.yml",
+    ".cirrus.yml",
+    ".buildkite",
+    "appveyor.yml",
+    ".appveyor.yml",
+    "azure-pipelines.yml",
+    "bitbucket-pipelines.yml",
+    "Jenkinsfile",
+    "**/CODEOWNERS",
+    "**/ISSUE_TEMPLATE",
+    "**/ISSUE_TEMPLATE.md",
+    "**/PULL_REQUEST_TEMPLATE",
+    "**/PULL_REQUEST_TEMPLATE.md",
+    "**/FUNDING.yml",
+];
+
+/// Default for [`ExtractOptions::memory_budget`].
+pub const DEFAULT_MEMORY_BUDGET: u64 = 512 * 1024 * 1024; // 512 MB
+
+/// Modification time [`normalize_tree`] gives everything, in seconds since
+/// the Unix epoch: 1980-01-01, the earliest a zip entry can carry.
+pub const DETERMINISTIC_MTIME: u64 = 315_532_800;
+
+#[derive(Debug)]
+pub struct MemEntry {
+    pub rel_path:   PathBuf,
+    pub is_dir:     bool,
+    pub _data_size: u64,
+    pub unix_mode:  Option<u32>,
+    pub _file_idx:  usize,
+    pub data:       Vec<u8>,
+}
+
+/// What [`ExtractOptions::entry_hook`] does with an entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryAction {
+    /// Write the entry, with whatever changes the hook made to it.
+    Keep,
+    /// Leave the entry out.
+    Skip,
+    /// Write the entry with these contents instead.
+    Replace(Vec<u8>),
+}
+
+/// Called with every entry read into memory before it is written. It may
+/// change the entry's path, contents or mode in place.
+pub type EntryHook = dyn Fn(&mut MemEntry) -> EntryAction + Send + Sync;
+
+/// Why [`ExtractOptions`] left a file out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// Bigger than [`ExtractOptions::max_file_size`].
+    TooLarge,
+    /// Looks binary and [`ExtractOptions::skip_binaries`] is set.
+    Binary,
+}
+
+/// A file left out of an extraction, reported through
+/// [`ProgressSink::file_skipped`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedFile {
+    pub path:   PathBuf,
+    /// Uncompressed size.
+    pub size:   u64,
+    pub reason: SkipReason,
+}
+
+/// Whether `data`, the start of a file, looks binary: like Git, a NUL byte
+/// in the first 8000 bytes.
+pub fn looks_binary(data: &[u8]) -> bool {
+    data[..data.len().min(BINARY_SNIFF_BYTES)].contains(&0)
+}
+
+/// Line endings [`ExtractOptions::eol`] gives text files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Eol {
+    /// Leave them as they are in the archive.
+    #[default]
+    Keep,
+    Lf,
+    Crlf,
+    /// CRLF on Windows, LF elsewhere.
+    Native,
+}
+
+impl Eol {
+    /// What to end lines with, `None` to leave them alone.
+    fn newline(self) -> Option<&'static [u8]> {
+        match self {
+            Self::Keep => None,
+            Self::Lf => Some(b"\n"),
+            Self::Crlf => Some(b"\r\n"),
+            Self::Native if cfg!(windows) => Some(b"\r\n"),
+            Self::Native => Some(b"\n"),
+        }
+    }
+}
+
+/// Rewrites LF and CRLF line endings, in chunks that may split a CRLF pair.
+/// Lone CRs are kept.
+struct EolConverter {
+    newline:    &'static [u8],
+    pending_cr: bool,
+}
+
+impl EolConverter {
+    fn new(newline: &'static [u8]) -> Self {
+        Self {
+            newline,
+            pending_cr: false,
+        }
+    }
+
+    fn convert(&mut self, chunk: &[u8], out: &mut Vec<u8>) {
+        for &b in chunk {
+            if self.pending_cr {
+                self.pending_cr = false;
+                if b == b'\n' {
+                    out.extend_from_slice(self.newline);
+                    continue;
+                }
+                out.push(b'\r');
+            }
+            match b {
+                b'\r' => self.pending_cr = true,
+                b'\n' => out.extend_from_slice(self.newline),
+                _ => out.push(b),
+            }
+        }
+    }
+
+    fn finish(self, out: &mut Vec<u8>) {
+        if self.pending_cr {
+            out.push(b'\r');
+        }
+    }
+}
+
+/// Which leading directories of archive paths [`extract_zip_with`] removes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StripRoot {
+    /// Strip the single top-level folder GitHub and most other hosts put in
+    /// their archives, if every entry shares one.
+    #[default]
+    Auto,
+    /// Strip this many leading components, like `tar --strip-components`.
+    /// Entries with no more components than that are skipped.
+    Always(usize),
+    /// Keep paths as they are in the archive.
+    Never,
+}
+
+/// Where [`extract_zip_into`] puts entries.
+pub trait ExtractTarget {
+    /// Stores `entry`, whose path is relative to the root of the target.
+    fn write(&mut self, entry: MemEntry) -> anyhow::Result<()>;
+}
+
+/// Writes entries below a directory, as [`extract_zip_with`] does.
+#[derive(Debug, Clone)]
+pub struct DiskTarget {
+    root: PathBuf,
+}
+
+impl DiskTarget {
+    pub fn new(root: impl Into<PathBuf>) -> Self { Self { root: root.into() } }
+
+    pub fn root(&self) -> &Path { &self.root }
+}
+
+impl ExtractTarget for DiskTarget {
+    fn write(&mut self, entry: MemEntry) -> anyhow::Result<()> {
+        write_entry(&entry, &self.root)
+    }
+}
+
+/// Keeps the contents of regular files in memory, keyed by their path.
+/// Directories are implied by the paths; symlinks are kept as files holding
+/// their target.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryTarget {
+    pub files: HashMap<PathBuf, Vec<u8>>,
+}
+
+impl ExtractTarget for MemoryTarget {
+    fn write(&mut self, entry: MemEntry) -> anyhow::Result<()> {
+        if !entry.is_dir {
+            self.files.insert(entry.rel_path, entry.data);
+        }
+        Ok(())
+    }
+}
+
+/// Tuning knobs for [`extract_zip_with`].
+#[derive(Clone)]
+pub struct ExtractOptions {
+    /// Most uncompressed data buffered in memory at once. Entries are
+    /// batched up to this size and each batch is written in parallel;
+    /// entries bigger than the whole budget are streamed straight to disk.
+    /// `0` always streams.
+    pub memory_budget: u64,
+    /// Notified once per extracted entry.
+    pub progress:      Arc<dyn ProgressSink>,
+    /// Only extract entries below this directory (relative to the archive
+    /// root), with the prefix removed.
+    pub subpath:       Option<PathBuf>,
+    /// Skip entries whose path relative to the destination, or one of its
+    /// parent directories, matches.
+    pub exclude:       Option<GlobSet>,
+    /// Leave files already in the destination alone instead of overwriting
+    /// them.
+    pub skip_existing: bool,
+    pub strip_root:    StripRoot,
+    /// Leave out files bigger than this many bytes.
+    pub max_file_size: Option<u64>,
+    /// Leave out files that [`looks_binary`].
+    pub skip_binaries: bool,
+    /// Line endings of files that don't look binary.
+    pub eol:           Eol,
+    /// What to do with paths that are the same file on a case-insensitive
+    /// or Unicode-normalizing destination filesystem.
+    pub collisions:    CollisionPolicy,
+    /// Write entries one at a time in path order, instead of in parallel in
+    /// archive order. See [`normalize_tree`] for the rest of a reproducible
+    /// snapshot.
+    pub deterministic: bool,
+    /// Run on every entry before it is written, after the other filters
+    /// and line ending conversion. Files that would otherwise be streamed
+    /// are buffered so the hook sees them. [`Self::collisions`] and
+    /// [`Self::skip_existing`] apply to the path in the archive, not the
+    /// one the hook gives.
+    pub entry_hook:    Option<Arc<EntryHook>>,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        Self {
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+            progress:      Arc::new(NoProgress),
+            subpath:       None,
+            exclude:       None,
+            skip_existing: false,
+            strip_root:    StripRoot::Auto,
+            max_file_size: None,
+            skip_binaries: false,
+            eol:           Eol::Keep,
+            collisions:    CollisionPolicy::Error,
+            deterministic: false,
+            entry_hook:    None,
+        }
+    }
+}
+
+impl ExtractOptions {
+    /// Whether the file at `rel_path`, of `size` bytes and starting with
+    /// `head`, is left out by [`Self::max_file_size`] or
+    /// [`Self::skip_binaries`], reporting it to [`Self::progress`] if so.
+    /// An empty `head` checks the size alone, before reading the file.
+    pub(crate) fn skips(
+        &self,
+        rel_path: &Path,
+        size: u64,
+        head: &[u8],
+    ) -> bool {
+        let reason = if self.max_file_size.is_some_and(|max| size > max) {
+            SkipReason::TooLarge
+        } else if self.skip_binaries && looks_binary(head) {
+            SkipReason::Binary
+        } else {
+            return false;
+        };
+        self.progress.file_skipped(&SkippedFile {
+            path: rel_path.to_path_buf(),
+            size,
+            reason,
+        });
+        true
+    }
+
+    /// `data` with the line endings of [`Self::eol`], unless it looks
+    /// binary.
+    pub(crate) fn with_eol(&self, data: Vec<u8>) -> Vec<u8> {
+        match self.eol.newline() {
+            Some(newline) if !looks_binary(&data) => {
+                let mut out = Vec::with_capacity(data.len());
+                let mut converter = EolConverter::new(newline);
+                converter.convert(&data, &mut out);
+                converter.finish(&mut out);
+                out
+            },
+            _ => data,
+        }
+    }
+
+    /// `entry` as [`Self::entry_hook`] leaves it, `None` if it is skipped.
+    /// A path the hook gives has to stay inside the destination.
+    pub(crate) fn hook(
+        &self,
+        mut entry: MemEntry,
+    ) -> anyhow::Result<Option<MemEntry>> {
+        let Some(hook) = &self.entry_hook else {
+            return Ok(Some(entry));
+        };
+        match hook(&mut entry) {
+            EntryAction::Keep => {},
+            EntryAction::Skip => return Ok(None),
+            EntryAction::Replace(data) => entry.data = data,
+        }
+        let path = &entry.rel_path;
+        if path.as_os_str().is_empty()
+            || !path.components().all(|c| matches!(c, Component::Normal(_)))
+        {
+            return Err(anyhow!(
+                "Unsafe path '{}' from the entry hook.",
+                path.display()
+            ));
+        }
+        Ok(Some(entry))
+    }
+}
+
+impl fmt::Debug for ExtractOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtractOptions")
+            .field("memory_budget", &self.memory_budget)
+            .field("subpath", &self.subpath)
+            .field("skip_existing", &self.skip_existing)
+            .field("strip_root", &self.strip_root)
+            .field("max_file_size", &self.max_file_size)
+            .field("skip_binaries", &self.skip_binaries)
+            .field("eol", &self.eol)
+            .field("collisions", &self.collisions)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Strips the single top-level folder GitHub puts in zipballs
+/// (`owner-repo-sha/`), as long as every entry seen so far shares it.
+#[derive(Debug, Default)]
+struct RootStripper {
+    root_prefix:   Option<PathBuf>,
+    root_mismatch: bool,
+}
+
+impl RootStripper {
+    fn strip(&mut self, in_path: &Path) -> PathBuf {
+        if !self.root_mismatch {
+            if let Some(first) = in_path.components().next() {
+                let first_str = first.as_os_str().to_string_lossy();
+                if first_str.is_empty() {
+                    self.root_mismatch = true;
+                } else if let Some(ref current_prefix) = self.root_prefix {
+                    if current_prefix.as_os_str() != first.as_os_str() {
+                        self.root_mismatch = true;
+                    }
+                } else {
+                    self.root_prefix = Some(PathBuf::from(first.as_os_str()));
+                }
+            } else {
+                self.root_mismatch = true;
+            }
+        }
+
+        if !self.root_mismatch {
+            if let Some(ref root) = self.root_prefix {
+                match in_path.strip_prefix(root) {
+                    Ok(p) => p.to_path_buf(),
+                    Err(_) => {
+                        self.root_mismatch = true;
+                        in_path.to_path_buf()
+                    },
+                }
+            } else {
+                in_path.to_path_buf()
+            }
+        } else {
+            in_path.to_path_buf()
+        }
+    }
+}
+
+pub fn write_entry(entry: &MemEntry, dest_dir: &Path) -> anyhow::Result<()> {
+    if entry.is_dir {
+        create_contained_dir(dest_dir, &entry.rel_path)?;
+        return Ok(());
+    }
+    if is_symlink(entry.unix_mode)
+        && write_symlink(dest_dir, &entry.rel_path, &entry.data)?
+    {
+        return Ok(());
+    }
+
+    let outpath = create_file_path(dest_dir, &entry.rel_path)?;
+    let mut outfile = File::create(&outpath)?;
+    outfile.write_all(&entry.data)?;
+
+    #[cfg(unix)]
+    if let Some(mode) = entry.unix_mode {
+        let _ = set_permissions(&outpath, Permissions::from_mode(mode));
+    }
+    Ok(())
+}
+
+/// Makes the metadata of everything under `root` reproducible: modification
+/// times become [`DETERMINISTIC_MTIME`], directories `0755` and files `0755`
+/// or `0644` by whether anyone could execute them. Symlinks and a top-level
+/// `.git` are left alone.
+#[cfg(feature = "native")]
+pub fn normalize_tree(root: &Path) -> io::Result<()> {
+    let mtime = UNIX_EPOCH + Duration::from_secs(DETERMINISTIC_MTIME);
+    let times = FileTimes::new().set_accessed(mtime).set_modified(mtime);
+    normalize_dir(root, true, &times)?;
+    set_permissions(root, Permissions::from_mode(0o755))?;
+    File::open(root)?.set_times(times)
+}
+
+#[cfg(feature = "native")]
+fn normalize_dir(dir: &Path, top: bool, times: &FileTimes) -> io::Result<()> {
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+        let meta = symlink_metadata(&path)?;
+        if meta.is_symlink() || top && path.ends_with(".git") {
+            continue;
+        }
+        let mode = if meta.is_dir() {
+            normalize_dir(&path, false, times)?;
+            0o755
+        } else if meta.permissions().mode() & 0o111 != 0 {
+            0o755
+        } else {
+            0o644
+        };
+        set_permissions(&path, Permissions::from_mode(mode))?;
+        File::open(&path)?.set_times(*times)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn is_symlink(unix_mode: Option<u32>) -> bool {
+    unix_mode.is_some_and(|m| m & S_IFMT == S_IFLNK)
+}
+
+/// Whether `target`, relative to the directory containing `rel_path`, stays
+/// inside the extraction root.
+fn symlink_target_is_contained(rel_path: &Path, target: &Path) -> bool {
+    let mut depth: usize = rel_path.components().count().saturating_sub(1);
+
+    for component in target.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {},
+            Component::ParentDir => match depth.checked_sub(1) {
+                Some(d) => depth = d,
+                None => return false,
+            },
+            Component::RootDir | Component::Prefix(_) => return false,
+        }
+    }
+    true
+}
+
+/// Creates directory `rel_dir` of `dest_dir` and returns where it really
+/// is, failing if that is outside `dest_dir` by way of a symlink, such as
+/// one extracted earlier.
+fn create_contained_dir(
+    dest_dir: &Path,
+    rel_dir: &Path,
+) -> anyhow::Result<PathBuf> {
+    create_dir_all(dest_dir)?;
+    let root = dest_dir.canonicalize()?;
+    let mut dir = root.clone();
+    for component in rel_dir.components() {
+        let Component::Normal(part) = component else {
+            return Err(anyhow!("Unsafe path '{}'.", rel_dir.display()));
+        };
+        dir.push(part);
+        if dir.symlink_metadata().is_ok_and(|m| m.is_symlink()) {
+            dir = dir.canonicalize()?;
+            if !dir.starts_with(&root) {
+                return Err(anyhow!(
+                    "'{}' leads outside the destination through a symlink.",
+                    rel_dir.display()
+                ));
+            }
+        }
+    }
+    create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Where the file `rel_path` of `dest_dir` goes, once its directory is
+/// created. A symlink already there is removed rather than written through.
+fn create_file_path(
+    dest_dir: &Path,
+    rel_path: &Path,
+) -> anyhow::Result<PathBuf> {
+    let parent = rel_path.parent().unwrap_or(Path::new(""));
+    let name = rel_path
+        .file_name()
+        .ok_or_else(|| anyhow!("Unsafe path '{}'.", rel_path.display()))?;
+    let outpath = create_contained_dir(dest_dir, parent)?.join(name);
+    #[cfg(unix)]
+    if outpath.symlink_metadata().is_ok_and(|m| m.is_symlink()) {
+        remove_file(&outpath)?;
+    }
+    Ok(outpath)
+}
+
+/// Recreates a symlink entry whose content is `target`. Returns `false` if
+/// the link would point outside `dest_dir`, going by where its directory
+/// really is, in which case callers write the entry as a regular file
+/// instead.
+fn write_symlink(
+    dest_dir: &Path,
+    rel_path: &Path,
+    target: &[u8],
+) -> anyhow::Result<bool> {
+    let Ok(target) = std::str::from_utf8(target) else {
+        return Ok(false);
+    };
+    let target = Path::new(target);
+
+    if !symlink_target_is_contained(rel_path, target) {
+        return Ok(false);
+    }
+
+    #[cfg(unix)]
+    {
+        let outpath = create_file_path(dest_dir, rel_path)?;
+        let real_rel = outpath.strip_prefix(dest_dir.canonicalize()?)?;
+        if !symlink_target_is_contained(real_rel, target) {
+            return Ok(false);
+        }
+        if outpath.symlink_metadata().is_ok() {
+            remove_file(&outpath)?;
+        }
+        std::os::unix::fs::symlink(target, &outpath)?;
+        Ok(true)
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = dest_dir;
+        Ok(false)
+    }
+}
+
+#[cfg(feature = "zip")]
+pub fn extract_zip(zip_path: &Path, dest_dir: &Path) -> anyhow::Result<()> {
+    extract_zip_with(zip_path, dest_dir, &ExtractOptions::default())
+}
+
+#[cfg(feature = "zip")]
+pub fn extract_zip_with(
+    zip_path: &Path,
+    dest_dir: &Path,
+    options: &ExtractOptions,
+) -> anyhow::Result<()> {
+    let f = File::open(zip_path)?;
+    #[cfg(feature = "mmap")]
+    {
+        let mmap = unsafe { MmapOptions::new().map(&f)? };
+        extract_zip_reader(io::Cursor::new(&mmap[..]), dest_dir, options)
+    }
+    #[cfg(not(feature = "mmap"))]
+    extract_zip_reader(BufReader::new(f), dest_dir, options)
+}
+
+#[cfg(feature = "zip")]
+fn extract_zip_reader(
+    reader: impl Read + Seek,
+    dest_dir: &Path,
+    options: &ExtractOptions,
+) -> anyhow::Result<()> {
+    let mut archive = ZipArchive::new(reader)?;
+
+    if archive.is_empty() {
+        return Err(anyhow!("Zip archive is empty."));
+    }
+
+    create_dir_all(dest_dir)?;
+    check_free_space(&mut archive, dest_dir, options)?;
+
+    let progress = options.progress.as_ref();
+    progress.extract_started(archive.len() as u64);
+    let matched = extract_batched(&mut archive, dest_dir, options)?;
+
+    progress.extract_finished();
+    check_subpath_matched(options, matched)
+}
+
+/// Fails early if the entries of `archive` that `options` lets through
+/// don't fit in the free space of the filesystem holding `dest_dir`, going
+/// by the uncompressed sizes in the central directory.
+#[cfg(feature = "zip")]
+fn check_free_space<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    dest_dir: &Path,
+    options: &ExtractOptions,
+) -> anyhow::Result<()> {
+    let needed = extracted_size(archive, options)?;
+    let available = free_space(dest_dir)?;
+    if needed > available {
+        return Err(anyhow!(
+            "Not enough disk space in '{}': the archive needs {} MiB, but \
+             only {} MiB are free.",
+            dest_dir.display(),
+            needed.div_ceil(1024 * 1024),
+            available / (1024 * 1024)
+        ));
+    }
+    Ok(())
+}
+
+/// Total uncompressed size of the files of `archive` that `options` lets
+/// through. Files already in the destination are counted too.
+#[cfg(feature = "zip")]
+fn extracted_size<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    options: &ExtractOptions,
+) -> anyhow::Result<u64> {
+    let mut mapper = EntryMapper::new(options, Path::new(""));
+    let mut total = 0u64;
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i)?;
+        let in_path = zip_entry_path(&file)?;
+        let size = file.size();
+        if mapper.filter(&in_path).is_some()
+            && options.max_file_size.is_none_or(|max| size <= max)
+        {
+            total = total.saturating_add(size);
+        }
+    }
+    Ok(total)
+}
+
+/// Bytes available to unprivileged users on the filesystem holding `dir`.
+#[cfg(all(unix, feature = "zip"))]
+#[allow(clippy::unnecessary_cast)]
+fn free_space(dir: &Path) -> io::Result<u64> {
+    let path = CString::new(dir.as_os_str().as_bytes())?;
+    let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is NUL-terminated and `statvfs` fills in `stat` when it
+    // returns 0.
+    let stat = unsafe {
+        if libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        stat.assume_init()
+    };
+    Ok((stat.f_bavail as u64).saturating_mul(stat.f_frsize as u64))
+}
+
+/// Unknown elsewhere, so nothing is ruled out.
+#[cfg(all(not(unix), feature = "zip"))]
+fn free_space(_dir: &Path) -> io::Result<u64> { Ok(u64::MAX) }
+
+/// Extracts a zip, tar, `.tar.gz` or `.tar.zst` archive, telling them apart
+/// by their first bytes rather than by the file name.
+pub fn extract_archive_with(
+    archive_path: &Path,
+    dest_dir: &Path,
+    options: &ExtractOptions,
+) -> anyhow::Result<()> {
+    let mut file = File::open(archive_path)?;
+    let mut magic = Vec::with_capacity(4);
+    Read::by_ref(&mut file).take(4).read_to_end(&mut magic)?;
+    file.rewind()?;
+
+    match magic[..] {
+        #[cfg(feature = "zip")]
+        [b'P', b'K', ..] => extract_zip_with(archive_path, dest_dir, options),
+        #[cfg(not(feature = "zip"))]
+        [b'P', b'K', ..] => {
+            Err(anyhow!("Extracting zip archives needs the zip feature."))
+        },
+        [0x1f, 0x8b, ..] => extract_tar_with(
+            GzDecoder::new(BufReader::new(file)),
+            dest_dir,
+            options,
+        ),
+        #[cfg(feature = "zstd")]
+        [0x28, 0xb5, 0x2f, 0xfd] => {
+            extract_tar_with(zstd::Decoder::new(file)?, dest_dir, options)
+        },
+        #[cfg(not(feature = "zstd"))]
+        [0x28, 0xb5, 0x2f, 0xfd] => Err(anyhow!(
+            "Extracting .tar.zst archives needs the zstd feature."
+        )),
+        _ => extract_tar_with(BufReader::new(file), dest_dir, options),
+    }
+}
+
+/// Extracts the tar archive read from `reader` into `dest_dir`, one entry at
+/// a time in archive order. Only directories, regular files and symlinks
+/// are extracted; hard links and special files are left out. Files over
+/// [`ExtractOptions::memory_budget`] are streamed to disk rather than read
+/// into memory, unless there is an [`ExtractOptions::entry_hook`].
+/// [`ExtractOptions::deterministic`] doesn't apply.
+pub fn extract_tar_with(
+    reader: impl Read,
+    dest_dir: &Path,
+    options: &ExtractOptions,
+) -> anyhow::Result<()> {
+    create_dir_all(dest_dir)?;
+    let mut mapper = EntryMapper::new(options, dest_dir);
+    mapper.guard_collisions(dest_dir)?;
+    let progress = options.progress.as_ref();
+
+    let mut archive = tar::Archive::new(reader);
+    let mut buf = vec![0u8; STREAM_BUFFER_BYTES];
+    let (mut seen, mut matched) = (0, 0);
+    for (index, entry) in archive.entries()?.enumerate() {
+        if progress.is_cancelled() {
+            return Err(cancelled().into());
+        }
+        let mut entry = entry?;
+        seen += 1;
+        let kind = entry.header().entry_type();
+        if !(kind.is_dir() || kind.is_file() || kind.is_symlink()) {
+            continue;
+        }
+        let in_path = tar_entry_path(&entry.path()?)?;
+        if in_path.as_os_str().is_empty() {
+            continue;
+        }
+        let Some(rel_path) = mapper.map(&in_path)? else {
+            progress.extract_progress(1);
+            continue;
+        };
+        matched += 1;
+
+        let size = entry.size();
+        if kind.is_file()
+            && size > options.memory_budget
+            && options.entry_hook.is_none()
+        {
+            // Too big to ever buffer.
+            let mode = entry.header().mode().ok().map(|m| m & 0o7777);
+            write_streamed(
+                &mut entry, dest_dir, &rel_path, size, mode, options, &mut buf,
+            )?;
+        } else if let Some(entry) =
+            read_tar_entry(&mut entry, rel_path, index, options)?
+        {
+            write_entry(&entry, dest_dir)?;
+        }
+        progress.extract_progress(1);
+    }
+    if seen == 0 {
+        return Err(anyhow!("Tar archive is empty."));
+    }
+
+    progress.extract_finished();
+    check_subpath_matched(options, matched + mapper.skipped)
+}
+
+/// `path` of a tar entry without its `.` components. Absolute paths and
+/// `..` are errors rather than being written outside the destination.
+fn tar_entry_path(path: &Path) -> anyhow::Result<PathBuf> {
+    path.components()
+        .filter(|c| *c != Component::CurDir)
+        .map(|c| match c {
+            Component::Normal(part) => Ok(part),
+            _ => Err(anyhow!("Unsafe path '{}' in archive.", path.display())),
+        })
+        .collect()
+}
+
+/// Reads a directory, file or symlink entry of a tar archive into memory,
+/// `None` if [`ExtractOptions::skips`] the file.
+fn read_tar_entry<R: Read>(
+    entry: &mut tar::Entry<R>,
+    rel_path: PathBuf,
+    index: usize,
+    options: &ExtractOptions,
+) -> anyhow::Result<Option<MemEntry>> {
+    let kind = entry.header().entry_type();
+    let mode = entry.header().mode().ok().map(|m| m & 0o7777);
+    let size = entry.size();
+
+    let (unix_mode, data) = if kind.is_dir() {
+        (mode, Vec::new())
+    } else if kind.is_symlink() {
+        let target = entry.link_name()?.ok_or_else(|| {
+            anyhow!("Symlink '{}' has no target.", rel_path.display())
+        })?;
+        let target = target.to_string_lossy().as_bytes().to_vec();
+        (Some(S_IFLNK | 0o777), target)
+    } else {
+        if options.skips(&rel_path, size, &[]) {
+            return Ok(None);
+        }
+        // The header's size isn't trusted with an allocation.
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+        if options.skips(&rel_path, size, &data) {
+            return Ok(None);
+        }
+        (mode, options.with_eol(data))
+    };
+
+    options.hook(MemEntry {
+        rel_path,
+        is_dir: kind.is_dir(),
+        _data_size: size,
+        unix_mode,
+        _file_idx: index,
+        data,
+    })
+}
+
+/// Commit a provider archive was made from, as far as the archive itself
+/// tells: the full SHA GitHub stores in the zip comment, otherwise the hex
+/// suffix of the root folder (`owner-repo-<sha>`), which may be abbreviated.
+/// `None` if neither is present, or if the archive isn't a zip, such as the
+/// tarballs of sourcehut.
+#[cfg(feature = "zip")]
+pub fn archive_commit(path: &Path) -> anyhow::Result<Option<String>> {
+    let mut file = File::open(path)?;
+    let mut magic = [0; 2];
+    if file.read_exact(&mut magic).is_err() || magic != *b"PK" {
+        return Ok(None);
+    }
+    file.rewind()?;
+    let archive = ZipArchive::new(file)?;
+    let comment = String::from_utf8_lossy(archive.comment()).trim().to_string();
+    if comment.len() == 40 && is_hex(&comment) {
+        return Ok(Some(comment.to_ascii_lowercase()));
+    }
+
+    let root = archive
+        .file_names()
+        .find_map(|name| name.split('/').next().filter(|s| !s.is_empty()));
+    Ok(root
+        .and_then(|r| r.rsplit_once('-'))
+        .map(|(_, suffix)| suffix)
+        .filter(|s| s.len() >= 7 && is_hex(s))
+        .map(str::to_ascii_lowercase))
+}
+
+#[cfg(feature = "zip")]
+fn is_hex(s: &str) -> bool { s.bytes().all(|b| b.is_ascii_hexdigit()) }
+
+/// Extracts the zip archive read from `reader` into `target`, returning how
+/// many entries were stored. [`ExtractOptions::memory_budget`] and
+/// [`ExtractOptions::skip_existing`] don't apply; entries go to `target` one
+/// at a time.
+#[cfg(feature = "zip")]
+pub fn extract_zip_into(
+    reader: impl Read + Seek,
+    target: &mut dyn ExtractTarget,
+    options: &ExtractOptions,
+) -> anyhow::Result<u64> {
+    let mut archive = ZipArchive::new(reader)?;
+    if archive.is_empty() {
+        return Err(anyhow!("Zip archive is empty."));
+    }
+
+    let mut mapper = EntryMapper::new(options, Path::new(""));
+    mapper.existing_in = None;
+    let progress = options.progress.as_ref();
+    progress.extract_started(archive.len() as u64);
+
+    let mut written = 0;
+    for i in 0..archive.len() {
+        if progress.is_cancelled() {
+            return Err(cancelled().into());
+        }
+        if let Some(entry) = read_entry(&mut archive, i, &mut mapper)? {
+            target.write(entry)?;
+            written += 1;
+        }
+        progress.extract_progress(1);
+    }
+
+    progress.extract_finished();
+    check_subpath_matched(options, written)?;
+    Ok(written)
+}
+
+/// Maps archive paths to destination-relative paths, applying
+/// [`ExtractOptions::strip_root`] and the [`ExtractOptions::subpath`],
+/// [`ExtractOptions::exclude`] and [`ExtractOptions::skip_existing`]
+/// filters.
+struct EntryMapper<'a> {
+    options:     &'a ExtractOptions,
+    strip_root:  StripRoot,
+    stripper:    RootStripper,
+    subpath:     Option<&'a Path>,
+    exclude:     Option<&'a GlobSet>,
+    /// Destination to look for existing files in, if they are kept.
+    existing_in: Option<&'a Path>,
+    /// Entries left out only because they already exist.
+    skipped:     u64,
+    /// Set when extracting to disk, per [`ExtractOptions::collisions`].
+    collisions:  Option<CollisionGuard>,
+}
+
+impl<'a> EntryMapper<'a> {
+    fn new(options: &'a ExtractOptions, dest_dir: &'a Path) -> Self {
+        Self {
+            options,
+            strip_root: options.strip_root,
+            stripper: RootStripper::default(),
+            subpath: options.subpath.as_deref(),
+            exclude: options.exclude.as_ref(),
+            existing_in: options.skip_existing.then_some(dest_dir),
+            skipped: 0,
+            collisions: None,
+        }
+    }
+
+    /// Checks for [`ExtractOptions::collisions`] on the filesystem of
+    /// `dest_dir`, which has to exist.
+    fn guard_collisions(&mut self, dest_dir: &Path) -> io::Result<()> {
+        self.collisions =
+            CollisionGuard::for_dir(dest_dir, self.options.collisions)?;
+        Ok(())
+    }
+
+    /// Returns `None` for entries that shouldn't be written.
+    fn map(&mut self, in_path: &Path) -> anyhow::Result<Option<PathBuf>> {
+        let Some(rel_path) = self.filter(in_path) else {
+            return Ok(None);
+        };
+        let rel_path = match &mut self.collisions {
+            Some(guard) => guard.check(&rel_path)?,
+            None => rel_path,
+        };
+        if let Some(dest) = self.existing_in
+            && dest.join(&rel_path).symlink_metadata().is_ok()
+        {
+            self.skipped += 1;
+            return Ok(None);
+        }
+        Ok(Some(rel_path))
+    }
+
+    /// Applies the path mapping and filters of the options.
+    fn filter(&mut self, in_path: &Path) -> Option<PathBuf> {
+        let rel_path = match self.strip_root {
+            StripRoot::Auto => self.stripper.strip(in_path),
+            StripRoot::Always(n) => in_path.components().skip(n).collect(),
+            StripRoot::Never => in_path.to_path_buf(),
+        };
+        let rel_path = match self.subpath {
+            Some(sub) => rel_path.strip_prefix(sub).ok()?.to_path_buf(),
+            None => rel_path,
+        };
+        if let Some(exclude) = self.exclude
+            && rel_path.ancestors().any(|p| exclude.is_match(p))
+        {
+            return None;
+        }
+        if rel_path.as_os_str().is_empty() {
+            return None;
+        }
+        Some(rel_path)
+    }
+}
+
+/// Compiles `patterns` for [`ExtractOptions::exclude`].
+#[cfg(feature = "native")]
+pub(crate) fn exclude_set(
+    patterns: impl IntoIterator<Item = impl AsRef<str>>,
+) -> io::Result<GlobSet> {
+    let mut globs = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern.as_ref()).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidInput, e.to_string())
+        })?;
+        globs.add(glob);
+    }
+    globs
+        .build()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))
+}
+
+fn check_subpath_matched(
+    options: &ExtractOptions,
+    matched: u64,
+) -> anyhow::Result<()> {
+    match &options.subpath {
+        Some(sub) if matched == 0 => {
+            Err(anyhow!("Path '{}' not found in archive.", sub.display()))
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Path of `file` in its archive. Absolute paths and `..` are errors
+/// rather than being written outside the destination, as for tar entries.
+#[cfg(feature = "zip")]
+fn zip_entry_path<R: Read + ?Sized>(
+    file: &ZipFile<'_, R>,
+) -> anyhow::Result<PathBuf> {
+    file.enclosed_name()
+        .ok_or_else(|| anyhow!("Unsafe path '{}' in archive.", file.name()))
+}
+
+/// Reads entry `index` of `archive` into memory, `None` if `mapper` leaves
+/// it out.
+#[cfg(feature = "zip")]
+fn read_entry<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    index: usize,
+    mapper: &mut EntryMapper,
+) -> anyhow::Result<Option<MemEntry>> {
+    let mut file = archive.by_index(index)?;
+
+    let in_path = zip_entry_path(&file)?;
+    let Some(rel_path) = mapper.map(&in_path)? else {
+        return Ok(None);
+    };
+    read_zip_file(&mut file, rel_path, index, mapper.options)
+}
+
+/// Reads `file`, entry `index` of its archive, into memory as `rel_path`,
+/// `None` if the filters or [`ExtractOptions::entry_hook`] of `options`
+/// leave it out.
+#[cfg(feature = "zip")]
+fn read_zip_file<R: Read + ?Sized>(
+    file: &mut ZipFile<'_, R>,
+    rel_path: PathBuf,
+    index: usize,
+    options: &ExtractOptions,
+) -> anyhow::Result<Option<MemEntry>> {
+    let is_dir = file.name().ends_with('/');
+    let unix_mode = file.unix_mode();
+    let filtered = !is_dir && !is_symlink(unix_mode);
+    if filtered && options.skips(&rel_path, file.size(), &[]) {
+        return Ok(None);
+    }
+
+    let (data_size, data) = if is_dir {
+        (0, Vec::new())
+    } else {
+        let size = file.size();
+        let mut buf = Vec::with_capacity(size as usize);
+        io::copy(file, &mut buf)?;
+        (size, buf)
+    };
+    if filtered && options.skips(&rel_path, data_size, &data) {
+        return Ok(None);
+    }
+    let data = if filtered { options.with_eol(data) } else { data };
+
+    options.hook(MemEntry {
+        rel_path,
+        is_dir,
+        _data_size: data_size,
+        unix_mode,
+        _file_idx: index,
+        data,
+    })
+}
+
+#[cfg(feature = "zip")]
+fn extract_batched<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    dest_dir: &Path,
+    options: &ExtractOptions,
+) -> anyhow::Result<u64> {
+    let progress = options.progress.as_ref();
+    let mut mapper = EntryMapper::new(options, dest_dir);
+    mapper.guard_collisions(dest_dir)?;
+    let mut buf = vec![0u8; STREAM_BUFFER_BYTES];
+    let mut batch: Vec<MemEntry> = Vec::new();
+    let mut batch_size: u64 = 0;
+    let mut written = 0;
+
+    let mut order: Vec<usize> = (0..archive.len()).collect();
+    if options.deterministic {
+        order.sort_by_key(|&i| archive.name_for_index(i));
+    }
+    for i in order {
+        if progress.is_cancelled() {
+            return Err(cancelled().into());
+        }
+        let mut file = archive.by_index(i)?;
+
+        let in_path = zip_entry_path(&file)?;
+        let Some(rel_path) = mapper.map(&in_path)? else {
+            progress.extract_progress(1);
+            continue;
+        };
+        written += 1;
+
+        let is_dir = file.name().ends_with('/');
+        let size = if is_dir { 0 } else { file.size() };
+        let filtered = !is_dir && !is_symlink(file.unix_mode());
+        if filtered && options.skips(&rel_path, size, &[]) {
+            progress.extract_progress(1);
+            continue;
+        }
+        if size > options.memory_budget && options.entry_hook.is_none() {
+            // Too big to ever buffer.
+            write_zip_file(&mut file, dest_dir, &rel_path, options, &mut buf)?;
+            progress.extract_progress(1);
+            continue;
+        }
+        if batch_size + size > options.memory_budget {
+            write_batch(
+                std::mem::take(&mut batch),
+                batch_size,
+                dest_dir,
+                options,
+            )?;
+            batch_size = 0;
+        }
+
+        let mut data = Vec::with_capacity(size as usize);
+        if !is_dir {
+            io::copy(&mut file, &mut data)?;
+        }
+        if filtered && options.skips(&rel_path, size, &data) {
+            progress.extract_progress(1);
+            continue;
+        }
+        if filtered {
+            data = options.with_eol(data);
+        }
+        let Some(entry) = options.hook(MemEntry {
+            rel_path,
+            is_dir,
+            _data_size: size,
+            unix_mode: file.unix_mode(),
+            _file_idx: i,
+            data,
+        })?
+        else {
+            progress.extract_progress(1);
+            continue;
+        };
+        batch_size += size;
+        batch.push(entry);
+    }
+    write_batch(batch, batch_size, dest_dir, options)?;
+
+    Ok(written + mapper.skipped)
+}
+
+/// Writes buffered entries holding `size` bytes, in parallel if that's
+/// worth it and the `parallel` feature is on.
+#[cfg(feature = "zip")]
+fn write_batch(
+    batch: Vec<MemEntry>,
+    size: u64,
+    dest_dir: &Path,
+    options: &ExtractOptions,
+) -> anyhow::Result<()> {
+    let progress = options.progress.as_ref();
+    #[cfg(feature = "parallel")]
+    if size > PARALLEL_THRESHOLD_BYTES && !options.deterministic {
+        return batch.into_par_iter().try_for_each(
+            |entry| -> anyhow::Result<()> {
+                if progress.is_cancelled() {
+                    return Err(cancelled().into());
+                }
+                write_entry(&entry, dest_dir)?;
+                progress.extract_progress(1);
+                Ok(())
+            },
+        );
+    }
+    #[cfg(not(feature = "parallel"))]
+    let _ = size;
+
+    for entry in batch {
+        if progress.is_cancelled() {
+            return Err(cancelled().into());
+        }
+        write_entry(&entry, dest_dir)?;
+        progress.extract_progress(1);
+    }
+    Ok(())
+}
+
+/// Extracts entries from a non-seekable zip stream as their local headers
+/// arrive, returning how many entries were written.
+///
+/// This fails on entries that defer their sizes to a trailing data
+/// descriptor; callers are expected to fall back to [`extract_zip_with`] on
+/// the complete archive in that case.
+#[cfg(feature = "zip")]
+pub fn extract_zip_stream(
+    mut reader: impl Read,
+    dest_dir: &Path,
+    options: &ExtractOptions,
+) -> anyhow::Result<u64> {
+    let mut mapper = EntryMapper::new(options, dest_dir);
+    let mut buf = vec![0u8; STREAM_BUFFER_BYTES];
+    let mut count = 0;
+
+    create_dir_all(dest_dir)?;
+    mapper.guard_collisions(dest_dir)?;
+
+    while let Some(mut file) = read_zipfile_from_stream(&mut reader)? {
+        if options.progress.is_cancelled() {
+            return Err(cancelled().into());
+        }
+        let in_path = zip_entry_path(&file)?;
+        let Some(rel_path) = mapper.map(&in_path)? else {
+            continue;
+        };
+
+        if options.entry_hook.is_some() {
+            let index = count as usize;
+            if let Some(entry) =
+                read_zip_file(&mut file, rel_path, index, options)?
+            {
+                write_entry(&entry, dest_dir)?;
+            }
+        } else {
+            write_zip_file(&mut file, dest_dir, &rel_path, options, &mut buf)?;
+        }
+        count += 1;
+    }
+
+    check_subpath_matched(options, count + mapper.skipped)?;
+    Ok(count)
+}
+
+/// Reads the entries of a zip stream into memory one at a time, without
+/// writing anything, so callers can index or transform them as they see
+/// fit. Paths have the top-level folder stripped like [`extract_zip`] does.
+///
+/// Like [`extract_zip_stream`], this yields an error for entries that defer
+/// their sizes to a trailing data descriptor, and file modes are only known
+/// when the local headers carry them.
+#[cfg(feature = "zip")]
+pub fn iter_zip_entries(
+    reader: impl Read,
+) -> impl Iterator<Item = anyhow::Result<MemEntry>> {
+    static DEFAULT_OPTIONS: Lazy<ExtractOptions> =
+        Lazy::new(ExtractOptions::default);
+    iter_zip_entries_with(reader, &DEFAULT_OPTIONS)
+}
+
+/// [`iter_zip_entries`] with the path mapping and filters of `options`.
+/// [`ExtractOptions::skip_existing`] doesn't apply, since nothing is
+/// written.
+#[cfg(feature = "zip")]
+pub fn iter_zip_entries_with(
+    reader: impl Read,
+    options: &ExtractOptions,
+) -> impl Iterator<Item = anyhow::Result<MemEntry>> {
+    let mut mapper = EntryMapper::new(options, Path::new(""));
+    mapper.existing_in = None;
+    ZipEntries {
+        reader,
+        mapper,
+        index: 0,
+        done: false,
+    }
+}
+
+#[cfg(feature = "zip")]
+struct ZipEntries<'a, R> {
+    reader: R,
+    mapper: EntryMapper<'a>,
+    index:  usize,
+    done:   bool,
+}
+
+#[cfg(feature = "zip")]
+impl<R: Read> Iterator for ZipEntries<'_, R> {
+    type Item = anyhow::Result<MemEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let mut file = match read_zipfile_from_stream(&mut self.reader) {
+                Ok(Some(file)) => file,
+                Ok(None) => break,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e.into()));
+                },
+            };
+            let index = self.index;
+            self.index += 1;
+
+            let rel_path = match zip_entry_path(&file)
+                .and_then(|in_path| self.mapper.map(&in_path))
+            {
+                Ok(Some(rel_path)) => rel_path,
+                Ok(None) => continue,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                },
+            };
+
+            match read_zip_file(&mut file, rel_path, index, self.mapper.options)
+            {
+                Ok(Some(entry)) => return Some(Ok(entry)),
+                Ok(None) => continue,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                },
+            }
+        }
+        self.done = true;
+        None
+    }
+}
+
+/// Decompresses `file` to `rel_path` below `dest_dir` through `buf`, unless
+/// [`ExtractOptions::max_file_size`] or [`ExtractOptions::skip_binaries`]
+/// leave it out, and converts [`ExtractOptions::eol`]. Both streaming
+/// extractors go through here so they treat entries the same way
+/// [`write_entry`] does for buffered ones.
+#[cfg(feature = "zip")]
+fn write_zip_file<R: Read + ?Sized>(
+    file: &mut ZipFile<'_, R>,
+    dest_dir: &Path,
+    rel_path: &Path,
+    options: &ExtractOptions,
+    buf: &mut [u8],
+) -> anyhow::Result<()> {
+    if file.name().ends_with('/') {
+        create_contained_dir(dest_dir, rel_path)?;
+        return Ok(());
+    }
+
+    if is_symlink(file.unix_mode()) {
+        let mut target = Vec::new();
+        file.read_to_end(&mut target)?;
+        if !write_symlink(dest_dir, rel_path, &target)? {
+            File::create(create_file_path(dest_dir, rel_path)?)?
+                .write_all(&target)?;
+        }
+        return Ok(());
+    }
+
+    let (size, mode) = (file.size(), file.unix_mode());
+    write_streamed(file, dest_dir, rel_path, size, mode, options, buf)
+}
+
+/// Writes the `size` bytes of `reader` to the file `rel_path` below
+/// `dest_dir` through `buf`, with permissions `unix_mode`, unless
+/// [`ExtractOptions::skips`] it, converting [`ExtractOptions::eol`] on the
+/// way.
+fn write_streamed(
+    reader: &mut impl Read,
+    dest_dir: &Path,
+    rel_path: &Path,
+    size: u64,
+    unix_mode: Option<u32>,
+    options: &ExtractOptions,
+    buf: &mut [u8],
+) -> anyhow::Result<()> {
+    // Sniff the start of the file before anything is created for it.
+    let head = if options.skip_binaries || options.eol != Eol::Keep {
+        read_head(reader, &mut buf[..BINARY_SNIFF_BYTES])?
+    } else {
+        0
+    };
+    if options.skips(rel_path, size, &buf[..head]) {
+        return Ok(());
+    }
+
+    let outpath = create_file_path(dest_dir, rel_path)?;
+    let mut outfile = File::create(&outpath)?;
+    match options.eol.newline() {
+        Some(newline) if !looks_binary(&buf[..head]) => {
+            copy_with_eol(reader, &mut outfile, head, buf, newline)?;
+        },
+        _ => {
+            outfile.write_all(&buf[..head])?;
+            copy_with_buffer(reader, &mut outfile, buf)?;
+        },
+    }
+
+    #[cfg(unix)]
+    if let Some(mode) = unix_mode {
+        let _ = set_permissions(&outpath, Permissions::from_mode(mode));
+    }
+    Ok(())
+}
+
+/// Fills `buf` from `reader` as far as it goes, returning how many bytes
+/// were read.
+fn read_head(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Like [`copy_with_buffer`], converting line endings to `newline`. The
+/// first `head` bytes of `buf` were already read from `reader`.
+fn copy_with_eol(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    head: usize,
+    buf: &mut [u8],
+    newline: &'static [u8],
+) -> io::Result<()> {
+    let mut converter = EolConverter::new(newline);
+    let mut out = Vec::with_capacity(buf.len() * 2);
+    let mut n = head;
+    loop {
+        converter.convert(&buf[..n], &mut out);
+        writer.write_all(&out)?;
+        out.clear();
+        n = match reader.read(buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => 0,
+            Err(e) => return Err(e),
+        };
+    }
+    converter.finish(&mut out);
+    writer.write_all(&out)
+}
+
+fn copy_with_buffer(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    buf: &mut [u8],
+) -> io::Result<u64> {
+    let mut written = 0;
+    loop {
+        let n = match reader.read(buf) {
+            Ok(0) => return Ok(written),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buf[..n])?;
+        written += n as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::read_to_string,
+        io::Cursor,
+        sync::atomic::{AtomicU64, Ordering},
+    };
+
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    use super::*;
+
+    fn build_zip(dir: &Path, files: &[(&str, &str)]) -> PathBuf {
+        let path = dir.join("fixture.zip");
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        let opts = SimpleFileOptions::default();
+
+        zip.add_directory("owner-repo-abc123/", opts).unwrap();
+        for (name, contents) in files {
+            zip.start_file(format!("owner-repo-abc123/{}", name), opts)
+                .unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn test_write_entry_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dest = temp_dir.path();
+
+        let entry = MemEntry {
+            rel_path:   PathBuf::from("test.txt"),
+            is_dir:     false,
+            _data_size: 11,
+            unix_mode:  Some(0o644),
+            _file_idx:  0,
+            data:       b"hello world".to_vec(),
+        };
+
+        write_entry(&entry, dest).unwrap();
+
+        let file_path = dest.join("test.txt");
+        assert!(file_path.exists());
+        let content = read_to_string(&file_path).unwrap();
+        assert_eq!(content, "hello world");
+    }
+
+    #[test]
+    fn test_write_entry_nested_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dest = temp_dir.path();
+
+        let entry = MemEntry {
+            rel_path:   PathBuf::from("nested/dir/test.txt"),
+            is_dir:     false,
+            _data_size: 5,
+            unix_mode:  Some(0o644),
+            _file_idx:  0,
+            data:       b"hello".to_vec(),
+        };
+
+        write_entry(&entry, dest).unwrap();
+
+        let file_path = dest.join("nested/dir/test.txt");
+        assert!(file_path.exists());
+        let content = read_to_string(&file_path).unwrap();
+        assert_eq!(content, "hello");
+    }
+
+    #[test]
+    fn test_write_entry_directory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dest = temp_dir.path();
+
+        let entry = MemEntry {
+            rel_path:   PathBuf::from("mydir"),
+            is_dir:     true,
+            _data_size: 0,
+            unix_mode:  None,
+            _file_idx:  0,
+            data:       Vec::new(),
+        };
+
+        write_entry(&entry, dest).unwrap();
+
+        let dir_path = dest.join("mydir");
+        assert!(dir_path.is_dir());
+    }
+
+    #[test]
+    fn test_mem_entry_debug() {
+        let entry = MemEntry {
+            rel_path:   PathBuf::from("test.txt"),
+            is_dir:     false,
+            _data_size: 5,
+            unix_mode:  Some(0o644),
+            _file_idx:  0,
+            data:       b"hello".to_vec(),
+        };
+
+        let debug_str = format!("{:?}", entry);
+        assert!(debug_str.contains("test.txt"));
+        assert!(debug_str.contains("false"));
+    }
+
+    #[test]
+    fn test_extract_zip_strips_root_folder() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let zip_path = build_zip(
+            temp_dir.path(),
+            &[("README.md", "readme"), ("src/lib.rs", "fn main() {}")],
+        );
+        let dest = temp_dir.path().join("out");
+
+        extract_zip(&zip_path, &dest).unwrap();
+
+        assert_eq!(read_to_string(dest.join("README.md")).unwrap(), "readme");
+        assert_eq!(
+            read_to_string(dest.join("src/lib.rs")).unwrap(),
+            "fn main() {}"
+        );
+    }
+
+    #[test]
+    fn test_archive_commit_reads_comment_then_root_folder() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let zip_path = build_zip(temp_dir.path(), &[("a.txt", "a")]);
+        assert_eq!(archive_commit(&zip_path).unwrap(), None);
+
+        let sha = "0123456789abcdef0123456789abcdef01234567";
+        let path = temp_dir.path().join("commented.zip");
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        zip.set_comment(sha);
+        zip.add_directory("owner-repo-0123456/", SimpleFileOptions::default())
+            .unwrap();
+        zip.finish().unwrap();
+        assert_eq!(archive_commit(&path).unwrap().as_deref(), Some(sha));
+
+        let path = temp_dir.path().join("bare.zip");
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        zip.add_directory("owner-repo-0123456/", SimpleFileOptions::default())
+            .unwrap();
+        zip.finish().unwrap();
+        assert_eq!(archive_commit(&path).unwrap().as_deref(), Some("0123456"));
+
+        let path = temp_dir.path().join("repo-0123456.tar.gz");
+        let gz = flate2::write::GzEncoder::new(
+            File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        tar::Builder::new(gz).into_inner().unwrap().finish().unwrap();
+        assert_eq!(archive_commit(&path).unwrap(), None);
+    }
+
+    #[test]
+    fn test_extract_zip_strip_root_modes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let zip_path = build_zip(
+            temp_dir.path(),
+            &[("README.md", "r"), ("src/a.rs", "a")],
+        );
+
+        let kept = temp_dir.path().join("kept");
+        let options = ExtractOptions {
+            strip_root: StripRoot::Never,
+            ..Default::default()
+        };
+        extract_zip_with(&zip_path, &kept, &options).unwrap();
+        assert!(kept.join("owner-repo-abc123/README.md").exists());
+
+        let stripped = temp_dir.path().join("stripped");
+        let options = ExtractOptions {
+            strip_root: StripRoot::Always(2),
+            ..Default::default()
+        };
+        extract_zip_with(&zip_path, &stripped, &options).unwrap();
+        assert!(stripped.join("a.rs").exists());
+        assert!(!stripped.join("README.md").exists());
+    }
+
+    #[test]
+    fn test_extract_zip_streaming_matches_in_memory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let zip_path = build_zip(
+            temp_dir.path(),
+            &[("a.txt", "alpha"), ("nested/b.txt", "beta")],
+        );
+        let dest = temp_dir.path().join("out");
+        let options = ExtractOptions {
+            memory_budget: 0,
+            ..Default::default()
+        };
+
+        extract_zip_with(&zip_path, &dest, &options).unwrap();
+
+        assert_eq!(read_to_string(dest.join("a.txt")).unwrap(), "alpha");
+        assert_eq!(read_to_string(dest.join("nested/b.txt")).unwrap(), "beta");
+    }
+
+    #[test]
+    fn test_extract_zip_spills_entries_over_budget() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let big = "x".repeat(64);
+        let zip_path = build_zip(
+            temp_dir.path(),
+            &[("a.txt", "alpha"), ("big.bin", &big), ("b.txt", "beta")],
+        );
+        let dest = temp_dir.path().join("out");
+        let options = ExtractOptions {
+            memory_budget: 8,
+            ..Default::default()
+        };
+
+        extract_zip_with(&zip_path, &dest, &options).unwrap();
+
+        assert_eq!(read_to_string(dest.join("a.txt")).unwrap(), "alpha");
+        assert_eq!(read_to_string(dest.join("big.bin")).unwrap(), big);
+        assert_eq!(read_to_string(dest.join("b.txt")).unwrap(), "beta");
+    }
+
+    #[derive(Default)]
+    struct EntryCounter {
+        total: AtomicU64,
+        done:  AtomicU64,
+    }
+
+    impl ProgressSink for EntryCounter {
+        fn extract_started(&self, total_entries: u64) {
+            self.total.store(total_entries, Ordering::Relaxed);
+        }
+
+        fn extract_progress(&self, entries: u64) {
+            self.done.fetch_add(entries, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_extract_zip_reports_every_entry() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let zip_path =
+            build_zip(temp_dir.path(), &[("a.txt", "a"), ("b.txt", "b")]);
+        let counter = Arc::new(EntryCounter::default());
+        let options = ExtractOptions {
+            progress: counter.clone(),
+            ..Default::default()
+        };
+
+        extract_zip_with(&zip_path, &temp_dir.path().join("out"), &options)
+            .unwrap();
+
+        assert_eq!(counter.total.load(Ordering::Relaxed), 3);
+        assert_eq!(counter.done.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn test_extract_zip_stream_from_reader() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let zip_path = build_zip(
+            temp_dir.path(),
+            &[("a.txt", "alpha"), ("nested/b.txt", "beta")],
+        );
+        let dest = temp_dir.path().join("out");
+
+        let count = extract_zip_stream(
+            File::open(&zip_path).unwrap(),
+            &dest,
+            &ExtractOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(count, 2);
+        assert_eq!(read_to_string(dest.join("a.txt")).unwrap(), "alpha");
+        assert_eq!(read_to_string(dest.join("nested/b.txt")).unwrap(), "beta");
+    }
+
+    #[test]
+    fn test_iter_zip_entries_reads_without_writing() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let zip_path = build_zip(
+            temp_dir.path(),
+            &[("a.txt", "alpha"), ("nested/b.txt", "beta")],
+        );
+
+        let entries: Vec<MemEntry> =
+            iter_zip_entries(File::open(&zip_path).unwrap())
+                .collect::<anyhow::Result<_>>()
+                .unwrap();
+
+        let files: Vec<(PathBuf, &[u8])> = entries
+            .iter()
+            .filter(|e| !e.is_dir)
+            .map(|e| (e.rel_path.clone(), e.data.as_slice()))
+            .collect();
+        assert_eq!(
+            files,
+            [
+                (PathBuf::from("a.txt"), &b"alpha"[..]),
+                (PathBuf::from("nested/b.txt"), &b"beta"[..]),
+            ]
+        );
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_extract_zip_into_memory_target() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let zip_path = build_zip(
+            temp_dir.path(),
+            &[("a.txt", "alpha"), ("nested/b.txt", "beta")],
+        );
+        let archive = std::fs::read(&zip_path).unwrap();
+
+        let mut target = MemoryTarget::default();
+        let written = extract_zip_into(
+            Cursor::new(archive),
+            &mut target,
+            &ExtractOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(written, 2);
+        assert_eq!(target.files[Path::new("a.txt")], b"alpha");
+        assert_eq!(target.files[Path::new("nested/b.txt")], b"beta");
+    }
+
+    #[derive(Default)]
+    struct SkipLog(std::sync::Mutex<Vec<SkippedFile>>);
+
+    impl ProgressSink for SkipLog {
+        fn file_skipped(&self, file: &SkippedFile) {
+            self.0.lock().unwrap().push(file.clone());
+        }
+    }
+
+    #[test]
+    fn test_extract_zip_skips_large_and_binary_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let zip_path = build_zip(
+            temp_dir.path(),
+            &[
+                ("README.md", "readme"),
+                ("fixtures/big.txt", "0123456789abcdef"),
+                ("logo.png", "\u{89}PNG\0\0"),
+            ],
+        );
+
+        // Both the buffered and the streaming path.
+        for memory_budget in [DEFAULT_MEMORY_BUDGET, 0] {
+            let dest = temp_dir.path().join(format!("out-{}", memory_budget));
+            let log = Arc::new(SkipLog::default());
+            let options = ExtractOptions {
+                memory_budget,
+                progress: log.clone(),
+                max_file_size: Some(10),
+                skip_binaries: true,
+                ..Default::default()
+            };
+
+            extract_zip_with(&zip_path, &dest, &options).unwrap();
+
+            assert!(dest.join("README.md").exists());
+            assert!(!dest.join("fixtures").exists());
+            assert!(!dest.join("logo.png").exists());
+            let mut skipped = log.0.lock().unwrap().clone();
+            skipped.sort_by(|a, b| a.path.cmp(&b.path));
+            assert_eq!(
+                skipped
+                    .iter()
+                    .map(|f| (f.path.to_str().unwrap(), f.reason))
+                    .collect::<Vec<_>>(),
+                vec![
+                    ("fixtures/big.txt", SkipReason::TooLarge),
+                    ("logo.png", SkipReason::Binary),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_entry_hook_rewrites_entries() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let zip_path = build_zip(
+            temp_dir.path(),
+            &[("a.txt", "alpha"), (".env", "SECRET=1"), ("b.txt", "beta")],
+        );
+        let hook = |entry: &mut MemEntry| match entry.rel_path.to_str() {
+            Some("a.txt") => {
+                entry.rel_path = PathBuf::from("docs/a.md");
+                EntryAction::Keep
+            },
+            Some(".env") => EntryAction::Replace(b"SECRET=".to_vec()),
+            Some("b.txt") => EntryAction::Skip,
+            _ => EntryAction::Keep,
+        };
+
+        // Even entries over the budget reach the hook.
+        for memory_budget in [DEFAULT_MEMORY_BUDGET, 0] {
+            let dest = temp_dir.path().join(format!("out-{}", memory_budget));
+            let options = ExtractOptions {
+                memory_budget,
+                entry_hook: Some(Arc::new(hook)),
+                ..Default::default()
+            };
+
+            extract_zip_with(&zip_path, &dest, &options).unwrap();
+
+            assert_eq!(
+                read_to_string(dest.join("docs/a.md")).unwrap(),
+                "alpha"
+            );
+            assert_eq!(read_to_string(dest.join(".env")).unwrap(), "SECRET=");
+            assert!(!dest.join("a.txt").exists());
+            assert!(!dest.join("b.txt").exists());
+        }
+
+        let options = ExtractOptions {
+            entry_hook: Some(Arc::new(|entry: &mut MemEntry| {
+                entry.rel_path = PathBuf::from("../escaped");
+                EntryAction::Keep
+            })),
+            ..Default::default()
+        };
+        let dest = temp_dir.path().join("unsafe");
+        assert!(extract_zip_with(&zip_path, &dest, &options).is_err());
+        assert!(!temp_dir.path().join("escaped").exists());
+    }
+
+    #[test]
+    fn test_extract_zip_refuses_paths_leaving_dest() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let zip_path = temp_dir.path().join("slip.zip");
+        let mut zip = ZipWriter::new(File::create(&zip_path).unwrap());
+        zip.start_file("../DOTDOT.txt", SimpleFileOptions::default()).unwrap();
+        zip.write_all(b"escaped").unwrap();
+        zip.finish().unwrap();
+        let dest = temp_dir.path().join("a/out");
+        let options = ExtractOptions::default();
+
+        let err = extract_zip(&zip_path, &dest).unwrap_err();
+        assert!(err.to_string().contains("Unsafe path"), "{}", err);
+        assert!(extract_archive_with(&zip_path, &dest, &options).is_err());
+        let stream = File::open(&zip_path).unwrap();
+        assert!(extract_zip_stream(stream, &dest, &options).is_err());
+        let stream = File::open(&zip_path).unwrap();
+        assert!(iter_zip_entries(stream).any(|entry| entry.is_err()));
+        assert!(!temp_dir.path().join("a/DOTDOT.txt").exists());
+        assert!(!temp_dir.path().join("DOTDOT.txt").exists());
+    }
+
+    #[test]
+    fn test_eol_converter_handles_split_crlf() {
+        let mut out = Vec::new();
+        let mut converter = EolConverter::new(b"\n");
+        converter.convert(b"a\r\nb\r", &mut out);
+        converter.convert(b"\nc\rd\r", &mut out);
+        converter.finish(&mut out);
+        assert_eq!(out, b"a\nb\nc\rd\r");
+
+        let mut out = Vec::new();
+        let mut converter = EolConverter::new(b"\r\n");
+        converter.convert(b"a\nb\r\n", &mut out);
+        converter.finish(&mut out);
+        assert_eq!(out, b"a\r\nb\r\n");
+    }
+
+    #[test]
+    fn test_extract_zip_converts_eol_of_text_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let zip_path = build_zip(
+            temp_dir.path(),
+            &[("README.md", "a\nb\r\n"), ("data.bin", "x\0\n")],
+        );
+
+        for memory_budget in [DEFAULT_MEMORY_BUDGET, 0] {
+            let dest = temp_dir.path().join(format!("out-{}", memory_budget));
+            let options = ExtractOptions {
+                memory_budget,
+                eol: Eol::Crlf,
+                ..Default::default()
+            };
+
+            extract_zip_with(&zip_path, &dest, &options).unwrap();
+
+            assert_eq!(
+                read_to_string(dest.join("README.md")).unwrap(),
+                "a\r\nb\r\n"
+            );
+            assert_eq!(std::fs::read(dest.join("data.bin")).unwrap(), b"x\0\n");
+        }
+    }
+
+    #[test]
+    fn test_extract_zip_excludes_meta_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let zip_path = build_zip(
+            temp_dir.path(),
+            &[
+                ("README.md", "readme"),
+                (".github/workflows/ci.yml", "on: push"),
+                (".gitlab-ci.yml", "test:"),
+                ("docs/CODEOWNERS", "* @me"),
+                ("src/github.rs", "mod github;"),
+            ],
+        );
+        let dest = temp_dir.path().join("out");
+        let options = ExtractOptions {
+            exclude: Some(exclude_set(STRIP_META_PATTERNS).unwrap()),
+            ..Default::default()
+        };
+
+        extract_zip_with(&zip_path, &dest, &options).unwrap();
+
+        assert!(dest.join("README.md").exists());
+        assert!(dest.join("src/github.rs").exists());
+        assert!(!dest.join(".github").exists());
+        assert!(!dest.join(".gitlab-ci.yml").exists());
+        assert!(!dest.join("docs/CODEOWNERS").exists());
+    }
+
+    #[test]
+    fn test_deterministic_extraction_normalizes_metadata() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let zip_path = build_zip(
+            temp_dir.path(),
+            &[("b.txt", "b"), ("a/run.sh", "#!/bin/sh"), ("a/c.txt", "c")],
+        );
+        let dest = temp_dir.path().join("out");
+        let options = ExtractOptions {
+            deterministic: true,
+            ..Default::default()
+        };
+        extract_zip_with(&zip_path, &dest, &options).unwrap();
+        set_permissions(dest.join("a"), Permissions::from_mode(0o700)).unwrap();
+        set_permissions(dest.join("a/run.sh"), Permissions::from_mode(0o700))
+            .unwrap();
+
+        normalize_tree(&dest).unwrap();
+
+        let mtime = UNIX_EPOCH + Duration::from_secs(DETERMINISTIC_MTIME);
+        for (rel, mode) in
+            [("", 0o755), ("a", 0o755), ("a/run.sh", 0o755), ("b.txt", 0o644)]
+        {
+            let meta = std::fs::metadata(dest.join(rel)).unwrap();
+            assert_eq!(meta.permissions().mode() & 0o777, mode, "{}", rel);
+            assert_eq!(meta.modified().unwrap(), mtime, "{}", rel);
+        }
+    }
+
+    #[test]
+    fn test_extract_zip_skip_existing_keeps_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let zip_path =
+            build_zip(temp_dir.path(), &[("a.txt", "new"), ("b.txt", "new")]);
+        let dest = temp_dir.path().join("out");
+        create_dir_all(&dest).unwrap();
+        std::fs::write(dest.join("a.txt"), "old").unwrap();
+        let options = ExtractOptions {
+            skip_existing: true,
+            ..Default::default()
+        };
+
+        extract_zip_with(&zip_path, &dest, &options).unwrap();
+
+        assert_eq!(read_to_string(dest.join("a.txt")).unwrap(), "old");
+        assert_eq!(read_to_string(dest.join("b.txt")).unwrap(), "new");
+    }
+
+    #[test]
+    fn test_extracted_size_counts_filtered_entries() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let zip_path = build_zip(
+            temp_dir.path(),
+            &[("README.md", "root"), ("crates/foo/lib.rs", "foo")],
+        );
+        let mut archive =
+            ZipArchive::new(File::open(&zip_path).unwrap()).unwrap();
+        let options = ExtractOptions {
+            subpath: Some(PathBuf::from("crates/foo")),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            extracted_size(&mut archive, &ExtractOptions::default()).unwrap(),
+            7
+        );
+        assert_eq!(extracted_size(&mut archive, &options).unwrap(), 3);
+        assert!(free_space(temp_dir.path()).unwrap() > 0);
+    }
+
+    #[test]
+    fn test_extract_zip_subpath_only() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let zip_path = build_zip(
+            temp_dir.path(),
+            &[
+                ("README.md", "root"),
+                ("crates/foo/lib.rs", "foo"),
+                ("crates/bar/lib.rs", "bar"),
+            ],
+        );
+        let dest = temp_dir.path().join("out");
+        let options = ExtractOptions {
+            subpath: Some(PathBuf::from("crates/foo")),
+            ..Default::default()
+        };
+
+        extract_zip_with(&zip_path, &dest, &options).unwrap();
+
+        assert_eq!(read_to_string(dest.join("lib.rs")).unwrap(), "foo");
+        assert!(!dest.join("README.md").exists());
+        assert!(!dest.join("crates").exists());
+    }
+
+    #[test]
+    fn test_extract_zip_missing_subpath_fails() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let zip_path = build_zip(temp_dir.path(), &[("a.txt", "a")]);
+        let options = ExtractOptions {
+            subpath: Some(PathBuf::from("nope")),
+            ..Default::default()
+        };
+
+        let err =
+            extract_zip_with(&zip_path, &temp_dir.path().join("out"), &options)
+                .unwrap_err();
+        assert!(err.to_string().contains("nope"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_entry_symlink() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dest = temp_dir.path();
+        let link = |rel_path: &str, target: &str| MemEntry {
+            rel_path:   PathBuf::from(rel_path),
+            is_dir:     false,
+            _data_size: target.len() as u64,
+            unix_mode:  Some(0o120777),
+            _file_idx:  0,
+            data:       target.as_bytes().to_vec(),
+        };
+
+        write_entry(&link("docs/latest", "../README.md"), dest).unwrap();
+        let meta = dest.join("docs/latest").symlink_metadata().unwrap();
+        assert!(meta.file_type().is_symlink());
+        assert_eq!(
+            std::fs::read_link(dest.join("docs/latest")).unwrap(),
+            PathBuf::from("../README.md")
+        );
+
+        // Links escaping the destination are written as plain files.
+        write_entry(&link("evil", "../../etc/passwd"), dest).unwrap();
+        let meta = dest.join("evil").symlink_metadata().unwrap();
+        assert!(meta.file_type().is_file());
+    }
+
+    #[test]
+    fn test_extract_archive_detects_tar_gz() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("proj-1.0.tar.gz");
+        let gz = flate2::write::GzEncoder::new(
+            File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut tar = tar::Builder::new(gz);
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        tar.append_link(&mut header, "proj-1.0/LATEST", "README.md").unwrap();
+        for (name, contents) in [("README.md", "readme"), ("bin/run", "#!")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(if name == "bin/run" { 0o755 } else { 0o644 });
+            tar.append_data(
+                &mut header,
+                format!("./proj-1.0/{}", name),
+                contents.as_bytes(),
+            )
+            .unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+        let dest = temp_dir.path().join("out");
+
+        extract_archive_with(&path, &dest, &ExtractOptions::default()).unwrap();
+
+        assert_eq!(read_to_string(dest.join("README.md")).unwrap(), "readme");
+        let mode = dest.join("bin/run").metadata().unwrap().permissions();
+        assert_eq!(mode.mode() & 0o777, 0o755);
+        assert_eq!(
+            std::fs::read_link(dest.join("LATEST")).unwrap(),
+            PathBuf::from("README.md")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_refuses_writing_through_chained_symlinks() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let zip_path = temp_dir.path().join("chain.zip");
+        let mut zip = ZipWriter::new(File::create(&zip_path).unwrap());
+        let opts = SimpleFileOptions::default();
+        zip.add_symlink("proj/a", ".", opts).unwrap();
+        zip.add_symlink("proj/a/b", "..", opts).unwrap();
+        zip.start_file("proj/a/b/ESCAPED.txt", opts).unwrap();
+        zip.write_all(b"escaped").unwrap();
+        zip.finish().unwrap();
+
+        let tar_path = temp_dir.path().join("chain.tar");
+        let mut tar = tar::Builder::new(File::create(&tar_path).unwrap());
+        for (name, target) in [("proj/a", "."), ("proj/a/b", "..")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            tar.append_link(&mut header, name, target).unwrap();
+        }
+        let mut header = tar::Header::new_gnu();
+        header.set_size(7);
+        tar.append_data(&mut header, "proj/a/b/ESCAPED.txt", &b"escaped"[..])
+            .unwrap();
+        tar.into_inner().unwrap();
+
+        let options = ExtractOptions::default();
+        let out = temp_dir.path().join("out");
+        let dest = out.join("zip");
+        assert!(extract_zip_with(&zip_path, &dest, &options).is_err());
+        let dest = out.join("stream");
+        let stream = File::open(&zip_path).unwrap();
+        assert!(extract_zip_stream(stream, &dest, &options).is_err());
+        let dest = out.join("tar");
+        assert!(extract_archive_with(&tar_path, &dest, &options).is_err());
+
+        assert!(!out.join("ESCAPED.txt").exists());
+        // The stream carries no modes, so only the others make links.
+        for dest in ["zip", "tar"] {
+            let link = out.join(dest).join("b").symlink_metadata().unwrap();
+            assert!(!link.file_type().is_symlink(), "{}", dest);
+        }
+    }
+
+    #[test]
+    fn test_extract_tar_streams_entries_over_budget() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("big.tar");
+        let mut tar = tar::Builder::new(File::create(&path).unwrap());
+        for (name, contents) in [("proj/run.sh", "a\r\nb\r\n"), ("proj/x", "")]
+        {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o755);
+            tar.append_data(&mut header, name, contents.as_bytes()).unwrap();
+        }
+        tar.into_inner().unwrap();
+        let options = ExtractOptions {
+            memory_budget: 2,
+            eol: Eol::Lf,
+            ..Default::default()
+        };
+        let dest = temp_dir.path().join("out");
+
+        extract_archive_with(&path, &dest, &options).unwrap();
+        assert_eq!(read_to_string(dest.join("run.sh")).unwrap(), "a\nb\n");
+        #[cfg(unix)]
+        {
+            let mode = dest.join("run.sh").metadata().unwrap().permissions();
+            assert_eq!(mode.mode() & 0o777, 0o755);
+        }
+
+        // A header claiming far more than the archive holds only fails.
+        let mut header = tar::Header::new_gnu();
+        header.set_path("proj/huge").unwrap();
+        header.set_size(1 << 60);
+        header.set_cksum();
+        let mut data = header.as_bytes().to_vec();
+        data.extend_from_slice(&[b'x'; 512]);
+        let dest = temp_dir.path().join("huge");
+        let options = ExtractOptions::default();
+        assert!(extract_tar_with(&data[..], &dest, &options).is_err());
+    }
+
+    #[test]
+    fn test_symlink_target_containment() {
+        assert!(symlink_target_is_contained(
+            Path::new("a/b"),
+            Path::new("../c")
+        ));
+        assert!(symlink_target_is_contained(
+            Path::new("a"),
+            Path::new("./b")
+        ));
+        assert!(!symlink_target_is_contained(
+            Path::new("a"),
+            Path::new("../b")
+        ));
+        assert!(!symlink_target_is_contained(
+            Path::new("a"),
+            Path::new("/etc")
+        ));
+    }
+}
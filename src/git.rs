@@ -0,0 +1,165 @@
+use std::{
+    fs::remove_dir_all,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::Mutex,
+};
+
+use anyhow::anyhow;
+use ignore::{DirEntry, Error, WalkBuilder, WalkState};
+use WalkState::Continue;
+
+#[cfg(not(feature = "git2"))]
+pub use crate::git_cli::{
+    add_upstream_remote, clone_with_history, commit_branch, commit_snapshot,
+    commit_vendored, initialize_bare_repo, initialize_repo_with, push_head,
+    remote_default_branch, set_config, set_origin, set_upstream_base, tag_head,
+};
+#[cfg(not(feature = "git2"))]
+pub(crate) use crate::git_cli::{
+    enclosing_repo, has_upstream_remote, is_bare_repo,
+};
+#[cfg(feature = "git2")]
+pub use crate::git_libgit2::{
+    add_upstream_remote, clone_with_history, commit_branch, commit_snapshot,
+    commit_vendored, initialize_bare_repo, initialize_repo_with, push_head,
+    remote_default_branch, set_config, set_origin, set_upstream_base, tag_head,
+};
+#[cfg(feature = "git2")]
+pub(crate) use crate::git_libgit2::{
+    enclosing_repo, has_upstream_remote, is_bare_repo,
+};
+use crate::{GitripperError, Result};
+
+pub const DEFAULT_COMMIT_MESSAGE: &str = "Initial commit";
+pub const DEFAULT_AUTHOR_NAME: &str = "gitripper";
+pub const DEFAULT_AUTHOR_EMAIL: &str = "gitripper@localhost";
+/// Remote [`add_upstream_remote`] registers the original repository as.
+pub const UPSTREAM_REMOTE: &str = "upstream";
+/// Ref the ripped upstream commit is fetched into.
+pub const UPSTREAM_BASE_REF: &str = "refs/gitripper/upstream-base";
+
+/// Removes every `.git` directory found under `dirpath`, except the
+/// repository of `dirpath` itself.
+///
+/// Each element of the result is either the path of a removed directory or a
+/// non-fatal error encountered while walking or removing.
+pub fn remove_embedded_git(dirpath: &Path) -> Vec<anyhow::Result<PathBuf>> {
+    let results = Mutex::new(Vec::new());
+    let mut builder = WalkBuilder::new(dirpath);
+    // Match the walker to the enclosing rayon pool so `jobs` bounds it too.
+    builder
+        .standard_filters(false)
+        .hidden(false)
+        .threads(rayon::current_num_threads());
+
+    builder.build_parallel().run(|| {
+        Box::new(|res: Result<DirEntry, Error>| {
+            let outcome = match res {
+                Ok(entry)
+                    if entry.depth() == 1 && entry.file_name() == ".git" =>
+                {
+                    return WalkState::Skip;
+                },
+                Ok(entry) => {
+                    if entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false)
+                        && entry.file_name() == ".git"
+                    {
+                        let git_dir = entry.path().to_path_buf();
+                        Some(match remove_dir_all(&git_dir) {
+                            Ok(_) => Ok(git_dir),
+                            Err(e) => Err(anyhow!(
+                                "failed to remove embedded .git at {}: {}",
+                                git_dir.display(),
+                                e
+                            )),
+                        })
+                    } else {
+                        None
+                    }
+                },
+                Err(e) => Some(Err(anyhow!("walker error: {}", e))),
+            };
+
+            if let Some(o) = outcome {
+                results.lock().unwrap().push(o);
+            }
+            Continue
+        })
+    });
+
+    results.into_inner().unwrap()
+}
+
+pub fn check_git_installed() -> Result<()> {
+    match Command::new("git")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+    {
+        Ok(s) if s.success() => Ok(()),
+        _ => Err(GitripperError::GitNotFound),
+    }
+}
+
+/// How [`initialize_repo_with`] sets up the new repository.
+#[derive(Debug, Clone, Default)]
+pub struct InitOptions {
+    pub author_name:       Option<String>,
+    pub author_email:      Option<String>,
+    /// URL registered as `origin`.
+    pub remote:            Option<String>,
+    /// Branch `HEAD` points at. Defaults to git's `init.defaultBranch`.
+    pub initial_branch:    Option<String>,
+    /// Defaults to [`DEFAULT_COMMIT_MESSAGE`].
+    pub message:           Option<String>,
+    /// Sign the commit with `user.signingkey`, honoring `gpg.format` like
+    /// `git commit -S` does.
+    pub sign:              bool,
+    /// Leave out files matched by the extracted `.gitignore` files. By
+    /// default everything in the snapshot is committed, since upstream
+    /// tracks those files despite the ignore rules.
+    pub respect_gitignore: bool,
+}
+
+pub fn initialize_repo(
+    dest: &Path,
+    author_name: Option<&str>,
+    author_email: Option<&str>,
+    remote: Option<&str>,
+) -> anyhow::Result<()> {
+    initialize_repo_with(
+        dest,
+        &InitOptions {
+            author_name: author_name.map(str::to_string),
+            author_email: author_email.map(str::to_string),
+            remote: remote.map(str::to_string),
+            ..Default::default()
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, write};
+
+    use super::*;
+
+    #[test]
+    fn test_remove_embedded_git_nested() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        create_dir_all(root.join("vendor/lib/.git/objects")).unwrap();
+        create_dir_all(root.join(".git")).unwrap();
+        write(root.join("vendor/lib/file.txt"), "x").unwrap();
+
+        let results = remove_embedded_git(root);
+
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_ok());
+        assert!(!root.join("vendor/lib/.git").exists());
+        assert!(root.join("vendor/lib/file.txt").exists());
+        assert!(root.join(".git").exists());
+    }
+}
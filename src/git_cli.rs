@@ -0,0 +1,646 @@
+use std::{
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+use anyhow::anyhow;
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::{
+    provider::is_commit_sha, InitOptions, DEFAULT_AUTHOR_EMAIL,
+    DEFAULT_AUTHOR_NAME, DEFAULT_COMMIT_MESSAGE, UPSTREAM_BASE_REF,
+    UPSTREAM_REMOTE,
+};
+
+/// `git` run in the repository at `dir`, without looking for one in its
+/// parent directories, like opening it with libgit2 would.
+fn git_at(dir: &Path) -> Command {
+    let mut cmd = git_in(dir);
+    if let Some(parent) = dir.parent().and_then(|p| p.canonicalize().ok()) {
+        cmd.env("GIT_CEILING_DIRECTORIES", parent);
+    }
+    cmd
+}
+
+/// `git` run in `dir`, in whatever repository that is part of.
+fn git_in(dir: &Path) -> Command {
+    let mut cmd = Command::new("git");
+    cmd.arg("-C").arg(dir);
+    cmd
+}
+
+/// Runs `cmd`, failing with its stderr unless it succeeds.
+fn run(cmd: &mut Command) -> anyhow::Result<String> {
+    let output = output(cmd)?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn output(cmd: &mut Command) -> anyhow::Result<Output> {
+    cmd.output().map_err(|e| anyhow!("failed to run git: {}", e))
+}
+
+/// Refuses `value`, a `what` such as a URL or ref, if git could take it for
+/// an option like `--upload-pack=<command>`. Positional arguments also go
+/// after `--end-of-options`, but `checkout -B` can't have one.
+fn positional<'a>(what: &str, value: &'a str) -> anyhow::Result<&'a str> {
+    if value.starts_with('-') {
+        return Err(anyhow!("'{}' isn't a valid {}", value, what));
+    }
+    Ok(value)
+}
+
+/// Adds `url` as the remote `name` of the repository at `dest`.
+fn add_remote(dest: &Path, name: &str, url: &str) -> anyhow::Result<String> {
+    run(git_at(dest).args(["remote", "add", "--end-of-options", name, url]))
+}
+
+/// Sets `key` to `value` in the config of the repository at `dest`.
+fn set_value(dest: &Path, key: &str, value: &str) -> anyhow::Result<String> {
+    run(git_at(dest).args(["config", "--end-of-options", key, value]))
+}
+
+/// Config for one command sending `user` and `password` the way libgit2's
+/// plaintext credentials do, without storing them in the repository.
+fn credential_args(credentials: Option<(&str, &str)>) -> Vec<String> {
+    match credentials {
+        Some((user, password)) => {
+            let basic = STANDARD.encode(format!("{}:{}", user, password));
+            vec![
+                "-c".to_string(),
+                format!("http.extraHeader=Authorization: Basic {}", basic),
+            ]
+        },
+        None => Vec::new(),
+    }
+}
+
+pub fn initialize_repo_with(
+    dest: &Path,
+    options: &InitOptions,
+) -> anyhow::Result<()> {
+    create_repo(dest, options, false)?;
+    commit_all(|| git_at(dest), options, false, ".")?;
+
+    if let Some(r) = &options.remote {
+        add_remote(dest, "origin", r)?;
+    }
+
+    Ok(())
+}
+
+/// Creates a bare repository at `dest` whose only commit holds the files
+/// under `tree`, which is left as is.
+pub fn initialize_bare_repo(
+    dest: &Path,
+    tree: &Path,
+    options: &InitOptions,
+) -> anyhow::Result<()> {
+    create_repo(dest, options, true)?;
+    let git = || {
+        let mut cmd = Command::new("git");
+        cmd.arg("--git-dir").arg(dest).arg("--work-tree").arg(tree);
+        cmd.current_dir(tree);
+        cmd
+    };
+    commit_all(git, options, false, ".")?;
+    // The index was only needed to build the tree.
+    fs::remove_file(dest.join("index"))?;
+
+    if let Some(r) = &options.remote {
+        add_remote(dest, "origin", r)?;
+    }
+
+    Ok(())
+}
+
+fn create_repo(
+    dest: &Path,
+    options: &InitOptions,
+    bare: bool,
+) -> anyhow::Result<()> {
+    fs::create_dir_all(dest)?;
+    let mut init = git_at(dest);
+    init.args(["init", "--quiet"]);
+    if bare {
+        init.arg("--bare");
+    }
+    if let Some(branch) = &options.initial_branch {
+        init.arg("--initial-branch").arg(branch);
+    }
+    run(&mut init)?;
+
+    if let Some(name) = &options.author_name {
+        set_value(dest, "user.name", name)?;
+    }
+    if let Some(email) = &options.author_email {
+        set_value(dest, "user.email", email)?;
+    }
+    Ok(())
+}
+
+/// Commits the current contents of the working tree of the repository at
+/// `dest` on top of `HEAD`, including deletions. Returns `None` without
+/// committing if nothing changed.
+///
+/// Only the author, message, signing and `.gitignore` settings of `options`
+/// are used.
+pub fn commit_snapshot(
+    dest: &Path,
+    options: &InitOptions,
+) -> anyhow::Result<Option<String>> {
+    commit_all(|| git_at(dest), options, true, ".")
+}
+
+/// Commits the files under `tree` to the repository at `dest` as the only
+/// commit of a new branch `branch`, sharing no history with the others.
+/// `HEAD`, the index and the working tree of `dest` are left alone. Returns
+/// the new commit.
+///
+/// Only the author, message, signing and `.gitignore` settings of `options`
+/// are used.
+pub fn commit_branch(
+    dest: &Path,
+    branch: &str,
+    tree: &Path,
+    options: &InitOptions,
+) -> anyhow::Result<String> {
+    let refname = format!("refs/heads/{}", branch);
+    run(git_at(dest).args(["check-ref-format", &refname]))
+        .map_err(|_| anyhow!("'{}' is not a valid branch name", branch))?;
+
+    // A throwaway index keeps the one of `dest` untouched.
+    let index = tempfile::tempdir()?;
+    let git_dir = dest.join(".git");
+    let git = || {
+        let mut cmd = Command::new("git");
+        cmd.arg("--git-dir").arg(&git_dir).arg("--work-tree").arg(tree);
+        cmd.current_dir(tree).env("GIT_INDEX_FILE", index.path().join("index"));
+        cmd
+    };
+    let mut add = git();
+    add.args(["add", "--all"]);
+    if !options.respect_gitignore {
+        add.arg("--force");
+    }
+    run(add.arg("--").arg("."))?;
+    let tree_id = run(git().arg("write-tree"))?;
+
+    let name = options.author_name.as_deref().unwrap_or(DEFAULT_AUTHOR_NAME);
+    let email = options.author_email.as_deref().unwrap_or(DEFAULT_AUTHOR_EMAIL);
+    let message = options.message.as_deref().unwrap_or(DEFAULT_COMMIT_MESSAGE);
+    let commit = run(git()
+        .args(["commit-tree", &tree_id])
+        .arg(if options.sign { "-S" } else { "--no-gpg-sign" })
+        .arg("-m")
+        .arg(message)
+        .env("GIT_AUTHOR_NAME", name)
+        .env("GIT_AUTHOR_EMAIL", email)
+        .env("GIT_COMMITTER_NAME", name)
+        .env("GIT_COMMITTER_EMAIL", email))?;
+    // Fails rather than replace a branch that already exists.
+    run(git_at(dest).args(["update-ref", &refname, &commit, ""]))?;
+    Ok(commit)
+}
+
+/// Creates the annotated tag `name` with `message` on `HEAD` of the
+/// repository at `dest`, which may be bare. Fails if the tag exists.
+///
+/// Only the author settings of `options` are used, for the tagger.
+pub fn tag_head(
+    dest: &Path,
+    name: &str,
+    message: &str,
+    options: &InitOptions,
+) -> anyhow::Result<()> {
+    let tagger = options.author_name.as_deref().unwrap_or(DEFAULT_AUTHOR_NAME);
+    let email = options.author_email.as_deref().unwrap_or(DEFAULT_AUTHOR_EMAIL);
+    run(git_at(dest)
+        .args(["tag", "--annotate", "--no-sign", "--message", message])
+        .args(["--end-of-options", name])
+        .env("GIT_COMMITTER_NAME", tagger)
+        .env("GIT_COMMITTER_EMAIL", email))?;
+    Ok(())
+}
+
+/// The working tree of the repository `dir` belongs to. `dir` itself need
+/// not exist yet.
+pub(crate) fn enclosing_repo(dir: &Path) -> anyhow::Result<PathBuf> {
+    let existing = dir
+        .ancestors()
+        .find(|p| p.exists())
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    if run(git_in(existing).args(["rev-parse", "--is-bare-repository"]))?
+        == "true"
+    {
+        return Err(anyhow!("{} is in a bare repository", dir.display()));
+    }
+    Ok(PathBuf::from(run(
+        git_in(existing).args(["rev-parse", "--show-toplevel"])
+    )?))
+}
+
+/// Commits the current contents of `dir` to the repository it is a
+/// subdirectory of, including deletions below `dir`, on top of `HEAD` if
+/// there is one. Changes already staged elsewhere are committed too, as
+/// with `git commit`. Returns `None` without committing if nothing changed.
+///
+/// Only the author, message, signing and `.gitignore` settings of `options`
+/// are used.
+pub fn commit_vendored(
+    dir: &Path,
+    options: &InitOptions,
+) -> anyhow::Result<Option<String>> {
+    let workdir = enclosing_repo(dir)?.canonicalize()?;
+    let relative = dir.canonicalize()?.strip_prefix(&workdir)?.to_path_buf();
+    let pathspec = relative
+        .to_str()
+        .ok_or_else(|| anyhow!("{} is not valid UTF-8", relative.display()))?;
+    if pathspec.is_empty() {
+        return Err(anyhow!(
+            "{} is the top of its repository, not a subdirectory",
+            dir.display()
+        ));
+    }
+
+    let has_head =
+        output(git_in(&workdir).args(["rev-parse", "-q", "--verify", "HEAD"]))?
+            .status
+            .success();
+    commit_all(|| git_in(&workdir), options, has_head, pathspec)
+}
+
+/// Stages `pathspec` and commits the index with `git` as made by `git`.
+/// With a `parent`, returns `None` without committing if the tree is the
+/// same as that of `HEAD`.
+fn commit_all(
+    git: impl Fn() -> Command,
+    options: &InitOptions,
+    parent: bool,
+    pathspec: &str,
+) -> anyhow::Result<Option<String>> {
+    let mut add = git();
+    add.args(["add", "--all"]);
+    if !options.respect_gitignore {
+        add.arg("--force");
+    }
+    run(add.arg("--").arg(pathspec))?;
+
+    if parent
+        && output(git().args(["diff", "--cached", "--quiet"]))?.status.success()
+    {
+        return Ok(None);
+    }
+
+    let name = options.author_name.as_deref().unwrap_or(DEFAULT_AUTHOR_NAME);
+    let email = options.author_email.as_deref().unwrap_or(DEFAULT_AUTHOR_EMAIL);
+    let message = options.message.as_deref().unwrap_or(DEFAULT_COMMIT_MESSAGE);
+
+    let mut commit = git();
+    commit
+        .args(["commit", "--quiet", "--allow-empty", "--no-verify"])
+        .arg(if options.sign { "--gpg-sign" } else { "--no-gpg-sign" })
+        .arg("--message")
+        .arg(message)
+        .env("GIT_AUTHOR_NAME", name)
+        .env("GIT_AUTHOR_EMAIL", email)
+        .env("GIT_COMMITTER_NAME", name)
+        .env("GIT_COMMITTER_EMAIL", email);
+    run(&mut commit)?;
+
+    Ok(Some(run(git().args(["rev-parse", "HEAD"]))?))
+}
+
+/// Fetches `reference` from `url` into a new repository at `dest` and checks
+/// it out with its history, returning the checked out commit.
+///
+/// `depth` limits how many commits are fetched. Branches and tags get a local
+/// branch of the same name; commit SHAs leave `HEAD` detached.
+pub fn clone_with_history(
+    url: &str,
+    dest: &Path,
+    reference: &str,
+    depth: Option<u32>,
+    credentials: Option<(&str, &str)>,
+) -> anyhow::Result<String> {
+    let url = positional("repository URL", url)?;
+    let reference = positional("ref", reference)?;
+    fs::create_dir_all(dest)?;
+    run(git_at(dest).args(["init", "--quiet"]))?;
+    add_remote(dest, "origin", url)?;
+
+    let mut fetch = git_at(dest);
+    fetch.args(credential_args(credentials)).args(["fetch", "--quiet"]);
+    if let Some(d) = depth {
+        fetch.arg(format!("--depth={}", d));
+    }
+    run(fetch.args(["--end-of-options", "origin", reference]))?;
+    let commit = run(git_at(dest).args(["rev-parse", "FETCH_HEAD^{commit}"]))?;
+
+    let mut checkout = git_at(dest);
+    checkout.args(["checkout", "--quiet", "--force"]);
+    if is_commit_sha(reference) {
+        checkout.args(["--detach", &commit]);
+    } else {
+        checkout.args(["-B", reference, &commit]);
+    }
+    run(&mut checkout)?;
+
+    Ok(commit)
+}
+
+/// Branch `HEAD` of the repository at `url` points at.
+pub fn remote_default_branch(url: &str) -> anyhow::Result<String> {
+    let url = positional("repository URL", url)?;
+    let listing = run(Command::new("git")
+        .args(["ls-remote", "--symref", "--end-of-options", url])
+        .arg("HEAD"))?;
+    listing
+        .lines()
+        .find_map(|line| line.strip_prefix("ref: ")?.split_once('\t'))
+        .map(|(head, _)| head.strip_prefix("refs/heads/").unwrap_or(head))
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("{} has no default branch", url))
+}
+
+/// Points `origin` at `url`, or removes it when `url` is `None`.
+pub fn set_origin(dest: &Path, url: Option<&str>) -> anyhow::Result<()> {
+    let has_origin =
+        output(git_at(dest).args(["remote", "get-url", "origin"]))?
+            .status
+            .success();
+
+    match url {
+        Some(u) if has_origin => run(git_at(dest)
+            .args(["remote", "set-url", "--end-of-options"])
+            .args(["origin", u]))?,
+        Some(u) => add_remote(dest, "origin", u)?,
+        None => run(git_at(dest).args(["remote", "remove", "origin"]))?,
+    };
+    Ok(())
+}
+
+/// Sets each of `entries`, as `(key, value)`, in the config of the repository
+/// at `dest`, which may be bare.
+pub fn set_config(
+    dest: &Path,
+    entries: &[(String, String)],
+) -> anyhow::Result<()> {
+    for (key, value) in entries {
+        set_value(dest, key, value)?;
+    }
+    Ok(())
+}
+
+/// Pushes the branch `HEAD` is on to `remote` of the repository at `dest`
+/// and makes it the branch's upstream. Returns the branch name.
+pub fn push_head(
+    dest: &Path,
+    remote: &str,
+    credentials: Option<(&str, &str)>,
+) -> anyhow::Result<String> {
+    let refname = run(git_at(dest).args(["symbolic-ref", "-q", "HEAD"]))
+        .map_err(|_| anyhow!("HEAD is detached; there is no branch to push"))?;
+    let branch =
+        refname.strip_prefix("refs/heads/").unwrap_or(&refname).to_string();
+
+    run(git_at(dest)
+        .args(credential_args(credentials))
+        .args(["push", "--quiet", "--porcelain", "--set-upstream"])
+        .args(["--end-of-options", positional("remote", remote)?])
+        .arg(format!("{0}:{0}", refname)))?;
+    Ok(branch)
+}
+
+/// Registers `url` as the [`UPSTREAM_REMOTE`] of the repository at `dest`,
+/// with the usual branch refspec and, when `base` is given, see
+/// [`set_upstream_base`].
+pub fn add_upstream_remote(
+    dest: &Path,
+    url: &str,
+    base: Option<&str>,
+) -> anyhow::Result<()> {
+    add_remote(dest, UPSTREAM_REMOTE, url)?;
+    if let Some(sha) = base {
+        set_upstream_base(dest, sha)?;
+    }
+    Ok(())
+}
+
+/// Points the [`UPSTREAM_BASE_REF`] refspec of the [`UPSTREAM_REMOTE`] at
+/// commit `sha`, replacing an earlier one. A snapshot has none of the
+/// upstream objects, so the ref itself appears with the next
+/// `git fetch upstream`.
+pub fn set_upstream_base(dest: &Path, sha: &str) -> anyhow::Result<()> {
+    let key = format!("remote.{}.fetch", UPSTREAM_REMOTE);
+    // Exits with 5 when there was nothing to remove.
+    let unset = output(
+        git_at(dest)
+            .args(["config", "--unset-all", &key])
+            .arg(format!(":{}$", UPSTREAM_BASE_REF)),
+    )?;
+    if !unset.status.success() && unset.status.code() != Some(5) {
+        return Err(anyhow!(
+            "git failed: {}",
+            String::from_utf8_lossy(&unset.stderr).trim()
+        ));
+    }
+    run(git_at(dest)
+        .args(["config", "--add", &key])
+        .arg(format!("+{}:{}", sha, UPSTREAM_BASE_REF)))?;
+    Ok(())
+}
+
+pub(crate) fn is_bare_repo(dest: &Path) -> bool {
+    dest.is_dir()
+        && run(git_at(dest).args(["rev-parse", "--is-bare-repository"]))
+            .is_ok_and(|out| out == "true")
+        && is_repo_root(dest)
+}
+
+/// Whether the repository at `dest` has an [`UPSTREAM_REMOTE`].
+pub(crate) fn has_upstream_remote(dest: &Path) -> bool {
+    dest.is_dir()
+        && output(git_at(dest).args(["remote", "get-url", UPSTREAM_REMOTE]))
+            .is_ok_and(|out| out.status.success())
+}
+
+/// Whether `dest` is the git directory of a bare repository, rather than
+/// somewhere inside one.
+fn is_repo_root(dest: &Path) -> bool {
+    let Ok(git_dir) =
+        run(git_at(dest).args(["rev-parse", "--absolute-git-dir"]))
+    else {
+        return false;
+    };
+    let canonical = |p: &OsStr| Path::new(p).canonicalize().ok();
+    canonical(git_dir.as_ref())
+        .is_some_and(|g| Some(g) == canonical(dest.as_os_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, write};
+
+    use super::*;
+    use crate::initialize_repo;
+
+    fn git_out(dir: &Path, args: &[&str]) -> String {
+        run(git_at(dir).args(args)).unwrap()
+    }
+
+    #[test]
+    fn test_initialize_and_commit_snapshot() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        write(root.join("README.md"), "hello").unwrap();
+        write(root.join("old.txt"), "x").unwrap();
+        initialize_repo(root, Some("Tester"), None, None).unwrap();
+        assert_eq!(
+            git_out(root, &["log", "-1", "--format=%an %s"]),
+            format!("Tester {}", DEFAULT_COMMIT_MESSAGE)
+        );
+
+        fs::remove_file(root.join("old.txt")).unwrap();
+        let options = InitOptions {
+            message: Some("Update".to_string()),
+            ..Default::default()
+        };
+        let sha = commit_snapshot(root, &options).unwrap().unwrap();
+        assert_eq!(git_out(root, &["rev-parse", "HEAD"]), sha);
+        assert_eq!(
+            git_out(root, &["ls-tree", "--name-only", "HEAD"]),
+            "README.md"
+        );
+        assert!(commit_snapshot(root, &options).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_commit_branch_leaves_head_alone() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().join("repo");
+        let tree = temp_dir.path().join("tree");
+        create_dir_all(&root).unwrap();
+        create_dir_all(&tree).unwrap();
+        write(root.join("README.md"), "main").unwrap();
+        write(tree.join("CHANGES.md"), "v2").unwrap();
+        initialize_repo(&root, None, None, None).unwrap();
+        let head = git_out(&root, &["rev-parse", "HEAD"]);
+
+        let options = InitOptions::default();
+        let sha = commit_branch(&root, "v2.0.0", &tree, &options).unwrap();
+
+        assert_eq!(git_out(&root, &["rev-parse", "HEAD"]), head);
+        assert_eq!(git_out(&root, &["rev-parse", "v2.0.0"]), sha);
+        assert_eq!(
+            git_out(&root, &["ls-tree", "--name-only", "v2.0.0"]),
+            "CHANGES.md"
+        );
+        assert_eq!(git_out(&root, &["status", "--porcelain"]), "");
+        assert!(commit_branch(&root, "v2.0.0", &tree, &options).is_err());
+    }
+
+    #[test]
+    fn test_commit_vendored_only_commits_subdirectory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        write(root.join("README.md"), "host").unwrap();
+        initialize_repo(root, None, None, None).unwrap();
+        write(root.join("scratch.txt"), "not mine").unwrap();
+        let vendor = root.join("third_party/foo");
+        create_dir_all(&vendor).unwrap();
+        write(vendor.join("lib.rs"), "fn foo() {}").unwrap();
+
+        commit_vendored(&vendor, &InitOptions::default()).unwrap().unwrap();
+
+        let files = git_out(root, &["ls-tree", "-r", "--name-only", "HEAD"]);
+        assert_eq!(files, "README.md\nthird_party/foo/lib.rs");
+        assert!(commit_vendored(root, &InitOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_bare_repo_and_upstream_remote() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let tree = temp_dir.path().join("tree");
+        let dest = temp_dir.path().join("mirror.git");
+        create_dir_all(&tree).unwrap();
+        write(tree.join("lib.rs"), "x").unwrap();
+        initialize_bare_repo(&dest, &tree, &InitOptions::default()).unwrap();
+
+        assert!(is_bare_repo(&dest));
+        assert!(!is_bare_repo(&dest.join("refs")));
+        assert!(!dest.join("index").exists());
+        assert_eq!(
+            git_out(&dest, &["ls-tree", "--name-only", "HEAD"]),
+            "lib.rs"
+        );
+
+        assert!(!has_upstream_remote(&dest));
+        add_upstream_remote(&dest, "https://example.com/o/r.git", Some("aaa"))
+            .unwrap();
+        set_upstream_base(&dest, "bbb").unwrap();
+        assert!(has_upstream_remote(&dest));
+        let key = format!("remote.{}.fetch", UPSTREAM_REMOTE);
+        assert_eq!(
+            git_out(&dest, &["config", "--get-all", &key]),
+            format!(
+                "+refs/heads/*:refs/remotes/upstream/*\n+bbb:{}",
+                UPSTREAM_BASE_REF
+            )
+        );
+    }
+
+    #[test]
+    fn test_clone_and_push() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let upstream = temp_dir.path().join("upstream");
+        create_dir_all(&upstream).unwrap();
+        write(upstream.join("README.md"), "hello").unwrap();
+        initialize_repo(&upstream, None, None, None).unwrap();
+        let branch = git_out(&upstream, &["branch", "--show-current"]);
+
+        let dest = temp_dir.path().join("clone");
+        let url = format!("file://{}", upstream.display());
+        assert_eq!(remote_default_branch(&url).unwrap(), branch);
+        let sha = clone_with_history(&url, &dest, &branch, None, None).unwrap();
+        assert_eq!(git_out(&dest, &["rev-parse", "HEAD"]), sha);
+        assert!(dest.join("README.md").exists());
+
+        let bare = temp_dir.path().join("remote.git");
+        create_dir_all(&bare).unwrap();
+        git_out(&bare, &["init", "--bare", "--quiet"]);
+        set_origin(&dest, Some(&format!("file://{}", bare.display()))).unwrap();
+        assert_eq!(push_head(&dest, "origin", None).unwrap(), branch);
+        assert_eq!(git_out(&bare, &["rev-parse", &branch]), sha);
+
+        set_origin(&dest, None).unwrap();
+        assert!(git_out(&dest, &["remote"]).is_empty());
+    }
+
+    #[test]
+    fn test_clone_refuses_options_as_arguments() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let upstream = temp_dir.path().join("upstream");
+        create_dir_all(&upstream).unwrap();
+        write(upstream.join("README.md"), "hello").unwrap();
+        initialize_repo(&upstream, None, None, None).unwrap();
+        let url = format!("file://{}", upstream.display());
+        let marker = temp_dir.path().join("pwned");
+        let option =
+            format!("--upload-pack=touch {};git-upload-pack", marker.display());
+
+        let dest = temp_dir.path().join("clone");
+        assert!(remote_default_branch(&option).is_err());
+        assert!(clone_with_history(&option, &dest, "main", None, None).is_err());
+        assert!(clone_with_history(&url, &dest, &option, None, None).is_err());
+        assert!(!marker.exists());
+    }
+}
@@ -0,0 +1,782 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::anyhow;
+use git2::{
+    build::CheckoutBuilder, BranchType, Commit, Cred, Direction, FetchOptions,
+    Index, IndexAddOption, ObjectType, PushOptions, Reference, Remote,
+    RemoteCallbacks, Repository, RepositoryInitOptions, Signature,
+};
+
+use crate::{
+    provider::is_commit_sha, InitOptions, DEFAULT_AUTHOR_EMAIL,
+    DEFAULT_AUTHOR_NAME, DEFAULT_COMMIT_MESSAGE, UPSTREAM_BASE_REF,
+    UPSTREAM_REMOTE,
+};
+
+pub fn initialize_repo_with(
+    dest: &Path,
+    options: &InitOptions,
+) -> anyhow::Result<()> {
+    let repo = create_repo(dest, options, false)?;
+    commit_all(&repo, options, None, "*")?;
+
+    if let Some(r) = &options.remote {
+        repo.remote("origin", r)?;
+    }
+
+    Ok(())
+}
+
+/// Creates a bare repository at `dest` whose only commit holds the files
+/// under `tree`, which is left as is.
+pub fn initialize_bare_repo(
+    dest: &Path,
+    tree: &Path,
+    options: &InitOptions,
+) -> anyhow::Result<()> {
+    let repo = create_repo(dest, options, true)?;
+    repo.set_workdir(tree, false)?;
+    commit_all(&repo, options, None, "*")?;
+    // The index was only needed to build the tree.
+    fs::remove_file(dest.join("index"))?;
+
+    if let Some(r) = &options.remote {
+        repo.remote("origin", r)?;
+    }
+
+    Ok(())
+}
+
+fn create_repo(
+    dest: &Path,
+    options: &InitOptions,
+    bare: bool,
+) -> anyhow::Result<Repository> {
+    let mut init_opts = RepositoryInitOptions::new();
+    init_opts.bare(bare);
+    if let Some(branch) = &options.initial_branch {
+        init_opts.initial_head(branch);
+    }
+    let repo = Repository::init_opts(dest, &init_opts)?;
+    let author_name = options.author_name.as_deref();
+    let author_email = options.author_email.as_deref();
+
+    if author_name.is_some() || author_email.is_some() {
+        let mut cfg = repo.config()?;
+
+        if let Some(name) = author_name {
+            cfg.set_str("user.name", name)?;
+        }
+
+        if let Some(email) = author_email {
+            cfg.set_str("user.email", email)?;
+        }
+    }
+
+    Ok(repo)
+}
+
+/// Commits the current contents of the working tree of the repository at
+/// `dest` on top of `HEAD`, including deletions. Returns `None` without
+/// committing if nothing changed.
+///
+/// Only the author, message, signing and `.gitignore` settings of `options`
+/// are used.
+pub fn commit_snapshot(
+    dest: &Path,
+    options: &InitOptions,
+) -> anyhow::Result<Option<String>> {
+    let repo = Repository::open(dest)?;
+    let parent = repo.head()?.peel_to_commit()?;
+    commit_all(&repo, options, Some(&parent), "*")
+}
+
+/// Commits the files under `tree` to the repository at `dest` as the only
+/// commit of a new branch `branch`, sharing no history with the others.
+/// `HEAD`, the index and the working tree of `dest` are left alone. Returns
+/// the new commit.
+///
+/// Only the author, message, signing and `.gitignore` settings of `options`
+/// are used.
+pub fn commit_branch(
+    dest: &Path,
+    branch: &str,
+    tree: &Path,
+    options: &InitOptions,
+) -> anyhow::Result<String> {
+    let refname = format!("refs/heads/{}", branch);
+    if !Reference::is_valid_name(&refname) {
+        return Err(anyhow!("'{}' is not a valid branch name", branch));
+    }
+
+    // A throwaway index keeps the one of `dest` untouched.
+    let repo = Repository::open(dest)?;
+    let mut index = Index::new()?;
+    repo.set_workdir(tree, false)?;
+    repo.set_index(&mut index)?;
+    let add_option = if options.respect_gitignore {
+        IndexAddOption::DEFAULT
+    } else {
+        IndexAddOption::FORCE
+    };
+    index.add_all(["*"].iter(), add_option, None)?;
+    let tree = repo.find_tree(index.write_tree_to(&repo)?)?;
+
+    let sig_name =
+        options.author_name.as_deref().unwrap_or(DEFAULT_AUTHOR_NAME);
+    let sig_email =
+        options.author_email.as_deref().unwrap_or(DEFAULT_AUTHOR_EMAIL);
+    let signature = Signature::now(sig_name, sig_email)?;
+    let message = options.message.as_deref().unwrap_or(DEFAULT_COMMIT_MESSAGE);
+    let commit = if options.sign {
+        let buffer = repo.commit_create_buffer(
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &[],
+        )?;
+        let buffer = std::str::from_utf8(&buffer)?;
+        repo.commit_signed(buffer, &sign_buffer(&repo, buffer)?, None)?
+    } else {
+        repo.commit(None, &signature, &signature, message, &tree, &[])?
+    };
+    // Fails rather than replace a branch that already exists.
+    repo.reference(&refname, commit, false, message)?;
+    Ok(commit.to_string())
+}
+
+/// Creates the annotated tag `name` with `message` on `HEAD` of the
+/// repository at `dest`, which may be bare. Fails if the tag exists.
+///
+/// Only the author settings of `options` are used, for the tagger.
+pub fn tag_head(
+    dest: &Path,
+    name: &str,
+    message: &str,
+    options: &InitOptions,
+) -> anyhow::Result<()> {
+    let repo = Repository::open(dest)?;
+    let head = repo.head()?.peel(ObjectType::Commit)?;
+    let tagger = Signature::now(
+        options.author_name.as_deref().unwrap_or(DEFAULT_AUTHOR_NAME),
+        options.author_email.as_deref().unwrap_or(DEFAULT_AUTHOR_EMAIL),
+    )?;
+    repo.tag(name, &head, &tagger, message, false)?;
+    Ok(())
+}
+
+/// The working tree of the repository `dir` belongs to. `dir` itself need
+/// not exist yet.
+pub(crate) fn enclosing_repo(dir: &Path) -> anyhow::Result<PathBuf> {
+    let repo = discover_repo(dir)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("repository has no working tree"))?;
+    Ok(workdir.to_path_buf())
+}
+
+fn discover_repo(dir: &Path) -> anyhow::Result<Repository> {
+    let existing = dir
+        .ancestors()
+        .find(|p| p.exists())
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let repo = Repository::discover(existing)?;
+    if repo.is_bare() {
+        return Err(anyhow!("{} is in a bare repository", dir.display()));
+    }
+    Ok(repo)
+}
+
+/// Commits the current contents of `dir` to the repository it is a
+/// subdirectory of, including deletions below `dir`, on top of `HEAD` if
+/// there is one. Changes already staged elsewhere are committed too, as
+/// with `git commit`. Returns `None` without committing if nothing changed.
+///
+/// Only the author, message, signing and `.gitignore` settings of `options`
+/// are used.
+pub fn commit_vendored(
+    dir: &Path,
+    options: &InitOptions,
+) -> anyhow::Result<Option<String>> {
+    let repo = discover_repo(dir)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("repository has no working tree"))?
+        .canonicalize()?;
+    let relative = dir.canonicalize()?.strip_prefix(&workdir)?.to_path_buf();
+    let pathspec = relative
+        .to_str()
+        .ok_or_else(|| anyhow!("{} is not valid UTF-8", relative.display()))?;
+    if pathspec.is_empty() {
+        return Err(anyhow!(
+            "{} is the top of its repository, not a subdirectory",
+            dir.display()
+        ));
+    }
+
+    let parent = match repo.head() {
+        Ok(head) => Some(head.peel_to_commit()?),
+        Err(e) if e.code() == git2::ErrorCode::UnbornBranch => None,
+        Err(e) => return Err(e.into()),
+    };
+    commit_all(&repo, options, parent.as_ref(), pathspec)
+}
+
+fn commit_all(
+    repo: &Repository,
+    options: &InitOptions,
+    parent: Option<&Commit>,
+    pathspec: &str,
+) -> anyhow::Result<Option<String>> {
+    let mut index = repo.index()?;
+    let add_option = if options.respect_gitignore {
+        IndexAddOption::DEFAULT
+    } else {
+        IndexAddOption::FORCE
+    };
+    index.add_all([pathspec].iter(), add_option, None)?;
+    if parent.is_some() {
+        // Drop files that are gone from the new snapshot.
+        index.update_all([pathspec].iter(), None)?;
+    }
+    index.write()?;
+    let tree_id = index.write_tree()?;
+    if parent.is_some_and(|p| p.tree_id() == tree_id) {
+        return Ok(None);
+    }
+
+    let tree = repo.find_tree(tree_id)?;
+    let sig_name =
+        options.author_name.as_deref().unwrap_or(DEFAULT_AUTHOR_NAME);
+    let sig_email =
+        options.author_email.as_deref().unwrap_or(DEFAULT_AUTHOR_EMAIL);
+    let signature = Signature::now(sig_name, sig_email)?;
+    let parents: Vec<&Commit> = parent.into_iter().collect();
+
+    let message = options.message.as_deref().unwrap_or(DEFAULT_COMMIT_MESSAGE);
+
+    let commit = if options.sign {
+        let buffer = repo.commit_create_buffer(
+            &signature, &signature, message, &tree, &parents,
+        )?;
+        let buffer = std::str::from_utf8(&buffer)?;
+        let commit =
+            repo.commit_signed(buffer, &sign_buffer(repo, buffer)?, None)?;
+
+        // `commit_signed` doesn't move any refs, so point the branch `HEAD`
+        // refers to at the new commit ourselves.
+        let head = repo.find_reference("HEAD")?;
+        let branch = head
+            .symbolic_target()
+            .ok_or_else(|| anyhow!("HEAD is not a symbolic reference"))?;
+        repo.reference(branch, commit, true, message)?;
+        commit
+    } else {
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )?
+    };
+
+    Ok(Some(commit.to_string()))
+}
+
+/// Produces a detached signature of a commit `buffer` with the tool `git`
+/// itself would use.
+fn sign_buffer(repo: &Repository, buffer: &str) -> anyhow::Result<String> {
+    let cfg = repo.config()?.snapshot()?;
+    let key = cfg.get_string("user.signingkey").map_err(|_| {
+        anyhow!("signing requested but user.signingkey is not set")
+    })?;
+    let format =
+        cfg.get_string("gpg.format").unwrap_or_else(|_| "openpgp".into());
+
+    let output = if format == "ssh" {
+        let program = cfg
+            .get_string("gpg.ssh.program")
+            .unwrap_or_else(|_| "ssh-keygen".into());
+        let dir = tempfile::tempdir()?;
+        let key_path = if Path::new(&key).exists() {
+            PathBuf::from(&key)
+        } else {
+            // A literal public key, as `git` also accepts.
+            let p = dir.path().join("key.pub");
+            std::fs::write(&p, &key)?;
+            p
+        };
+
+        run_signer(
+            Command::new(program)
+                .args(["-Y", "sign", "-n", "git", "-f"])
+                .arg(key_path),
+            buffer,
+        )?
+    } else {
+        let program =
+            cfg.get_string("gpg.program").unwrap_or_else(|_| "gpg".into());
+        run_signer(
+            Command::new(program).args([
+                "--armor",
+                "--detach-sign",
+                "-u",
+                &key,
+            ]),
+            buffer,
+        )?
+    };
+
+    Ok(output)
+}
+
+fn run_signer(cmd: &mut Command, input: &str) -> anyhow::Result<String> {
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("failed to run signing program: {}", e))?;
+
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("signing program has no stdin"))?
+        .write_all(input.as_bytes())?;
+    let output = child.wait_with_output()?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "signing failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Fetches `reference` from `url` into a new repository at `dest` and checks
+/// it out with its history, returning the checked out commit.
+///
+/// `depth` limits how many commits are fetched. Branches and tags get a local
+/// branch of the same name; commit SHAs leave `HEAD` detached.
+pub fn clone_with_history(
+    url: &str,
+    dest: &Path,
+    reference: &str,
+    depth: Option<u32>,
+    credentials: Option<(&str, &str)>,
+) -> anyhow::Result<String> {
+    let repo = Repository::init(dest)?;
+    let mut remote = repo.remote("origin", url)?;
+
+    let mut callbacks = RemoteCallbacks::new();
+    if let Some((user, password)) = credentials {
+        callbacks.credentials(move |_, _, _| {
+            Cred::userpass_plaintext(user, password)
+        });
+    }
+
+    let mut fetch = FetchOptions::new();
+    fetch.remote_callbacks(callbacks);
+    if let Some(d) = depth {
+        fetch.depth(i32::try_from(d).unwrap_or(i32::MAX));
+    }
+
+    remote.fetch(&[reference], Some(&mut fetch), None)?;
+    let commit = repo.find_reference("FETCH_HEAD")?.peel_to_commit()?;
+
+    if is_commit_sha(reference) {
+        repo.set_head_detached(commit.id())?;
+    } else {
+        repo.branch(reference, &commit, false)?;
+        repo.set_head(&format!("refs/heads/{}", reference))?;
+    }
+    repo.checkout_head(Some(CheckoutBuilder::new().force()))?;
+
+    Ok(commit.id().to_string())
+}
+
+/// Branch `HEAD` of the repository at `url` points at.
+pub fn remote_default_branch(url: &str) -> anyhow::Result<String> {
+    let mut remote = Remote::create_detached(url)?;
+    remote.connect(Direction::Fetch)?;
+    let head = remote.default_branch()?;
+    let head = head
+        .as_str()
+        .ok_or_else(|| anyhow!("{} has no default branch", url))?;
+    Ok(head.strip_prefix("refs/heads/").unwrap_or(head).to_string())
+}
+
+/// Points `origin` at `url`, or removes it when `url` is `None`.
+pub fn set_origin(dest: &Path, url: Option<&str>) -> anyhow::Result<()> {
+    let repo = Repository::open(dest)?;
+
+    match url {
+        Some(u) if repo.find_remote("origin").is_ok() => {
+            repo.remote_set_url("origin", u)?
+        },
+        Some(u) => drop(repo.remote("origin", u)?),
+        None => repo.remote_delete("origin")?,
+    }
+    Ok(())
+}
+
+/// Sets each of `entries`, as `(key, value)`, in the config of the repository
+/// at `dest`, which may be bare.
+pub fn set_config(
+    dest: &Path,
+    entries: &[(String, String)],
+) -> anyhow::Result<()> {
+    let mut config = Repository::open(dest)?.config()?;
+    for (key, value) in entries {
+        config.set_str(key, value)?;
+    }
+    Ok(())
+}
+
+/// Pushes the branch `HEAD` is on to `remote` of the repository at `dest`
+/// and makes it the branch's upstream. Returns the branch name.
+pub fn push_head(
+    dest: &Path,
+    remote: &str,
+    credentials: Option<(&str, &str)>,
+) -> anyhow::Result<String> {
+    let repo = Repository::open(dest)?;
+    let head = repo.head()?;
+    if !head.is_branch() {
+        return Err(anyhow!("HEAD is detached; there is no branch to push"));
+    }
+    let refname = head.name().ok_or_else(|| anyhow!("invalid HEAD name"))?;
+    let branch = head.shorthand().unwrap_or(refname).to_string();
+
+    let mut callbacks = RemoteCallbacks::new();
+    if let Some((user, password)) = credentials {
+        callbacks.credentials(move |_, _, _| {
+            Cred::userpass_plaintext(user, password)
+        });
+    }
+    callbacks.push_update_reference(|name, status| match status {
+        Some(message) => Err(git2::Error::from_str(&format!(
+            "{} was rejected: {}",
+            name, message
+        ))),
+        None => Ok(()),
+    });
+    let mut push = PushOptions::new();
+    push.remote_callbacks(callbacks);
+
+    repo.find_remote(remote)?
+        .push(&[format!("{0}:{0}", refname)], Some(&mut push))?;
+    repo.find_branch(&branch, BranchType::Local)?
+        .set_upstream(Some(&format!("{}/{}", remote, branch)))?;
+    Ok(branch)
+}
+
+/// Registers `url` as the [`UPSTREAM_REMOTE`] of the repository at `dest`,
+/// with the usual branch refspec and, when `base` is given, see
+/// [`set_upstream_base`].
+pub fn add_upstream_remote(
+    dest: &Path,
+    url: &str,
+    base: Option<&str>,
+) -> anyhow::Result<()> {
+    let repo = Repository::open(dest)?;
+    repo.remote(UPSTREAM_REMOTE, url)?;
+    if let Some(sha) = base {
+        set_upstream_base(dest, sha)?;
+    }
+    Ok(())
+}
+
+/// Points the [`UPSTREAM_BASE_REF`] refspec of the [`UPSTREAM_REMOTE`] at
+/// commit `sha`, replacing an earlier one. A snapshot has none of the
+/// upstream objects, so the ref itself appears with the next
+/// `git fetch upstream`.
+pub fn set_upstream_base(dest: &Path, sha: &str) -> anyhow::Result<()> {
+    let repo = Repository::open(dest)?;
+    let key = format!("remote.{}.fetch", UPSTREAM_REMOTE);
+    let mut cfg = repo.config()?;
+    match cfg.remove_multivar(&key, &format!(":{}$", UPSTREAM_BASE_REF)) {
+        Err(e) if e.code() != git2::ErrorCode::NotFound => return Err(e.into()),
+        _ => {},
+    }
+    repo.remote_add_fetch(
+        UPSTREAM_REMOTE,
+        &format!("+{}:{}", sha, UPSTREAM_BASE_REF),
+    )?;
+    Ok(())
+}
+
+/// Whether the repository at `dest` has an [`UPSTREAM_REMOTE`].
+pub(crate) fn is_bare_repo(dest: &Path) -> bool {
+    Repository::open(dest).is_ok_and(|r| r.is_bare())
+}
+
+pub(crate) fn has_upstream_remote(dest: &Path) -> bool {
+    Repository::open(dest).is_ok_and(|r| r.find_remote(UPSTREAM_REMOTE).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, write};
+
+    use git2::Oid;
+
+    use super::*;
+    use crate::initialize_repo;
+
+    #[test]
+    fn test_initialize_repo_commits_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        write(root.join("README.md"), "hello").unwrap();
+
+        initialize_repo(root, Some("Tester"), Some("t@example.com"), None)
+            .unwrap();
+
+        let repo = Repository::open(root).unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.message(), Some(DEFAULT_COMMIT_MESSAGE));
+        assert_eq!(head.author().name(), Some("Tester"));
+        assert!(head.tree().unwrap().get_name("README.md").is_some());
+    }
+
+    #[test]
+    fn test_commit_snapshot_records_changes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        write(root.join("README.md"), "hello").unwrap();
+        write(root.join("old.txt"), "x").unwrap();
+        initialize_repo(root, None, None, None).unwrap();
+
+        std::fs::remove_file(root.join("old.txt")).unwrap();
+        write(root.join("README.md"), "hello again").unwrap();
+        let options = InitOptions {
+            message: Some("Update".to_string()),
+            ..Default::default()
+        };
+        let oid = commit_snapshot(root, &options).unwrap().unwrap();
+
+        let repo = Repository::open(root).unwrap();
+        let commit = repo.find_commit(Oid::from_str(&oid).unwrap()).unwrap();
+        assert_eq!(commit.parent_count(), 1);
+        assert!(commit.tree().unwrap().get_name("old.txt").is_none());
+        assert_eq!(repo.head().unwrap().target(), Oid::from_str(&oid).ok());
+
+        assert!(commit_snapshot(root, &options).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_commit_vendored_only_commits_subdirectory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        write(root.join("README.md"), "host").unwrap();
+        initialize_repo(root, None, None, None).unwrap();
+        write(root.join("scratch.txt"), "not mine").unwrap();
+        let vendor = root.join("third_party/foo");
+        create_dir_all(vendor.join("src")).unwrap();
+        write(vendor.join("src/lib.rs"), "fn foo() {}").unwrap();
+
+        let oid =
+            commit_vendored(&vendor, &InitOptions::default()).unwrap().unwrap();
+
+        let repo = Repository::open(root).unwrap();
+        let tree = repo
+            .find_commit(Oid::from_str(&oid).unwrap())
+            .unwrap()
+            .tree()
+            .unwrap();
+        assert!(tree.get_path(Path::new("third_party/foo/src/lib.rs")).is_ok());
+        assert!(tree.get_path(Path::new("README.md")).is_ok());
+        assert!(tree.get_name("scratch.txt").is_none());
+        assert!(commit_vendored(root, &InitOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_upstream_remote_tracks_base() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        write(root.join("README.md"), "hello").unwrap();
+        initialize_repo(root, None, None, None).unwrap();
+
+        add_upstream_remote(root, "https://example.com/o/r.git", Some("aaa"))
+            .unwrap();
+        set_upstream_base(root, "bbb").unwrap();
+
+        let repo = Repository::open(root).unwrap();
+        let remote = repo.find_remote(UPSTREAM_REMOTE).unwrap();
+        assert_eq!(remote.url(), Some("https://example.com/o/r.git"));
+        let refspecs: Vec<String> = remote
+            .fetch_refspecs()
+            .unwrap()
+            .iter()
+            .flatten()
+            .map(str::to_string)
+            .collect();
+        assert_eq!(
+            refspecs,
+            [
+                "+refs/heads/*:refs/remotes/upstream/*".to_string(),
+                format!("+bbb:{}", UPSTREAM_BASE_REF),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_push_head_to_new_origin() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().join("work");
+        create_dir_all(&root).unwrap();
+        write(root.join("README.md"), "hello").unwrap();
+        initialize_repo(&root, None, None, None).unwrap();
+        let bare = temp_dir.path().join("remote.git");
+        Repository::init_bare(&bare).unwrap();
+
+        set_origin(&root, Some(&format!("file://{}", bare.display()))).unwrap();
+        let branch = push_head(&root, "origin", None).unwrap();
+
+        let local = Repository::open(&root).unwrap();
+        let remote = Repository::open_bare(&bare).unwrap();
+        assert_eq!(
+            remote
+                .find_reference(&format!("refs/heads/{}", branch))
+                .unwrap()
+                .target(),
+            local.head().unwrap().target()
+        );
+    }
+
+    #[test]
+    fn test_commit_branch_leaves_head_alone() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().join("repo");
+        let tree = temp_dir.path().join("tree");
+        create_dir_all(&root).unwrap();
+        create_dir_all(&tree).unwrap();
+        write(root.join("README.md"), "main").unwrap();
+        write(tree.join("CHANGES.md"), "v2").unwrap();
+        initialize_repo(&root, None, None, None).unwrap();
+        let head = Repository::open(&root).unwrap().head().unwrap().target();
+
+        let options = InitOptions::default();
+        let sha = commit_branch(&root, "v2.0.0", &tree, &options).unwrap();
+
+        let repo = Repository::open(&root).unwrap();
+        assert_eq!(repo.head().unwrap().target(), head);
+        let commit = repo
+            .find_branch("v2.0.0", BranchType::Local)
+            .unwrap()
+            .get()
+            .peel_to_commit()
+            .unwrap();
+        assert_eq!(commit.id().to_string(), sha);
+        assert_eq!(commit.parent_count(), 0);
+        let files = commit.tree().unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(files.get_name("CHANGES.md").is_some());
+        assert!(repo.statuses(None).unwrap().is_empty());
+        assert!(commit_branch(&root, "v2.0.0", &tree, &options).is_err());
+    }
+
+    #[test]
+    fn test_initialize_bare_repo_commits_tree() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let tree = temp_dir.path().join("tree");
+        let dest = temp_dir.path().join("mirror.git");
+        create_dir_all(tree.join("src")).unwrap();
+        write(tree.join("src/lib.rs"), "x").unwrap();
+
+        let options = InitOptions {
+            initial_branch: Some("main".to_string()),
+            ..Default::default()
+        };
+        initialize_bare_repo(&dest, &tree, &options).unwrap();
+
+        let repo = Repository::open(&dest).unwrap();
+        assert!(repo.is_bare());
+        assert!(!dest.join("index").exists());
+        let head = repo.head().unwrap();
+        assert_eq!(head.shorthand(), Some("main"));
+        let tree = head.peel_to_commit().unwrap().tree().unwrap();
+        assert!(tree.get_path(Path::new("src/lib.rs")).is_ok());
+    }
+
+    #[test]
+    fn test_clone_with_history_keeps_commits() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let upstream = temp_dir.path().join("upstream");
+        create_dir_all(&upstream).unwrap();
+        write(upstream.join("README.md"), "hello").unwrap();
+        initialize_repo(&upstream, None, None, None).unwrap();
+        let branch = Repository::open(&upstream)
+            .unwrap()
+            .head()
+            .unwrap()
+            .shorthand()
+            .unwrap()
+            .to_string();
+
+        let dest = temp_dir.path().join("clone");
+        let url = format!("file://{}", upstream.display());
+        assert_eq!(remote_default_branch(&url).unwrap(), branch);
+        let oid = clone_with_history(&url, &dest, &branch, None, None).unwrap();
+
+        let repo = Repository::open(&dest).unwrap();
+        assert_eq!(repo.head().unwrap().target(), Oid::from_str(&oid).ok());
+        assert!(dest.join("README.md").exists());
+
+        set_origin(&dest, None).unwrap();
+        assert!(repo.find_remote("origin").is_err());
+    }
+
+    #[test]
+    fn test_initialize_repo_with_branch_and_message() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        write(root.join("README.md"), "hello").unwrap();
+
+        let options = InitOptions {
+            initial_branch: Some("trunk".to_string()),
+            message: Some("Import of foo@abc123".to_string()),
+            ..Default::default()
+        };
+        initialize_repo_with(root, &options).unwrap();
+
+        let repo = Repository::open(root).unwrap();
+        let head = repo.head().unwrap();
+        assert_eq!(head.shorthand(), Some("trunk"));
+        assert_eq!(
+            head.peel_to_commit().unwrap().message(),
+            Some("Import of foo@abc123")
+        );
+    }
+
+    #[test]
+    fn test_initialize_repo_gitignore_handling() {
+        for respect in [false, true] {
+            let temp_dir = tempfile::tempdir().unwrap();
+            let root = temp_dir.path();
+            write(root.join(".gitignore"), "*.log\n").unwrap();
+            write(root.join("build.log"), "tracked upstream").unwrap();
+
+            let options = InitOptions {
+                respect_gitignore: respect,
+                ..Default::default()
+            };
+            initialize_repo_with(root, &options).unwrap();
+
+            let repo = Repository::open(root).unwrap();
+            let tree = repo.head().unwrap().peel_to_tree().unwrap();
+            assert_eq!(tree.get_name("build.log").is_some(), !respect);
+        }
+    }
+}
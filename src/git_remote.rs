@@ -0,0 +1,122 @@
+#[cfg(feature = "native")]
+use reqwest::blocking::Client;
+use url::Url;
+
+use crate::provider::{Provider, RepoLocation};
+#[cfg(feature = "native")]
+use crate::{git::remote_default_branch, GitripperError, Result};
+
+const SCHEMES: [&str; 5] = ["http", "https", "ssh", "git", "file"];
+
+/// Any other git repository, fetched with git itself since there is no API
+/// or archive to download: a shallow clone of the branch stands in for the
+/// archive.
+///
+/// Tried after every other provider. The location's owner is the host
+/// (`local` for `file://` URLs), its repository the last path segment, and
+/// the URL is kept as [`RepoLocation::remote`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GitRemote;
+
+/// Parses `http(s)://`, `ssh://`, `git://` and `file://` URLs and scp-like
+/// `user@host:path` ones. URLs, users and hosts starting with `-` are
+/// refused, since git or ssh could take them for options.
+pub fn parse_git_url(url: &str) -> Option<RepoLocation> {
+    let url = url.trim();
+    if url.starts_with('-') {
+        return None;
+    }
+    let (host, path) = match Url::parse(url) {
+        Ok(parsed) if parsed.username().starts_with('-') => return None,
+        Ok(parsed) if SCHEMES.contains(&parsed.scheme()) => (
+            parsed.host_str().unwrap_or("local").to_string(),
+            parsed.path().to_string(),
+        ),
+        Ok(_) => return None,
+        Err(_) => {
+            let (user_host, path) = url.split_once(':')?;
+            let (user, host) = user_host.split_once('@')?;
+            if user.starts_with('-') || host.is_empty() || host.contains('/') {
+                return None;
+            }
+            (host.to_string(), path.to_string())
+        },
+    };
+
+    if host.starts_with('-') {
+        return None;
+    }
+    let name = path.trim_end_matches('/').rsplit('/').next()?;
+    let name = name.strip_suffix(".git").unwrap_or(name);
+    if name.is_empty() {
+        return None;
+    }
+    let mut location = RepoLocation::new(host, name);
+    location.remote = Some(url.to_string());
+    Some(location)
+}
+
+impl Provider for GitRemote {
+    fn name(&self) -> &'static str { "git" }
+
+    fn parse_url(&self, url: &str) -> Option<RepoLocation> {
+        parse_git_url(url)
+    }
+
+    #[cfg(feature = "native")]
+    fn default_branch(
+        &self,
+        _client: &Client,
+        location: &RepoLocation,
+        _token: Option<&str>,
+    ) -> Result<String> {
+        remote_default_branch(&self.clone_url(location))
+            .map_err(GitripperError::Clone)
+    }
+
+    fn serves_archives(&self) -> bool { false }
+
+    fn archive_url(&self, location: &RepoLocation, _reference: &str) -> String {
+        self.clone_url(location)
+    }
+
+    fn clone_url(&self, location: &RepoLocation) -> String {
+        location.remote.clone().unwrap_or_else(|| {
+            format!("https://{}/{}.git", location.owner, location.repo)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_git_url_forms() {
+        for (url, owner) in [
+            ("https://git.example.com/group/tool.git", "git.example.com"),
+            ("ssh://git@git.example.com:2222/tool", "git.example.com"),
+            ("git@git.example.com:group/tool.git", "git.example.com"),
+            ("file:///srv/git/tool.git/", "local"),
+        ] {
+            let loc = parse_git_url(url).unwrap();
+            assert_eq!(
+                (loc.owner.as_str(), loc.repo.as_str()),
+                (owner, "tool")
+            );
+            assert_eq!(GitRemote.clone_url(&loc), url);
+        }
+
+        for url in [
+            "not a url",
+            "https://example.com",
+            "gitlab:group/proj",
+            "--upload-pack=touch@host:x",
+            "-oProxyCommand=touch@host:x",
+            "ssh://-oProxyCommand=touch/x",
+            "ssh://-oProxyCommand=touch@host/x",
+        ] {
+            assert!(parse_git_url(url).is_none(), "{}", url);
+        }
+    }
+}
@@ -0,0 +1,1045 @@
+#[cfg(feature = "native")]
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "native")]
+use reqwest::{blocking::Client, StatusCode};
+#[cfg(feature = "native")]
+use serde_json::{json, Value};
+
+use crate::{
+    parse_github_location,
+    provider::{
+        is_commit_sha, parse_repo_path, strip_host, Provider, RepoLocation,
+    },
+};
+#[cfg(feature = "native")]
+use crate::{
+    provider::{
+        download_archive, fetch_json, fetch_pages, fetch_raw, join_url_path,
+        non_empty_str, not_found_as, rate_limit_error, release_tag_name,
+        str_array, NotFoundCause, RefType, ReleaseAsset, RemoteRef, TreeEntry,
+        LATEST_RELEASE,
+    },
+    GitripperError, ProgressSink, RepoMetadata, Result,
+};
+
+pub const GITHUB_API: &str = "https://api.github.com";
+pub const GITHUB_HOST: &str = "github.com";
+pub const DEFAULT_BRANCH: &str = "main";
+
+/// Serves github.com archives without going through the API.
+const CODELOAD_URL: &str = "https://codeload.github.com";
+
+const ACCEPT_HEADER: &str = "application/vnd.github+json";
+/// Makes the blobs API send the contents instead of base64 in JSON.
+#[cfg(feature = "native")]
+const RAW_ACCEPT_HEADER: &str = "application/vnd.github.raw+json";
+
+#[cfg(feature = "native")]
+pub fn get_default_branch(
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    token: Option<&str>,
+) -> Result<String> {
+    GitHub::default().default_branch(
+        client,
+        &RepoLocation::new(owner, repo),
+        token,
+    )
+}
+
+#[cfg(feature = "native")]
+pub fn download_zip(
+    // TODO: this function might be broken, do we need `NamedTempFile`?
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    reference: &str,
+    token: Option<&str>,
+    dest_dir: &Path,
+    progress: &dyn ProgressSink,
+) -> Result<PathBuf> {
+    download_archive(
+        client,
+        &GitHub::default(),
+        &RepoLocation::new(owner, repo),
+        reference,
+        token,
+        dest_dir,
+        progress,
+    )
+}
+
+/// A repository as listed by [`GitHub::list_org_repos`] or
+/// [`GitHub::list_user_repos`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrgRepo {
+    pub name:     String,
+    /// Web URL of the repository, which [`GitHub::parse_url`] accepts.
+    pub html_url: String,
+    pub archived: bool,
+    pub fork:     bool,
+    pub topics:   Vec<String>,
+}
+
+/// Which repositories of a user [`GitHub::list_user_repos`] lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserRepos {
+    /// Repositories the user starred.
+    Starred,
+    /// Repositories the user owns.
+    Owned,
+}
+
+/// A repository found by [`GitHub::search_repos`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoMatch {
+    /// `owner/repo`.
+    pub full_name:   String,
+    /// Web URL of the repository, which [`GitHub::parse_url`] accepts.
+    pub html_url:    String,
+    pub description: Option<String>,
+    pub stars:       u64,
+}
+
+/// Which repositories of an organization to keep. Archived repositories and
+/// forks are skipped unless included.
+#[derive(Debug, Clone, Default)]
+pub struct RepoFilter {
+    pub include_archived: bool,
+    pub include_forks:    bool,
+    /// Only keep repositories with at least one of these topics.
+    pub topics:           Vec<String>,
+}
+
+impl RepoFilter {
+    pub fn matches(&self, repo: &OrgRepo) -> bool {
+        (self.include_archived || !repo.archived)
+            && (self.include_forks || !repo.fork)
+            && (self.topics.is_empty()
+                || self.topics.iter().any(|t| repo.topics.contains(t)))
+    }
+}
+
+/// github.com or a GitHub Enterprise Server instance, through the REST API.
+#[derive(Debug, Clone)]
+pub struct GitHub {
+    api_url: String,
+    host:    String,
+}
+
+impl GitHub {
+    /// A GitHub Enterprise Server instance. `api_url` is either the full API
+    /// base (`https://ghe.example.com/api/v3`) or just the instance URL, in
+    /// which case `/api/v3` is appended.
+    pub fn with_api_url(api_url: &str) -> Self {
+        let api_url = api_url.trim().trim_end_matches('/');
+        if api_url.eq_ignore_ascii_case(GITHUB_API) {
+            return Self::default();
+        }
+
+        let without_scheme =
+            api_url.split_once("://").map_or(api_url, |(_, rest)| rest);
+        let (host, api_path) =
+            without_scheme.split_once('/').unwrap_or((without_scheme, ""));
+
+        let api_url = if api_path.is_empty() {
+            format!("{}/api/v3", api_url)
+        } else {
+            api_url.to_string()
+        };
+
+        Self {
+            api_url,
+            host: host.to_string(),
+        }
+    }
+
+    pub fn api_url(&self) -> &str { &self.api_url }
+
+    /// Host repository URLs are matched against, e.g. `github.com`.
+    pub fn host(&self) -> &str { &self.host }
+
+    pub fn is_enterprise(&self) -> bool { self.api_url != GITHUB_API }
+
+    #[cfg(feature = "native")]
+    fn release_json(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        release: &str,
+        token: Option<&str>,
+    ) -> Result<Value> {
+        let url = if release == LATEST_RELEASE {
+            format!(
+                "{}/repos/{}/{}/releases/latest",
+                self.api_url, location.owner, location.repo
+            )
+        } else {
+            format!(
+                "{}/repos/{}/{}/releases/tags/{}",
+                self.api_url, location.owner, location.repo, release
+            )
+        };
+        fetch_json(client, self, &url, token).map_err(|e| {
+            not_found_as(e, || {
+                format!(
+                    "Release '{}' of {} not found (404).",
+                    release, location
+                )
+            })
+        })
+    }
+
+    /// Every repository of `org` that passes `filter`, following the
+    /// pagination of the repos API.
+    #[cfg(feature = "native")]
+    pub fn list_org_repos(
+        &self,
+        client: &Client,
+        org: &str,
+        filter: &RepoFilter,
+        token: Option<&str>,
+    ) -> Result<Vec<OrgRepo>> {
+        let url = format!("{}/orgs/{}/repos?type=all", self.api_url, org);
+        let items = fetch_pages(client, self, &url, token).map_err(|e| {
+            not_found_as(e, || format!("Organization {} not found (404).", org))
+        })?;
+
+        Ok(items
+            .iter()
+            .filter_map(parse_org_repo)
+            .filter(|r| filter.matches(r))
+            .collect())
+    }
+
+    /// Every repository `user` starred or owns that passes `filter`,
+    /// following the pagination of the API.
+    #[cfg(feature = "native")]
+    pub fn list_user_repos(
+        &self,
+        client: &Client,
+        user: &str,
+        which: UserRepos,
+        filter: &RepoFilter,
+        token: Option<&str>,
+    ) -> Result<Vec<OrgRepo>> {
+        let items =
+            fetch_pages(client, self, &self.user_repos_url(user, which), token)
+                .map_err(|e| {
+                    not_found_as(e, || {
+                        format!("User {} not found (404).", user)
+                    })
+                })?;
+
+        Ok(items
+            .iter()
+            .filter_map(parse_org_repo)
+            .filter(|r| filter.matches(r))
+            .collect())
+    }
+
+    #[cfg(feature = "native")]
+    fn user_repos_url(&self, user: &str, which: UserRepos) -> String {
+        match which {
+            UserRepos::Starred => {
+                format!("{}/users/{}/starred", self.api_url, user)
+            },
+            UserRepos::Owned => {
+                format!("{}/users/{}/repos?type=owner", self.api_url, user)
+            },
+        }
+    }
+
+    /// The first `limit` (at most 100) repositories matching `query`, in the
+    /// syntax of GitHub's search, best match first.
+    #[cfg(feature = "native")]
+    pub fn search_repos(
+        &self,
+        client: &Client,
+        query: &str,
+        limit: usize,
+        token: Option<&str>,
+    ) -> Result<Vec<RepoMatch>> {
+        let mut url = join_url_path(&self.api_url, "search/repositories")?;
+        url.query_pairs_mut()
+            .append_pair("q", query)
+            .append_pair("per_page", &limit.clamp(1, 100).to_string());
+        let v = fetch_json(client, self, url.as_str(), token)?;
+
+        Ok(v.get("items")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(parse_repo_match)
+            .take(limit)
+            .collect())
+    }
+
+    /// Creates an empty repository and returns its clone URL. `name` is
+    /// either `repo`, created for the authenticated user, or `org/repo`.
+    /// `description` is shown on its page.
+    #[cfg(feature = "native")]
+    pub fn create_repo(
+        &self,
+        client: &Client,
+        name: &str,
+        description: Option<&str>,
+        private: bool,
+        token: &str,
+    ) -> Result<String> {
+        let (url, repo) = match name.split_once('/') {
+            Some((org, repo)) => {
+                (format!("{}/orgs/{}/repos", self.api_url, org), repo)
+            },
+            None => (format!("{}/user/repos", self.api_url), name),
+        };
+        let mut body = json!({ "name": repo, "private": private });
+        if let Some(description) = description {
+            body["description"] = json!(description);
+        }
+        let (header, value) = self.auth_header(token);
+        let res = client
+            .post(&url)
+            .header(header, value)
+            .header("Accept", ACCEPT_HEADER)
+            .json(&body)
+            .send()?;
+
+        if let Some(e) = rate_limit_error(res.status(), res.headers()) {
+            return Err(e);
+        }
+        let status = res.status();
+        if !status.is_success() {
+            let txt = res.text().unwrap_or_default();
+            return Err(GitripperError::HttpStatus {
+                status:  status.as_u16(),
+                message: format!(
+                    "Failed to create repository {}: {} {}",
+                    name, status, txt
+                ),
+            });
+        }
+
+        let v: Value = res.json()?;
+        v.get("clone_url")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| GitripperError::HttpStatus {
+                status:  status.as_u16(),
+                message: format!("No clone URL for new repository {}", name),
+            })
+    }
+}
+
+/// The files in a response of the trees API. Submodules are left out.
+#[cfg(feature = "native")]
+fn parse_tree(v: &Value) -> Result<Vec<TreeEntry>> {
+    if v.get("truncated").and_then(Value::as_bool) == Some(true) {
+        return Err(GitripperError::Unsupported(
+            "the tree is too large for GitHub to list in full; rip it without \
+             sparse patterns"
+                .to_string(),
+        ));
+    }
+
+    Ok(v.get("tree")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter(|e| e.get("type").and_then(Value::as_str) == Some("blob"))
+        .filter_map(|e| {
+            Some(TreeEntry {
+                path: e.get("path")?.as_str()?.to_string(),
+                sha:  e.get("sha")?.as_str()?.to_string(),
+                mode: u32::from_str_radix(e.get("mode")?.as_str()?, 8).ok()?,
+                size: e.get("size").and_then(Value::as_u64),
+            })
+        })
+        .collect())
+}
+
+#[cfg(feature = "native")]
+fn parse_org_repo(v: &Value) -> Option<OrgRepo> {
+    let flag = |key| v.get(key).and_then(Value::as_bool).unwrap_or(false);
+
+    Some(OrgRepo {
+        name:     v.get("name")?.as_str()?.to_string(),
+        html_url: v.get("html_url")?.as_str()?.to_string(),
+        archived: flag("archived"),
+        fork:     flag("fork"),
+        topics:   v
+            .get("topics")
+            .and_then(Value::as_array)
+            .map(|ts| {
+                ts.iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+    })
+}
+
+#[cfg(feature = "native")]
+fn parse_repo_match(v: &Value) -> Option<RepoMatch> {
+    Some(RepoMatch {
+        full_name:   v.get("full_name")?.as_str()?.to_string(),
+        html_url:    v.get("html_url")?.as_str()?.to_string(),
+        description: non_empty_str(v, "/description"),
+        stars:       v
+            .get("stargazers_count")
+            .and_then(Value::as_u64)
+            .unwrap_or(0),
+    })
+}
+
+impl Default for GitHub {
+    fn default() -> Self {
+        Self {
+            api_url: GITHUB_API.to_string(),
+            host:    GITHUB_HOST.to_string(),
+        }
+    }
+}
+
+/// Fills in the ref, path or pull request named by the part of a GitHub URL
+/// after `owner/repo/`. See [`parse_github_location`].
+pub(crate) fn parse_github_suffix(location: &mut RepoLocation, suffix: &str) {
+    let suffix = suffix.trim_end_matches('/');
+    let (kind, rest) = suffix.split_once('/').unwrap_or((suffix, ""));
+    let (first, tail) = match rest.split_once('/') {
+        Some((f, t)) => (f, Some(t).filter(|t| !t.is_empty())),
+        None => (rest, None),
+    };
+    if first.is_empty() {
+        return;
+    }
+
+    match kind {
+        "tree" => {
+            location.reference = Some(first.to_string());
+            location.path = tail.map(str::to_string);
+        },
+        "blob" => {
+            location.reference = Some(first.to_string());
+            location.path = tail
+                .and_then(|file| file.rsplit_once('/'))
+                .map(|(dir, _)| dir.to_string());
+        },
+        "commit" | "commits" => location.reference = Some(first.to_string()),
+        "releases" if first == "tag" => {
+            location.reference = tail.map(str::to_string);
+        },
+        "pull" => location.pull = first.parse().ok(),
+        _ => {},
+    }
+}
+
+impl Provider for GitHub {
+    fn name(&self) -> &'static str { "GitHub" }
+
+    fn shorthand(&self) -> Option<&'static str> { Some("github") }
+
+    fn parse_url(&self, url: &str) -> Option<RepoLocation> {
+        if self.is_enterprise() {
+            let rest = strip_host(url.trim(), &self.host)?;
+            let mut location = parse_repo_path(rest, "tree/")?;
+            if let Some(suffix) = rest.splitn(3, '/').nth(2) {
+                parse_github_suffix(&mut location, suffix);
+            }
+            Some(location)
+        } else {
+            parse_github_location(url).ok()
+        }
+    }
+
+    #[cfg(feature = "native")]
+    fn default_branch(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        token: Option<&str>,
+    ) -> Result<String> {
+        let url = format!(
+            "{}/repos/{}/{}",
+            self.api_url, location.owner, location.repo
+        );
+        let v = fetch_json(client, self, &url, token).map_err(|e| {
+            not_found_as(e, || {
+                format!("Repository {} not found (404).", location)
+            })
+        })?;
+        Ok(v.get("default_branch")
+            .and_then(|b| b.as_str())
+            .unwrap_or(DEFAULT_BRANCH)
+            .to_string())
+    }
+
+    #[cfg(feature = "native")]
+    fn resolve_commit(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        reference: &str,
+        token: Option<&str>,
+    ) -> Result<Option<String>> {
+        let url = format!(
+            "{}/repos/{}/{}/commits/{}",
+            self.api_url, location.owner, location.repo, reference
+        );
+        let v = fetch_json(client, self, &url, token).map_err(|e| match e {
+            // What GitHub answers for a repository without any commits.
+            GitripperError::HttpStatus {
+                status: 409,
+                message,
+            } if message.contains("Git Repository is empty") => {
+                GitripperError::EmptyRepository(location.to_string())
+            },
+            e => e,
+        })?;
+        Ok(v.get("sha").and_then(|s| s.as_str()).map(str::to_string))
+    }
+
+    #[cfg(feature = "native")]
+    fn release_tag(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        release: &str,
+        token: Option<&str>,
+    ) -> Result<String> {
+        release_tag_name(&self.release_json(client, location, release, token)?)
+    }
+
+    #[cfg(feature = "native")]
+    fn release_assets(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        release: &str,
+        token: Option<&str>,
+    ) -> Result<(String, Vec<ReleaseAsset>)> {
+        let v = self.release_json(client, location, release, token)?;
+        let assets = v
+            .get("assets")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|a| {
+                Some(ReleaseAsset {
+                    name: a.get("name")?.as_str()?.to_string(),
+                    size: a.get("size").and_then(Value::as_u64),
+                    // The API URL rather than `browser_download_url`, so
+                    // private repositories work with a token.
+                    url:  a.get("url")?.as_str()?.to_string(),
+                })
+            })
+            .collect();
+
+        Ok((release_tag_name(&v)?, assets))
+    }
+
+    #[cfg(feature = "native")]
+    fn list_refs(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        ref_type: RefType,
+        token: Option<&str>,
+    ) -> Result<Vec<RemoteRef>> {
+        let endpoint = match ref_type {
+            RefType::Branch => "branches",
+            RefType::Tag => "tags",
+        };
+        let url = format!(
+            "{}/repos/{}/{}/{}",
+            self.api_url, location.owner, location.repo, endpoint
+        );
+        let items = fetch_pages(client, self, &url, token).map_err(|e| {
+            not_found_as(e, || {
+                format!("Repository {} not found (404).", location)
+            })
+        })?;
+
+        // Neither listing includes dates; see `commit_date`.
+        Ok(items
+            .iter()
+            .filter_map(|v| {
+                Some(RemoteRef {
+                    name:   v.get("name")?.as_str()?.to_string(),
+                    commit: v.get("commit")?.get("sha")?.as_str()?.to_string(),
+                    date:   None,
+                })
+            })
+            .collect())
+    }
+
+    #[cfg(feature = "native")]
+    fn commit_date(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        sha: &str,
+        token: Option<&str>,
+    ) -> Result<Option<String>> {
+        let url = format!(
+            "{}/repos/{}/{}/commits/{}",
+            self.api_url, location.owner, location.repo, sha
+        );
+        let v = fetch_json(client, self, &url, token)?;
+        Ok(v.pointer("/commit/committer/date")
+            .and_then(Value::as_str)
+            .map(str::to_string))
+    }
+
+    #[cfg(feature = "native")]
+    fn metadata(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        token: Option<&str>,
+    ) -> Result<RepoMetadata> {
+        let url = format!(
+            "{}/repos/{}/{}",
+            self.api_url, location.owner, location.repo
+        );
+        let v = fetch_json(client, self, &url, token)?;
+        Ok(RepoMetadata {
+            description: non_empty_str(&v, "/description"),
+            homepage:    non_empty_str(&v, "/homepage"),
+            topics:      str_array(&v, "/topics"),
+        })
+    }
+
+    /// The size GitHub reports, in KiB.
+    #[cfg(feature = "native")]
+    fn repo_size(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        token: Option<&str>,
+    ) -> Result<Option<u64>> {
+        let url = format!(
+            "{}/repos/{}/{}",
+            self.api_url, location.owner, location.repo
+        );
+        let v = fetch_json(client, self, &url, token)?;
+        Ok(v.get("size").and_then(Value::as_u64).map(|kib| kib * 1024))
+    }
+
+    #[cfg(feature = "native")]
+    fn fork_parent(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        token: Option<&str>,
+    ) -> Result<Option<RepoLocation>> {
+        let url = format!(
+            "{}/repos/{}/{}",
+            self.api_url, location.owner, location.repo
+        );
+        let v = fetch_json(client, self, &url, token)?;
+        if v.get("fork").and_then(Value::as_bool) != Some(true) {
+            return Ok(None);
+        }
+        Ok(non_empty_str(&v, "/parent/full_name")
+            .as_deref()
+            .and_then(|name| name.split_once('/'))
+            .map(|(owner, repo)| RepoLocation {
+                owner: owner.to_string(),
+                repo: repo.to_string(),
+                ..location.clone()
+            }))
+    }
+
+    #[cfg(feature = "native")]
+    fn moved_to(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        token: Option<&str>,
+    ) -> Result<Option<RepoLocation>> {
+        let url = format!(
+            "{}/repos/{}/{}",
+            self.api_url, location.owner, location.repo
+        );
+        // A moved repository answers with a 301 to its new home, which is
+        // followed; its name there tells where that is.
+        let v = fetch_json(client, self, &url, token)?;
+        let Some((owner, repo)) = v
+            .get("full_name")
+            .and_then(Value::as_str)
+            .and_then(|name| name.split_once('/'))
+        else {
+            return Ok(None);
+        };
+        if owner.eq_ignore_ascii_case(&location.owner)
+            && repo.eq_ignore_ascii_case(&location.repo)
+        {
+            return Ok(None);
+        }
+        Ok(Some(RepoLocation {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            ..location.clone()
+        }))
+    }
+
+    #[cfg(feature = "native")]
+    fn diagnose_not_found(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        token: Option<&str>,
+    ) -> Result<Option<NotFoundCause>> {
+        if let Some(token) = token {
+            let (name, value) = self.auth_header(token);
+            let res = client
+                .get(format!("{}/user", self.api_url))
+                .header(name, value)
+                .send()?;
+            if res.status() == StatusCode::UNAUTHORIZED {
+                return Ok(Some(NotFoundCause::BadToken));
+            }
+            // Classic tokens list their scopes; fine-grained and app tokens
+            // don't, and have their access checked below.
+            let scopes = res
+                .headers()
+                .get("x-oauth-scopes")
+                .and_then(|v| v.to_str().ok());
+            if let Some(scopes) = scopes
+                && res.status().is_success()
+            {
+                let scopes: Vec<String> = scopes
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                if !scopes.iter().any(|s| s == "repo") {
+                    return Ok(Some(NotFoundCause::MissingScopes(scopes)));
+                }
+            }
+        }
+
+        let url = format!("{}/users/{}", self.api_url, location.owner);
+        match fetch_json(client, self, &url, token) {
+            Ok(_) if token.is_some() => Ok(Some(NotFoundCause::NoAccess)),
+            Ok(_) => Ok(Some(NotFoundCause::MaybePrivate)),
+            Err(GitripperError::HttpStatus { status: 404, .. }) => {
+                Ok(Some(NotFoundCause::NoSuchOwner(location.owner.clone())))
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    #[cfg(feature = "native")]
+    fn pull_request_head(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        number: u64,
+        token: Option<&str>,
+    ) -> Result<RepoLocation> {
+        let url = format!(
+            "{}/repos/{}/{}/pulls/{}",
+            self.api_url, location.owner, location.repo, number
+        );
+        let v = fetch_json(client, self, &url, token).map_err(|e| {
+            not_found_as(e, || {
+                format!(
+                    "Pull request #{} of {} not found (404).",
+                    number, location
+                )
+            })
+        })?;
+        let head = v.get("head").ok_or_else(|| GitripperError::HttpStatus {
+            status:  200,
+            message: "Pull request has no head.".to_string(),
+        })?;
+        let field = |path| head.pointer(path).and_then(Value::as_str);
+
+        // A deleted fork leaves `head.repo` empty; its commits stay
+        // reachable from the base repository by SHA.
+        let mut head_location = match (
+            field("/repo/owner/login"),
+            field("/repo/name"),
+            field("/ref"),
+        ) {
+            (Some(owner), Some(repo), Some(reference)) => {
+                let mut l = RepoLocation::new(owner, repo);
+                l.reference = Some(reference.to_string());
+                l
+            },
+            _ => {
+                let mut l = RepoLocation::new(&location.owner, &location.repo);
+                l.reference = field("/sha").map(str::to_string);
+                l
+            },
+        };
+        head_location.path = location.path.clone();
+        Ok(head_location)
+    }
+
+    #[cfg(feature = "native")]
+    fn list_tree(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        reference: &str,
+        token: Option<&str>,
+    ) -> Result<Vec<TreeEntry>> {
+        let url = format!(
+            "{}/repos/{}/{}/git/trees/{}?recursive=1",
+            self.api_url, location.owner, location.repo, reference
+        );
+        let v = fetch_json(client, self, &url, token).map_err(|e| {
+            not_found_as(e, || {
+                format!(
+                    "Tree of '{}' in {} not found (404).",
+                    reference, location
+                )
+            })
+        })?;
+        parse_tree(&v)
+    }
+
+    #[cfg(feature = "native")]
+    fn fetch_blob(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        sha: &str,
+        token: Option<&str>,
+    ) -> Result<Vec<u8>> {
+        let url = format!(
+            "{}/repos/{}/{}/git/blobs/{}",
+            self.api_url, location.owner, location.repo, sha
+        );
+        fetch_raw(
+            client,
+            self,
+            &url,
+            Some(RAW_ACCEPT_HEADER),
+            token,
+            &format!("blob {}", sha),
+        )
+    }
+
+    #[cfg(feature = "native")]
+    fn fetch_file(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        reference: &str,
+        path: &str,
+        token: Option<&str>,
+    ) -> Result<Vec<u8>> {
+        let base = format!(
+            "{}/repos/{}/{}/contents",
+            self.api_url, location.owner, location.repo
+        );
+        let mut url = join_url_path(&base, path)?;
+        url.query_pairs_mut().append_pair("ref", reference);
+        fetch_raw(
+            client,
+            self,
+            url.as_str(),
+            Some(RAW_ACCEPT_HEADER),
+            token,
+            path,
+        )
+        .map_err(|e| {
+            not_found_as(e, || {
+                format!(
+                    "'{}' not found in {} at {} (404).",
+                    path, location, reference
+                )
+            })
+        })
+    }
+
+    fn archive_url(&self, location: &RepoLocation, reference: &str) -> String {
+        format!(
+            "{}/repos/{}/{}/zipball/{}",
+            self.api_url, location.owner, location.repo, reference
+        )
+    }
+
+    /// The codeload archive of a commit or a branch, for github.com only.
+    fn fallback_archive_url(
+        &self,
+        location: &RepoLocation,
+        reference: &str,
+    ) -> Option<String> {
+        if self.is_enterprise() {
+            return None;
+        }
+        let reference = if is_commit_sha(reference) {
+            reference.to_string()
+        } else {
+            format!("refs/heads/{}", reference)
+        };
+        Some(format!(
+            "{}/{}/{}/zip/{}",
+            CODELOAD_URL, location.owner, location.repo, reference
+        ))
+    }
+
+    fn clone_url(&self, location: &RepoLocation) -> String {
+        format!("https://{}/{}.git", self.host, location)
+    }
+
+    fn accept_header(&self) -> Option<&'static str> { Some(ACCEPT_HEADER) }
+
+    fn token_env(&self) -> Option<&'static str> { Some("GITHUB_TOKEN") }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enterprise_api_url() {
+        let ghe = GitHub::with_api_url("https://ghe.example.com/");
+        assert_eq!(ghe.api_url(), "https://ghe.example.com/api/v3");
+        assert_eq!(ghe.host(), "ghe.example.com");
+
+        let ghe = GitHub::with_api_url("https://ghe.example.com/api/v3");
+        assert_eq!(ghe.api_url(), "https://ghe.example.com/api/v3");
+        assert!(!GitHub::with_api_url(GITHUB_API).is_enterprise());
+    }
+
+    #[test]
+    fn test_codeload_fallback_only_for_github_com() {
+        let location = RepoLocation::new("octo", "widget");
+        assert_eq!(
+            GitHub::default().fallback_archive_url(&location, "dev"),
+            Some(
+                "https://codeload.github.com/octo/widget/zip/refs/heads/dev"
+                    .to_string()
+            )
+        );
+        let sha = "0123456789abcdef0123456789abcdef01234567";
+        assert!(GitHub::default()
+            .fallback_archive_url(&location, sha)
+            .unwrap()
+            .ends_with("/zip/0123456789abcdef0123456789abcdef01234567"));
+        let ghe = GitHub::with_api_url("https://ghe.example.com");
+        assert_eq!(ghe.fallback_archive_url(&location, "dev"), None);
+    }
+
+    #[test]
+    fn test_parse_tree_keeps_files() {
+        let tree = parse_tree(&serde_json::json!({
+            "truncated": false,
+            "tree": [
+                { "path": "src", "mode": "040000", "type": "tree", "sha": "a" },
+                {
+                    "path": "src/run.sh",
+                    "mode": "100755",
+                    "type": "blob",
+                    "sha": "b",
+                    "size": 12,
+                },
+                { "path": "m", "mode": "160000", "type": "commit", "sha": "c" },
+            ],
+        }))
+        .unwrap();
+
+        assert_eq!(
+            tree,
+            [TreeEntry {
+                path: "src/run.sh".to_string(),
+                sha:  "b".to_string(),
+                mode: 0o100755,
+                size: Some(12),
+            }]
+        );
+        assert!(parse_tree(&serde_json::json!({ "truncated": true })).is_err());
+    }
+
+    #[test]
+    fn test_org_repo_filter() {
+        let repo = parse_org_repo(&serde_json::json!({
+            "name": "tool",
+            "html_url": "https://github.com/team/tool",
+            "fork": true,
+            "topics": ["cli", "rust"],
+        }))
+        .unwrap();
+        assert!(!repo.archived);
+        assert_eq!(repo.topics, ["cli", "rust"]);
+
+        assert!(!RepoFilter::default().matches(&repo));
+        let filter = RepoFilter {
+            include_forks: true,
+            ..Default::default()
+        };
+        assert!(filter.matches(&repo));
+        assert!(RepoFilter {
+            topics: vec!["web".to_string(), "rust".to_string()],
+            ..filter.clone()
+        }
+        .matches(&repo));
+        assert!(!RepoFilter {
+            topics: vec!["web".to_string()],
+            ..filter
+        }
+        .matches(&repo));
+    }
+
+    #[test]
+    fn test_user_repos_urls() {
+        let ghe = GitHub::with_api_url("https://ghe.example.com");
+        assert_eq!(
+            ghe.user_repos_url("octo", UserRepos::Starred),
+            "https://ghe.example.com/api/v3/users/octo/starred"
+        );
+        assert_eq!(
+            GitHub::default().user_repos_url("octo", UserRepos::Owned),
+            "https://api.github.com/users/octo/repos?type=owner"
+        );
+    }
+
+    #[test]
+    fn test_parse_repo_match() {
+        let found = parse_repo_match(&serde_json::json!({
+            "full_name": "rust-lang/regex",
+            "html_url": "https://github.com/rust-lang/regex",
+            "description": null,
+            "stargazers_count": 3500,
+        }))
+        .unwrap();
+        assert_eq!(found.full_name, "rust-lang/regex");
+        assert_eq!(found.description, None);
+        assert_eq!(found.stars, 3500);
+        let unnamed = serde_json::json!({ "html_url": "https://github.com/x" });
+        assert!(parse_repo_match(&unnamed).is_none());
+    }
+
+    #[test]
+    fn test_enterprise_urls() {
+        let ghe = GitHub::with_api_url("https://ghe.example.com");
+        let loc = ghe
+            .parse_url("https://ghe.example.com/team/tool/tree/v2/src")
+            .unwrap();
+        assert_eq!(loc.owner, "team");
+        assert_eq!(loc.repo, "tool");
+        assert_eq!(loc.reference.as_deref(), Some("v2"));
+        assert_eq!(loc.path.as_deref(), Some("src"));
+        assert!(ghe.parse_url("https://github.com/team/tool").is_none());
+
+        let pull = ghe.parse_url("https://ghe.example.com/team/tool/pull/7");
+        assert_eq!(pull.unwrap().pull, Some(7));
+
+        assert_eq!(
+            ghe.archive_url(&loc, "v2"),
+            "https://ghe.example.com/api/v3/repos/team/tool/zipball/v2"
+        );
+    }
+}
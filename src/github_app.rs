@@ -0,0 +1,165 @@
+use std::{
+    fmt, fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use reqwest::blocking::{Client, RequestBuilder};
+use serde_json::{json, Value};
+
+use crate::{provider::rate_limit_error, GitHub, GitripperError, Result};
+
+/// GitHub rejects app JWTs valid for more than ten minutes.
+const JWT_LIFETIME_SECS: u64 = 9 * 60;
+/// Backdates `iat` to allow for clock drift, as GitHub recommends.
+const JWT_CLOCK_SKEW_SECS: u64 = 60;
+
+/// A GitHub App, which authenticates with short-lived installation tokens
+/// minted from its private key instead of a personal access token.
+pub struct GitHubApp {
+    app_id: String,
+    key:    EncodingKey,
+}
+
+impl fmt::Debug for GitHubApp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GitHubApp")
+            .field("app_id", &self.app_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl GitHubApp {
+    /// `pem` is the RSA private key downloaded from the app's settings.
+    pub fn new(app_id: impl Into<String>, pem: &[u8]) -> Result<Self> {
+        let key = EncodingKey::from_rsa_pem(pem).map_err(|e| {
+            GitripperError::AppAuth(format!("invalid private key: {}", e))
+        })?;
+        Ok(Self {
+            app_id: app_id.into(),
+            key,
+        })
+    }
+
+    pub fn from_key_file(
+        app_id: impl Into<String>,
+        path: &Path,
+    ) -> Result<Self> {
+        Self::new(app_id, &fs::read(path)?)
+    }
+
+    pub fn app_id(&self) -> &str { &self.app_id }
+
+    /// A JWT identifying the app itself, valid for nine minutes.
+    pub fn jwt(&self) -> Result<String> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let claims = json!({
+            "iat": now - JWT_CLOCK_SKEW_SECS,
+            "exp": now + JWT_LIFETIME_SECS,
+            "iss": self.app_id,
+        });
+
+        encode(&Header::new(Algorithm::RS256), &claims, &self.key).map_err(
+            |e| GitripperError::AppAuth(format!("could not sign JWT: {}", e)),
+        )
+    }
+
+    /// Mints an installation token for the installation covering `repo` of
+    /// `owner`, or the installation on the organization or user `owner`
+    /// itself when `repo` is `None`.
+    pub fn installation_token(
+        &self,
+        client: &Client,
+        github: &GitHub,
+        owner: &str,
+        repo: Option<&str>,
+    ) -> Result<String> {
+        let jwt = self.jwt()?;
+
+        let api = github.api_url();
+        let installation = match repo {
+            Some(repo) => send(
+                client.get(format!(
+                    "{}/repos/{}/{}/installation",
+                    api, owner, repo
+                )),
+                &jwt,
+            ),
+            // Accounts are either organizations or users; try both.
+            None => send(
+                client.get(format!("{}/orgs/{}/installation", api, owner)),
+                &jwt,
+            )
+            .or_else(|e| match e {
+                GitripperError::HttpStatus { status: 404, .. } => send(
+                    client.get(format!("{}/users/{}/installation", api, owner)),
+                    &jwt,
+                ),
+                e => Err(e),
+            }),
+        };
+        let installation = installation.map_err(|e| match e {
+            GitripperError::HttpStatus { status: 404, .. } => {
+                GitripperError::AppAuth(format!(
+                    "app {} is not installed on {}",
+                    self.app_id,
+                    repo.map_or(owner.to_string(), |r| {
+                        format!("{}/{}", owner, r)
+                    })
+                ))
+            },
+            e => e,
+        })?;
+        let id = installation.get("id").and_then(Value::as_u64).ok_or_else(
+            || GitripperError::AppAuth("installation has no id".to_string()),
+        )?;
+
+        let url = format!("{}/app/installations/{}/access_tokens", api, id);
+        let v = send(client.post(&url), &jwt)?;
+        v.get("token").and_then(Value::as_str).map(str::to_string).ok_or_else(
+            || {
+                GitripperError::AppAuth(
+                    "no token in access token response".to_string(),
+                )
+            },
+        )
+    }
+}
+
+/// Sends an app-authenticated API request and parses the JSON body.
+fn send(req: RequestBuilder, jwt: &str) -> Result<Value> {
+    let res = req
+        .header("Authorization", format!("Bearer {}", jwt))
+        .header("Accept", "application/vnd.github+json")
+        .send()?;
+
+    if let Some(e) = rate_limit_error(res.status(), res.headers()) {
+        return Err(e);
+    }
+    let status = res.status();
+    if !status.is_success() {
+        let txt = res.text().unwrap_or_default();
+        return Err(GitripperError::HttpStatus {
+            status:  status.as_u16(),
+            message: format!("GitHub App request failed: {} {}", status, txt),
+        });
+    }
+    Ok(res.json()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_invalid_key() {
+        assert!(matches!(
+            GitHubApp::new("1", b"not a key"),
+            Err(GitripperError::AppAuth(_))
+        ));
+    }
+}
@@ -0,0 +1,335 @@
+#[cfg(feature = "native")]
+use reqwest::blocking::Client;
+#[cfg(feature = "native")]
+use serde_json::Value;
+
+use crate::provider::{strip_host, Provider, RepoLocation};
+#[cfg(feature = "native")]
+use crate::{
+    provider::{
+        fetch_json, fetch_pages, fetch_raw, join_url_path, non_empty_str,
+        not_found_as, release_tag_name, str_array, RefType, RemoteRef,
+        LATEST_RELEASE,
+    },
+    GitripperError, RepoMetadata, Result, DEFAULT_BRANCH,
+};
+
+pub const GITLAB_HOST: &str = "gitlab.com";
+
+/// A GitLab instance, through the v4 REST API.
+#[derive(Debug, Clone)]
+pub struct GitLab {
+    host: String,
+}
+
+impl GitLab {
+    /// A self-hosted instance reachable at `https://<host>`.
+    pub fn new(host: impl Into<String>) -> Self { Self { host: host.into() } }
+
+    pub fn host(&self) -> &str { &self.host }
+
+    fn project_url(&self, location: &RepoLocation) -> String {
+        format!(
+            "https://{}/api/v4/projects/{}",
+            self.host,
+            project_id(location)
+        )
+    }
+}
+
+impl Default for GitLab {
+    fn default() -> Self { Self::new(GITLAB_HOST) }
+}
+
+/// URL-encoded `namespace/project` path, which the API accepts in place of
+/// the numeric project ID.
+fn project_id(location: &RepoLocation) -> String {
+    format!("{}/{}", location.owner, location.repo).replace('/', "%2F")
+}
+
+/// Parses GitLab project URLs, including nested groups and
+/// `/-/tree/<ref>/<path>` links.
+pub fn parse_gitlab_url(url: &str, host: &str) -> Option<RepoLocation> {
+    let rest = strip_host(url.trim(), host)?;
+    let (project, extra) = match rest.split_once("/-/") {
+        Some((p, e)) => (p, Some(e)),
+        None => (rest, None),
+    };
+
+    let project = project.trim_end_matches('/');
+    let project = project.strip_suffix(".git").unwrap_or(project);
+    let (owner, repo) = project.rsplit_once('/')?;
+
+    if owner.is_empty()
+        || repo.is_empty()
+        || owner.split('/').any(|s| s.is_empty())
+    {
+        return None;
+    }
+
+    let mut location = RepoLocation::new(owner, repo);
+
+    if let Some(tree) = extra.and_then(|e| e.strip_prefix("tree/")) {
+        let tree = tree.trim_end_matches('/');
+        let (reference, path) = match tree.split_once('/') {
+            Some((r, p)) => (r, Some(p)),
+            None => (tree, None),
+        };
+
+        if !reference.is_empty() {
+            location.reference = Some(reference.to_string());
+            location.path = path.map(str::to_string);
+        }
+    }
+
+    Some(location)
+}
+
+impl Provider for GitLab {
+    fn name(&self) -> &'static str { "GitLab" }
+
+    fn shorthand(&self) -> Option<&'static str> { Some("gitlab") }
+
+    fn parse_url(&self, url: &str) -> Option<RepoLocation> {
+        parse_gitlab_url(url, &self.host)
+    }
+
+    #[cfg(feature = "native")]
+    fn default_branch(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        token: Option<&str>,
+    ) -> Result<String> {
+        let v = fetch_json(client, self, &self.project_url(location), token)
+            .map_err(|e| {
+                not_found_as(e, || {
+                    format!("Project {} not found (404).", location)
+                })
+            })?;
+
+        Ok(v.get("default_branch")
+            .and_then(|b| b.as_str())
+            .unwrap_or(DEFAULT_BRANCH)
+            .to_string())
+    }
+
+    #[cfg(feature = "native")]
+    fn fork_parent(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        token: Option<&str>,
+    ) -> Result<Option<RepoLocation>> {
+        let v = fetch_json(client, self, &self.project_url(location), token)?;
+        Ok(
+            non_empty_str(&v, "/forked_from_project/path_with_namespace")
+                .as_deref()
+                .and_then(|path| path.rsplit_once('/'))
+                .map(|(owner, repo)| RepoLocation {
+                    owner: owner.to_string(),
+                    repo: repo.to_string(),
+                    ..location.clone()
+                }),
+        )
+    }
+
+    #[cfg(feature = "native")]
+    fn metadata(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        token: Option<&str>,
+    ) -> Result<RepoMetadata> {
+        let v = fetch_json(client, self, &self.project_url(location), token)?;
+        // `tag_list` is what versions before 14.0 call the topics.
+        let mut topics = str_array(&v, "/topics");
+        if topics.is_empty() {
+            topics = str_array(&v, "/tag_list");
+        }
+        Ok(RepoMetadata {
+            description: non_empty_str(&v, "/description"),
+            homepage: None,
+            topics,
+        })
+    }
+
+    /// Only members with at least reporter access see the statistics.
+    #[cfg(feature = "native")]
+    fn repo_size(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        token: Option<&str>,
+    ) -> Result<Option<u64>> {
+        let url = format!("{}?statistics=true", self.project_url(location));
+        let v = fetch_json(client, self, &url, token)?;
+        Ok(v.pointer("/statistics/repository_size").and_then(Value::as_u64))
+    }
+
+    #[cfg(feature = "native")]
+    fn resolve_commit(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        reference: &str,
+        token: Option<&str>,
+    ) -> Result<Option<String>> {
+        let url = format!(
+            "{}/repository/commits/{}",
+            self.project_url(location),
+            reference.replace('/', "%2F")
+        );
+        let v = fetch_json(client, self, &url, token)?;
+        Ok(v.get("id").and_then(|s| s.as_str()).map(str::to_string))
+    }
+
+    #[cfg(feature = "native")]
+    fn release_tag(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        release: &str,
+        token: Option<&str>,
+    ) -> Result<String> {
+        let release_path = if release == LATEST_RELEASE {
+            "permalink/latest".to_string()
+        } else {
+            release.replace('/', "%2F")
+        };
+        let url =
+            format!("{}/releases/{}", self.project_url(location), release_path);
+        let v = fetch_json(client, self, &url, token).map_err(|e| {
+            not_found_as(e, || {
+                format!(
+                    "Release '{}' of {} not found (404).",
+                    release, location
+                )
+            })
+        })?;
+
+        release_tag_name(&v)
+    }
+
+    #[cfg(feature = "native")]
+    fn list_refs(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        ref_type: RefType,
+        token: Option<&str>,
+    ) -> Result<Vec<RemoteRef>> {
+        let endpoint = match ref_type {
+            RefType::Branch => "branches",
+            RefType::Tag => "tags",
+        };
+        let url =
+            format!("{}/repository/{}", self.project_url(location), endpoint);
+        let items = fetch_pages(client, self, &url, token).map_err(|e| {
+            not_found_as(e, || format!("Project {} not found (404).", location))
+        })?;
+
+        Ok(items
+            .iter()
+            .filter_map(|v| {
+                let commit = v.get("commit")?;
+                Some(RemoteRef {
+                    name:   v.get("name")?.as_str()?.to_string(),
+                    commit: commit.get("id")?.as_str()?.to_string(),
+                    date:   commit
+                        .get("committed_date")
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                })
+            })
+            .collect())
+    }
+
+    #[cfg(feature = "native")]
+    fn fetch_file(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        reference: &str,
+        path: &str,
+        token: Option<&str>,
+    ) -> Result<Vec<u8>> {
+        let base = format!("{}/repository/files", self.project_url(location));
+        let mut url = join_url_path(&base, "")?;
+        // The file path is a single segment, with its slashes encoded.
+        url.path_segments_mut()
+            .map_err(|_| GitripperError::InvalidUrl(base.clone()))?
+            .push(path.trim_matches('/'))
+            .push("raw");
+        url.query_pairs_mut().append_pair("ref", reference);
+        fetch_raw(client, self, url.as_str(), None, token, path)
+    }
+
+    fn archive_url(&self, location: &RepoLocation, reference: &str) -> String {
+        format!(
+            "{}/repository/archive.zip?sha={}",
+            self.project_url(location),
+            reference
+        )
+    }
+
+    fn clone_url(&self, location: &RepoLocation) -> String {
+        format!("https://{}/{}.git", self.host, location)
+    }
+
+    fn git_credentials(&self, token: &str) -> (String, String) {
+        ("oauth2".to_string(), token.to_string())
+    }
+
+    fn auth_header(&self, token: &str) -> (&'static str, String) {
+        ("PRIVATE-TOKEN", token.to_string())
+    }
+
+    fn token_env(&self) -> Option<&'static str> { Some("GITLAB_TOKEN") }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_gitlab_url_forms() {
+        for url in [
+            "https://gitlab.com/group/repo",
+            "https://gitlab.com/group/repo.git",
+            "git@gitlab.com:group/repo.git",
+            "ssh://git@gitlab.com/group/repo",
+        ] {
+            let loc = parse_gitlab_url(url, GITLAB_HOST).unwrap();
+            assert_eq!(loc, RepoLocation::new("group", "repo"), "{}", url);
+        }
+
+        assert!(
+            parse_gitlab_url("https://gitlab.com/group", GITLAB_HOST).is_none()
+        );
+        assert!(
+            parse_gitlab_url("https://github.com/a/b", GITLAB_HOST).is_none()
+        );
+    }
+
+    #[test]
+    fn test_parse_gitlab_url_tree() {
+        let url = "https://gitlab.com/a/b/c/-/tree/dev/docs/api";
+        let loc = parse_gitlab_url(url, GITLAB_HOST).unwrap();
+        assert_eq!(loc.owner, "a/b");
+        assert_eq!(loc.repo, "c");
+        assert_eq!(loc.reference.as_deref(), Some("dev"));
+        assert_eq!(loc.path.as_deref(), Some("docs/api"));
+    }
+
+    #[test]
+    fn test_gitlab_api_urls() {
+        let gitlab = GitLab::default();
+        let loc = RepoLocation::new("a/b", "c");
+        assert_eq!(
+            gitlab.archive_url(&loc, "main"),
+            "https://gitlab.com/api/v4/projects/a%2Fb%2Fc/repository/\
+             archive.zip?sha=main"
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use std::{
+    io::stderr,
+    process::{Command, Stdio},
+};
+
+use crate::{GitripperError, Result, RipReport};
+
+/// Runs `command` through the platform shell in `report.dest`, with the
+/// details of the rip in `GITRIPPER_PROVIDER`, `GITRIPPER_OWNER`,
+/// `GITRIPPER_REPO`, `GITRIPPER_REF`, `GITRIPPER_COMMIT` (empty when
+/// unknown) and `GITRIPPER_DEST`. Its output goes to stderr so it never
+/// mixes with machine-readable results on stdout.
+pub(crate) fn run_hook(command: &str, report: &RipReport) -> Result<()> {
+    let dest = std::path::absolute(&report.dest)?;
+    let status = shell(command)
+        .current_dir(&dest)
+        .env("GITRIPPER_PROVIDER", report.provider)
+        .env("GITRIPPER_OWNER", &report.owner)
+        .env("GITRIPPER_REPO", &report.repo)
+        .env("GITRIPPER_REF", &report.reference)
+        .env("GITRIPPER_COMMIT", report.commit.as_deref().unwrap_or(""))
+        .env("GITRIPPER_DEST", &dest)
+        .stdin(Stdio::null())
+        .stdout(stderr())
+        .status()
+        .map_err(|e| hook_error(command, e.to_string()))?;
+
+    if !status.success() {
+        return Err(hook_error(command, status.to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("cmd");
+    cmd.args(["/C", command]);
+    cmd
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", command]);
+    cmd
+}
+
+fn hook_error(command: &str, reason: String) -> GitripperError {
+    GitripperError::Hook {
+        command: command.to_string(),
+        reason,
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use std::{fs::read_to_string, path::Path};
+
+    use super::*;
+
+    fn report(dest: &Path) -> RipReport {
+        RipReport {
+            provider:  "GitHub",
+            owner:     "rust-lang".to_string(),
+            repo:      "regex".to_string(),
+            reference: "master".to_string(),
+            commit:    None,
+            dest:      dest.to_path_buf(),
+        }
+    }
+
+    #[test]
+    fn test_hook_runs_in_dest_with_rip_env() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let report = report(temp_dir.path());
+
+        run_hook(
+            "echo \"$GITRIPPER_OWNER/$GITRIPPER_REPO@$GITRIPPER_REF\" > out",
+            &report,
+        )
+        .unwrap();
+
+        assert_eq!(
+            read_to_string(temp_dir.path().join("out")).unwrap(),
+            "rust-lang/regex@master\n"
+        );
+    }
+
+    #[test]
+    fn test_failing_hook_is_an_error() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        assert!(matches!(
+            run_hook("exit 3", &report(temp_dir.path())),
+            Err(GitripperError::Hook { .. })
+        ));
+    }
+}
@@ -0,0 +1,142 @@
+use std::{fs, io, path::PathBuf, time::Duration};
+
+use reqwest::{
+    blocking::{Client, ClientBuilder},
+    Certificate, NoProxy, Proxy,
+};
+
+use crate::Result;
+
+/// How long to wait for a connection unless configured otherwise.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long to wait for a response, or for the next chunk of its body,
+/// unless configured otherwise.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Network settings for the HTTP clients a [`crate::Ripper`] builds.
+///
+/// Without an explicit proxy, `HTTPS_PROXY`, `HTTP_PROXY`, `ALL_PROXY` and
+/// `NO_PROXY` (or their lowercase forms) are honored.
+#[derive(Debug, Clone, Default)]
+pub struct HttpOptions {
+    /// Proxy for every request, e.g. `http://proxy.example.com:3128`.
+    /// Hosts in `NO_PROXY` still bypass it.
+    pub proxy:           Option<String>,
+    /// PEM files of extra root certificates to trust, e.g. of a corporate
+    /// TLS-intercepting proxy or a forge with a private CA.
+    pub ca_certs:        Vec<PathBuf>,
+    /// Accept any certificate. Only for debugging; this makes the
+    /// connection trivially interceptable.
+    pub insecure:        bool,
+    /// Defaults to [`DEFAULT_CONNECT_TIMEOUT`].
+    pub connect_timeout: Option<Duration>,
+    /// Longest wait for a response and then for each chunk of its body, so
+    /// a large download on a slow link is fine as long as data keeps
+    /// arriving. Defaults to [`DEFAULT_TIMEOUT`].
+    pub timeout:         Option<Duration>,
+    /// Interval of TCP keepalive probes; the reqwest default if unset.
+    pub tcp_keepalive:   Option<Duration>,
+    /// Never negotiate HTTP/2.
+    pub http1_only:      bool,
+}
+
+/// Applies the settings shared by the blocking and async client builders,
+/// which have the same methods but no common trait.
+macro_rules! configure {
+    ($options:expr, $builder:expr) => {{
+        let options = $options;
+        let mut builder = $builder.connect_timeout(
+            options.connect_timeout.unwrap_or(DEFAULT_CONNECT_TIMEOUT),
+        );
+
+        if let Some(interval) = options.tcp_keepalive {
+            builder = builder.tcp_keepalive(interval);
+        }
+        if options.http1_only {
+            builder = builder.http1_only();
+        }
+        if let Some(url) = &options.proxy {
+            builder =
+                builder.proxy(Proxy::all(url)?.no_proxy(NoProxy::from_env()));
+        }
+        for pem in options.read_ca_certs()? {
+            builder =
+                builder.tls_certs_merge(Certificate::from_pem_bundle(&pem)?);
+        }
+        if options.insecure {
+            builder = builder.tls_danger_accept_invalid_certs(true);
+        }
+        builder
+    }};
+}
+
+impl HttpOptions {
+    /// A client builder with these settings applied.
+    pub fn client_builder(&self, user_agent: &str) -> Result<ClientBuilder> {
+        // The blocking client applies its timeout to waiting for the
+        // response and to every read of the body, not to the whole request.
+        let builder = Client::builder()
+            .user_agent(user_agent)
+            .timeout(self.timeout.unwrap_or(DEFAULT_TIMEOUT));
+        Ok(configure!(self, builder))
+    }
+
+    pub fn build_client(&self, user_agent: &str) -> Result<Client> {
+        Ok(self.client_builder(user_agent)?.build()?)
+    }
+
+    /// An async client with these settings, for [`crate::async_pipeline`].
+    #[cfg(feature = "async")]
+    pub(crate) fn build_async_client(
+        &self,
+        user_agent: &str,
+    ) -> Result<reqwest::Client> {
+        let builder = reqwest::Client::builder()
+            .user_agent(user_agent)
+            .read_timeout(self.timeout.unwrap_or(DEFAULT_TIMEOUT));
+        Ok(configure!(self, builder).build()?)
+    }
+
+    fn read_ca_certs(&self) -> io::Result<Vec<Vec<u8>>> {
+        self.ca_certs
+            .iter()
+            .map(|path| {
+                fs::read(path).map_err(|e| {
+                    io::Error::new(
+                        e.kind(),
+                        format!("could not read {}: {}", path.display(), e),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GitripperError;
+
+    #[test]
+    fn test_builds_configured_client() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let missing = HttpOptions {
+            ca_certs: vec![temp_dir.path().join("missing.pem")],
+            ..Default::default()
+        };
+        assert!(matches!(
+            missing.build_client("test"),
+            Err(GitripperError::Io(_))
+        ));
+
+        let options = HttpOptions {
+            proxy: Some("http://proxy.example.com:3128".to_string()),
+            insecure: true,
+            timeout: Some(Duration::from_secs(600)),
+            tcp_keepalive: Some(Duration::from_secs(15)),
+            http1_only: true,
+            ..Default::default()
+        };
+        assert!(options.build_client("test").is_ok());
+    }
+}
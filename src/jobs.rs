@@ -0,0 +1,360 @@
+use std::{
+    collections::VecDeque,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Condvar, Mutex, MutexGuard},
+};
+
+use serde_json::{json, Value};
+use tempfile::NamedTempFile;
+
+/// Name of the state file batch runs keep in their destination.
+pub const JOBS_FILE: &str = ".gitripper-jobs.json";
+
+/// Where a [`Job`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+impl JobStatus {
+    pub fn name(self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Done => "done",
+            JobStatus::Failed => "failed",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [Self::Queued, Self::Running, Self::Done, Self::Failed]
+            .into_iter()
+            .find(|s| s.name() == name)
+    }
+}
+
+/// A rip of one URL in a [`JobQueue`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Job {
+    pub id:                u64,
+    pub url:               String,
+    /// Branch to rip instead of the default one.
+    pub branch:            Option<String>,
+    /// Where the rip goes, when that is chosen up front, or went.
+    pub dest:              Option<PathBuf>,
+    pub status:            JobStatus,
+    pub error:             Option<String>,
+    /// Progress of the job while it runs, which isn't saved.
+    pub downloaded_bytes:  u64,
+    pub total_bytes:       Option<u64>,
+    pub extracted_entries: u64,
+    pub total_entries:     Option<u64>,
+}
+
+impl Job {
+    fn new(id: u64, url: String) -> Self {
+        Self {
+            id,
+            url,
+            branch: None,
+            dest: None,
+            status: JobStatus::Queued,
+            error: None,
+            downloaded_bytes: 0,
+            total_bytes: None,
+            extracted_entries: 0,
+            total_entries: None,
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "url": self.url,
+            "branch": self.branch,
+            "dest": self.dest,
+            "status": self.status.name(),
+            "error": self.error,
+        })
+    }
+
+    fn from_json(v: &Value) -> Option<Self> {
+        let optional =
+            |key| v.get(key).and_then(Value::as_str).map(str::to_string);
+        let mut job = Self::new(v.get("id")?.as_u64()?, optional("url")?);
+        job.branch = optional("branch");
+        job.dest = optional("dest").map(PathBuf::from);
+        job.status = JobStatus::from_name(v.get("status")?.as_str()?)?;
+        job.error = optional("error");
+        Some(job)
+    }
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    jobs:    Vec<Job>,
+    /// Indexes into `jobs` of the queued jobs, oldest first.
+    pending: VecDeque<usize>,
+    stopped: bool,
+}
+
+/// Rips waiting, running and finished, shared between threads and saved to
+/// a state file whenever a job is added or changes status, so an interrupted
+/// run can pick up where it stopped with [`JobQueue::resume`].
+#[derive(Debug, Default)]
+pub struct JobQueue {
+    path:  Option<PathBuf>,
+    state: Mutex<QueueState>,
+    ready: Condvar,
+}
+
+impl JobQueue {
+    /// An empty queue that isn't saved anywhere.
+    pub fn new() -> Self { Self::default() }
+
+    /// An empty queue saved to `path`, replacing any state already there.
+    pub fn create(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let queue = Self {
+            path: Some(path.into()),
+            ..Self::default()
+        };
+        queue.save(&queue.lock())?;
+        Ok(queue)
+    }
+
+    /// The queue saved at `path`, or an empty one if there is none yet. Jobs
+    /// that didn't finish, whether still queued, interrupted or failed, are
+    /// queued again.
+    pub fn resume(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Self::create(path);
+            },
+            Err(e) => return Err(e),
+        };
+        let v: Value = serde_json::from_str(&text)?;
+        let jobs = v
+            .get("jobs")
+            .and_then(Value::as_array)
+            .and_then(|jobs| jobs.iter().map(Job::from_json).collect())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("malformed job state in {}", path.display()),
+                )
+            })?;
+
+        let mut state = QueueState {
+            jobs,
+            ..QueueState::default()
+        };
+        for (index, job) in state.jobs.iter_mut().enumerate() {
+            if job.status != JobStatus::Done {
+                job.status = JobStatus::Queued;
+                job.error = None;
+                state.pending.push_back(index);
+            }
+        }
+        let queue = Self {
+            path:  Some(path),
+            state: Mutex::new(state),
+            ready: Condvar::new(),
+        };
+        queue.save(&queue.lock())?;
+        Ok(queue)
+    }
+
+    /// Queues a rip of `url`, of `branch` if given, into where `dest` says
+    /// the job with the id it is given goes, if anywhere.
+    pub fn push(
+        &self,
+        url: impl Into<String>,
+        branch: Option<String>,
+        dest: impl FnOnce(u64) -> Option<PathBuf>,
+    ) -> io::Result<Job> {
+        let mut state = self.lock();
+        let id = state.jobs.iter().map(|j| j.id).max().unwrap_or(0) + 1;
+        let mut job = Job::new(id, url.into());
+        job.branch = branch;
+        job.dest = dest(id);
+
+        let index = state.jobs.len();
+        state.jobs.push(job.clone());
+        state.pending.push_back(index);
+        self.save(&state)?;
+        self.ready.notify_one();
+        Ok(job)
+    }
+
+    /// Queues each of `urls` that has no job yet, returning how many did.
+    pub fn extend_missing(&self, urls: &[String]) -> io::Result<usize> {
+        let mut state = self.lock();
+        let mut added = 0;
+        for url in urls {
+            if state.jobs.iter().any(|j| j.url == *url) {
+                continue;
+            }
+            let id = state.jobs.iter().map(|j| j.id).max().unwrap_or(0) + 1;
+            let index = state.jobs.len();
+            state.jobs.push(Job::new(id, url.clone()));
+            state.pending.push_back(index);
+            added += 1;
+        }
+        self.save(&state)?;
+        self.ready.notify_all();
+        Ok(added)
+    }
+
+    /// Every job, in the order they were added.
+    pub fn jobs(&self) -> Vec<Job> { self.lock().jobs.clone() }
+
+    pub fn get(&self, id: u64) -> Option<Job> {
+        self.lock().jobs.iter().find(|j| j.id == id).cloned()
+    }
+
+    /// Takes the oldest queued job and marks it running, without waiting.
+    pub fn try_next(&self) -> Option<Job> { self.take(&mut self.lock()) }
+
+    /// Waits for a queued job and marks it running, `None` once the queue
+    /// is [stopped](Self::stop).
+    pub fn next(&self) -> Option<Job> {
+        let mut state = self.lock();
+        loop {
+            if state.stopped {
+                return None;
+            }
+            if let Some(job) = self.take(&mut state) {
+                return Some(job);
+            }
+            state = self.ready.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// Records how job `id` ended: where it went, or why it failed.
+    pub fn finish(
+        &self,
+        id: u64,
+        outcome: std::result::Result<Option<PathBuf>, String>,
+    ) -> io::Result<()> {
+        let mut state = self.lock();
+        if let Some(job) = state.jobs.iter_mut().find(|j| j.id == id) {
+            match outcome {
+                Ok(dest) => {
+                    job.status = JobStatus::Done;
+                    job.dest = dest.or(job.dest.take());
+                },
+                Err(error) => {
+                    job.status = JobStatus::Failed;
+                    job.error = Some(error);
+                },
+            }
+        }
+        self.save(&state)
+    }
+
+    /// Makes [`JobQueue::next`] return `None` from now on.
+    pub fn stop(&self) {
+        self.lock().stopped = true;
+        self.ready.notify_all();
+    }
+
+    /// Changes job `id` without saving the queue, for progress.
+    pub(crate) fn update(&self, id: u64, f: impl FnOnce(&mut Job)) {
+        if let Some(job) = self.lock().jobs.iter_mut().find(|j| j.id == id) {
+            f(job);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn take(&self, state: &mut QueueState) -> Option<Job> {
+        let index = state.pending.pop_front()?;
+        let job = &mut state.jobs[index];
+        job.status = JobStatus::Running;
+        let job = job.clone();
+        // Resuming queues running jobs again, so failing to record one only
+        // leaves the state file a step behind.
+        let _ = self.save(state);
+        Some(job)
+    }
+
+    /// Replaces the state file, if there is one, all at once.
+    fn save(&self, state: &QueueState) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        fs::create_dir_all(dir)?;
+        let jobs: Vec<Value> = state.jobs.iter().map(Job::to_json).collect();
+        let text = serde_json::to_string_pretty(&json!({ "jobs": jobs }))?;
+
+        let file = NamedTempFile::new_in(dir)?;
+        fs::write(file.path(), text + "\n")?;
+        file.persist(path).map_err(|e| e.error)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_requeues_unfinished_jobs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("state").join(JOBS_FILE);
+        let urls: Vec<String> =
+            ["a", "b", "c", "d"].iter().map(|u| u.to_string()).collect();
+
+        let queue = JobQueue::create(&path).unwrap();
+        assert_eq!(queue.extend_missing(&urls[..3]).unwrap(), 3);
+        let a = queue.try_next().unwrap();
+        queue.finish(a.id, Ok(Some(PathBuf::from("out/a")))).unwrap();
+        let b = queue.try_next().unwrap();
+        queue.finish(b.id, Err("boom".to_string())).unwrap();
+        // `c` is left running, as if the run was killed.
+        queue.try_next().unwrap();
+        drop(queue);
+
+        let queue = JobQueue::resume(&path).unwrap();
+        assert_eq!(queue.extend_missing(&urls).unwrap(), 1);
+        let jobs = queue.jobs();
+        let statuses: Vec<_> = jobs
+            .iter()
+            .map(|j| (j.url.as_str(), j.status, j.error.clone()))
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ("a", JobStatus::Done, None),
+                ("b", JobStatus::Queued, None),
+                ("c", JobStatus::Queued, None),
+                ("d", JobStatus::Queued, None),
+            ]
+        );
+        assert_eq!(queue.get(1).unwrap().dest, Some(PathBuf::from("out/a")));
+
+        let next: Vec<_> =
+            std::iter::from_fn(|| queue.try_next()).map(|j| j.id).collect();
+        assert_eq!(next, [2, 3, 4]);
+        let e = queue
+            .push("e", None, |id| Some(PathBuf::from(id.to_string())))
+            .unwrap();
+        assert_eq!((e.id, e.dest), (5, Some(PathBuf::from("5"))));
+
+        let created = JobQueue::create(&path).unwrap();
+        assert!(created.jobs().is_empty());
+        assert!(JobQueue::resume(&path).unwrap().jobs().is_empty());
+    }
+}
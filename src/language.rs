@@ -0,0 +1,116 @@
+use std::path::Path;
+
+use phf::{phf_map, Map};
+
+/// What a file holds, as far as its name tells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileKind {
+    /// Programming language or format, e.g. `Rust` or `Markdown`.
+    pub language: &'static str,
+    pub mime:     &'static str,
+}
+
+const fn kind(language: &'static str, mime: &'static str) -> FileKind {
+    FileKind { language, mime }
+}
+
+/// Kinds keyed by lowercase file extension.
+static KIND_BY_EXT: Map<&'static str, FileKind> = phf_map! {
+    "rs" => kind("Rust", "text/x-rust"),
+    "c" => kind("C", "text/x-c"),
+    "h" => kind("C", "text/x-c"),
+    "cc" => kind("C++", "text/x-c++"),
+    "cpp" => kind("C++", "text/x-c++"),
+    "cxx" => kind("C++", "text/x-c++"),
+    "hpp" => kind("C++", "text/x-c++"),
+    "cs" => kind("C#", "text/x-csharp"),
+    "go" => kind("Go", "text/x-go"),
+    "java" => kind("Java", "text/x-java"),
+    "kt" => kind("Kotlin", "text/x-kotlin"),
+    "kts" => kind("Kotlin", "text/x-kotlin"),
+    "scala" => kind("Scala", "text/x-scala"),
+    "swift" => kind("Swift", "text/x-swift"),
+    "m" => kind("Objective-C", "text/x-objectivec"),
+    "py" => kind("Python", "text/x-python"),
+    "rb" => kind("Ruby", "text/x-ruby"),
+    "php" => kind("PHP", "application/x-httpd-php"),
+    "pl" => kind("Perl", "text/x-perl"),
+    "lua" => kind("Lua", "text/x-lua"),
+    "js" => kind("JavaScript", "text/javascript"),
+    "mjs" => kind("JavaScript", "text/javascript"),
+    "cjs" => kind("JavaScript", "text/javascript"),
+    "jsx" => kind("JavaScript", "text/javascript"),
+    "ts" => kind("TypeScript", "application/typescript"),
+    "tsx" => kind("TypeScript", "application/typescript"),
+    "html" => kind("HTML", "text/html"),
+    "htm" => kind("HTML", "text/html"),
+    "css" => kind("CSS", "text/css"),
+    "scss" => kind("SCSS", "text/x-scss"),
+    "sh" => kind("Shell", "application/x-sh"),
+    "bash" => kind("Shell", "application/x-sh"),
+    "zsh" => kind("Shell", "application/x-sh"),
+    "ps1" => kind("PowerShell", "text/plain"),
+    "sql" => kind("SQL", "application/sql"),
+    "hs" => kind("Haskell", "text/x-haskell"),
+    "ex" => kind("Elixir", "text/x-elixir"),
+    "exs" => kind("Elixir", "text/x-elixir"),
+    "erl" => kind("Erlang", "text/x-erlang"),
+    "clj" => kind("Clojure", "text/x-clojure"),
+    "zig" => kind("Zig", "text/x-zig"),
+    "dart" => kind("Dart", "application/dart"),
+    "md" => kind("Markdown", "text/markdown"),
+    "rst" => kind("reStructuredText", "text/x-rst"),
+    "txt" => kind("Text", "text/plain"),
+    "json" => kind("JSON", "application/json"),
+    "yaml" => kind("YAML", "application/yaml"),
+    "yml" => kind("YAML", "application/yaml"),
+    "toml" => kind("TOML", "application/toml"),
+    "xml" => kind("XML", "application/xml"),
+    "csv" => kind("CSV", "text/csv"),
+    "proto" => kind("Protocol Buffers", "text/plain"),
+    "png" => kind("Image", "image/png"),
+    "jpg" => kind("Image", "image/jpeg"),
+    "jpeg" => kind("Image", "image/jpeg"),
+    "gif" => kind("Image", "image/gif"),
+    "svg" => kind("Image", "image/svg+xml"),
+    "webp" => kind("Image", "image/webp"),
+    "ico" => kind("Image", "image/vnd.microsoft.icon"),
+    "pdf" => kind("PDF", "application/pdf"),
+    "zip" => kind("Archive", "application/zip"),
+    "gz" => kind("Archive", "application/gzip"),
+    "tar" => kind("Archive", "application/x-tar"),
+    "wasm" => kind("WebAssembly", "application/wasm"),
+};
+
+/// Kinds of files recognized by their whole name.
+static KIND_BY_NAME: Map<&'static str, FileKind> = phf_map! {
+    "Makefile" => kind("Makefile", "text/x-makefile"),
+    "Dockerfile" => kind("Dockerfile", "text/x-dockerfile"),
+    "CMakeLists.txt" => kind("CMake", "text/x-cmake"),
+    "Cargo.lock" => kind("TOML", "application/toml"),
+};
+
+/// Kind of the file at `path`, from its name or else its extension.
+pub fn detect_kind(path: &Path) -> Option<FileKind> {
+    let name = path.file_name()?.to_str()?;
+    if let Some(kind) = KIND_BY_NAME.get(name) {
+        return Some(*kind);
+    }
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    KIND_BY_EXT.get(ext.as_str()).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_kind() {
+        let language = |p: &str| detect_kind(Path::new(p)).map(|k| k.language);
+        assert_eq!(language("src/main.rs"), Some("Rust"));
+        assert_eq!(language("docs/README.MD"), Some("Markdown"));
+        assert_eq!(language("build/Makefile"), Some("Makefile"));
+        assert_eq!(language("Cargo.lock"), Some("TOML"));
+        assert_eq!(language("LICENSE"), None);
+    }
+}
@@ -0,0 +1,117 @@
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use crate::{GitripperError, RepoLocation};
+
+/// Layout batch and org rips use unless another is configured.
+pub const DEFAULT_DEST_TEMPLATE: &str = "{owner}/{repo}";
+
+const PLACEHOLDERS: [&str; 4] = ["owner", "repo", "ref", "provider"];
+
+/// A destination path template such as `{owner}/{repo}-{ref}`.
+///
+/// Placeholders are `{owner}`, `{repo}`, `{ref}` (the resolved branch, tag
+/// or commit) and `{provider}` (lowercase, e.g. `github`). Slashes in
+/// `{ref}` become `-`, so a branch like `feature/x` stays one path
+/// component; a GitLab `group/subgroup` owner keeps its nesting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DestLayout {
+    template: String,
+}
+
+impl DestLayout {
+    /// Fails on unknown or unterminated placeholders.
+    pub fn new(template: impl Into<String>) -> Result<Self, GitripperError> {
+        let template = template.into();
+        let mut rest = template.as_str();
+
+        while let Some(start) = rest.find('{') {
+            let Some(len) = rest[start..].find('}') else {
+                return Err(GitripperError::InvalidTemplate(format!(
+                    "unterminated placeholder in '{}'",
+                    template
+                )));
+            };
+            let name = &rest[start + 1..start + len];
+            if !PLACEHOLDERS.contains(&name) {
+                return Err(GitripperError::InvalidTemplate(format!(
+                    "unknown placeholder {{{}}} in '{}'; expected one of {}",
+                    name,
+                    template,
+                    PLACEHOLDERS.map(|p| format!("{{{}}}", p)).join(", ")
+                )));
+            }
+            rest = &rest[start + len + 1..];
+        }
+
+        Ok(Self { template })
+    }
+
+    pub fn template(&self) -> &str { &self.template }
+
+    /// Relative (unless the template is absolute) destination of `location`
+    /// ripped at `reference` from the provider named `provider`.
+    pub fn render(
+        &self,
+        provider: &str,
+        location: &RepoLocation,
+        reference: &str,
+    ) -> PathBuf {
+        PathBuf::from(
+            self.template
+                .replace("{owner}", &location.owner)
+                .replace("{repo}", &location.repo)
+                .replace("{ref}", &reference.replace(['/', '\\'], "-"))
+                .replace("{provider}", &provider.to_lowercase()),
+        )
+    }
+}
+
+impl Default for DestLayout {
+    fn default() -> Self {
+        Self {
+            template: DEFAULT_DEST_TEMPLATE.to_string(),
+        }
+    }
+}
+
+impl FromStr for DestLayout {
+    type Err = GitripperError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> { Self::new(s) }
+}
+
+impl fmt::Display for DestLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.template)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_substitutes_placeholders() {
+        let layout =
+            DestLayout::new("{provider}/{owner}/{repo}-{ref}").unwrap();
+        let location = RepoLocation::new("rust-lang", "regex");
+
+        assert_eq!(
+            layout.render("GitHub", &location, "feature/x"),
+            PathBuf::from("github/rust-lang/regex-feature-x")
+        );
+    }
+
+    #[test]
+    fn test_new_rejects_bad_placeholders() {
+        assert!(matches!(
+            DestLayout::new("{owner}/{name}"),
+            Err(GitripperError::InvalidTemplate(_))
+        ));
+        assert!(matches!(
+            DestLayout::new("{owner"),
+            Err(GitripperError::InvalidTemplate(_))
+        ));
+        assert!(DestLayout::new("mirrors/{repo}").is_ok());
+    }
+}
@@ -0,0 +1,276 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use reqwest::blocking::Client;
+use serde_json::{json, Value};
+
+use crate::{
+    compute_sha256_hex, provider::rate_limit_error, GitripperError, Provider,
+    RepoLocation, Result,
+};
+
+/// First line of every Git LFS pointer file.
+const POINTER_VERSION: &str = "version https://git-lfs.github.com/spec/v1";
+/// Pointer files are tiny; anything bigger is real content.
+const MAX_POINTER_SIZE: u64 = 1024;
+/// Objects per batch request, the limit most LFS servers enforce.
+const BATCH_SIZE: usize = 100;
+const LFS_MEDIA_TYPE: &str = "application/vnd.git-lfs+json";
+
+/// The object a Git LFS pointer file stands in for.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LfsPointer {
+    /// SHA-256 of the object, in hex.
+    pub oid:  String,
+    pub size: u64,
+}
+
+/// Parses the contents of a Git LFS pointer file.
+pub fn parse_lfs_pointer(data: &[u8]) -> Option<LfsPointer> {
+    let text = std::str::from_utf8(data).ok()?;
+    let mut lines = text.lines();
+    if lines.next()? != POINTER_VERSION {
+        return None;
+    }
+
+    let (mut oid, mut size) = (None, None);
+    for line in lines {
+        match line.split_once(' ') {
+            Some(("oid", v)) => {
+                oid = v.strip_prefix("sha256:").map(str::to_string)
+            },
+            Some(("size", v)) => size = v.parse().ok(),
+            _ => {},
+        }
+    }
+
+    let oid = oid.filter(|o| {
+        o.len() == 64 && o.bytes().all(|b| b.is_ascii_hexdigit())
+    })?;
+    Some(LfsPointer { oid, size: size? })
+}
+
+/// Every LFS pointer file under `root`, keyed by the object it points to.
+/// The repository at the top of `root` is skipped.
+pub(crate) fn find_lfs_pointers(
+    root: &Path,
+) -> io::Result<BTreeMap<LfsPointer, Vec<PathBuf>>> {
+    let mut pointers = BTreeMap::new();
+    collect_pointers(root, true, &mut pointers)?;
+    Ok(pointers)
+}
+
+fn collect_pointers(
+    dir: &Path,
+    top: bool,
+    out: &mut BTreeMap<LfsPointer, Vec<PathBuf>>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if top && entry.file_name() == ".git" {
+            continue;
+        }
+
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_pointers(&entry.path(), false, out)?;
+        } else if file_type.is_file()
+            && entry.metadata()?.len() <= MAX_POINTER_SIZE
+        {
+            let mut data = Vec::new();
+            File::open(entry.path())?.read_to_end(&mut data)?;
+            if let Some(pointer) = parse_lfs_pointer(&data) {
+                out.entry(pointer).or_default().push(entry.path());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the pointer files in `pointers` with the objects they stand
+/// for, fetched through the LFS batch API of `location`. Returns how many
+/// objects were downloaded.
+pub(crate) fn fetch_lfs_objects(
+    client: &Client,
+    provider: &dyn Provider,
+    location: &RepoLocation,
+    token: Option<&str>,
+    pointers: &BTreeMap<LfsPointer, Vec<PathBuf>>,
+) -> Result<usize> {
+    let endpoint =
+        format!("{}/info/lfs/objects/batch", provider.clone_url(location));
+    let all: Vec<&LfsPointer> = pointers.keys().collect();
+
+    for chunk in all.chunks(BATCH_SIZE) {
+        let objects: Vec<Value> = chunk
+            .iter()
+            .map(|p| json!({ "oid": p.oid, "size": p.size }))
+            .collect();
+        let body = json!({
+            "operation": "download",
+            "transfers": ["basic"],
+            "objects": objects,
+        });
+
+        let mut req = client
+            .post(&endpoint)
+            .header("Accept", LFS_MEDIA_TYPE)
+            .header("Content-Type", LFS_MEDIA_TYPE)
+            .body(body.to_string());
+        if let Some(t) = token {
+            let (user, password) = provider.git_credentials(t);
+            req = req.basic_auth(user, Some(password));
+        }
+
+        let res = req.send()?;
+        if let Some(e) = rate_limit_error(res.status(), res.headers()) {
+            return Err(e);
+        }
+        let status = res.status();
+        if !status.is_success() {
+            let txt = res.text().unwrap_or_default();
+            return Err(GitripperError::HttpStatus {
+                status:  status.as_u16(),
+                message: format!(
+                    "LFS batch request failed: {} {}",
+                    status, txt
+                ),
+            });
+        }
+
+        let v: Value = res.json()?;
+        for object in v
+            .get("objects")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+        {
+            download_object(client, object, pointers)?;
+        }
+    }
+
+    Ok(pointers.len())
+}
+
+/// Downloads one object of a batch response over every pointer file that
+/// refers to it.
+fn download_object(
+    client: &Client,
+    object: &Value,
+    pointers: &BTreeMap<LfsPointer, Vec<PathBuf>>,
+) -> Result<()> {
+    let oid = object.get("oid").and_then(Value::as_str).unwrap_or_default();
+    let Some((pointer, paths)) = pointers.iter().find(|(p, _)| p.oid == oid)
+    else {
+        return Ok(());
+    };
+
+    if let Some(error) = object.get("error") {
+        let message = error.get("message").and_then(Value::as_str);
+        return Err(GitripperError::HttpStatus {
+            status:  error.get("code").and_then(Value::as_u64).unwrap_or(404)
+                as u16,
+            message: format!(
+                "LFS object {} is unavailable: {}",
+                oid,
+                message.unwrap_or("unknown error")
+            ),
+        });
+    }
+    let Some(download) = object.pointer("/actions/download") else {
+        return Err(GitripperError::HttpStatus {
+            status:  404,
+            message: format!("LFS object {} has no download action", oid),
+        });
+    };
+
+    let href = download.get("href").and_then(Value::as_str).unwrap_or("");
+    let mut req = client.get(href);
+    if let Some(headers) = download.get("header").and_then(Value::as_object) {
+        for (name, value) in headers {
+            if let Some(value) = value.as_str() {
+                req = req.header(name.as_str(), value);
+            }
+        }
+    }
+
+    let mut res = req.send()?;
+    let status = res.status();
+    if !status.is_success() {
+        return Err(GitripperError::HttpStatus {
+            status:  status.as_u16(),
+            message: format!(
+                "Failed to download LFS object {}: {}",
+                oid, status
+            ),
+        });
+    }
+
+    // Write next to the first pointer so the rename below stays on one
+    // filesystem, and only replace pointers once the hash checks out.
+    let first = &paths[0];
+    let partial = first.with_file_name(format!(".{}.lfs-partial", oid));
+    io::copy(&mut res, &mut File::create(&partial)?)?;
+    let actual = compute_sha256_hex(&partial)?;
+    if actual != pointer.oid {
+        let _ = fs::remove_file(&partial);
+        return Err(GitripperError::ChecksumMismatch {
+            name: first.display().to_string(),
+            expected: pointer.oid.clone(),
+            actual,
+        });
+    }
+
+    for path in &paths[1..] {
+        fs::copy(&partial, path)?;
+    }
+    fs::rename(&partial, first)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, write};
+
+    use super::*;
+
+    const OID: &str =
+        "4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393";
+
+    fn pointer_text(oid: &str) -> String {
+        format!("{}\noid sha256:{}\nsize 12345\n", POINTER_VERSION, oid)
+    }
+
+    #[test]
+    fn test_parse_lfs_pointer() {
+        let pointer = parse_lfs_pointer(pointer_text(OID).as_bytes()).unwrap();
+        assert_eq!(pointer.oid, OID);
+        assert_eq!(pointer.size, 12345);
+
+        assert_eq!(parse_lfs_pointer(b"just a text file\n"), None);
+        assert_eq!(parse_lfs_pointer(pointer_text("abc").as_bytes()), None);
+    }
+
+    #[test]
+    fn test_find_lfs_pointers_groups_by_object() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        create_dir_all(root.join("assets")).unwrap();
+        create_dir_all(root.join(".git")).unwrap();
+        write(root.join("assets/a.bin"), pointer_text(OID)).unwrap();
+        write(root.join("b.bin"), pointer_text(OID)).unwrap();
+        write(root.join(".git/c.bin"), pointer_text(OID)).unwrap();
+        write(root.join("README.md"), "hello").unwrap();
+
+        let pointers = find_lfs_pointers(root).unwrap();
+
+        assert_eq!(pointers.len(), 1);
+        let mut paths = pointers.values().next().unwrap().clone();
+        paths.sort();
+        assert_eq!(paths, [root.join("assets/a.bin"), root.join("b.bin")]);
+    }
+}
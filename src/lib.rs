@@ -1,29 +1,183 @@
-use std::{
-    fs::{create_dir_all, read_to_string, set_permissions, File, Permissions},
-    io::{self, Cursor, Write},
-    os::unix::fs::PermissionsExt,
-    path::{Path, PathBuf},
-};
+mod archive;
+#[cfg(feature = "async")]
+pub mod async_pipeline;
+#[cfg(feature = "native")]
+mod attest;
+#[cfg(feature = "native")]
+mod auth;
+mod azure;
+#[cfg(feature = "native")]
+mod batch;
+mod bitbucket;
+#[cfg(feature = "native")]
+mod cache;
+mod cancel;
+#[cfg(feature = "capi")]
+pub mod capi;
+mod collision;
+#[cfg(feature = "native")]
+mod config;
+#[cfg(feature = "native")]
+mod dedupe;
+#[cfg(feature = "native")]
+mod diff;
+mod direct;
+mod error;
+mod extract;
+#[cfg(feature = "native")]
+mod git;
+#[cfg(all(feature = "native", not(feature = "git2")))]
+mod git_cli;
+#[cfg(feature = "git2")]
+mod git_libgit2;
+mod git_remote;
+mod github;
+#[cfg(feature = "native")]
+mod github_app;
+mod gitlab;
+#[cfg(feature = "native")]
+mod hooks;
+#[cfg(feature = "native")]
+mod http;
+#[cfg(feature = "native")]
+mod jobs;
+mod language;
+mod layout;
+#[cfg(feature = "native")]
+mod lfs;
+#[cfg(feature = "native")]
+mod license;
+#[cfg(feature = "native")]
+mod manifest;
+#[cfg(feature = "native")]
+mod metadata;
+#[cfg(feature = "mock")]
+pub mod mock;
+#[cfg(feature = "native")]
+mod nested;
+mod progress;
+#[cfg(feature = "native")]
+mod provenance;
+mod provider;
+#[cfg(feature = "native")]
+mod receipt;
+#[cfg(feature = "native")]
+mod release;
+#[cfg(feature = "native")]
+mod retry;
+#[cfg(feature = "native")]
+mod ripper;
+#[cfg(feature = "native")]
+mod scheduler;
+#[cfg(feature = "native")]
+mod secrets;
+#[cfg(feature = "native")]
+mod server;
+mod sourcehut;
+#[cfg(feature = "native")]
+mod stats;
+#[cfg(feature = "native")]
+mod template;
 
-use anyhow::anyhow;
-use memmap2::MmapOptions;
 use once_cell::sync::Lazy;
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use regex::Regex;
-use zip::ZipArchive;
 
-const RE_GITHUB_PATTERN: &str = r"(?xi)^(?:https?://github\.com/|git@github\.com:|ssh://git@github\.com/)([^/]+)/([^/]+?)(?:\.git)?(?:/|$)";
-const PARALLEL_THRESHOLD_BYTES: u64 = 10_485_760; // 10 MB
+#[cfg(feature = "zip")]
+pub use crate::extract::{
+    archive_commit, extract_zip, extract_zip_into, extract_zip_stream,
+    extract_zip_with, iter_zip_entries, iter_zip_entries_with,
+};
+use crate::github::parse_github_suffix;
+pub use crate::{
+    archive::{ArchiveFormat, ArchiveTarget},
+    azure::{parse_azure_url, AzureDevOps, AZURE_HOST},
+    bitbucket::{parse_bitbucket_url, Bitbucket, BITBUCKET_API},
+    cancel::CancellationToken,
+    collision::{CollisionPolicy, FsSemantics},
+    direct::{parse_archive_url, DirectArchive},
+    error::{GitripperError, Result},
+    extract::{
+        extract_archive_with, extract_tar_with, looks_binary, write_entry,
+        DiskTarget, EntryAction, EntryHook, Eol, ExtractOptions, ExtractTarget,
+        MemEntry, MemoryTarget, SkipReason, SkippedFile, StripRoot,
+        DEFAULT_MEMORY_BUDGET, DETERMINISTIC_MTIME, STRIP_META_PATTERNS,
+    },
+    git_remote::{parse_git_url, GitRemote},
+    github::{
+        GitHub, OrgRepo, RepoFilter, RepoMatch, UserRepos, DEFAULT_BRANCH,
+        GITHUB_API, GITHUB_HOST,
+    },
+    gitlab::{parse_gitlab_url, GitLab, GITLAB_HOST},
+    language::{detect_kind, FileKind},
+    layout::{DestLayout, DEFAULT_DEST_TEMPLATE},
+    progress::{NoProgress, ProgressSink},
+    provider::{
+        default_providers, detect_provider, Provider, RefType, ReleaseAsset,
+        RemoteRef, RepoLocation, TreeEntry, LATEST_RELEASE,
+    },
+    sourcehut::{parse_sourcehut_url, SourceHut, SOURCEHUT_HOST},
+};
+#[cfg(feature = "native")]
+pub use crate::{
+    attest::{
+        provenance_statement, sign_attestation, write_attestation, BuilderInfo,
+        RipSource, ATTESTATION_BUNDLE_FILE, ATTESTATION_FILE,
+    },
+    auth::{
+        credential_host, delete_token, find_token, store_token, token_from_gh,
+        token_from_git_credential, token_from_keyring, TokenSource,
+        KEYRING_SERVICE,
+    },
+    batch::{read_url_list, BatchResult, DEFAULT_BATCH_PARALLELISM},
+    cache::{
+        compute_blake3_hex, compute_sha256_hex, Cache, CacheEntry, CleanPolicy,
+        CleanReport,
+    },
+    config::{Config, CONFIG_FILE},
+    dedupe::{dedupe_tree, DedupeReport},
+    diff::{diff_trees, ChangeKind, FileChange, SnapshotDiff},
+    extract::normalize_tree,
+    git::{
+        add_upstream_remote, check_git_installed, clone_with_history,
+        commit_branch, commit_snapshot, commit_vendored, initialize_bare_repo,
+        initialize_repo, initialize_repo_with, push_head,
+        remote_default_branch, remove_embedded_git, set_config, set_origin,
+        set_upstream_base, tag_head, InitOptions, DEFAULT_AUTHOR_EMAIL,
+        DEFAULT_AUTHOR_NAME, DEFAULT_COMMIT_MESSAGE, UPSTREAM_BASE_REF,
+        UPSTREAM_REMOTE,
+    },
+    github::{download_zip, get_default_branch},
+    github_app::GitHubApp,
+    http::HttpOptions,
+    jobs::{Job, JobQueue, JobStatus, JOBS_FILE},
+    lfs::{parse_lfs_pointer, LfsPointer},
+    license::{detect_license, write_attribution, LicenseInfo, NOTICES_FILE},
+    manifest::{verify_manifest, write_manifest, MANIFEST_FILE},
+    metadata::{write_metadata, RepoMetadata, METADATA_FILE, METADATA_SECTION},
+    nested::{extract_nested, NestedReport},
+    provenance::{Provenance, PROVENANCE_FILE, PROVENANCE_FILE_NO_GIT},
+    provider::{
+        archive_size, download_archive, download_archive_ranged,
+        download_archive_to, download_asset, NotFoundCause,
+    },
+    receipt::{Receipt, RECEIPT_FILE},
+    release::{AssetCheck, DownloadedAsset},
+    retry::{is_transient, RetryPolicy, DEFAULT_RETRIES},
+    ripper::{
+        OverwritePolicy, RefKind, RipEvent, RipReport, Ripper, RipperBuilder,
+        UpdateReport, DEFAULT_UPDATE_MESSAGE, DEFAULT_USER_AGENT,
+        DEFAULT_VENDOR_MESSAGE,
+    },
+    scheduler::{BatchLimits, Stage},
+    secrets::{scan_secrets, SecretFinding, SecretPolicy},
+    server::{BuilderFactory, Server},
+    stats::{rip_stats, LanguageStats, RipStats},
+    template::{
+        render_template, TemplateManifest, TemplateVariable, TEMPLATE_MANIFEST,
+    },
+};
 
-#[derive(Debug)]
-pub struct MemEntry {
-    pub rel_path:   PathBuf,
-    pub is_dir:     bool,
-    pub _data_size: u64,
-    pub unix_mode:  Option<u32>,
-    pub _file_idx:  usize,
-    pub data:       Vec<u8>,
-}
+const RE_GITHUB_PATTERN: &str = r"(?xi)^(?:https?://github\.com/|git@github\.com:|ssh://git@github\.com/)([^/]+)/([^/]+?)(?:\.git)?(?:/|$)";
 
 pub fn parse_github_url(url: &str) -> Result<(String, String), &'static str> {
     static RE_GITHUB: Lazy<Regex> =
@@ -41,124 +195,26 @@ pub fn parse_github_url(url: &str) -> Result<(String, String), &'static str> {
     }
 }
 
-pub fn write_entry(entry: &MemEntry, dest_dir: &Path) -> anyhow::Result<()> {
-    let outpath = dest_dir.join(&entry.rel_path);
-
-    if entry.is_dir {
-        create_dir_all(&outpath)?;
-    } else {
-        if let Some(parent) = outpath.parent() {
-            create_dir_all(parent)?;
-        }
-        let mut outfile = File::create(&outpath)?;
-        outfile.write_all(&entry.data)?;
-
-        #[cfg(unix)]
-        if let Some(mode) = entry.unix_mode {
-            let _ = set_permissions(&outpath, Permissions::from_mode(mode));
-        }
-    }
-    Ok(())
-}
-
-pub fn extract_zip(zip_path: &Path, dest_dir: &Path) -> anyhow::Result<()> {
-    let f = File::open(zip_path)?;
-    let mmap = unsafe { MmapOptions::new().map(&f)? };
-    let cursor = Cursor::new(&mmap[..]);
-    let mut archive = ZipArchive::new(cursor)?;
-    let len = archive.len();
-
-    if len == 0 {
-        return Err(anyhow!("Zip archive is empty."));
-    }
-
-    create_dir_all(dest_dir)?;
-
-    let mut entries: Vec<MemEntry> = Vec::with_capacity(len);
-    let mut root_prefix: Option<PathBuf> = None;
-    let mut root_mismatch = false;
-    let mut total_size: u64 = 0;
-
-    for i in 0..len {
-        let mut file = archive.by_index(i)?;
-
-        let in_path = file
-            .enclosed_name()
-            .map(|p| p.to_path_buf())
-            .unwrap_or_else(|| PathBuf::from(file.name()));
-
-        if !root_mismatch {
-            if let Some(first) = in_path.components().next() {
-                let first_str = first.as_os_str().to_string_lossy();
-                if first_str.is_empty() {
-                    root_mismatch = true;
-                } else if let Some(ref current_prefix) = root_prefix {
-                    if current_prefix.as_os_str() != first.as_os_str() {
-                        root_mismatch = true;
-                    }
-                } else {
-                    root_prefix = Some(PathBuf::from(first.as_os_str()));
-                }
-            } else {
-                root_mismatch = true;
-            }
-        }
-
-        let rel_path = if !root_mismatch {
-            if let Some(ref root) = root_prefix {
-                match in_path.strip_prefix(root) {
-                    Ok(p) => p.to_path_buf(),
-                    Err(_) => {
-                        root_mismatch = true;
-                        in_path.clone()
-                    },
-                }
-            } else {
-                in_path.clone()
-            }
-        } else {
-            in_path.clone()
-        };
-
-        if rel_path.as_os_str().is_empty() {
-            continue;
-        }
-
-        let is_dir = file.name().ends_with('/');
-        let unix_mode = file.unix_mode();
-
-        let (data_size, data) = if is_dir {
-            (0, Vec::new())
-        } else {
-            let size = file.size();
-            let mut buf = Vec::with_capacity(size as usize);
-            io::copy(&mut file, &mut buf)?;
-            (size, buf)
-        };
-
-        total_size += data_size;
+/// Like [`parse_github_url`], but also picks up the ref, path or pull
+/// request of `tree/<ref>/<path>`, `blob/<ref>/<file>`, `commit/<sha>`,
+/// `releases/tag/<tag>` and `pull/<number>` URLs.
+///
+/// The first segment after `tree/` or `blob/` is taken as the ref, so branch
+/// names containing `/` need to be passed separately. A `blob/` URL selects
+/// the directory containing the file.
+pub fn parse_github_location(url: &str) -> Result<RepoLocation, &'static str> {
+    static RE_GITHUB: Lazy<Regex> =
+        Lazy::new(|| Regex::new(RE_GITHUB_PATTERN).unwrap());
 
-        entries.push(MemEntry {
-            rel_path,
-            is_dir,
-            _data_size: data_size,
-            unix_mode,
-            _file_idx: i,
-            data,
-        });
-    }
+    let (owner, repo) = parse_github_url(url)?;
+    let mut location = RepoLocation::new(owner, repo);
 
-    if total_size > PARALLEL_THRESHOLD_BYTES {
-        entries.into_par_iter().try_for_each(
-            |entry| -> anyhow::Result<()> { write_entry(&entry, dest_dir) },
-        )?;
-    } else {
-        for entry in entries {
-            write_entry(&entry, dest_dir)?;
-        }
+    let trimmed = url.trim();
+    if let Some(m) = RE_GITHUB.find(trimmed) {
+        parse_github_suffix(&mut location, &trimmed[m.end()..]);
     }
 
-    Ok(())
+    Ok(location)
 }
 
 #[cfg(test)]
@@ -242,82 +298,55 @@ mod tests {
     }
 
     #[test]
-    fn test_write_entry_file() {
-        let temp_dir = tempfile::tempdir().unwrap();
-        let dest = temp_dir.path();
-
-        let entry = MemEntry {
-            rel_path:   PathBuf::from("test.txt"),
-            is_dir:     false,
-            _data_size: 11,
-            unix_mode:  Some(0o644),
-            _file_idx:  0,
-            data:       b"hello world".to_vec(),
-        };
-
-        write_entry(&entry, dest).unwrap();
-
-        let file_path = dest.join("test.txt");
-        assert!(file_path.exists());
-        let content = read_to_string(&file_path).unwrap();
-        assert_eq!(content, "hello world");
+    fn test_parse_github_location_plain() {
+        let loc =
+            parse_github_location("https://github.com/user/repo").unwrap();
+        assert_eq!(loc.owner, "user");
+        assert_eq!(loc.repo, "repo");
+        assert_eq!(loc.reference, None);
+        assert_eq!(loc.path, None);
     }
 
     #[test]
-    fn test_write_entry_nested_file() {
-        let temp_dir = tempfile::tempdir().unwrap();
-        let dest = temp_dir.path();
-
-        let entry = MemEntry {
-            rel_path:   PathBuf::from("nested/dir/test.txt"),
-            is_dir:     false,
-            _data_size: 5,
-            unix_mode:  Some(0o644),
-            _file_idx:  0,
-            data:       b"hello".to_vec(),
-        };
-
-        write_entry(&entry, dest).unwrap();
-
-        let file_path = dest.join("nested/dir/test.txt");
-        assert!(file_path.exists());
-        let content = read_to_string(&file_path).unwrap();
-        assert_eq!(content, "hello");
+    fn test_parse_github_location_blob_commit_and_pull() {
+        let url = "https://github.com/user/repo/blob/main/src/lib.rs";
+        let loc = parse_github_location(url).unwrap();
+        assert_eq!(loc.reference.as_deref(), Some("main"));
+        assert_eq!(loc.path.as_deref(), Some("src"));
+
+        let loc = parse_github_location(
+            "https://github.com/user/repo/blob/v1/README",
+        )
+        .unwrap();
+        assert_eq!(loc.reference.as_deref(), Some("v1"));
+        assert_eq!(loc.path, None);
+
+        let url = "https://github.com/user/repo/commit/0123abc";
+        let loc = parse_github_location(url).unwrap();
+        assert_eq!(loc.reference.as_deref(), Some("0123abc"));
+
+        let url = "https://github.com/user/repo/pull/123/files";
+        let loc = parse_github_location(url).unwrap();
+        assert_eq!(loc.repo, "repo");
+        assert_eq!(loc.pull, Some(123));
+        assert_eq!(loc.reference, None);
     }
 
     #[test]
-    fn test_write_entry_directory() {
-        let temp_dir = tempfile::tempdir().unwrap();
-        let dest = temp_dir.path();
-
-        let entry = MemEntry {
-            rel_path:   PathBuf::from("mydir"),
-            is_dir:     true,
-            _data_size: 0,
-            unix_mode:  None,
-            _file_idx:  0,
-            data:       Vec::new(),
-        };
-
-        write_entry(&entry, dest).unwrap();
-
-        let dir_path = dest.join("mydir");
-        assert!(dir_path.is_dir());
+    fn test_parse_github_location_tree_with_path() {
+        let url = "https://github.com/user/repo/tree/main/crates/foo/";
+        let loc = parse_github_location(url).unwrap();
+        assert_eq!(loc.owner, "user");
+        assert_eq!(loc.repo, "repo");
+        assert_eq!(loc.reference.as_deref(), Some("main"));
+        assert_eq!(loc.path.as_deref(), Some("crates/foo"));
     }
 
     #[test]
-    fn test_mem_entry_debug() {
-        let entry = MemEntry {
-            rel_path:   PathBuf::from("test.txt"),
-            is_dir:     false,
-            _data_size: 5,
-            unix_mode:  Some(0o644),
-            _file_idx:  0,
-            data:       b"hello".to_vec(),
-        };
-
-        let debug_str = format!("{:?}", entry);
-        assert!(debug_str.contains("test.txt"));
-        assert!(debug_str.contains("false"));
+    fn test_parse_github_location_tree_ref_only() {
+        let url = "https://github.com/user/repo/tree/v1.0";
+        let loc = parse_github_location(url).unwrap();
+        assert_eq!(loc.reference.as_deref(), Some("v1.0"));
+        assert_eq!(loc.path, None);
     }
 }
@@ -0,0 +1,248 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{manifest::collect_files, RipReport};
+
+/// File [`write_attribution`] adds an entry to, at the top of the rip.
+pub const NOTICES_FILE: &str = "THIRD_PARTY_NOTICES.md";
+
+/// Header lines searched for `SPDX-License-Identifier` tags.
+const SPDX_HEADER_LINES: usize = 20;
+
+/// Distinctive phrases of common license texts and their SPDX identifiers.
+/// More specific licenses come before those whose phrases they share.
+const LICENSE_PHRASES: &[(&str, &[&str])] = &[
+    (
+        "AGPL-3.0",
+        &["GNU AFFERO GENERAL PUBLIC LICENSE", "Version 3"],
+    ),
+    (
+        "LGPL-3.0",
+        &["GNU LESSER GENERAL PUBLIC LICENSE", "Version 3"],
+    ),
+    (
+        "LGPL-2.1",
+        &["GNU LESSER GENERAL PUBLIC LICENSE", "Version 2.1"],
+    ),
+    ("GPL-3.0", &["GNU GENERAL PUBLIC LICENSE", "Version 3"]),
+    ("GPL-2.0", &["GNU GENERAL PUBLIC LICENSE", "Version 2"]),
+    ("Apache-2.0", &["Apache License", "Version 2.0"]),
+    ("MPL-2.0", &["Mozilla Public License Version 2.0"]),
+    ("BSL-1.0", &["Boost Software License - Version 1.0"]),
+    ("Unlicense", &["This is free and unencumbered software"]),
+    (
+        "ISC",
+        &["Permission to use, copy, modify, and/or distribute"],
+    ),
+    (
+        "BSD-3-Clause",
+        &[
+            "Redistribution and use in source and binary forms",
+            "Neither the name",
+        ],
+    ),
+    (
+        "BSD-2-Clause",
+        &["Redistribution and use in source and binary forms"],
+    ),
+    ("MIT", &["Permission is hereby granted, free of charge"]),
+];
+
+/// The license of a rip, as found by [`detect_license`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LicenseInfo {
+    /// SPDX identifier, if the license was recognized.
+    pub spdx: Option<String>,
+    /// License file at the top of the rip, relative to it.
+    pub file: Option<PathBuf>,
+    pub text: Option<String>,
+}
+
+/// Finds the license of the files under `dir`: a `LICENSE`, `LICENCE` or
+/// `COPYING` file at the top, recognized by its wording or an SPDX tag, or
+/// else the first `SPDX-License-Identifier` in the header of a source file.
+pub fn detect_license(dir: &Path) -> io::Result<LicenseInfo> {
+    let mut info = LicenseInfo::default();
+
+    let mut candidates = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+        .map(|e| e.file_name())
+        .filter(|name| is_license_file(&name.to_string_lossy()))
+        .collect::<Vec<_>>();
+    candidates.sort();
+    if let Some(name) = candidates.first() {
+        let text =
+            String::from_utf8_lossy(&fs::read(dir.join(name))?).into_owned();
+        info.spdx = spdx_tag(&text).or_else(|| license_by_phrase(&text));
+        info.file = Some(PathBuf::from(name));
+        info.text = Some(text);
+    }
+    if info.spdx.is_some() {
+        return Ok(info);
+    }
+
+    let mut files = Vec::new();
+    collect_files(dir, Path::new(""), &mut files)?;
+    files.sort();
+    for rel in files {
+        let Ok(text) = fs::read(dir.join(&rel)) else {
+            continue;
+        };
+        let header = String::from_utf8_lossy(&text[..text.len().min(4096)])
+            .lines()
+            .take(SPDX_HEADER_LINES)
+            .collect::<Vec<_>>()
+            .join("\n");
+        if let Some(spdx) = spdx_tag(&header) {
+            info.spdx = Some(spdx);
+            break;
+        }
+    }
+    Ok(info)
+}
+
+fn is_license_file(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    let stem = upper.split('.').next().unwrap_or_default();
+    ["LICENSE", "LICENCE", "COPYING"]
+        .iter()
+        .any(|p| stem == *p || stem.starts_with(&format!("{}-", p)))
+}
+
+fn spdx_tag(text: &str) -> Option<String> {
+    text.lines().find_map(|line| {
+        let (_, rest) = line.split_once("SPDX-License-Identifier:")?;
+        let id =
+            rest.trim().trim_end_matches("*/").trim_end_matches("-->").trim();
+        (!id.is_empty()).then(|| id.to_string())
+    })
+}
+
+fn license_by_phrase(text: &str) -> Option<String> {
+    // Line breaks fall anywhere in license texts, so compare by words.
+    let words = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    LICENSE_PHRASES
+        .iter()
+        .find(|(_, phrases)| phrases.iter().all(|p| words.contains(p)))
+        .map(|(spdx, _)| spdx.to_string())
+}
+
+/// Adds the entry of `report` with its license to the [`NOTICES_FILE`] in
+/// `dir`, replacing an earlier entry for the same repository and keeping
+/// anything else already in the file.
+pub fn write_attribution(
+    dir: &Path,
+    report: &RipReport,
+    license: &LicenseInfo,
+) -> io::Result<PathBuf> {
+    let path = dir.join(NOTICES_FILE);
+    let existing = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            "# Third-party notices\n".to_string()
+        },
+        Err(e) => return Err(e),
+    };
+
+    let name = format!("{}/{}", report.owner, report.repo);
+    let begin = format!("<!-- gitripper:{} -->", name);
+    let end = format!("<!-- /gitripper:{} -->", name);
+    let mut entry = format!("{}\n## {}\n\n", begin, name);
+    entry.push_str(&format!("- Provider: {}\n", report.provider));
+    entry.push_str(&format!("- Reference: {}\n", report.reference));
+    entry.push_str(&format!(
+        "- Commit: {}\n",
+        report.commit.as_deref().unwrap_or("unknown")
+    ));
+    entry.push_str(&format!(
+        "- License: {}\n",
+        license.spdx.as_deref().unwrap_or("unknown")
+    ));
+    if let Some(text) = &license.text {
+        entry.push_str(&format!("\n```text\n{}\n```\n", text.trim_end()));
+    }
+    entry.push_str(&end);
+
+    let text = match (existing.find(&begin), existing.find(&end)) {
+        (Some(start), Some(stop)) if start < stop => format!(
+            "{}{}{}",
+            &existing[..start],
+            entry,
+            &existing[stop + end.len()..]
+        ),
+        _ => format!("{}\n{}\n", existing.trim_end(), entry),
+    };
+    fs::write(&path, text)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+
+    use super::*;
+
+    #[test]
+    fn test_detects_license_file_by_wording() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        write(
+            dir.join("LICENSE-MIT"),
+            "MIT License\n\nPermission is hereby granted, free\nof charge, to \
+             any person",
+        )
+        .unwrap();
+
+        let info = detect_license(dir).unwrap();
+        assert_eq!(info.spdx.as_deref(), Some("MIT"));
+        assert_eq!(info.file, Some(PathBuf::from("LICENSE-MIT")));
+    }
+
+    #[test]
+    fn test_falls_back_to_spdx_headers() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::create_dir(dir.join("src")).unwrap();
+        write(
+            dir.join("src/lib.c"),
+            "/* SPDX-License-Identifier: Apache-2.0 OR MIT */\nint x;\n",
+        )
+        .unwrap();
+
+        let info = detect_license(dir).unwrap();
+        assert_eq!(info.spdx.as_deref(), Some("Apache-2.0 OR MIT"));
+        assert_eq!(info.file, None);
+    }
+
+    #[test]
+    fn test_attribution_entry_is_replaced() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        write(dir.join(NOTICES_FILE), "# Notices\n\nUpstream's own.\n")
+            .unwrap();
+        let mut report = RipReport {
+            provider:  "GitHub",
+            owner:     "o".to_string(),
+            repo:      "r".to_string(),
+            reference: "main".to_string(),
+            commit:    Some("abc".to_string()),
+            dest:      dir.to_path_buf(),
+        };
+        let license = LicenseInfo {
+            spdx: Some("MIT".to_string()),
+            ..Default::default()
+        };
+
+        write_attribution(dir, &report, &license).unwrap();
+        report.commit = Some("def".to_string());
+        write_attribution(dir, &report, &license).unwrap();
+
+        let text = fs::read_to_string(dir.join(NOTICES_FILE)).unwrap();
+        assert!(text.starts_with("# Notices\n\nUpstream's own.\n"));
+        assert_eq!(text.matches("## o/r").count(), 1);
+        assert!(text.contains("- Commit: def"));
+    }
+}
@@ -1,34 +1,46 @@
 use std::{
     env::var,
-    fs::{remove_dir_all, File},
-    io::{self, stdin, stdout, Write},
+    io::{self, stderr, stdin, stdout, IsTerminal, Write},
     path::{Path, PathBuf},
-    process::{exit, Command, Stdio},
-    time::{Duration, SystemTime},
+    process::exit,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    thread::sleep,
+    time::{Duration, Instant},
 };
 
-use anyhow::anyhow;
-use clap::Parser;
-use git2::{IndexAddOption, Repository, Signature};
-use gitripper::{extract_zip, parse_github_url};
-use ignore::{DirEntry, Error, WalkBuilder, WalkState};
-use once_cell::sync::Lazy;
-use phf::{phf_map, Map};
+use clap::{ArgGroup, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::{generate, Shell};
+use clap_mangen::Man;
+use dialoguer::{Confirm, FuzzySelect, Input, Password};
+use gitripper::{
+    credential_host, delete_token, detect_provider, find_token, is_transient,
+    push_head, read_url_list, rip_stats, set_origin, store_token,
+    verify_manifest, AssetCheck, BatchLimits, BuilderInfo, Cache,
+    CancellationToken, ChangeKind, CleanPolicy, CollisionPolicy, Config,
+    DestLayout, EntryAction, Eol, GitHub, GitHubApp, GitripperError,
+    HttpOptions, JobQueue, JobStatus, OverwritePolicy, ProgressSink,
+    Provenance, Provider, RefType, RepoFilter, RepoLocation, RetryPolicy,
+    RipEvent, RipReport, RipStats, Ripper, RipperBuilder, SecretPolicy, Server,
+    SkipReason, StripRoot, TemplateVariable, UserRepos,
+    DEFAULT_BATCH_PARALLELISM, DEFAULT_RETRIES, GITHUB_HOST, JOBS_FILE,
+    LATEST_RELEASE,
+};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use indicatif::{ProgressBar, ProgressStyle};
 use reqwest::blocking::Client;
-use serde_json::Value;
-use tempfile::tempdir;
-use WalkState::Continue;
+use serde_json::{json, Value};
 
-const DEFAULT_BRANCH: &str = "main";
-const DEFAULT_COMMIT_MESSAGE: &str = "Initial commit";
 const TIMEOUT_GET_REPO_SECS: u64 = 30;
 const TIMEOUT_DOWNLOAD_SECS: u64 = 60;
-const TIMEOUT_GET_REPO: Duration = Duration::from_secs(TIMEOUT_GET_REPO_SECS);
-const TIMEOUT_DOWNLOAD: Duration = Duration::from_secs(TIMEOUT_DOWNLOAD_SECS);
-const ACCEPT_HEADER: &str = "application/vnd.github+json";
-const ARCHIVE_PREFIX: &str = "archive-";
-const GITHUB_API: &str = "https://api.github.com";
 const USER_AGENT: &str = BUILD_USER_AGENT;
+/// Largest files listed by --stats.
+const STATS_LARGEST_FILES: usize = 10;
+/// Repositories bigger than this many MiB need confirming by default.
+const DEFAULT_SIZE_LIMIT_MIB: u64 = 1024;
+
 const ERR_INVALID_URL: i32 = 2;
 const ERR_DEST_EXISTS: i32 = 3;
 const ERR_CLEANUP_FAILED: i32 = 4;
@@ -36,6 +48,20 @@ const ERR_GIT_NOT_FOUND: i32 = 5;
 const ERR_DOWNLOAD_FAILED: i32 = 6;
 const ERR_EXTRACTION_FAILED: i32 = 7;
 const ERR_INIT_FAILED: i32 = 8;
+const ERR_IO: i32 = 9;
+const ERR_RATE_LIMITED: i32 = 10;
+const ERR_BATCH_FAILED: i32 = 11;
+const ERR_NOT_A_RIP: i32 = 12;
+const ERR_VERIFY_FAILED: i32 = 13;
+const ERR_CHECKSUM_MISMATCH: i32 = 14;
+const ERR_INVALID_TEMPLATE: i32 = 15;
+const ERR_HOOK_FAILED: i32 = 16;
+const ERR_PUSH_FAILED: i32 = 17;
+const ERR_SECRETS_FOUND: i32 = 18;
+const ERR_ATTESTATION_FAILED: i32 = 19;
+const ERR_TOO_LARGE: i32 = 20;
+/// 128 + SIGINT, as shells report a process killed by Ctrl-C.
+const ERR_CANCELLED: i32 = 130;
 
 const fn max_timeout_secs(a: u64, b: u64) -> u64 {
     if a > b {
@@ -53,62 +79,327 @@ const BUILD_VERSION: &str = env!("CARGO_PKG_VERSION");
 const OPTIONAL_FLAG: Option<&'static str> = option_env!("MY_BUILD_FLAG");
 
 include!(concat!(env!("OUT_DIR"), "/generated.rs"));
+
+/// Prints a human-readable status line: on stdout normally, on stderr with
+/// `--output json` or `cat` so stdout carries nothing but their output.
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if json_output() || STDOUT_IS_DATA.load(Ordering::Relaxed) {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+
+fn json_output() -> bool { OUTPUT.get() == Some(&OutputFormat::Json) }
+
 #[cfg(feature = "zip")]
 fn zip_enabled() {
-    println!("feature 'zip' is compiled in");
+    status!("feature 'zip' is compiled in");
 }
 
-static MIME_BY_EXT: Map<&'static str, &'static str> = phf_map! {
-    "rs" => "text/rust",
-    "md" => "text/markdown",
-    "json" => "application/json",
-};
+static HTTP_CLIENT: OnceLock<Client> = OnceLock::new();
+static OUTPUT: OnceLock<OutputFormat> = OnceLock::new();
+/// Set when stdout carries file contents, as for `cat`.
+static STDOUT_IS_DATA: AtomicBool = AtomicBool::new(false);
+/// Archive bytes fetched over the network during this run.
+static DOWNLOADED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Builds the global HTTP client from the network options of `args`.
+fn init_client(args: &Args) -> Result<(), GitripperError> {
+    let client = http_options(args).build_client(USER_AGENT)?;
+    let _ = HTTP_CLIENT.set(client);
+    Ok(())
+}
 
-static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
-    Client::builder()
-        .user_agent(USER_AGENT)
-        .build()
-        .expect("failed to build global HTTP client")
-});
+fn http_options(args: &Args) -> HttpOptions {
+    HttpOptions {
+        proxy:           args.proxy.clone(),
+        ca_certs:        args.ca_cert.clone(),
+        insecure:        args.insecure,
+        connect_timeout: args.connect_timeout.map(Duration::from_secs),
+        timeout:         args.timeout.map(Duration::from_secs),
+        tcp_keepalive:   args.tcp_keepalive.map(Duration::from_secs),
+        http1_only:      args.http1,
+    }
+}
+
+fn get_client() -> &'static Client {
+    HTTP_CLIENT.get().expect("HTTP client used before init_client")
+}
 
-fn get_client() -> &'static Client { &HTTP_CLIENT }
+/// Cancelled by the first Ctrl-C; every ripper is built with it.
+fn interrupt() -> &'static CancellationToken {
+    static INTERRUPT: OnceLock<CancellationToken> = OnceLock::new();
+    INTERRUPT.get_or_init(CancellationToken::new)
+}
+
+/// Makes the first Ctrl-C stop the running rips, which clean up after
+/// themselves, and a second one exit right away.
+fn handle_interrupts() {
+    let result = ctrlc::set_handler(|| {
+        if interrupt().is_cancelled() {
+            exit(ERR_CANCELLED);
+        }
+        interrupt().cancel();
+        eprintln!("Interrupted, cleaning up; press Ctrl-C again to quit now.");
+    });
+    if let Err(e) = result {
+        eprintln!("Warning: Ctrl-C will not clean up: {}", e);
+    }
+}
+
+/// Sleeps for `duration`, returning `false` early on Ctrl-C.
+fn sleep_uninterrupted(duration: Duration) -> bool {
+    let deadline = Instant::now() + duration;
+    while !interrupt().is_cancelled() {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return true;
+        }
+        sleep(left.min(Duration::from_millis(200)));
+    }
+    false
+}
 
 fn touch_compile_items() {
     let _ = max_timeout_secs(1u64, 2u64);
     let _ = MAX_TIMEOUT_SECS;
-    let _ = DEFAULT_README;
     let _ = BUILD_VERSION;
     let _ = OPTIONAL_FLAG;
-    let _ = MIME_BY_EXT.get("md");
 
     if cfg!(feature = "zip") {
         zip_enabled();
     } else {
-        println!("feature 'zip' not enabled");
+        status!("feature 'zip' not enabled");
     }
 
-    println!("BUILD_FEATURES_CSV = {}", BUILD_FEATURES_CSV);
+    status!("BUILD_FEATURES_CSV = {}", BUILD_FEATURES_CSV);
 }
 
 #[derive(Parser, Debug)]
 #[command(
     author,
     version,
-    about = "Download a GitHub repository's contents and create a local git \
-             repo."
+    about = "Download a GitHub, GitLab, Bitbucket, Azure DevOps or sourcehut \
+             repository's contents and create a local git repo."
 )]
 struct Args {
-    url: Option<String>,
-
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Repository URLs, including those of any git remote, direct archive
+    /// URLs or paths of downloaded archives. `owner/repo` is short for a
+    /// GitHub repository, and `gitlab:group/project` for one on GitLab
+    /// (likewise `bitbucket:`, `azure:` and `sourcehut:`). More than one
+    /// switches to batch mode.
+    #[arg(value_name = "URL")]
+    urls: Vec<String>,
+
+    /// Read more repository URLs from a file, one per line.
+    #[arg(long, value_name = "FILE")]
+    from_file: Option<PathBuf>,
+
+    /// In batch, org and server mode, how many repositories to rip at once.
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_BATCH_PARALLELISM)]
+    parallel: usize,
+
+    /// In batch and org mode, how many archives to download at once across
+    /// all repositories.
+    #[arg(long, value_name = "N")]
+    max_downloads: Option<usize>,
+
+    /// In batch and org mode, how many archives to extract at once.
+    #[arg(long, value_name = "N")]
+    max_extractions: Option<usize>,
+
+    /// In batch and org mode, hold off new downloads while this many MiB of
+    /// downloaded archives wait to be extracted.
+    #[arg(long, value_name = "MIB")]
+    max_in_flight: Option<u64>,
+
+    /// In batch, org and server mode, record every job in this file
+    /// instead of `.gitripper-jobs.json` in the destination.
+    #[arg(long, value_name = "FILE")]
+    state_file: Option<PathBuf>,
+
+    /// In batch, org and server mode, pick up an interrupted run from its
+    /// state file: repositories already ripped are skipped and the rest
+    /// ripped again.
     #[arg(long)]
+    resume: bool,
+
+    #[arg(long, group = "ref")]
     branch: Option<String>,
 
+    /// Rip a tag instead of a branch.
+    #[arg(long, group = "ref")]
+    tag: Option<String>,
+
+    /// Rip a commit, by full or abbreviated SHA.
+    #[arg(long, group = "ref", value_name = "SHA")]
+    commit: Option<String>,
+
+    /// Rip the head of a pull request, including ones from forks.
+    #[arg(long, group = "ref", value_name = "NUMBER")]
+    pr: Option<u64>,
+
+    /// Rip the tag of a release; `latest` picks the newest one.
+    #[arg(long, group = "ref", value_name = "TAG")]
+    release: Option<String>,
+
+    /// Rip several refs into one new repository, each as a branch of the
+    /// same name holding its own snapshot commit.
+    #[arg(
+        long,
+        value_name = "REF,...",
+        value_delimiter = ',',
+        conflicts_with_all = [
+            "ref",
+            "keep_history",
+            "history_depth",
+            "vendor",
+            "bare",
+            "no_init",
+            "output_archive",
+            "dest_template",
+        ]
+    )]
+    refs: Vec<String>,
+
+    /// Only rip this subdirectory of the repository.
+    #[arg(long)]
+    path: Option<PathBuf>,
+
+    /// Never ask for input; without a ref flag, rip the default branch.
+    #[arg(long)]
+    non_interactive: bool,
+
+    /// Ask before ripping a repository bigger than this many MiB, and
+    /// refuse to without --yes when there is no one to ask.
+    #[arg(long, value_name = "MIB", default_value_t = DEFAULT_SIZE_LIMIT_MIB)]
+    size_limit: u64,
+
+    /// Rip repositories of any size without asking.
+    #[arg(long, short = 'y')]
+    yes: bool,
+
+    /// API token. For Bitbucket, `username:app_password` also works; for
+    /// Azure DevOps, use a personal access token.
     #[arg(long)]
     token: Option<String>,
 
+    /// Authenticate as this GitHub App, with an installation token minted
+    /// for the repository or organization being ripped.
+    #[arg(
+        long,
+        env = "GITRIPPER_APP_ID",
+        value_name = "ID",
+        requires = "app_key",
+        conflicts_with = "token"
+    )]
+    app_id: Option<String>,
+
+    /// Private key (PEM) of the GitHub App given with --app-id.
+    #[arg(
+        long,
+        env = "GITRIPPER_APP_KEY",
+        value_name = "PEM",
+        requires = "app_id"
+    )]
+    app_key: Option<PathBuf>,
+
     #[arg(long)]
     dest: Option<PathBuf>,
 
+    /// Import into this subdirectory of an existing repository, e.g.
+    /// third_party/foo, and commit it there instead of creating a new
+    /// repository. With --force, replaces an earlier import.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = [
+            "dest",
+            "dest_template",
+            "keep_history",
+            "history_depth",
+            "no_init",
+        ]
+    )]
+    vendor: Option<PathBuf>,
+
+    /// Make the destination a bare repository holding the snapshot as a
+    /// single commit, ready to be served from a git server.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "keep_history",
+            "history_depth",
+            "vendor",
+            "no_init",
+        ]
+    )]
+    bare: bool,
+
+    /// Write the snapshot into this archive (.zip, .tar, .tar.gz or
+    /// .tar.zst) instead of a directory.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = [
+            "dest",
+            "dest_template",
+            "keep_history",
+            "history_depth",
+            "vendor",
+            "bare",
+            "sparse",
+        ]
+    )]
+    output_archive: Option<PathBuf>,
+
+    /// Destination layout with {owner}, {repo}, {ref} and {provider}
+    /// placeholders. Relative to --dest in batch and org mode, where it
+    /// defaults to `{owner}/{repo}`.
+    #[arg(long, env = "GITRIPPER_DEST_TEMPLATE", value_name = "TEMPLATE")]
+    dest_template: Option<DestLayout>,
+
+    /// Send every request through this proxy. Without it, HTTPS_PROXY,
+    /// HTTP_PROXY and NO_PROXY are honored.
+    #[arg(long, value_name = "URL")]
+    proxy: Option<String>,
+
+    /// Also trust the root certificates in this PEM file. Repeatable.
+    #[arg(long, value_name = "PEM")]
+    ca_cert: Vec<PathBuf>,
+
+    /// Don't verify TLS certificates. Only for debugging.
+    #[arg(long)]
+    insecure: bool,
+
+    /// Seconds to wait for a response, and then for each chunk of it.
+    /// Downloads may take longer in total as long as data keeps arriving.
+    #[arg(long, env = "GITRIPPER_TIMEOUT", value_name = "SECS")]
+    timeout: Option<u64>,
+
+    /// Seconds to wait for a connection.
+    #[arg(long, env = "GITRIPPER_CONNECT_TIMEOUT", value_name = "SECS")]
+    connect_timeout: Option<u64>,
+
+    /// Send TCP keepalive probes at this interval, in seconds.
+    #[arg(long, env = "GITRIPPER_TCP_KEEPALIVE", value_name = "SECS")]
+    tcp_keepalive: Option<u64>,
+
+    /// Don't use HTTP/2.
+    #[arg(long, env = "GITRIPPER_HTTP1")]
+    http1: bool,
+
+    /// GitHub Enterprise Server API base, e.g.
+    /// `https://ghe.example.com/api/v3`.
+    #[arg(long, env = "GITRIPPER_API_URL", value_name = "URL")]
+    api_url: Option<String>,
+
     #[arg(long)]
     author_name: Option<String>,
 
@@ -118,329 +409,2240 @@ struct Args {
     #[arg(long)]
     remote: Option<String>,
 
+    /// Create this repository on GitHub (or --api-url), for your account or
+    /// as ORG/NAME, and make it origin. Add --push to upload the rip.
+    #[arg(
+        long,
+        value_name = "[ORG/]NAME",
+        conflicts_with_all = ["remote", "vendor", "no_init"]
+    )]
+    create_remote: Option<String>,
+
+    /// Make the repository created by --create-remote private.
+    #[arg(long, requires = "create_remote")]
+    private: bool,
+
+    /// Push the new repository's branch to origin, as set by --remote or
+    /// --create-remote.
+    #[arg(long, conflicts_with_all = ["vendor", "no_init"])]
+    push: bool,
+
+    /// Register the original repository as the `upstream` remote. The next
+    /// `git fetch upstream` also fetches the ripped commit into
+    /// refs/gitripper/upstream-base, to diff or merge against.
+    #[arg(
+        long,
+        conflicts_with_all = ["keep_history", "history_depth", "vendor"]
+    )]
+    upstream_remote: bool,
+
+    /// Create an annotated tag with this name on the snapshot commit,
+    /// recording the upstream ref and commit SHA.
+    #[arg(
+        long,
+        value_name = "NAME",
+        conflicts_with_all = [
+            "keep_history",
+            "history_depth",
+            "vendor",
+            "no_init",
+        ]
+    )]
+    tag_as: Option<String>,
+
+    /// With --tag or --release, tag the snapshot commit with the same name,
+    /// as with --tag-as.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "tag_as",
+            "keep_history",
+            "history_depth",
+            "vendor",
+            "no_init",
+        ]
+    )]
+    copy_tag: bool,
+
+    /// Store the upstream description, homepage and topics in the git config
+    /// of the new repository as gitripper.*, or in METADATA.toml without
+    /// one. --create-remote also uses the description.
+    #[arg(long)]
+    import_metadata: bool,
+
+    /// Branch the new repository starts on.
+    #[arg(long, value_name = "BRANCH")]
+    initial_branch: Option<String>,
+
+    /// Write a MANIFEST.blake3 of every extracted file, checked by `verify`.
+    #[arg(long)]
+    manifest: bool,
+
+    /// Record the BLAKE3 and SHA-256 of the downloaded archive in
+    /// .gitripper-receipt.json.
+    #[arg(long, conflicts_with_all = ["keep_history", "sparse"])]
+    receipt: bool,
+
+    /// Resolve the ref to a commit first and fail if the downloaded archive
+    /// is of a different one.
+    #[arg(long)]
+    verify_commit: bool,
+
+    /// If the repository is a fork, rip the repository it was forked from
+    /// instead.
+    #[arg(long)]
+    prefer_parent: bool,
+
+    /// Detect the upstream license and credit the repository in
+    /// THIRD_PARTY_NOTICES.md.
     #[arg(long)]
+    attribution: bool,
+
+    /// Give the rip of a repository without any commits the bundled
+    /// README.md instead of an empty initial commit.
+    #[arg(long)]
+    empty_readme: bool,
+
+    /// Write an in-toto statement with SLSA provenance for the ripped files
+    /// to .gitripper-attestation.intoto.json.
+    #[arg(long, conflicts_with = "keep_history")]
+    attest: bool,
+
+    /// Sign the --attest statement with `cosign sign-blob`, keyless unless
+    /// cosign is configured with a key, into
+    /// .gitripper-attestation.sigstore.json.
+    #[arg(long, requires = "attest")]
+    sign_attestation: bool,
+
+    /// Summarize the ripped files: count, size, largest files and bytes per
+    /// language. Part of the record with --output json.
+    #[arg(long)]
+    stats: bool,
+
+    /// Replace files with identical contents by hardlinks to one copy.
+    #[arg(long)]
+    dedupe: bool,
+
+    /// Expand zip and tar archives found in the rip into a directory next
+    /// to each, and archives found in those down to DEPTH levels (1 if not
+    /// given). The file size limits apply at every level.
+    #[arg(
+        long,
+        value_name = "DEPTH",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "1",
+        conflicts_with = "keep_history"
+    )]
+    extract_nested: Option<usize>,
+
+    /// Scan the ripped files for credentials before committing them. `warn`
+    /// lists what was found; `abort` also stops before the commit.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "ACTION",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "warn"
+    )]
+    scan_secrets: Option<SecretAction>,
+
+    /// Replace Git LFS pointer files with the real objects.
+    #[arg(long)]
+    lfs: bool,
+
+    /// Shell command to run in the destination before the import commit,
+    /// with GITRIPPER_OWNER, GITRIPPER_REPO, GITRIPPER_REF, GITRIPPER_COMMIT
+    /// and GITRIPPER_DEST set. Repeatable; the environment variable takes
+    /// one command per line. Runs after the `post` list under [hooks] in
+    /// config.toml in $GITRIPPER_CONFIG_DIR or ~/.config/gitripper.
+    #[arg(
+        long,
+        value_name = "CMD",
+        env = "GITRIPPER_POST_HOOKS",
+        value_delimiter = '\n'
+    )]
+    post_hook: Vec<String>,
+
+    /// Remove this many leading directories from archive paths instead of
+    /// the single top-level folder hosts wrap their archives in. 0 keeps
+    /// paths as they are.
+    #[arg(long, value_name = "N")]
+    strip_components: Option<usize>,
+
+    /// Leave out CI configuration and hosting metadata: .github/,
+    /// .gitlab-ci.yml, CODEOWNERS, issue templates, funding files and the
+    /// like.
+    #[arg(long)]
+    strip_meta: bool,
+
+    /// Also leave out paths matching this glob, relative to the
+    /// destination. Repeatable.
+    #[arg(
+        long,
+        value_name = "GLOB",
+        env = "GITRIPPER_STRIP",
+        value_delimiter = ','
+    )]
+    strip: Vec<String>,
+
+    /// Keep files matching this glob, relative to the destination, but
+    /// empty them, for secrets and fixtures that mustn't be copied.
+    /// Repeatable.
+    #[arg(
+        long,
+        value_name = "GLOB",
+        env = "GITRIPPER_REDACT",
+        value_delimiter = ',',
+        conflicts_with = "keep_history"
+    )]
+    redact: Vec<String>,
+
+    /// Leave out files bigger than this many MiB.
+    #[arg(long, value_name = "MIB", conflicts_with = "keep_history")]
+    max_file_size: Option<u64>,
+
+    /// Leave out files that look binary (a NUL byte near the start).
+    #[arg(long, conflicts_with = "keep_history")]
+    skip_binaries: bool,
+
+    /// Line endings of text files: `native` is CRLF on Windows and LF
+    /// elsewhere, `keep` leaves them as upstream has them.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "EOL",
+        default_value_t = LineEnding::Keep,
+        conflicts_with = "keep_history"
+    )]
+    eol: LineEnding,
+
+    /// What to do with paths that are the same file on this filesystem,
+    /// like README.md and Readme.md on a case-insensitive one: fail, write
+    /// the later one as Readme~1.md, or let it overwrite the first.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "ACTION",
+        default_value_t = OnCollision::Error
+    )]
+    on_collision: OnCollision,
+
+    /// Make the snapshot reproducible: write files in path order, with a
+    /// fixed modification time (1980-01-01) and normalized permissions.
+    #[arg(long, conflicts_with = "keep_history")]
+    deterministic: bool,
+
+    /// Only rip files matching this glob, relative to the destination,
+    /// downloading them one by one instead of the whole archive. Repeatable.
+    /// GitHub only.
+    #[arg(
+        long,
+        value_name = "GLOB",
+        value_delimiter = ',',
+        conflicts_with_all = ["keep_history", "history_depth"]
+    )]
+    sparse: Vec<String>,
+
+    /// Treat the repository as a project template: fill `{{ name }}`
+    /// placeholders in files and paths from --define, prompts and the
+    /// defaults in its gitripper.template.toml.
+    #[arg(long)]
+    template: bool,
+
+    /// Value of a template variable. Repeatable.
+    #[arg(
+        long,
+        value_name = "NAME=VALUE",
+        value_parser = parse_define,
+        requires = "template"
+    )]
+    define: Vec<(String, String)>,
+
+    /// Sign the commit with your configured user.signingkey.
+    #[arg(long, short = 'S', conflicts_with = "no_init")]
+    sign: bool,
+
+    /// Don't commit files matched by the repository's .gitignore files.
+    #[arg(long, conflicts_with = "no_init")]
+    respect_gitignore: bool,
+
+    /// Commit message; {owner}, {repo}, {ref} and {commit} are filled in.
+    #[arg(long, short = 'm')]
+    message: Option<String>,
+
+    /// Replace a non-empty destination.
+    #[arg(long, conflicts_with_all = ["merge", "skip_existing", "backup"])]
     force: bool,
+
+    /// Extract over a non-empty destination, overwriting the files in the
+    /// archive and keeping the rest. A repository there gets a new commit.
+    #[arg(long, conflicts_with_all = ["skip_existing", "backup"])]
+    merge: bool,
+
+    /// Like --merge, but keep files that already exist.
+    #[arg(long, conflicts_with = "backup")]
+    skip_existing: bool,
+
+    /// Move a non-empty destination to DEST.bak-<unix time> first.
+    #[arg(long)]
+    backup: bool,
+
+    /// How many times to retry transient network failures.
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_RETRIES)]
+    retries: u32,
+
+    /// Sleep until the API rate limit resets instead of failing.
+    #[arg(long)]
+    wait_for_rate_limit: bool,
+
+    /// Clone with git to keep the repository's history.
+    #[arg(long)]
+    keep_history: bool,
+
+    /// Only download and extract the files; don't create a git repository.
+    #[arg(long, conflicts_with_all = ["keep_history", "history_depth", "remote"])]
+    no_init: bool,
+
+    /// With --keep-history, only fetch this many recent commits.
+    #[arg(long, value_name = "N", requires = "keep_history")]
+    depth: Option<u32>,
+
+    /// Keep only the last N commits and drop the upstream remote. Shorthand
+    /// for --keep-history --depth N without an origin.
+    #[arg(long, value_name = "N", conflicts_with_all = ["keep_history", "depth"])]
+    history_depth: Option<u32>,
+
+    /// Neither read nor populate the archive cache.
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Download even if the commit is cached, replacing the cached copy, and
+    /// skip the ETag revalidation of API responses.
+    #[arg(long, conflicts_with = "no_cache")]
+    refresh: bool,
+
+    /// Never use the network; rip from the archive cache alone and fail if
+    /// the repository and ref were not cached before.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "no_cache",
+            "refresh",
+            "keep_history",
+            "lfs",
+            "sparse",
+        ]
+    )]
+    offline: bool,
+
+    /// Download large archives over this many parallel connections when the
+    /// server supports range requests.
+    #[arg(
+        long,
+        env = "GITRIPPER_DOWNLOAD_THREADS",
+        value_name = "N",
+        default_value_t = 1
+    )]
+    download_threads: usize,
+
+    /// Cap the threads used to write files and walk directories. Defaults
+    /// to one per core.
+    #[arg(long, env = "GITRIPPER_JOBS", value_name = "N")]
+    jobs: Option<usize>,
+
+    /// Most uncompressed data (in MiB) to buffer while extracting. Files are
+    /// written in batches of up to this size; bigger ones are streamed to
+    /// disk.
+    #[arg(long, value_name = "MIB")]
+    memory_budget: Option<u64>,
+
+    #[arg(long)]
+    no_progress: bool,
+
+    /// Format of the result on stdout. `json` prints one record of the run
+    /// for scripts and CI and moves all logs to stderr.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value_t = OutputFormat::Human,
+        env = "GITRIPPER_OUTPUT"
+    )]
+    output: OutputFormat,
+
+    /// Extract the archive while it is still downloading.
+    #[cfg(feature = "async")]
+    #[arg(long = "async")]
+    overlap: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Manage tokens stored in the OS keyring.
+    Auth {
+        #[command(subcommand)]
+        action: AuthCommand,
+    },
+    /// Manage the archive cache.
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommand,
+    },
+    /// Rip every repository of a GitHub organization into
+    /// `<dest>/<org>/<repo>`. Options go before the subcommand.
+    Org {
+        org: String,
+
+        /// Also rip archived repositories.
+        #[arg(long)]
+        include_archived: bool,
+
+        /// Also rip forks.
+        #[arg(long)]
+        include_forks: bool,
+
+        /// Only rip repositories with this topic. Repeatable; any match
+        /// counts.
+        #[arg(long, value_name = "TOPIC")]
+        topic: Vec<String>,
+    },
+    /// Rip every repository a GitHub user starred or owns into
+    /// `<dest>/<owner>/<repo>`. Options go before the subcommand.
+    #[command(group(ArgGroup::new("which").required(true)))]
+    User {
+        user: String,
+
+        /// Rip the repositories the user starred.
+        #[arg(long, group = "which")]
+        starred: bool,
+
+        /// Rip the repositories the user owns.
+        #[arg(long, group = "which")]
+        owned: bool,
+
+        /// With --owned, also rip archived repositories.
+        #[arg(long, conflicts_with = "starred")]
+        include_archived: bool,
+
+        /// With --owned, also rip forks.
+        #[arg(long, conflicts_with = "starred")]
+        include_forks: bool,
+    },
+    /// Search GitHub for repositories, e.g.
+    /// `gitripper search regex language:rust`. Options go before the
+    /// subcommand.
+    Search {
+        /// Search terms and qualifiers, joined with spaces.
+        #[arg(required = true)]
+        query: Vec<String>,
+
+        /// How many repositories to show, at most 100.
+        #[arg(long, value_name = "N", default_value_t = 20)]
+        limit: usize,
+
+        /// Pick one of the repositories found and rip it.
+        #[arg(long)]
+        pick: bool,
+    },
+    /// List the branches of a repository.
+    Branches {
+        url: String,
+
+        /// Also print the date of each branch's last commit.
+        #[arg(long)]
+        dates: bool,
+    },
+    /// List the tags of a repository.
+    Tags {
+        url: String,
+
+        /// Also print the date of each tag's commit.
+        #[arg(long)]
+        dates: bool,
+    },
+    /// Print a single file of a repository to stdout, without ripping it.
+    Cat {
+        url:       String,
+        /// Path of the file in the repository.
+        path:      String,
+        /// Branch, tag or commit to read the file from.
+        #[arg(long = "ref", value_name = "REF")]
+        reference: Option<String>,
+    },
+    /// Compare an earlier rip with the current upstream snapshot.
+    Diff {
+        /// Directory of the earlier rip.
+        dest: PathBuf,
+
+        /// Print unified diffs of changed text files.
+        #[arg(long, short = 'p')]
+        patch: bool,
+    },
+    /// Apply upstream changes to an earlier rip as a new commit.
+    Update {
+        /// Directory of the earlier rip.
+        dest: PathBuf,
+    },
+    /// Rip a repository, then keep it up to date: every interval, check the
+    /// head of the ref and apply upstream changes as a new commit, running
+    /// any --post-hook again. Options go before the subcommand.
+    Watch {
+        url: String,
+
+        /// Time between checks, e.g. `90s`, `10m` or `1h`.
+        #[arg(
+            long,
+            value_name = "DURATION",
+            default_value = "10m",
+            value_parser = parse_interval
+        )]
+        interval: Duration,
+    },
+    /// List or download the assets of a release into the destination
+    /// (the current directory by default). Assets are checked against any
+    /// `*.sha256` file the release publishes.
+    Release {
+        url: String,
+
+        /// Release tag, or `latest`.
+        #[arg(long, value_name = "TAG", default_value = LATEST_RELEASE)]
+        tag: String,
+
+        /// Download assets whose names match this glob. Repeatable; without
+        /// it the assets are only listed.
+        #[arg(long, value_name = "GLOB")]
+        asset: Vec<String>,
+    },
+    /// Serve an HTTP API that queues rips into archives in the destination
+    /// (the current directory by default), reports their progress and
+    /// hands out the archives. Options go before the subcommand.
+    Serve {
+        /// Address to listen on. There is no authentication, so keep it
+        /// local or behind a proxy that has some.
+        #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+        listen: String,
+
+        /// Also accept direct links to archives and plain git remotes,
+        /// which lets clients have the server fetch any URL it can reach,
+        /// over ssh with its own keys too.
+        #[arg(long)]
+        allow_any_host: bool,
+    },
+    /// Check the files of a rip against its MANIFEST.blake3.
+    Verify {
+        /// Directory of a rip made with --manifest.
+        dest: PathBuf,
+    },
+    /// Print a shell completion script, e.g.
+    /// `gitripper completions bash > /etc/bash_completion.d/gitripper`.
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Print the gitripper(1) man page.
+    Man {
+        /// Instead write one page per subcommand into this directory.
+        #[arg(long, value_name = "DIR")]
+        dir: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum AuthCommand {
+    /// Store a token for a host, prompting for it without echo.
+    Login {
+        #[arg(long, default_value = GITHUB_HOST)]
+        host: String,
+
+        /// Read the token from stdin instead of prompting.
+        #[arg(long)]
+        with_token: bool,
+    },
+    /// Remove the stored token of a host.
+    Logout {
+        #[arg(long, default_value = GITHUB_HOST)]
+        host: String,
+    },
+    /// Show where the token for a host would come from.
+    Status {
+        #[arg(long, default_value = GITHUB_HOST)]
+        host: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheCommand {
+    /// Remove cached archives; everything unless limits are given.
+    Clean {
+        /// Remove archives unused for more than this many days.
+        #[arg(long, value_name = "DAYS")]
+        max_age: Option<u64>,
+
+        /// Remove least recently used archives until the cache fits.
+        #[arg(long, value_name = "MIB")]
+        max_size: Option<u64>,
+    },
+    /// Print the cache directory.
+    Dir,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Human,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum LineEnding {
+    Lf,
+    Crlf,
+    Native,
+    Keep,
+}
+
+impl LineEnding {
+    fn eol(self) -> Eol {
+        match self {
+            Self::Lf => Eol::Lf,
+            Self::Crlf => Eol::Crlf,
+            Self::Native => Eol::Native,
+            Self::Keep => Eol::Keep,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OnCollision {
+    Error,
+    Rename,
+    Overwrite,
+}
+
+impl OnCollision {
+    fn policy(self) -> CollisionPolicy {
+        match self {
+            Self::Error => CollisionPolicy::Error,
+            Self::Rename => CollisionPolicy::Rename,
+            Self::Overwrite => CollisionPolicy::Overwrite,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SecretAction {
+    Warn,
+    Abort,
+}
+
+/// This build of gitripper, as named in `--attest` statements.
+fn builder_info() -> BuilderInfo {
+    let id = if GIT_REMOTE_URL.starts_with("https://") {
+        GIT_REMOTE_URL.trim_end_matches(".git").to_string()
+    } else {
+        format!("https://crates.io/crates/{}", BUILD_PKG_NAME)
+    };
+    let version = [
+        (BUILD_PKG_NAME, BUILD_PKG_VERSION),
+        ("commit", GIT_HASH_LONG),
+        ("describe", GIT_DESCRIBE),
+        ("rustc", RUSTC_VERSION),
+        ("target", BUILD_TARGET),
+        ("profile", BUILD_PROFILE),
+        ("features", BUILD_FEATURES_CSV),
+    ]
+    .into_iter()
+    .filter(|(_, value)| !value.is_empty())
+    .map(|(name, value)| (name.to_string(), value.to_string()))
+    .collect();
+    BuilderInfo { id, version }
+}
+
+fn secret_policy(action: Option<SecretAction>) -> SecretPolicy {
+    match action {
+        None => SecretPolicy::Off,
+        Some(SecretAction::Warn) => SecretPolicy::Warn,
+        Some(SecretAction::Abort) => SecretPolicy::Abort,
+    }
+}
+
+/// Terminal progress bars for the download and extraction phases.
+#[derive(Default)]
+struct BarProgress {
+    bar: Mutex<Option<ProgressBar>>,
+}
+
+impl BarProgress {
+    fn start(&self, bar: ProgressBar) { *self.bar.lock().unwrap() = Some(bar); }
+
+    fn inc(&self, n: u64) {
+        if let Some(bar) = self.bar.lock().unwrap().as_ref() {
+            bar.inc(n);
+        }
+    }
+
+    fn finish(&self) {
+        if let Some(bar) = self.bar.lock().unwrap().take() {
+            bar.finish_and_clear();
+        }
+    }
+}
+
+impl ProgressSink for BarProgress {
+    fn download_started(&self, total_bytes: Option<u64>) {
+        let bar = match total_bytes {
+            Some(len) => ProgressBar::new(len).with_style(
+                ProgressStyle::with_template(
+                    "{bar:40} {bytes}/{total_bytes} ({bytes_per_sec}, {eta})",
+                )
+                .unwrap(),
+            ),
+            None => ProgressBar::new_spinner().with_style(
+                ProgressStyle::with_template(
+                    "{spinner} {bytes} ({bytes_per_sec})",
+                )
+                .unwrap(),
+            ),
+        };
+        self.start(bar);
+    }
+
+    fn download_progress(&self, bytes: u64) { self.inc(bytes); }
+
+    fn download_finished(&self) { self.finish(); }
+
+    fn extract_started(&self, total_entries: u64) {
+        self.start(ProgressBar::new(total_entries).with_style(
+            ProgressStyle::with_template("{bar:40} {pos}/{len} files").unwrap(),
+        ));
+    }
+
+    fn extract_progress(&self, entries: u64) { self.inc(entries); }
+
+    fn extract_finished(&self) { self.finish(); }
 }
 
 fn main() {
-    if let Err(code) = run() {
-        exit(code);
+    handle_interrupts();
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+        if let GitripperError::RateLimited { .. } = e {
+            eprintln!(
+                "Hint: pass --token to raise the limit, or \
+                 --wait-for-rate-limit to wait for the reset."
+            );
+        }
+        exit(exit_code(&e));
     }
 }
 
-fn run() -> Result<(), i32> {
-    touch_compile_items();
+fn exit_code(e: &GitripperError) -> i32 {
+    match e {
+        GitripperError::InvalidUrl(_) => ERR_INVALID_URL,
+        GitripperError::Cancelled => ERR_CANCELLED,
+        GitripperError::DestinationExists(_) => ERR_DEST_EXISTS,
+        GitripperError::Cleanup { .. } => ERR_CLEANUP_FAILED,
+        GitripperError::NotARip(_) => ERR_NOT_A_RIP,
+        GitripperError::Unsupported(_) => ERR_DOWNLOAD_FAILED,
+        GitripperError::ChecksumMismatch { .. }
+        | GitripperError::CommitMismatch { .. } => ERR_CHECKSUM_MISMATCH,
+        GitripperError::InvalidTemplate(_) | GitripperError::Template(_) => {
+            ERR_INVALID_TEMPLATE
+        },
+        GitripperError::Hook { .. } => ERR_HOOK_FAILED,
+        GitripperError::Push(_) => ERR_PUSH_FAILED,
+        GitripperError::SecretsFound { .. } => ERR_SECRETS_FOUND,
+        GitripperError::Attestation(_) => ERR_ATTESTATION_FAILED,
+        GitripperError::AppAuth(_) => ERR_DOWNLOAD_FAILED,
+        GitripperError::Keyring(_) => ERR_IO,
+        GitripperError::GitNotFound => ERR_GIT_NOT_FOUND,
+        GitripperError::Http(_) | GitripperError::HttpStatus { .. } => {
+            ERR_DOWNLOAD_FAILED
+        },
+        GitripperError::RateLimited { .. } => ERR_RATE_LIMITED,
+        GitripperError::Extraction(_) => ERR_EXTRACTION_FAILED,
+        GitripperError::Clone(_) => ERR_DOWNLOAD_FAILED,
+        GitripperError::Offline
+        | GitripperError::DeadlineExceeded
+        | GitripperError::NotCached { .. }
+        | GitripperError::EmptyRepository(_) => ERR_DOWNLOAD_FAILED,
+        GitripperError::TooLarge { .. } => ERR_TOO_LARGE,
+        GitripperError::GitInit(_) => ERR_INIT_FAILED,
+        GitripperError::Io(_) => ERR_IO,
+    }
+}
 
+fn run() -> Result<(), GitripperError> {
     let mut args = Args::parse();
-    let token = args.token.take().or_else(|| var("GITHUB_TOKEN").ok());
-    let url = read_url_from_args(&args)?;
-    let (owner, repo) = parse_github_url(&url).map_err(|_| ERR_INVALID_URL)?;
+    let _ = OUTPUT.set(args.output);
+    if matches!(args.command, Some(Command::Cat { .. })) {
+        STDOUT_IS_DATA.store(true, Ordering::Relaxed);
+    }
+    // Generated files go to stdout, so skip the build diagnostics.
+    match args.command.take() {
+        Some(Command::Completions { shell }) => {
+            generate(shell, &mut Args::command(), "gitripper", &mut stdout());
+            return Ok(());
+        },
+        Some(Command::Man { dir }) => return run_man(dir.as_deref()),
+        command => args.command = command,
+    }
+    touch_compile_items();
+    init_client(&args)?;
+    match args.command.take() {
+        Some(Command::Cache { action }) => return run_cache(&action),
+        Some(Command::Auth { action }) => return run_auth(&action),
+        Some(Command::Org {
+            org,
+            include_archived,
+            include_forks,
+            topic,
+        }) => {
+            let filter = RepoFilter {
+                include_archived,
+                include_forks,
+                topics: topic,
+            };
+            return run_org(&mut args, &org, &filter);
+        },
+        Some(Command::User {
+            user,
+            starred,
+            include_archived,
+            include_forks,
+            ..
+        }) => {
+            // Everything starred was chosen by hand, so nothing is skipped.
+            let (which, filter) = if starred {
+                (
+                    UserRepos::Starred,
+                    RepoFilter {
+                        include_archived: true,
+                        include_forks:    true,
+                        topics:           Vec::new(),
+                    },
+                )
+            } else {
+                (
+                    UserRepos::Owned,
+                    RepoFilter {
+                        include_archived,
+                        include_forks,
+                        topics: Vec::new(),
+                    },
+                )
+            };
+            return run_user(&mut args, &user, which, &filter);
+        },
+        Some(Command::Search { query, limit, pick }) => {
+            match run_search(&args, &query.join(" "), limit, pick)? {
+                Some(url) => args.urls = vec![url],
+                None => return Ok(()),
+            }
+        },
+        Some(Command::Branches { url, dates }) => {
+            return run_list_refs(&mut args, &url, RefType::Branch, dates);
+        },
+        Some(Command::Tags { url, dates }) => {
+            return run_list_refs(&mut args, &url, RefType::Tag, dates);
+        },
+        Some(Command::Cat {
+            url,
+            path,
+            reference,
+        }) => {
+            if reference.is_some() {
+                args.branch = reference;
+            }
+            return run_cat(&mut args, &url, &path);
+        },
+        Some(Command::Diff { dest, patch }) => {
+            return run_diff(&mut args, &dest, patch);
+        },
+        Some(Command::Update { dest }) => return run_update(&mut args, &dest),
+        Some(Command::Watch { url, interval }) => {
+            return run_watch(&mut args, &url, interval);
+        },
+        Some(Command::Release { url, tag, asset }) => {
+            return run_release(&mut args, &url, &tag, &asset);
+        },
+        Some(Command::Serve {
+            listen,
+            allow_any_host,
+        }) => return run_serve(args, &listen, allow_any_host),
+        Some(Command::Verify { dest }) => return run_verify(&dest),
+        Some(Command::Completions { .. } | Command::Man { .. }) | None => {},
+    }
 
-    if owner.is_empty() || repo.is_empty() {
-        eprintln!("Error: Could not determine repository owner or name.");
-        return Err(ERR_INVALID_URL);
+    let mut urls = args.urls.clone();
+    if let Some(file) = &args.from_file {
+        urls.extend(read_url_list(file)?);
+    }
+    if urls.len() > 1 || args.from_file.is_some() {
+        if args.output_archive.is_some() {
+            return Err(GitripperError::Unsupported(
+                "--output-archive takes a single URL".to_string(),
+            ));
+        }
+        if !args.refs.is_empty() {
+            return Err(GitripperError::Unsupported(
+                "--refs takes a single URL".to_string(),
+            ));
+        }
+        return run_batch(&mut args, &urls);
     }
 
-    let dest = prepare_destination(&args, &repo)?;
-    check_git_installed().map_err(|_| ERR_GIT_NOT_FOUND)?;
+    let url = match urls.pop() {
+        Some(u) => u,
+        None if args.non_interactive => {
+            return Err(GitripperError::InvalidUrl(String::new()));
+        },
+        None => prompt_for_url()?,
+    };
+    // Read before an app token replaces --token.
+    let publish_token = publish_token(&args)?;
+    use_app_token_for(&mut args, &url)?;
+    let token = args.token.take().or_else(|| token_from_env(&args, &url));
+    let no_ref = args.branch.is_none()
+        && args.tag.is_none()
+        && args.commit.is_none()
+        && args.release.is_none()
+        && args.pr.is_none()
+        && args.refs.is_empty();
+    if no_ref
+        && !args.non_interactive
+        && stdin().is_terminal()
+        && stderr().is_terminal()
+    {
+        let ripper = build_ripper(&args, token.clone())?;
+        match pick_reference(&ripper, &url) {
+            Some((RefType::Branch, name)) => args.branch = Some(name),
+            Some((RefType::Tag, name)) => args.tag = Some(name),
+            None => {},
+        }
+    }
+    let ripper = build_ripper(&args, token)?;
+    if let Some(path) = &args.output_archive {
+        let written = ripper.rip_to_archive(&url, path)?;
+        status!("Done. Wrote {} entries to: {}", written, path.display());
+        return Ok(());
+    }
+    if !args.refs.is_empty() {
+        let reports = ripper
+            .rip_refs(&url, &args.refs)
+            .map_err(|e| with_hint(&ripper, &url, e))?;
+        for report in &reports {
+            status!("Branch {} created", report.reference);
+        }
+        if let Some(report) = reports.first() {
+            publish(&args, &ripper, &url, report, publish_token.as_deref())?;
+            status!("Done. Repository copied to: {}", report.dest.display());
+        }
+        return Ok(());
+    }
+    let started = Instant::now();
+    let result = ripper.rip(&url).map_err(|e| with_hint(&ripper, &url, e));
+    if json_output() {
+        print_run_record(&[(url.as_str(), &result)], started, args.stats);
+    }
+    let report = result?;
+    if args.stats && !json_output() {
+        print_stats(&rip_stats(&report.dest, STATS_LARGEST_FILES)?);
+    }
 
-    let client = get_client();
+    if args.no_init {
+        status!("Done. Files extracted to: {}", report.dest.display());
+        return Ok(());
+    }
+    if args.vendor.is_some() {
+        status!("Done. Vendored into: {}", report.dest.display());
+        return Ok(());
+    }
+    publish(&args, &ripper, &url, &report, publish_token.as_deref())?;
+
+    if args.bare {
+        status!(
+            "Done. Bare repository created at: {}",
+            report.dest.display()
+        );
+        return Ok(());
+    }
+    status!("Done. Repository copied to: {}", report.dest.display());
+    if !args.keep_history && args.history_depth.is_none() {
+        status!("Note: this repository has no history from the original repo.");
+    }
+    Ok(())
+}
 
-    let reference =
-        determine_reference(&args, &client, &owner, &repo, token.as_deref());
+/// `e` with a hint at why `url` wasn't found appended, if it wasn't and the
+/// provider can tell.
+fn with_hint(ripper: &Ripper, url: &str, e: GitripperError) -> GitripperError {
+    match e {
+        GitripperError::HttpStatus {
+            status: 404,
+            message,
+        } => match ripper.diagnose_not_found(url) {
+            Some(cause) => GitripperError::HttpStatus {
+                status:  404,
+                message: format!("{}\nHint: {}", message, cause),
+            },
+            None => GitripperError::HttpStatus {
+                status: 404,
+                message,
+            },
+        },
+        e => e,
+    }
+}
 
-    let tmp = tempdir().map_err(|_| ERR_DOWNLOAD_FAILED)?;
+/// Token for --create-remote and --push: --token, else whatever the
+/// GitHub host of --api-url has. Fails early if --create-remote has none.
+fn publish_token(args: &Args) -> Result<Option<String>, GitripperError> {
+    if args.create_remote.is_none() && !args.push {
+        return Ok(None);
+    }
+    // A clone's origin is the repository it came from.
+    if args.remote.is_none() && args.create_remote.is_none() {
+        return Err(GitripperError::Unsupported(
+            "--push needs --remote or --create-remote".to_string(),
+        ));
+    }
+    let github = github_for(args);
+    let token = args.token.clone().or_else(|| github_env_token(&github));
+    if token.is_none() && args.create_remote.is_some() {
+        return Err(GitripperError::Unsupported(format!(
+            "--create-remote needs a token for {}; pass --token or set \
+             GITHUB_TOKEN",
+            github.host()
+        )));
+    }
+    Ok(token)
+}
 
-    let zip_path = download_archive(
-        &client,
-        &owner,
-        &repo,
-        &reference,
-        token.as_deref(),
-        tmp.path(),
-    )?;
+/// Creates the --create-remote repository, described like `url` with
+/// --import-metadata, and pushes with --push.
+fn publish(
+    args: &Args,
+    ripper: &Ripper,
+    url: &str,
+    report: &RipReport,
+    token: Option<&str>,
+) -> Result<(), GitripperError> {
+    let github = github_for(args);
+    if let (Some(name), Some(token)) = (&args.create_remote, token) {
+        let description = if args.import_metadata {
+            ripper
+                .metadata(url)
+                .inspect_err(|e| {
+                    eprintln!("Warning: could not fetch the description: {}", e)
+                })
+                .ok()
+                .and_then(|m| m.description)
+        } else {
+            None
+        };
+        let url = github.create_repo(
+            get_client(),
+            name,
+            description.as_deref(),
+            args.private,
+            token,
+        )?;
+        set_origin(&report.dest, Some(&url))
+            .map_err(GitripperError::GitInit)?;
+        status!("Created {} and set it as origin", url);
+    }
+    if args.push {
+        let credentials = token.map(|t| github.git_credentials(t));
+        let branch = push_head(
+            &report.dest,
+            "origin",
+            credentials.as_ref().map(|(u, p)| (u.as_str(), p.as_str())),
+        )
+        .map_err(GitripperError::Push)?;
+        status!("Pushed {} to origin", branch);
+    }
+    Ok(())
+}
 
-    extract_zip(&zip_path, &dest).map_err(|e| {
-        eprintln!("Failed to extract archive: {}", e);
-        ERR_EXTRACTION_FAILED
-    })?;
+/// Rips every URL into `<dest>/<owner>/<repo>`, or the --dest-template
+/// layout under `<dest>`, and prints a summary. Exits
+/// with [`ERR_BATCH_FAILED`] if any of them failed, or [`ERR_CANCELLED`] if
+/// interrupted.
+fn run_batch(args: &mut Args, urls: &[String]) -> Result<(), GitripperError> {
+    // An app token only covers one installation; the first URL picks it.
+    if let Some(first) = urls.first() {
+        use_app_token_for(args, first)?;
+    }
+    // Only fall back to an environment token if it can't leak to a
+    // different host.
+    let token = args.token.take().or_else(|| {
+        let (first, rest) = urls.split_first()?;
+        let name = detect_provider(first)?.0.name();
+        rest.iter()
+            .all(|u| detect_provider(u).is_some_and(|(p, _)| p.name() == name))
+            .then(|| token_from_env(args, first))
+            .flatten()
+    });
 
-    remove_embedded_git(&dest);
-    println!("Initializing new git repository...");
-
-    initialize_repo(
-        &dest,
-        args.author_name.as_deref(),
-        args.author_email.as_deref(),
-        args.remote.as_deref(),
-    )
-    .map_err(|e| {
-        eprintln!("Failed to initialize repository: {}", e);
-        ERR_INIT_FAILED
-    })?;
+    // Interleaved progress bars and prompts from parallel rips are
+    // unreadable.
+    args.no_progress = true;
+    args.non_interactive = true;
+    let ripper = build_ripper(args, token)?;
+    rip_all(args, &ripper, urls)
+}
+
+/// Lists the repositories of `org` and rips them like [`run_batch`].
+fn run_org(
+    args: &mut Args,
+    org: &str,
+    filter: &RepoFilter,
+) -> Result<(), GitripperError> {
+    let github = github_for(args);
+    use_app_token(args, &github, org, None)?;
+    let token = args.token.take().or_else(|| github_env_token(&github));
+
+    args.no_progress = true;
+    args.non_interactive = true;
+    let ripper = build_ripper(args, token)?;
+    let repos = ripper.list_org_repos(&github, org, filter)?;
+    status!("Found {} matching repositories in {}.", repos.len(), org);
+
+    let urls: Vec<String> = repos.into_iter().map(|r| r.html_url).collect();
+    rip_all(args, &ripper, &urls)
+}
+
+/// Lists the repositories `user` starred or owns and rips them like
+/// [`run_batch`].
+fn run_user(
+    args: &mut Args,
+    user: &str,
+    which: UserRepos,
+    filter: &RepoFilter,
+) -> Result<(), GitripperError> {
+    let github = github_for(args);
+    let token = args.token.take().or_else(|| github_env_token(&github));
+
+    args.no_progress = true;
+    args.non_interactive = true;
+    let ripper = build_ripper(args, token)?;
+    let repos = ripper.list_user_repos(&github, user, which, filter)?;
+    let kind = match which {
+        UserRepos::Starred => "starred by",
+        UserRepos::Owned => "owned by",
+    };
+    status!("Found {} repositories {} {}.", repos.len(), kind, user);
+
+    let urls: Vec<String> = repos.into_iter().map(|r| r.html_url).collect();
+    rip_all(args, &ripper, &urls)
+}
+
+/// Prints the repositories on GitHub matching `query`, or with `pick` lets
+/// the user choose one and returns its URL to rip.
+fn run_search(
+    args: &Args,
+    query: &str,
+    limit: usize,
+    pick: bool,
+) -> Result<Option<String>, GitripperError> {
+    let github = github_for(args);
+    let token = args.token.clone().or_else(|| github_env_token(&github));
+    let ripper = build_ripper(args, token)?;
+    let mut found = ripper.search_repos(&github, query, limit)?;
+    if found.is_empty() {
+        status!("No repositories match '{}'.", query);
+        return Ok(None);
+    }
+
+    let width = found.iter().map(|r| r.full_name.len()).max().unwrap_or(0);
+    let lines: Vec<String> = found
+        .iter()
+        .map(|r| {
+            let line = format!("{:width$}  {:>7} stars", r.full_name, r.stars);
+            match &r.description {
+                Some(d) => format!("{}  {}", line, d),
+                None => line,
+            }
+        })
+        .collect();
+    if !pick {
+        for line in &lines {
+            println!("{}", line);
+        }
+        return Ok(None);
+    }
+    if args.non_interactive || !stdin().is_terminal() {
+        return Err(GitripperError::Unsupported(
+            "--pick needs an interactive terminal".to_string(),
+        ));
+    }
+
+    let choice = FuzzySelect::new()
+        .with_prompt("Repository to rip")
+        .items(&lines)
+        .default(0)
+        .interact_opt()
+        .map_err(|e| GitripperError::Io(e.into()))?;
+    match choice {
+        Some(i) => Ok(Some(found.swap_remove(i).html_url)),
+        None => Err(GitripperError::Cancelled),
+    }
+}
 
-    println!("Done. Repository copied to: {}", dest.display());
-    println!("Note: this repository has no history from the original repo.");
+/// Rips `urls` with `ripper` and prints the batch summary.
+fn rip_all(
+    args: &Args,
+    ripper: &Ripper,
+    urls: &[String],
+) -> Result<(), GitripperError> {
+    if args.create_remote.is_some() || args.push {
+        return Err(GitripperError::Unsupported(
+            "--create-remote and --push only work for a single repository"
+                .to_string(),
+        ));
+    }
+    let root = args.dest.clone().unwrap_or_else(|| PathBuf::from("."));
+    let queue = job_queue(args, &root)?;
+    queue.extend_missing(urls)?;
+    let ripped =
+        queue.jobs().iter().filter(|j| j.status == JobStatus::Done).count();
+    if ripped > 0 {
+        status!("Resuming; {} repositories were already ripped.", ripped);
+    }
+    let started = Instant::now();
+    let results = ripper.rip_jobs(&queue, &root, args.parallel)?;
+
+    let width = results.iter().map(|r| r.url.len()).max().unwrap_or(0);
+    let mut failed = 0;
+    status!();
+    for r in &results {
+        match &r.result {
+            Ok(report) => {
+                status!("ok      {:width$}  {}", r.url, report.dest.display())
+            },
+            Err(e) => {
+                failed += 1;
+                status!("FAILED  {:width$}  {}", r.url, e);
+            },
+        }
+    }
+    status!("\n{} succeeded, {} failed.", results.len() - failed, failed);
+    if json_output() {
+        let records: Vec<_> =
+            results.iter().map(|r| (r.url.as_str(), &r.result)).collect();
+        print_run_record(&records, started, args.stats);
+    }
+
+    if interrupt().is_cancelled() {
+        exit(ERR_CANCELLED);
+    }
+    if failed > 0 {
+        exit(ERR_BATCH_FAILED);
+    }
     Ok(())
 }
 
-fn read_url_from_args(args: &Args) -> Result<String, i32> {
-    if let Some(u) = args.url.clone() {
-        Ok(u)
+/// Jobs of a batch or server run into `root`, taken up from the state file
+/// with --resume and started afresh otherwise.
+fn job_queue(args: &Args, root: &Path) -> Result<JobQueue, GitripperError> {
+    let path = args.state_file.clone().unwrap_or_else(|| root.join(JOBS_FILE));
+    let queue = if args.resume {
+        JobQueue::resume(path)?
     } else {
-        print!("Enter repository URL: ");
-        stdout().flush().ok();
-        let mut input = String::new();
-        stdin().read_line(&mut input).map_err(|_| ERR_INVALID_URL)?;
-        Ok(input.trim().to_string())
-    }
+        JobQueue::create(path)?
+    };
+    Ok(queue)
+}
+
+/// Prints the `--output json` record of a run: one entry per URL plus
+/// totals, on a single line of stdout.
+fn print_run_record(
+    results: &[(&str, &Result<RipReport, GitripperError>)],
+    started: Instant,
+    stats: bool,
+) {
+    let records: Vec<Value> = results
+        .iter()
+        .map(|(url, result)| match result {
+            Ok(report) => {
+                let (files, bytes) = tree_size(&report.dest).unwrap_or((0, 0));
+                let stats = stats
+                    .then(|| rip_stats(&report.dest, STATS_LARGEST_FILES).ok())
+                    .flatten()
+                    .map(|s| s.to_json());
+                json!({
+                    "url": url,
+                    "status": "ok",
+                    "provider": report.provider,
+                    "owner": report.owner,
+                    "repo": report.repo,
+                    "ref": report.reference,
+                    "commit": report.commit,
+                    "dest": report.dest,
+                    "files": files,
+                    "bytes": bytes,
+                    "stats": stats,
+                })
+            },
+            Err(e) => json!({
+                "url": url,
+                "status": "error",
+                "error": e.to_string(),
+                "exit_code": exit_code(e),
+            }),
+        })
+        .collect();
+    let failed = results.iter().filter(|(_, r)| r.is_err()).count();
+
+    let record = json!({
+        "results": records,
+        "succeeded": results.len() - failed,
+        "failed": failed,
+        "downloaded_bytes": DOWNLOADED_BYTES.load(Ordering::Relaxed),
+        "duration_ms": started.elapsed().as_millis() as u64,
+    });
+    println!("{}", record);
 }
 
-fn prepare_destination(args: &Args, repo: &str) -> Result<PathBuf, i32> {
-    let dest = args
-        .dest
-        .clone()
-        .unwrap_or_else(|| PathBuf::from(format!("{}-copy", repo)));
+/// Reports the `--stats` of a rip.
+fn print_stats(stats: &RipStats) {
+    status!("{} file(s), {} byte(s)", stats.files, stats.bytes);
+    let width = stats.languages.iter().map(|l| l.language.len()).max();
+    for l in &stats.languages {
+        status!(
+            "  {:width$}  {:>6} file(s)  {:>12} byte(s)",
+            l.language,
+            l.files,
+            l.bytes,
+            width = width.unwrap_or(0)
+        );
+    }
+    if !stats.largest.is_empty() {
+        status!("Largest files:");
+        for (path, bytes) in &stats.largest {
+            status!("  {:>12}  {}", bytes, path.display());
+        }
+    }
+}
 
-    if dest.exists() {
-        let not_empty =
-            dest.read_dir().map(|mut rd| rd.next().is_some()).unwrap_or(false);
+/// Number of files under `dir` and their total size, leaving out the
+/// repository at its top.
+fn tree_size(dir: &Path) -> io::Result<(u64, u64)> {
+    let (mut files, mut bytes) = (0, 0);
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(d) = pending.pop() {
+        for entry in std::fs::read_dir(&d)? {
+            let entry = entry?;
+            if d == dir && entry.file_name() == ".git" {
+                continue;
+            }
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(entry.path());
+            } else if file_type.is_file() {
+                files += 1;
+                bytes += entry.metadata()?.len();
+            }
+        }
+    }
+    Ok((files, bytes))
+}
 
-        if not_empty && !args.force {
-            eprintln!(
-                "Destination '{}' exists and is not empty. Use --force to \
-                 overwrite.",
-                dest.display()
-            );
-            return Err(ERR_DEST_EXISTS);
+fn run_list_refs(
+    args: &mut Args,
+    url: &str,
+    ref_type: RefType,
+    dates: bool,
+) -> Result<(), GitripperError> {
+    use_app_token_for(args, url)?;
+    let token = args.token.take().or_else(|| token_from_env(args, url));
+    let ripper = build_ripper(args, token)?;
+    let refs = ripper.list_refs(url, ref_type, dates)?;
+
+    let width = refs.iter().map(|r| r.name.len()).max().unwrap_or(0);
+    for r in &refs {
+        let sha = r.commit.get(..12).unwrap_or(&r.commit);
+        match &r.date {
+            Some(date) if dates => {
+                println!("{:width$}  {}  {}", r.name, sha, date)
+            },
+            _ => println!("{:width$}  {}", r.name, sha),
         }
+    }
+    Ok(())
+}
 
-        if args.force {
-            remove_dir_all(&dest).map_err(|_| ERR_CLEANUP_FAILED)?;
+fn run_cat(
+    args: &mut Args,
+    url: &str,
+    path: &str,
+) -> Result<(), GitripperError> {
+    use_app_token_for(args, url)?;
+    let token = args.token.take().or_else(|| token_from_env(args, url));
+    let ripper = build_ripper(args, token)?;
+    let contents = ripper.cat(url, path)?;
+    stdout().write_all(&contents)?;
+    Ok(())
+}
+
+fn run_diff(
+    args: &mut Args,
+    dest: &Path,
+    patch: bool,
+) -> Result<(), GitripperError> {
+    let ripper = build_ripper_for_rip(args, dest)?;
+    let diff = ripper.diff(dest)?;
+    let upstream = &diff.upstream;
+    let commit = upstream.commit.as_deref().unwrap_or("unknown commit");
+
+    if diff.is_empty() {
+        println!(
+            "{} matches {} ({}).",
+            dest.display(),
+            upstream.reference,
+            commit
+        );
+        return Ok(());
+    }
+
+    for change in &diff.changes {
+        let marker = match change.kind {
+            ChangeKind::Added => 'A',
+            ChangeKind::Removed => 'D',
+            ChangeKind::Modified => 'M',
+        };
+        println!("{}  {}", marker, change.path.display());
+    }
+    if patch {
+        for change in &diff.changes {
+            match diff.unified_diff(change)? {
+                Some(text) => print!("\n{}", text),
+                None => {
+                    println!("\nBinary file {} differs", change.path.display())
+                },
+            }
         }
     }
+    println!(
+        "\n{} file(s) differ from {} ({}).",
+        diff.changes.len(),
+        upstream.reference,
+        commit
+    );
+    Ok(())
+}
 
-    Ok(dest)
+/// Builds a ripper for working on the earlier rip at `dest`, taking the
+/// token from the environment of its provider if none was given.
+fn build_ripper_for_rip(
+    args: &mut Args,
+    dest: &Path,
+) -> Result<Ripper, GitripperError> {
+    let url = Provenance::read(dest).ok().flatten().map(|p| p.url);
+    if let Some(url) = &url {
+        use_app_token_for(args, url)?;
+    }
+    let token =
+        args.token.take().or_else(|| token_from_env(args, url.as_deref()?));
+    build_ripper(args, token)
 }
 
-fn determine_reference(
-    args: &Args,
-    client: &Client,
-    owner: &str,
-    repo: &str,
-    token: Option<&str>,
-) -> String {
-    if let Some(b) = args.branch.clone() {
-        return b;
+fn run_update(args: &mut Args, dest: &Path) -> Result<(), GitripperError> {
+    let ripper = build_ripper_for_rip(args, dest)?;
+    let update = ripper.update(dest)?;
+    let commit = update.report.commit.as_deref().unwrap_or("unknown commit");
+
+    if update.updated {
+        println!(
+            "Updated {} to {} ({}).",
+            dest.display(),
+            update.report.reference,
+            commit
+        );
+    } else {
+        println!(
+            "{} is up to date with {} ({}).",
+            dest.display(),
+            update.report.reference,
+            commit
+        );
     }
+    Ok(())
+}
 
-    match get_default_branch(client, owner, repo, token) {
-        Ok(b) => {
-            println!("Using default branch '{}'", b);
-            b
+/// Rips `url` unless the destination already holds a rip of it, then
+/// updates it every `interval` until interrupted. Network failures only
+/// skip a check.
+fn run_watch(
+    args: &mut Args,
+    url: &str,
+    interval: Duration,
+) -> Result<(), GitripperError> {
+    use_app_token_for(args, url)?;
+    let token = args.token.take().or_else(|| token_from_env(args, url));
+    let ripper = build_ripper(args, token)?;
+    let (_, location) = ripper.locate(url)?;
+    let dest = ripper.destination_for(&location.repo);
+
+    match Provenance::read(&dest)? {
+        Some(p) if p.url != url.trim() => {
+            return Err(GitripperError::Unsupported(format!(
+                "{} is a rip of {}, not {}",
+                dest.display(),
+                p.url,
+                url
+            )));
         },
-        Err(e) => {
-            eprintln!(
-                "Warning: could not determine default branch: {}. Using '{}'.",
-                e, DEFAULT_BRANCH
+        Some(_) => {},
+        None => {
+            let report = ripper.rip_to(url, &dest)?;
+            status!(
+                "Ripped {} ({}) into {}.",
+                report.reference,
+                report.commit.as_deref().unwrap_or("unknown commit"),
+                dest.display()
             );
-            DEFAULT_BRANCH.to_string()
         },
     }
+
+    status!(
+        "Watching {} every {}s; press Ctrl-C to stop.",
+        url,
+        interval.as_secs()
+    );
+    while sleep_uninterrupted(interval) {
+        match ripper.update(&dest) {
+            Ok(update) if update.updated => status!(
+                "Updated {} to {} ({}).",
+                dest.display(),
+                update.report.reference,
+                update.report.commit.as_deref().unwrap_or("unknown commit")
+            ),
+            Ok(_) => {},
+            Err(e)
+                if is_transient(&e)
+                    || matches!(e, GitripperError::RateLimited { .. }) =>
+            {
+                eprintln!("Warning: check failed, retrying later: {}", e)
+            },
+            Err(e) => return Err(e),
+        }
+    }
+    status!("Stopped watching {}.", dest.display());
+    Ok(())
 }
 
-fn download_archive(
-    client: &Client,
-    owner: &str,
-    repo: &str,
-    reference: &str,
-    token: Option<&str>,
-    dest_dir: &Path,
-) -> Result<PathBuf, i32> {
-    match download_zip(client, owner, repo, reference, token, dest_dir) {
-        Ok(p) => {
-            println!("Downloaded archive to {}", p.display());
-            Ok(p)
-        },
-        Err(e) => {
-            eprintln!("Failed to download repository archive: {}", e);
-            Err(ERR_DOWNLOAD_FAILED)
+/// Serves the HTTP API on `listen` until interrupted, ripping with
+/// `args` and the environment token of each URL's host.
+fn run_serve(
+    mut args: Args,
+    listen: &str,
+    allow_any_host: bool,
+) -> Result<(), GitripperError> {
+    if args.create_remote.is_some() || args.push {
+        return Err(GitripperError::Unsupported(
+            "--create-remote and --push with the server".to_string(),
+        ));
+    }
+    args.no_progress = true;
+    args.non_interactive = true;
+    let root = args.dest.take().unwrap_or_else(|| PathBuf::from("."));
+    let queue = job_queue(&args, &root)?;
+    let queued =
+        queue.jobs().iter().filter(|j| j.status == JobStatus::Queued).count();
+    if queued > 0 {
+        status!("Resuming {} queued rips.", queued);
+    }
+    let workers = args.parallel;
+    let args = Arc::new(args);
+
+    let server = Server::bind(listen, root, queue, workers, move |url| {
+        let token = args.token.clone().or_else(|| token_from_env(&args, url));
+        ripper_builder(&args, token)
+    })?
+    .allow_any_host(allow_any_host);
+    let addr =
+        server.local_addr().map_or(listen.to_string(), |a| a.to_string());
+    status!("Listening on http://{}; press Ctrl-C to stop.", addr);
+    server.run(interrupt())?;
+    status!("Stopped serving.");
+    Ok(())
+}
+
+fn run_release(
+    args: &mut Args,
+    url: &str,
+    release: &str,
+    patterns: &[String],
+) -> Result<(), GitripperError> {
+    use_app_token_for(args, url)?;
+    let token = args.token.take().or_else(|| token_from_env(args, url));
+    let ripper = build_ripper(args, token)?;
+
+    if patterns.is_empty() {
+        let (tag, assets) = ripper.release_assets(url, release)?;
+        let width = assets.iter().map(|a| a.name.len()).max().unwrap_or(0);
+        for asset in &assets {
+            match asset.size {
+                Some(size) => println!("{:width$}  {}", asset.name, size),
+                None => println!("{}", asset.name),
+            }
+        }
+        println!("\n{} asset(s) in release {}.", assets.len(), tag);
+        return Ok(());
+    }
+
+    let dest = args.dest.clone().unwrap_or_else(|| PathBuf::from("."));
+    let downloaded =
+        ripper.download_release_assets(url, release, patterns, &dest)?;
+    for asset in &downloaded {
+        let status = match asset.check {
+            AssetCheck::Verified => "verified   ",
+            AssetCheck::NoChecksum => "no checksum",
+        };
+        println!("{}  {}", status, asset.path.display());
+    }
+    Ok(())
+}
+
+/// Renders the man page to stdout, or every page to `dir`.
+fn run_man(dir: Option<&Path>) -> Result<(), GitripperError> {
+    let cmd = Args::command();
+    match dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)?;
+            clap_mangen::generate_to(cmd, dir)?;
         },
+        None => Man::new(cmd).render(&mut stdout())?,
     }
+    Ok(())
 }
 
-fn get_default_branch(
-    client: &Client,
-    owner: &str,
-    repo: &str,
-    token: Option<&str>,
-) -> anyhow::Result<String> {
-    let url = format!("{}/repos/{}/{}", GITHUB_API, owner, repo);
-    let mut req = client.get(&url);
+/// Exits with [`ERR_VERIFY_FAILED`] if any file doesn't match the manifest.
+fn run_verify(dest: &Path) -> Result<(), GitripperError> {
+    let changes = verify_manifest(dest)?;
 
-    if let Some(t) = token {
-        req = req.header("Authorization", format!("token {}", t));
+    for change in &changes {
+        let status = match change.kind {
+            ChangeKind::Added => "EXTRA   ",
+            ChangeKind::Removed => "MISSING ",
+            ChangeKind::Modified => "MODIFIED",
+        };
+        println!("{}  {}", status, change.path.display());
     }
 
-    let res = req.timeout(TIMEOUT_GET_REPO).send()?;
+    if !changes.is_empty() {
+        println!("\n{} file(s) do not match the manifest.", changes.len());
+        exit(ERR_VERIFY_FAILED);
+    }
+    println!("All files in {} match the manifest.", dest.display());
+    Ok(())
+}
 
-    match res.status().as_u16() {
-        200 => {
-            let v: Value = res.json()?;
-            Ok(v.get("default_branch")
-                .and_then(|b| b.as_str())
-                .unwrap_or(DEFAULT_BRANCH)
-                .to_string())
+fn run_auth(action: &AuthCommand) -> Result<(), GitripperError> {
+    match action {
+        AuthCommand::Login { host, with_token } => {
+            let token = if *with_token {
+                let mut line = String::new();
+                stdin().read_line(&mut line)?;
+                line.trim().to_string()
+            } else {
+                Password::new()
+                    .with_prompt(format!("Token for {}", host))
+                    .interact()
+                    .map_err(|e| io::Error::other(e.to_string()))?
+            };
+            if token.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "no token given",
+                )
+                .into());
+            }
+            store_token(host, &token)?;
+            println!("Stored token for {} in the OS keyring.", host);
         },
-        404 => Err(anyhow!("Repository {}/{} not found (404).", owner, repo)),
-        s => {
-            let txt = res.text().unwrap_or_default();
-            Err(anyhow!("Failed to get repo info: {} {}", s, txt))
+        AuthCommand::Logout { host } => {
+            if delete_token(host)? {
+                println!("Removed the stored token for {}.", host);
+            } else {
+                println!("No token stored for {}.", host);
+            }
+        },
+        AuthCommand::Status { host } => {
+            let github = GitHub::default();
+            match find_token(&github, host) {
+                Some((_, source)) => {
+                    println!("Token for {} found in the {}.", host, source)
+                },
+                None => println!("No token found for {}.", host),
+            }
         },
     }
+    Ok(())
 }
 
-fn download_zip(
-    // TODO: this function might be broken, do we need `NamedTempFile`?
-    client: &Client,
+fn run_cache(action: &CacheCommand) -> Result<(), GitripperError> {
+    let cache = Cache::open_default().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "could not determine the cache directory",
+        )
+    })?;
+
+    match action {
+        CacheCommand::Clean { max_age, max_size } => {
+            let policy = CleanPolicy {
+                max_age:  max_age
+                    .map(|d| Duration::from_secs(d * 24 * 60 * 60)),
+                max_size: max_size.map(|m| m * 1024 * 1024),
+                all:      max_age.is_none() && max_size.is_none(),
+            };
+            let report = cache.clean(&policy)?;
+            println!(
+                "Removed {} cached archive(s), freed {:.1} MiB.",
+                report.removed,
+                report.freed_bytes as f64 / (1024.0 * 1024.0)
+            );
+        },
+        CacheCommand::Dir => println!("{}", cache.root().display()),
+    }
+    Ok(())
+}
+
+fn github_for(args: &Args) -> GitHub {
+    args.api_url.as_deref().map_or_else(GitHub::default, GitHub::with_api_url)
+}
+
+/// Token for `github` from its environment variable or the keyring.
+fn github_env_token(github: &GitHub) -> Option<String> {
+    var(github.token_env()?)
+        .ok()
+        .or_else(|| Some(find_token(github, github.host())?.0))
+}
+
+/// With --app-id, replaces the token with an installation token for the
+/// installation on `owner`, or on its `repo` if given.
+fn use_app_token(
+    args: &mut Args,
+    github: &GitHub,
     owner: &str,
-    repo: &str,
-    reference: &str,
-    token: Option<&str>,
-    dest_dir: &Path,
-) -> anyhow::Result<PathBuf> {
-    let url = format!(
-        "https://api.github.com/repos/{}/{}/zipball/{}",
-        owner, repo, reference
-    );
+    repo: Option<&str>,
+) -> Result<(), GitripperError> {
+    let (Some(id), Some(key)) = (&args.app_id, &args.app_key) else {
+        return Ok(());
+    };
+    let app = GitHubApp::from_key_file(id.clone(), key)?;
+    args.token =
+        Some(app.installation_token(get_client(), github, owner, repo)?);
+    Ok(())
+}
 
-    let mut req = client.get(&url).header("Accept", ACCEPT_HEADER);
+/// [`use_app_token`] for the repository `url` points at.
+fn use_app_token_for(args: &mut Args, url: &str) -> Result<(), GitripperError> {
+    if args.app_id.is_none() {
+        return Ok(());
+    }
+    let github = github_for(args);
+    let location = github.parse_url(url).ok_or_else(|| {
+        GitripperError::Unsupported(format!(
+            "--app-id only works with {} repositories, not {}",
+            github.host(),
+            url
+        ))
+    })?;
+    use_app_token(args, &github, &location.owner, Some(&location.repo))
+}
 
-    if let Some(t) = token {
-        req = req.header("Authorization", format!("token {}", t));
-    }
-
-    let mut resp = req.timeout(TIMEOUT_DOWNLOAD).send()?;
-    let status = resp.status();
-
-    if !status.is_success() {
-        return if status.as_u16() == 404 {
-            Err(anyhow!(
-                "Archive for {}/{}@{} not found (404).",
-                owner,
-                repo,
-                reference
-            ))
-        } else if status.is_redirection() {
-            Err(anyhow!("Unexpected redirect: {}", status))
-        } else {
-            Err(anyhow!("Failed to download archive: {}", status))
-        };
+fn enterprise_provider(args: &Args) -> Option<Arc<dyn Provider>> {
+    let api_url = args.api_url.as_deref()?;
+    Some(Arc::new(GitHub::with_api_url(api_url)))
+}
+
+/// Reads the token variable of the provider `url` belongs to, e.g.
+/// `GITHUB_TOKEN` or `GITLAB_TOKEN`, falling back to the keyring, the gh
+/// CLI and git's credential helpers.
+fn token_from_env(args: &Args, url: &str) -> Option<String> {
+    let provider = enterprise_provider(args)
+        .filter(|p| p.parse_url(url).is_some())
+        .or_else(|| detect_provider(url).map(|(p, _)| p))?;
+    if let Some(token) = provider.token_env().and_then(|v| var(v).ok()) {
+        return Some(token);
     }
 
-    let ts = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
-    let filename = format!("{}{}.zip", ARCHIVE_PREFIX, ts.as_nanos());
-    let path = dest_dir.join(filename);
-    let mut outfile = File::create(&path)?;
-    io::copy(&mut resp, &mut outfile)?;
-
-    Ok(path)
-}
-
-fn remove_embedded_git(dirpath: &Path) {
-    let mut builder = WalkBuilder::new(dirpath);
-    builder.standard_filters(false).hidden(false);
-
-    builder.build_parallel().run(|| {
-        Box::new(|res: Result<DirEntry, Error>| {
-            match res {
-                Ok(entry) => {
-                    if entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false)
-                        && entry.file_name() == ".git"
-                    {
-                        let git_dir = entry.path().to_path_buf();
-                        match remove_dir_all(&git_dir) {
-                            Ok(_) => println!(
-                                "Removed embedded .git at {}",
-                                git_dir.display()
-                            ),
-                            Err(e) => eprintln!(
-                                "Warning: failed to remove embedded .git at \
-                                 {}: {}",
-                                git_dir.display(),
-                                e
-                            ),
-                        }
-                    }
-                },
-                Err(e) => eprintln!("Warning: walker error: {}", e),
+    let location = provider.parse_url(url)?;
+    let host = credential_host(provider.as_ref(), &location)?;
+    find_token(provider.as_ref(), &host).map(|(token, _)| token)
+}
+
+fn print_event(event: &RipEvent) {
+    match event {
+        RipEvent::DefaultBranch(b) => status!("Using default branch '{}'", b),
+        RipEvent::Fork {
+            fork,
+            parent,
+            using_parent: true,
+        } => status!(
+            "note: {} is a fork of {}; ripping {}",
+            fork,
+            parent,
+            parent
+        ),
+        RipEvent::Fork { fork, parent, .. } => eprintln!(
+            "Warning: {} is a fork of {}, which may be more up to date; pass \
+             --prefer-parent to rip it instead",
+            fork, parent
+        ),
+        RipEvent::Moved { from, to } => {
+            status!("note: {} moved to {}; using the new name", from, to)
+        },
+        RipEvent::Cloning(u) => status!("Cloning {}...", u),
+        RipEvent::Downloaded(p) => {
+            if let Ok(meta) = p.metadata() {
+                DOWNLOADED_BYTES.fetch_add(meta.len(), Ordering::Relaxed);
             }
-            Continue
-        })
-    });
+            status!("Downloaded archive to {}", p.display())
+        },
+        RipEvent::BackedUp(p) => {
+            status!("Moved existing destination to {}", p.display())
+        },
+        RipEvent::RemovedEmbeddedGit(p) => {
+            status!("Removed embedded .git at {}", p.display())
+        },
+        RipEvent::SparseFetched(n) => status!("Fetched {} file(s)", n),
+        RipEvent::LfsObjects(n) => status!("Fetched {} LFS object(s)", n),
+        RipEvent::Attributed(license) => status!(
+            "Credited the repository in THIRD_PARTY_NOTICES.md (license: {})",
+            license.as_deref().unwrap_or("unknown")
+        ),
+        RipEvent::NestedExtracted(archives) => {
+            status!("Expanded {} nested archive(s):", archives.len());
+            for archive in archives {
+                status!("  {}", archive.display());
+            }
+        },
+        RipEvent::Deduplicated(d) => status!(
+            "Hardlinked {} duplicate file(s), saving {} byte(s)",
+            d.files,
+            d.bytes
+        ),
+        RipEvent::Attested { path, bundle } => match bundle {
+            Some(b) => status!(
+                "Wrote provenance to {}, signed in {}",
+                path.display(),
+                b.display()
+            ),
+            None => status!("Wrote provenance to {}", path.display()),
+        },
+        RipEvent::SkippedFiles(files) => {
+            status!("Skipped {} file(s):", files.len());
+            for f in files {
+                let reason = match f.reason {
+                    SkipReason::TooLarge => "too large",
+                    SkipReason::Binary => "binary",
+                };
+                status!(
+                    "  {} ({} bytes, {})",
+                    f.path.display(),
+                    f.size,
+                    reason
+                );
+            }
+        },
+        RipEvent::SecretFound(s) => eprintln!(
+            "Warning: possible {} in {}:{}",
+            s.rule,
+            s.path.display(),
+            s.line
+        ),
+        RipEvent::TemplateRendered(n) => {
+            status!("Filled template placeholders in {} file(s)", n)
+        },
+        RipEvent::RunningHook(cmd) => status!("Running post-hook: {}", cmd),
+        RipEvent::Initializing => {
+            status!("Initializing new git repository...")
+        },
+        RipEvent::Vendoring(p) => {
+            status!("Committing {} to its enclosing repository...", p.display())
+        },
+        RipEvent::UpstreamSet(u) => status!("Set remote upstream to {}", u),
+        RipEvent::Tagged(t) => status!("Tagged the snapshot as {}", t),
+        RipEvent::UpToDate {
+            dest,
+            reference,
+            commit,
+        } => status!(
+            "{} is up to date with {} ({}); skipping",
+            dest.display(),
+            reference,
+            commit
+        ),
+        RipEvent::RemoteSet(r) => status!("Set remote origin to {}", r),
+        RipEvent::CacheHit(p) => {
+            status!("Using cached archive {}", p.display())
+        },
+        RipEvent::Warning(w) => eprintln!("Warning: {}", w),
+        RipEvent::PullRequestHead { number, head } => {
+            status!("Pull request #{} is at {}", number, head)
+        },
+        RipEvent::Stage { repo, stage } => status!("{}: {}", repo, stage),
+        RipEvent::Retrying { delay, reason, .. } => eprintln!(
+            "Warning: {}. Retrying in {:.1}s...",
+            reason,
+            delay.as_secs_f64()
+        ),
+    }
 }
 
-fn check_git_installed() -> Result<(), ()> {
-    match Command::new("git")
-        .arg("--version")
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status()
-    {
-        Ok(s) if s.success() => Ok(()),
-        _ => Err(()),
+fn overwrite_policy(args: &Args) -> OverwritePolicy {
+    if args.force {
+        OverwritePolicy::Replace
+    } else if args.merge {
+        OverwritePolicy::Merge
+    } else if args.skip_existing {
+        OverwritePolicy::SkipExisting
+    } else if args.backup {
+        OverwritePolicy::Backup
+    } else {
+        OverwritePolicy::Fail
     }
 }
 
-fn initialize_repo(
-    dest: &Path,
-    author_name: Option<&str>,
-    author_email: Option<&str>,
-    remote: Option<&str>,
-) -> anyhow::Result<()> {
-    let repo = Repository::init(dest)?;
+/// Compiles `patterns`, failing on the first invalid one.
+fn glob_set(patterns: &[String]) -> Result<GlobSet, GitripperError> {
+    let mut globs = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidInput, e.to_string())
+        })?;
+        globs.add(glob);
+    }
+    Ok(globs.build().map_err(|e| {
+        io::Error::new(io::ErrorKind::InvalidInput, e.to_string())
+    })?)
+}
 
-    if author_name.is_some() || author_email.is_some() {
-        let mut cfg = repo.config()?;
+fn build_ripper(
+    args: &Args,
+    token: Option<String>,
+) -> Result<Ripper, GitripperError> {
+    ripper_builder(args, token)?.build()
+}
 
-        if let Some(name) = author_name {
-            cfg.set_str("user.name", name)?;
+/// A builder configured from `args`, with `token` if given.
+fn ripper_builder(
+    args: &Args,
+    token: Option<String>,
+) -> Result<RipperBuilder, GitripperError> {
+    let mut builder = Ripper::builder()
+        .client(get_client().clone())
+        .cancel_token(interrupt().clone())
+        .http_options(http_options(args))
+        .user_agent(USER_AGENT)
+        .overwrite(overwrite_policy(args))
+        .retry(RetryPolicy {
+            retries: args.retries,
+            wait_for_rate_limit: args.wait_for_rate_limit,
+            ..Default::default()
+        })
+        .refresh(args.refresh)
+        .offline(args.offline)
+        .download_threads(args.download_threads)
+        .batch_limits(BatchLimits {
+            downloads:       args.max_downloads,
+            extractions:     args.max_extractions,
+            in_flight_bytes: args.max_in_flight.map(|mib| mib * 1024 * 1024),
+        })
+        .keep_history(args.keep_history)
+        .no_init(args.no_init)
+        .manifest(args.manifest)
+        .receipt(args.receipt)
+        .attribution(args.attribution)
+        .sign_attestation(args.sign_attestation)
+        .verify_commit(args.verify_commit)
+        .prefer_parent(args.prefer_parent)
+        .dedupe(args.dedupe)
+        .extract_nested(args.extract_nested.unwrap_or(0))
+        .scan_secrets(secret_policy(args.scan_secrets))
+        .lfs(args.lfs)
+        .sign(args.sign)
+        .respect_gitignore(args.respect_gitignore)
+        .on_event(print_event);
+
+    if let Some(t) = token {
+        builder = builder.token(t);
+    }
+    if args.empty_readme {
+        builder = builder.empty_readme(DEFAULT_README);
+    }
+    if let Some(jobs) = args.jobs {
+        builder = builder.jobs(jobs);
+    }
+    if args.attest {
+        builder = builder.attest(builder_info());
+    }
+    let config = Config::open_default()?;
+    for hook in config.post_hooks.iter().chain(&args.post_hook) {
+        builder = builder.post_hook(hook);
+    }
+    builder = builder.strip_meta(args.strip_meta);
+    match args.strip_components {
+        Some(0) => builder = builder.strip_root(StripRoot::Never),
+        Some(n) => builder = builder.strip_root(StripRoot::Always(n)),
+        None => {},
+    }
+    for glob in &args.strip {
+        builder = builder.exclude(glob);
+    }
+    if !args.redact.is_empty() {
+        let redacted = glob_set(&args.redact)?;
+        builder = builder.entry_hook(move |entry| {
+            if !entry.is_dir && redacted.is_match(&entry.rel_path) {
+                EntryAction::Replace(Vec::new())
+            } else {
+                EntryAction::Keep
+            }
+        });
+    }
+    if let Some(mib) = args.max_file_size {
+        builder = builder.max_file_size(mib * 1024 * 1024);
+    }
+    builder = builder
+        .skip_binaries(args.skip_binaries)
+        .eol(args.eol.eol())
+        .collisions(args.on_collision.policy())
+        .deterministic(args.deterministic);
+    for glob in &args.sparse {
+        builder = builder.sparse(glob);
+    }
+    if args.template {
+        builder = builder.template(args.define.iter().cloned().collect());
+        if !args.non_interactive
+            && stdin().is_terminal()
+            && stderr().is_terminal()
+        {
+            builder = builder.template_prompt(prompt_template_variable);
         }
+    }
+    if !args.yes {
+        let interactive = !args.non_interactive
+            && stdin().is_terminal()
+            && stderr().is_terminal();
+        builder = builder.confirm_size_above(
+            args.size_limit * 1024 * 1024,
+            move |location, size| interactive && confirm_size(location, size),
+        );
+    }
+    if let Some(p) = enterprise_provider(args) {
+        builder = builder.provider(p);
+    }
+    if !args.no_cache
+        && let Some(cache) = Cache::open_default()
+    {
+        builder = builder.cache(cache);
+    }
+    if let Some(d) = args.dest.as_deref() {
+        builder = builder.dest(d);
+    }
+    if let Some(v) = args.vendor.as_deref() {
+        builder = builder.dest(v).vendor(true);
+    }
+    if let Some(layout) = &args.dest_template {
+        builder = builder.dest_layout(layout.clone());
+    }
+    if let Some(b) = args.branch.as_deref() {
+        builder = builder.reference(b);
+    }
+    if let Some(t) = args.tag.as_deref() {
+        builder = builder.tag(t);
+    }
+    if let Some(sha) = args.commit.as_deref() {
+        builder = builder.commit(sha);
+    }
+    if let Some(r) = args.release.as_deref() {
+        builder = builder.release(r);
+    }
+    if let Some(number) = args.pr {
+        builder = builder.pull_request(number);
+    }
+    if let Some(p) = args.path.as_deref() {
+        builder = builder.subpath(p);
+    }
+    if let Some(name) = args.author_name.as_deref() {
+        builder = builder.author_name(name);
+    }
+    if let Some(email) = args.author_email.as_deref() {
+        builder = builder.author_email(email);
+    }
+    if let Some(branch) = args.initial_branch.as_deref() {
+        builder = builder.initial_branch(branch);
+    }
+    if let Some(message) = args.message.as_deref() {
+        builder = builder.message(message);
+    }
+    builder = builder.upstream_remote(args.upstream_remote).bare(args.bare);
+    if let Some(name) = args.tag_as.as_deref() {
+        builder = builder.tag_as(name);
+    }
+    builder =
+        builder.copy_tag(args.copy_tag).import_metadata(args.import_metadata);
+    if let Some(r) = args.remote.as_deref() {
+        builder = builder.remote(r);
+    }
+    if let Some(depth) = args.depth {
+        builder = builder.history_depth(depth);
+    }
+    if let Some(depth) = args.history_depth {
+        builder =
+            builder.keep_history(true).history_depth(depth).detach_remote(true);
+    }
+    if let Some(mib) = args.memory_budget {
+        builder = builder.memory_budget(mib * 1024 * 1024);
+    }
+    #[cfg(feature = "async")]
+    {
+        builder = builder.overlap_extraction(args.overlap);
+    }
+    if !args.no_progress {
+        builder = builder.progress(Arc::new(BarProgress::default()));
+    }
+    Ok(builder)
+}
 
-        if let Some(email) = author_email {
-            cfg.set_str("user.email", email)?;
+/// Lets the user pick one of the branches and tags of `url`, with the
+/// default branch first. `None` if the URL already names a ref or pull
+/// request, nothing could be listed or the user cancelled.
+fn pick_reference(ripper: &Ripper, url: &str) -> Option<(RefType, String)> {
+    let (provider, location) = ripper.locate(url).ok()?;
+    if location.reference.is_some() || location.pull.is_some() {
+        return None;
+    }
+
+    let default = ripper.default_branch(provider.as_ref(), &location).ok();
+    let mut refs = Vec::new();
+    for ref_type in [RefType::Branch, RefType::Tag] {
+        match ripper.list_refs(url, ref_type, false) {
+            Ok(list) => {
+                refs.extend(list.into_iter().map(|r| (ref_type, r.name)))
+            },
+            Err(e) => {
+                eprintln!("Warning: could not list refs: {}", e);
+                return None;
+            },
         }
     }
+    if refs.len() < 2 {
+        return None;
+    }
 
-    let mut index = repo.index()?;
-    index.add_all(["*"].iter(), IndexAddOption::DEFAULT, None)?;
-    index.write()?;
-    let tree_id = index.write_tree()?;
-    let tree = repo.find_tree(tree_id)?;
-    let sig_name = author_name.unwrap_or("gitripper");
-    let sig_email = author_email.unwrap_or("gitripper@localhost");
-    let signature = Signature::now(sig_name, sig_email)?;
+    if let Some(d) = &default
+        && let Some(i) =
+            refs.iter().position(|(t, n)| *t == RefType::Branch && n == d)
+    {
+        let r = refs.remove(i);
+        refs.insert(0, r);
+    }
+    let items: Vec<String> = refs
+        .iter()
+        .map(|(t, n)| match t {
+            RefType::Branch if default.as_ref() == Some(n) => {
+                format!("{} (default branch)", n)
+            },
+            RefType::Branch => format!("{} (branch)", n),
+            RefType::Tag => format!("{} (tag)", n),
+        })
+        .collect();
+
+    let choice = FuzzySelect::new()
+        .with_prompt("Ref to rip")
+        .items(&items)
+        .default(0)
+        .interact_opt()
+        .ok()??;
+    Some(refs.swap_remove(choice))
+}
 
-    repo.commit(
-        Some("HEAD"),
-        &signature,
-        &signature,
-        DEFAULT_COMMIT_MESSAGE,
-        &tree,
-        &[],
-    )?;
+/// Parses `--interval`: a number of seconds, or a number with an `s`, `m`,
+/// `h` or `d` suffix.
+fn parse_interval(s: &str) -> Result<Duration, String> {
+    let (number, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, "s"),
+    };
+    let scale = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("unknown unit '{}' in '{}'", unit, s)),
+    };
+    match number.parse::<u64>().ok().and_then(|n| n.checked_mul(scale)) {
+        Some(secs) if secs > 0 => Ok(Duration::from_secs(secs)),
+        _ => Err(format!(
+            "expected a positive duration like 10m, got '{}'",
+            s
+        )),
+    }
+}
+
+fn parse_define(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .ok_or_else(|| format!("expected NAME=VALUE, got '{}'", s))
+}
 
-    if let Some(r) = remote {
-        repo.remote("origin", r)?;
-        println!("Set remote origin to {}", r);
+fn prompt_template_variable(var: &TemplateVariable) -> Option<String> {
+    let mut input = Input::<String>::new()
+        .with_prompt(var.prompt.as_deref().unwrap_or(&var.name));
+    if let Some(default) = &var.default {
+        input = input.default(default.clone());
     }
+    input.interact_text().ok()
+}
 
-    Ok(())
+fn confirm_size(location: &RepoLocation, size: u64) -> bool {
+    Confirm::new()
+        .with_prompt(format!(
+            "{} is about {} MiB. Rip it anyway?",
+            location,
+            size.div_ceil(1024 * 1024)
+        ))
+        .default(false)
+        .interact()
+        .unwrap_or(false)
+}
+
+fn prompt_for_url() -> Result<String, GitripperError> {
+    print!("Enter repository URL: ");
+    stdout().flush().ok();
+    let mut input = String::new();
+    stdin().read_line(&mut input)?;
+    Ok(input.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_interval("10m"), Ok(Duration::from_secs(600)));
+        assert_eq!(parse_interval("2d"), Ok(Duration::from_secs(172_800)));
+        assert!(parse_interval("0h").is_err());
+        assert!(parse_interval("5w").is_err());
+        assert_eq!(
+            parse_interval("999999999999999999d"),
+            Err("expected a positive duration like 10m, got \
+                 '999999999999999999d'"
+                .to_string())
+        );
+    }
 }
 
 /* TODO: Potential optimizations / alternative crates to consider
-        - tokio + reqwest (async) — overlap network + disk work and
-          parallelize downloads/IO.
         - isahc or curl — libcurl-based clients that can be faster and more
           featureful for many concurrent connections.
         - async-compression — for async decompression pipelines if you move to
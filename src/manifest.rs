@@ -0,0 +1,148 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use rayon::prelude::*;
+
+use crate::{
+    compute_blake3_hex, diff::compare_hashes, FileChange,
+    PROVENANCE_FILE_NO_GIT,
+};
+
+/// File at the top of a rip listing the BLAKE3 hash of every other file, in
+/// the format `b3sum` prints and `b3sum --check` reads.
+pub const MANIFEST_FILE: &str = "MANIFEST.blake3";
+
+/// Hashes every file under `dest` and writes them to [`MANIFEST_FILE`].
+pub fn write_manifest(dest: &Path) -> io::Result<PathBuf> {
+    let mut text = String::new();
+    for (path, hash) in hash_tree(dest)? {
+        text.push_str(&format!("{}  {}\n", hash, manifest_path(&path)));
+    }
+
+    let path = dest.join(MANIFEST_FILE);
+    fs::write(&path, text)?;
+    Ok(path)
+}
+
+/// Recomputes the hashes of the files under `dest` and compares them with
+/// its [`MANIFEST_FILE`]. An empty result means nothing changed; `Added` are
+/// files missing from the manifest and `Removed` files missing on disk.
+pub fn verify_manifest(dest: &Path) -> io::Result<Vec<FileChange>> {
+    let expected = read_manifest(&dest.join(MANIFEST_FILE))?;
+    Ok(compare_hashes(expected, hash_tree(dest)?))
+}
+
+fn read_manifest(path: &Path) -> io::Result<BTreeMap<PathBuf, String>> {
+    fs::read_to_string(path)?
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(|line| {
+            let (hash, path) = line.split_once("  ").ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("malformed manifest line: {}", line),
+                )
+            })?;
+            Ok((PathBuf::from(path), hash.to_string()))
+        })
+        .collect()
+}
+
+/// `/`-separated form of a relative path, as stored in the manifest.
+pub(crate) fn manifest_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// BLAKE3 hash of every file under `root`, keyed by relative path. Symlinks
+/// are hashed by their target. The repository, rip metadata and manifest at
+/// the top of `root` are skipped.
+pub(crate) fn hash_tree(root: &Path) -> io::Result<BTreeMap<PathBuf, String>> {
+    let mut files = Vec::new();
+    collect_files(root, Path::new(""), &mut files)?;
+
+    files
+        .into_par_iter()
+        .map(|rel| {
+            let path = root.join(&rel);
+            let hash = if fs::symlink_metadata(&path)?.is_symlink() {
+                let target = fs::read_link(&path)?;
+                blake3::hash(target.to_string_lossy().as_bytes())
+                    .to_hex()
+                    .to_string()
+            } else {
+                compute_blake3_hex(&path)?
+            };
+            Ok((rel, hash))
+        })
+        .collect()
+}
+
+pub(crate) fn collect_files(
+    root: &Path,
+    rel: &Path,
+    out: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(root.join(rel))? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if rel.as_os_str().is_empty()
+            && (name == ".git"
+                || name == PROVENANCE_FILE_NO_GIT
+                || name == MANIFEST_FILE)
+        {
+            continue;
+        }
+
+        let path = rel.join(&name);
+        if entry.file_type()?.is_dir() {
+            collect_files(root, &path, out)?;
+        } else {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, write};
+
+    use super::*;
+    use crate::ChangeKind;
+
+    #[test]
+    fn test_verify_detects_tampering() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dest = temp_dir.path();
+        create_dir_all(dest.join("src")).unwrap();
+        write(dest.join("src/lib.rs"), "fn main() {}").unwrap();
+        write(dest.join("README.md"), "hello").unwrap();
+
+        write_manifest(dest).unwrap();
+        assert!(verify_manifest(dest).unwrap().is_empty());
+
+        write(dest.join("src/lib.rs"), "fn evil() {}").unwrap();
+        fs::remove_file(dest.join("README.md")).unwrap();
+        write(dest.join("extra.txt"), "x").unwrap();
+
+        let kinds: Vec<_> = verify_manifest(dest)
+            .unwrap()
+            .into_iter()
+            .map(|c| (manifest_path(&c.path), c.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("README.md".to_string(), ChangeKind::Removed),
+                ("extra.txt".to_string(), ChangeKind::Added),
+                ("src/lib.rs".to_string(), ChangeKind::Modified),
+            ]
+        );
+    }
+}
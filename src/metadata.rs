@@ -0,0 +1,97 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use toml::{Table, Value};
+
+/// Upstream metadata at the top of a rip made without a repository, written
+/// by [`write_metadata`].
+pub const METADATA_FILE: &str = "METADATA.toml";
+/// Git config section the metadata is stored under in a ripped repository.
+pub const METADATA_SECTION: &str = "gitripper";
+
+/// What a provider says about a repository besides its files, from
+/// [`crate::Provider::metadata`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepoMetadata {
+    pub description: Option<String>,
+    pub homepage:    Option<String>,
+    pub topics:      Vec<String>,
+}
+
+impl RepoMetadata {
+    pub fn is_empty(&self) -> bool { *self == Self::default() }
+
+    /// Git config entries holding the metadata, such as
+    /// `gitripper.description`. Topics are joined with commas.
+    pub fn config_entries(&self) -> Vec<(String, String)> {
+        let key = |name: &str| format!("{}.{}", METADATA_SECTION, name);
+        let mut entries = Vec::new();
+        if let Some(description) = &self.description {
+            entries.push((key("description"), description.clone()));
+        }
+        if let Some(homepage) = &self.homepage {
+            entries.push((key("homepage"), homepage.clone()));
+        }
+        if !self.topics.is_empty() {
+            entries.push((key("topics"), self.topics.join(",")));
+        }
+        entries
+    }
+
+    /// The metadata as the TOML of a [`METADATA_FILE`].
+    pub fn to_toml(&self) -> String {
+        let mut table = Table::new();
+        if let Some(description) = &self.description {
+            table.insert("description".into(), description.clone().into());
+        }
+        if let Some(homepage) = &self.homepage {
+            table.insert("homepage".into(), homepage.clone().into());
+        }
+        if !self.topics.is_empty() {
+            let topics = self.topics.iter().cloned().map(Value::from);
+            table.insert("topics".into(), Value::Array(topics.collect()));
+        }
+        table.to_string()
+    }
+}
+
+/// Writes `metadata` to the [`METADATA_FILE`] in `dest`.
+pub fn write_metadata(
+    dest: &Path,
+    metadata: &RepoMetadata,
+) -> io::Result<PathBuf> {
+    let path = dest.join(METADATA_FILE);
+    fs::write(&path, metadata.to_toml())?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_entries_and_toml() {
+        let metadata = RepoMetadata {
+            description: Some("A \"small\" widget".to_string()),
+            homepage:    None,
+            topics:      vec!["rust".to_string(), "cli".to_string()],
+        };
+
+        assert_eq!(
+            metadata.config_entries(),
+            [
+                (
+                    "gitripper.description".to_string(),
+                    "A \"small\" widget".to_string()
+                ),
+                ("gitripper.topics".to_string(), "rust,cli".to_string()),
+            ]
+        );
+        let table: Table = metadata.to_toml().parse().unwrap();
+        assert_eq!(table["description"].as_str(), Some("A \"small\" widget"));
+        assert!(!table.contains_key("homepage"));
+        assert_eq!(table["topics"].as_array().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,295 @@
+//! A stand-in for a provider's HTTP API, enabled by the `mock` feature.
+//!
+//! [`MockServer`] answers plain HTTP/1.1 on a local port from a table of
+//! canned [`MockResponse`]s, so the whole pipeline — default branch, commit
+//! lookup, archive download, extraction — can be exercised against 404s,
+//! redirects and truncated bodies without touching the network. Point a
+//! provider at it with [`MockServer::github`] and rip
+//! [`MockServer::repo_url`].
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Cursor, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use serde_json::Value;
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+use crate::GitHub;
+
+/// A canned response served by a [`MockServer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockResponse {
+    pub status:         u16,
+    pub headers:        Vec<(String, String)>,
+    pub body:           Vec<u8>,
+    /// `Content-Length` announced instead of the body's real length, to
+    /// simulate a connection dropped mid-transfer.
+    pub content_length: Option<u64>,
+}
+
+impl MockResponse {
+    /// An empty response with `status`.
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+            content_length: None,
+        }
+    }
+
+    /// `200 OK` with `value` as its JSON body.
+    pub fn json(value: &Value) -> Self {
+        Self::status(200)
+            .header("Content-Type", "application/json")
+            .body(value.to_string())
+    }
+
+    /// `200 OK` with a zip archive as its body.
+    pub fn zip(archive: Vec<u8>) -> Self {
+        Self::status(200)
+            .header("Content-Type", "application/zip")
+            .body(archive)
+    }
+
+    /// `302 Found` pointing at `location`, which may be a path on the same
+    /// server.
+    pub fn redirect(location: &str) -> Self {
+        Self::status(302).header("Location", location)
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Announces `length` bytes but sends only the body, then closes.
+    pub fn truncated(mut self, length: u64) -> Self {
+        self.content_length = Some(length);
+        self
+    }
+
+    fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        let length = self.content_length.unwrap_or(self.body.len() as u64);
+        write!(out, "HTTP/1.1 {} Mock\r\n", self.status)?;
+        for (name, value) in &self.headers {
+            write!(out, "{}: {}\r\n", name, value)?;
+        }
+        write!(
+            out,
+            "Content-Length: {}\r\nConnection: close\r\n\r\n",
+            length
+        )?;
+        out.write_all(&self.body)?;
+        out.flush()
+    }
+}
+
+/// A request received by a [`MockServer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockRequest {
+    pub method:  String,
+    /// Path and query, as sent.
+    pub path:    String,
+    /// Header names are lowercase.
+    pub headers: Vec<(String, String)>,
+}
+
+impl MockRequest {
+    /// Value of the first header named `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    routes:   Mutex<HashMap<String, MockResponse>>,
+    requests: Mutex<Vec<MockRequest>>,
+    stopped:  AtomicBool,
+}
+
+/// HTTP server on `127.0.0.1` serving [`MockResponse`]s by path. Paths
+/// without a route get a `404`. Stops when dropped.
+#[derive(Debug)]
+pub struct MockServer {
+    addr:   SocketAddr,
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    /// Starts a server on a free port.
+    pub fn start() -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(Shared::default());
+        let serving = shared.clone();
+        let thread = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if serving.stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                // A client that hangs up early is its own test's problem.
+                if let Ok(stream) = stream {
+                    let _ = serve(&serving, stream);
+                }
+            }
+        });
+
+        Ok(Self {
+            addr,
+            shared,
+            thread: Some(thread),
+        })
+    }
+
+    /// Base URL of the server, e.g. `http://127.0.0.1:40123`.
+    pub fn url(&self) -> String { format!("http://{}", self.addr) }
+
+    /// Serves `response` for requests to `path` from now on.
+    pub fn route(&self, path: &str, response: MockResponse) -> &Self {
+        self.shared.routes.lock().unwrap().insert(path.to_string(), response);
+        self
+    }
+
+    /// Requests received so far, oldest first.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.shared.requests.lock().unwrap().clone()
+    }
+
+    /// A GitHub Enterprise provider whose API is this server, under
+    /// `/api/v3`.
+    pub fn github(&self) -> GitHub {
+        GitHub::with_api_url(&format!("{}/api/v3", self.url()))
+    }
+
+    /// URL of `owner/repo` as [`MockServer::github`] parses it.
+    pub fn repo_url(&self, owner: &str, repo: &str) -> String {
+        format!("{}/{}/{}", self.url(), owner, repo)
+    }
+
+    /// Routes the API calls of a rip of `owner/repo` at its default branch
+    /// `branch`: the repository, the commit `sha` and `archive` as its
+    /// zipball.
+    pub fn github_repo(
+        &self,
+        owner: &str,
+        repo: &str,
+        branch: &str,
+        sha: &str,
+        archive: Vec<u8>,
+    ) -> &Self {
+        let api = format!("/api/v3/repos/{}/{}", owner, repo);
+        self.route(
+            &api,
+            MockResponse::json(&serde_json::json!({
+                "default_branch": branch,
+            })),
+        )
+        .route(
+            &format!("{}/commits/{}", api, branch),
+            MockResponse::json(&serde_json::json!({ "sha": sha })),
+        )
+        .route(
+            &format!("{}/zipball/{}", api, branch),
+            MockResponse::zip(archive),
+        )
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.shared.stopped.store(true, Ordering::SeqCst);
+        // Wake the accept loop so it sees the flag.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn serve(shared: &Shared, mut stream: TcpStream) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers
+                .push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+    }
+
+    let response = shared
+        .routes
+        .lock()
+        .unwrap()
+        .get(&path)
+        .cloned()
+        .unwrap_or_else(|| MockResponse::status(404));
+    shared.requests.lock().unwrap().push(MockRequest {
+        method,
+        path,
+        headers,
+    });
+    response.write_to(&mut stream)
+}
+
+/// A zipball as GitHub serves it: every file of `files` under a single
+/// `root` directory, such as `owner-repo-abc1234`.
+pub fn zipball(root: &str, files: &[(&str, &str)]) -> Vec<u8> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    zip.add_directory(format!("{}/", root), options).unwrap();
+    for (path, contents) in files {
+        zip.start_file(format!("{}/{}", root, path), options).unwrap();
+        zip.write_all(contents.as_bytes()).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serves_routes_and_records_requests() {
+        let server = MockServer::start().unwrap();
+        server.route("/hello", MockResponse::status(200).body("hi"));
+        let client = reqwest::blocking::Client::new();
+
+        let res = client.get(format!("{}/hello", server.url())).send().unwrap();
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.text().unwrap(), "hi");
+        let res = client.get(format!("{}/nope", server.url())).send().unwrap();
+        assert_eq!(res.status(), 404);
+
+        let paths: Vec<_> =
+            server.requests().into_iter().map(|r| r.path).collect();
+        assert_eq!(paths, ["/hello", "/nope"]);
+    }
+}
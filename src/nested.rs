@@ -0,0 +1,138 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    archive::split_extension, extract_archive_with, manifest::collect_files,
+    ExtractOptions, StripRoot,
+};
+
+/// What [`extract_nested`] did.
+#[derive(Debug, Default)]
+pub struct NestedReport {
+    /// Archives expanded, relative to the root.
+    pub extracted: Vec<PathBuf>,
+    /// Archives left as they were, relative to the root, and why.
+    pub failed:    Vec<(PathBuf, String)>,
+}
+
+/// Expands the zip and tar archives found under `root` into a directory
+/// next to each, named after the archive without its extension, then the
+/// archives found in those, down to `depth` levels. The archives stay.
+///
+/// Every level is extracted with `options`, so their size limits, path
+/// checks and collision policy apply throughout. Nothing is filtered out by
+/// path and no leading directory is stripped, though. An archive that fails
+/// to extract, or whose directory is taken, is reported and left alone.
+pub fn extract_nested(
+    root: &Path,
+    depth: usize,
+    options: &ExtractOptions,
+) -> io::Result<NestedReport> {
+    let options = ExtractOptions {
+        subpath: None,
+        exclude: None,
+        skip_existing: false,
+        strip_root: StripRoot::Never,
+        ..options.clone()
+    };
+    let mut report = NestedReport::default();
+    let mut dirs = vec![PathBuf::new()];
+
+    for _ in 0..depth {
+        let mut archives = Vec::new();
+        for dir in &dirs {
+            let mut files = Vec::new();
+            collect_files(root, dir, &mut files)?;
+            archives.extend(files.into_iter().filter(|rel| {
+                fs::symlink_metadata(root.join(rel)).is_ok_and(|m| m.is_file())
+            }));
+        }
+        archives.sort();
+
+        let mut next = Vec::new();
+        for archive in archives {
+            let Some(target) = target_dir(&archive) else {
+                continue;
+            };
+            if fs::symlink_metadata(root.join(&target)).is_ok() {
+                report.failed.push((
+                    archive,
+                    format!("'{}' already exists", target.display()),
+                ));
+                continue;
+            }
+            match extract_archive_with(
+                &root.join(&archive),
+                &root.join(&target),
+                &options,
+            ) {
+                Ok(()) => {
+                    report.extracted.push(archive);
+                    next.push(target);
+                },
+                Err(e) => {
+                    let _ = fs::remove_dir_all(root.join(&target));
+                    report.failed.push((archive, e.to_string()));
+                },
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        dirs = next;
+    }
+    Ok(report)
+}
+
+/// Where the archive at `rel` is expanded to, `None` if it isn't one.
+fn target_dir(rel: &Path) -> Option<PathBuf> {
+    let name = rel.file_name()?.to_str()?;
+    let (stem, _) = split_extension(name).filter(|(s, _)| !s.is_empty())?;
+    Some(rel.with_file_name(stem))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    use super::*;
+
+    fn zip_of(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(io::Cursor::new(Vec::new()));
+        for (name, contents) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(contents).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_extract_nested_to_depth() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        let inner = zip_of(&[("deep.txt", b"deep")]);
+        let outer = zip_of(&[("a.txt", b"a"), ("inner.zip", &inner)]);
+        fs::create_dir(root.join("fixtures")).unwrap();
+        fs::write(root.join("fixtures/outer.zip"), outer).unwrap();
+        fs::write(root.join("broken.tar.gz"), b"not gzip").unwrap();
+
+        let report =
+            extract_nested(root, 1, &ExtractOptions::default()).unwrap();
+        assert_eq!(report.extracted, [PathBuf::from("fixtures/outer.zip")]);
+        assert_eq!(report.failed.len(), 1);
+        assert!(!root.join("broken").exists());
+        assert_eq!(fs::read(root.join("fixtures/outer/a.txt")).unwrap(), b"a");
+        assert!(!root.join("fixtures/outer/inner").exists());
+
+        fs::remove_dir_all(root.join("fixtures/outer")).unwrap();
+        extract_nested(root, 2, &ExtractOptions::default()).unwrap();
+        assert_eq!(
+            fs::read(root.join("fixtures/outer/inner/deep.txt")).unwrap(),
+            b"deep"
+        );
+    }
+}
@@ -0,0 +1,100 @@
+#[cfg(feature = "native")]
+use std::io::{self, Read};
+
+#[cfg(feature = "native")]
+use crate::cancel::cancelled;
+use crate::SkippedFile;
+
+/// Receives progress updates from downloads and extraction.
+///
+/// All methods have no-op defaults, so implementors only override what their
+/// UI cares about. Extraction may report from several threads at once.
+pub trait ProgressSink: Send + Sync {
+    /// A download began; `total_bytes` comes from `Content-Length` if the
+    /// server sent one.
+    fn download_started(&self, _total_bytes: Option<u64>) {}
+
+    /// `bytes` more bytes were received.
+    fn download_progress(&self, _bytes: u64) {}
+
+    fn download_finished(&self) {}
+
+    fn extract_started(&self, _total_entries: u64) {}
+
+    /// `entries` more archive entries were written.
+    fn extract_progress(&self, _entries: u64) {}
+
+    fn extract_finished(&self) {}
+
+    /// `file` was left out by [`crate::ExtractOptions::max_file_size`] or
+    /// [`crate::ExtractOptions::skip_binaries`].
+    fn file_skipped(&self, _file: &SkippedFile) {}
+
+    /// Whether the work reporting here should stop. Downloads and
+    /// extraction poll this between chunks and entries and fail once it is
+    /// true.
+    fn is_cancelled(&self) -> bool { false }
+}
+
+/// A [`ProgressSink`] that ignores everything.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoProgress;
+
+impl ProgressSink for NoProgress {}
+
+/// Wraps a reader and reports every successful read as download progress,
+/// failing once the sink [`ProgressSink::is_cancelled`].
+#[cfg(feature = "native")]
+pub(crate) struct ProgressReader<'a, R> {
+    inner:    R,
+    progress: &'a dyn ProgressSink,
+}
+
+#[cfg(feature = "native")]
+impl<'a, R> ProgressReader<'a, R> {
+    pub(crate) fn new(inner: R, progress: &'a dyn ProgressSink) -> Self {
+        Self { inner, progress }
+    }
+}
+
+#[cfg(feature = "native")]
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.progress.is_cancelled() {
+            return Err(cancelled());
+        }
+        let n = self.inner.read(buf)?;
+        if n > 0 {
+            self.progress.download_progress(n as u64);
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Counter(AtomicU64);
+
+    impl ProgressSink for Counter {
+        fn download_progress(&self, bytes: u64) {
+            self.0.fetch_add(bytes, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_progress_reader_counts_bytes() {
+        let counter = Counter::default();
+        let mut reader = ProgressReader::new(&b"hello world"[..], &counter);
+        let mut out = Vec::new();
+
+        io::copy(&mut reader, &mut out).unwrap();
+
+        assert_eq!(out, b"hello world");
+        assert_eq!(counter.0.load(Ordering::Relaxed), 11);
+    }
+}
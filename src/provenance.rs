@@ -0,0 +1,150 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::{json, Value};
+
+use crate::RipReport;
+
+/// Name of the metadata file inside `.git` of a ripped repository.
+pub const PROVENANCE_FILE: &str = "gitripper.json";
+/// Name of the metadata file at the top of a rip made without a repository.
+pub const PROVENANCE_FILE_NO_GIT: &str = ".gitripper.json";
+
+/// Where a rip came from, stored next to it so it can be updated later.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    /// URL the rip was made from.
+    pub url:       String,
+    pub provider:  String,
+    pub owner:     String,
+    pub repo:      String,
+    pub reference: String,
+    pub commit:    Option<String>,
+    /// Subdirectory of the repository that was ripped.
+    pub path:      Option<String>,
+    /// Seconds since the Unix epoch.
+    pub ripped_at: u64,
+}
+
+impl Provenance {
+    pub fn from_report(
+        url: &str,
+        report: &RipReport,
+        path: Option<&Path>,
+    ) -> Self {
+        Self {
+            url:       url.trim().to_string(),
+            provider:  report.provider.to_string(),
+            owner:     report.owner.clone(),
+            repo:      report.repo.clone(),
+            reference: report.reference.clone(),
+            commit:    report.commit.clone(),
+            path:      path.map(|p| p.to_string_lossy().into_owned()),
+            ripped_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        }
+    }
+
+    /// Metadata file of the rip at `dest`: inside `.git` when there is one,
+    /// so it never ends up in a commit, and in `dest` itself when it is a
+    /// bare repository.
+    pub fn file_in(dest: &Path) -> PathBuf {
+        let git_dir = dest.join(".git");
+        if git_dir.is_dir() {
+            git_dir.join(PROVENANCE_FILE)
+        } else if dest.join("HEAD").is_file() && dest.join("objects").is_dir() {
+            dest.join(PROVENANCE_FILE)
+        } else {
+            dest.join(PROVENANCE_FILE_NO_GIT)
+        }
+    }
+
+    /// Reads the metadata of the rip at `dest`, if it has any.
+    pub fn read(dest: &Path) -> io::Result<Option<Self>> {
+        let text = match fs::read_to_string(Self::file_in(dest)) {
+            Ok(t) => t,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let v: Value = serde_json::from_str(&text)?;
+
+        Self::from_json(&v).map(Some).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "malformed gitripper metadata",
+            )
+        })
+    }
+
+    pub fn write(&self, dest: &Path) -> io::Result<PathBuf> {
+        let path = Self::file_in(dest);
+        let text = serde_json::to_string_pretty(&self.to_json())?;
+        fs::write(&path, text + "\n")?;
+        Ok(path)
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "url": self.url,
+            "provider": self.provider,
+            "owner": self.owner,
+            "repo": self.repo,
+            "reference": self.reference,
+            "commit": self.commit,
+            "path": self.path,
+            "ripped_at": self.ripped_at,
+        })
+    }
+
+    fn from_json(v: &Value) -> Option<Self> {
+        let string = |key| Some(v.get(key)?.as_str()?.to_string());
+        let optional =
+            |key| v.get(key).and_then(Value::as_str).map(str::to_string);
+
+        Some(Self {
+            url:       string("url")?,
+            provider:  string("provider")?,
+            owner:     string("owner")?,
+            repo:      string("repo")?,
+            reference: string("reference")?,
+            commit:    optional("commit"),
+            path:      optional("path"),
+            ripped_at: v.get("ripped_at").and_then(Value::as_u64).unwrap_or(0),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trips_next_to_git_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dest = temp_dir.path();
+        assert_eq!(Provenance::read(dest).unwrap(), None);
+
+        let provenance = Provenance {
+            url:       "https://github.com/o/r".to_string(),
+            provider:  "GitHub".to_string(),
+            owner:     "o".to_string(),
+            repo:      "r".to_string(),
+            reference: "main".to_string(),
+            commit:    Some("abc".to_string()),
+            path:      None,
+            ripped_at: 1,
+        };
+        let path = provenance.write(dest).unwrap();
+        assert_eq!(path, dest.join(PROVENANCE_FILE_NO_GIT));
+
+        fs::create_dir(dest.join(".git")).unwrap();
+        let path = provenance.write(dest).unwrap();
+        assert_eq!(path, dest.join(".git").join(PROVENANCE_FILE));
+        assert_eq!(Provenance::read(dest).unwrap(), Some(provenance));
+    }
+}
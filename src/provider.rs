@@ -0,0 +1,1293 @@
+#[cfg(feature = "native")]
+use std::{
+    cell::RefCell,
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use std::{fmt, path::PathBuf, sync::Arc};
+
+#[cfg(feature = "native")]
+use reqwest::{
+    blocking::{Client, RequestBuilder, Response},
+    header::{
+        HeaderMap, CONTENT_LENGTH, CONTENT_RANGE, ETAG, IF_NONE_MATCH, RANGE,
+    },
+    StatusCode,
+};
+#[cfg(feature = "native")]
+use serde_json::Value;
+#[cfg(feature = "native")]
+use url::Url;
+
+use crate::{
+    azure::AzureDevOps, bitbucket::Bitbucket, direct::DirectArchive,
+    git_remote::GitRemote, github::GitHub, gitlab::GitLab,
+    sourcehut::SourceHut,
+};
+#[cfg(feature = "native")]
+use crate::{
+    progress::ProgressReader, Cache, GitripperError, ProgressSink,
+    RepoMetadata, Result,
+};
+
+#[cfg(feature = "native")]
+const ARCHIVE_PREFIX: &str = "archive-";
+#[cfg(feature = "native")]
+const ITEMS_PER_PAGE: usize = 100;
+/// Archives smaller than this are downloaded in one request even when more
+/// connections are allowed.
+#[cfg(feature = "native")]
+const MIN_RANGED_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Release name [`Provider::release_tag`] takes to mean the newest release.
+pub const LATEST_RELEASE: &str = "latest";
+
+#[cfg(feature = "native")]
+thread_local! {
+    /// Where [`fetch_json`] and [`download_archive_to`] keep responses for
+    /// conditional requests; see [`with_conditional_cache`].
+    static CONDITIONAL_CACHE: RefCell<Option<Cache>> =
+        const { RefCell::new(None) };
+}
+
+/// Runs `f` with conditional requests backed by `cache`: API and archive
+/// responses that come with an ETag are kept there, and the next request for
+/// the same URL sends it as `If-None-Match`, so an unchanged resource costs a
+/// `304` instead of a download.
+#[cfg(feature = "native")]
+pub(crate) fn with_conditional_cache<T>(
+    cache: Option<&Cache>,
+    f: impl FnOnce() -> T,
+) -> T {
+    let previous = CONDITIONAL_CACHE.replace(cache.cloned());
+    let result = f();
+    CONDITIONAL_CACHE.set(previous);
+    result
+}
+
+#[cfg(feature = "native")]
+fn conditional_cache() -> Option<Cache> {
+    CONDITIONAL_CACHE.with_borrow(Clone::clone)
+}
+
+#[cfg(feature = "native")]
+fn etag_of(res: &Response) -> Option<String> {
+    Some(res.headers().get(ETAG)?.to_str().ok()?.to_string())
+}
+
+/// A repository plus the optional ref and subdirectory a URL points at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoLocation {
+    /// User, organization or (for GitLab) `group/subgroup` path.
+    pub owner:     String,
+    pub repo:      String,
+    pub reference: Option<String>,
+    pub path:      Option<String>,
+    /// Pull request the URL points at, ripped at its head.
+    pub pull:      Option<u64>,
+    /// URL to clone from, for providers that can't build one from the owner
+    /// and repository.
+    pub remote:    Option<String>,
+}
+
+impl RepoLocation {
+    pub fn new(owner: impl Into<String>, repo: impl Into<String>) -> Self {
+        Self {
+            owner:     owner.into(),
+            repo:      repo.into(),
+            reference: None,
+            path:      None,
+            pull:      None,
+            remote:    None,
+        }
+    }
+}
+
+impl fmt::Display for RepoLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.owner, self.repo)
+    }
+}
+
+/// Likely reason a repository came back as not found, from
+/// [`Provider::diagnose_not_found`].
+#[cfg(feature = "native")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotFoundCause {
+    /// There is no such user or organization either, so the URL is likely
+    /// mistyped.
+    NoSuchOwner(String),
+    /// The owner exists and no token was given, so the repository may be
+    /// private.
+    MaybePrivate,
+    /// The token was rejected, e.g. because it expired or was revoked.
+    BadToken,
+    /// The token can't see private repositories; these are the scopes it
+    /// has.
+    MissingScopes(Vec<String>),
+    /// The token works, but its account can't see the repository.
+    NoAccess,
+}
+
+#[cfg(feature = "native")]
+impl fmt::Display for NotFoundCause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoSuchOwner(owner) => write!(
+                f,
+                "no user or organization '{}' exists; check the URL for typos",
+                owner
+            ),
+            Self::MaybePrivate => f.write_str(
+                "the repository may be private; pass --token or run \
+                 `gitripper auth login`",
+            ),
+            Self::BadToken => f.write_str(
+                "the token was rejected, it may have expired or been revoked; \
+                 run `gitripper auth login` for a new one",
+            ),
+            Self::MissingScopes(scopes) => write!(
+                f,
+                "the token lacks the `repo` scope private repositories need \
+                 (it has: {})",
+                if scopes.is_empty() {
+                    "none".to_string()
+                } else {
+                    scopes.join(", ")
+                }
+            ),
+            Self::NoAccess => f.write_str(
+                "the token's account can't see the repository; check the URL \
+                 and that it was granted access",
+            ),
+        }
+    }
+}
+
+/// Which refs [`Provider::list_refs`] lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefType {
+    Branch,
+    Tag,
+}
+
+/// A branch or tag of a remote repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteRef {
+    pub name:   String,
+    /// SHA of the commit the ref points at.
+    pub commit: String,
+    /// Commit date as reported by the provider (ISO 8601), if known.
+    pub date:   Option<String>,
+}
+
+/// A file attached to a release.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub size: Option<u64>,
+    /// Where to download the asset, with the provider's auth header.
+    pub url:  String,
+}
+
+/// A file in the tree of a commit, as listed by [`Provider::list_tree`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeEntry {
+    /// Relative to the repository root, with `/` separators.
+    pub path: String,
+    /// Blob SHA to pass to [`Provider::fetch_blob`].
+    pub sha:  String,
+    /// Git file mode, e.g. `0o100755` or `0o120000` for a symlink.
+    pub mode: u32,
+    pub size: Option<u64>,
+}
+
+/// A code hosting service gitripper can download snapshots from.
+pub trait Provider: fmt::Debug + Send + Sync {
+    /// Human-readable name, e.g. `"GitHub"`.
+    fn name(&self) -> &'static str;
+
+    /// Parses `url` if it points at a repository on this provider.
+    fn parse_url(&self, url: &str) -> Option<RepoLocation>;
+
+    /// Prefix of `<prefix>:owner/repo` shorthands for this provider, such as
+    /// `gitlab`. Bare `owner/repo` goes to the provider whose prefix is
+    /// `github`.
+    fn shorthand(&self) -> Option<&'static str> { None }
+
+    #[cfg(feature = "native")]
+    fn default_branch(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        token: Option<&str>,
+    ) -> Result<String>;
+
+    /// Full commit SHA `reference` currently points at, if the provider can
+    /// tell. Used as the cache key.
+    #[cfg(feature = "native")]
+    fn resolve_commit(
+        &self,
+        _client: &Client,
+        _location: &RepoLocation,
+        _reference: &str,
+        _token: Option<&str>,
+    ) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Tag the release named `release` was made from, or that of the newest
+    /// release for [`LATEST_RELEASE`].
+    #[cfg(feature = "native")]
+    fn release_tag(
+        &self,
+        _client: &Client,
+        _location: &RepoLocation,
+        _release: &str,
+        _token: Option<&str>,
+    ) -> Result<String> {
+        Err(GitripperError::Unsupported(format!(
+            "{} has no releases to rip",
+            self.name()
+        )))
+    }
+
+    /// Tag and attached files of the release named `release`, or of the
+    /// newest release for [`LATEST_RELEASE`].
+    #[cfg(feature = "native")]
+    fn release_assets(
+        &self,
+        _client: &Client,
+        _location: &RepoLocation,
+        _release: &str,
+        _token: Option<&str>,
+    ) -> Result<(String, Vec<ReleaseAsset>)> {
+        Err(GitripperError::Unsupported(format!(
+            "release assets are not supported for {}",
+            self.name()
+        )))
+    }
+
+    /// Every branch or tag of `location`. Dates may be left out if listing
+    /// them takes a request per ref; see [`Provider::commit_date`].
+    #[cfg(feature = "native")]
+    fn list_refs(
+        &self,
+        _client: &Client,
+        _location: &RepoLocation,
+        _ref_type: RefType,
+        _token: Option<&str>,
+    ) -> Result<Vec<RemoteRef>> {
+        Err(GitripperError::Unsupported(format!(
+            "listing refs is not supported for {}",
+            self.name()
+        )))
+    }
+
+    /// Commit date of `sha` (ISO 8601), if the provider can tell.
+    #[cfg(feature = "native")]
+    fn commit_date(
+        &self,
+        _client: &Client,
+        _location: &RepoLocation,
+        _sha: &str,
+        _token: Option<&str>,
+    ) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Where `location` lives now if it was renamed or transferred, keeping
+    /// its reference and path. `None` if it didn't move or the provider
+    /// can't tell.
+    #[cfg(feature = "native")]
+    fn moved_to(
+        &self,
+        _client: &Client,
+        _location: &RepoLocation,
+        _token: Option<&str>,
+    ) -> Result<Option<RepoLocation>> {
+        Ok(None)
+    }
+
+    /// Description, homepage and topics of `location`. Empty if the provider
+    /// has none.
+    #[cfg(feature = "native")]
+    fn metadata(
+        &self,
+        _client: &Client,
+        _location: &RepoLocation,
+        _token: Option<&str>,
+    ) -> Result<RepoMetadata> {
+        Ok(RepoMetadata::default())
+    }
+
+    /// Size of `location` in bytes as the provider reports it, usually that
+    /// of its git data rather than of an archive. `None` if the provider
+    /// can't tell.
+    #[cfg(feature = "native")]
+    fn repo_size(
+        &self,
+        _client: &Client,
+        _location: &RepoLocation,
+        _token: Option<&str>,
+    ) -> Result<Option<u64>> {
+        Ok(None)
+    }
+
+    /// The repository `location` was forked from, keeping its reference and
+    /// path. `None` if it isn't a fork or the provider can't tell.
+    #[cfg(feature = "native")]
+    fn fork_parent(
+        &self,
+        _client: &Client,
+        _location: &RepoLocation,
+        _token: Option<&str>,
+    ) -> Result<Option<RepoLocation>> {
+        Ok(None)
+    }
+
+    /// Why `location` may have come back as not found with `token`. `None`
+    /// if the provider can't tell.
+    #[cfg(feature = "native")]
+    fn diagnose_not_found(
+        &self,
+        _client: &Client,
+        _location: &RepoLocation,
+        _token: Option<&str>,
+    ) -> Result<Option<NotFoundCause>> {
+        Ok(None)
+    }
+
+    /// Repository and ref at the head of pull request `number`, which may
+    /// live in a fork.
+    #[cfg(feature = "native")]
+    fn pull_request_head(
+        &self,
+        _client: &Client,
+        _location: &RepoLocation,
+        _number: u64,
+        _token: Option<&str>,
+    ) -> Result<RepoLocation> {
+        Err(GitripperError::Unsupported(format!(
+            "pull requests are not supported for {}",
+            self.name()
+        )))
+    }
+
+    /// Every file in the tree of `reference`, without downloading them.
+    #[cfg(feature = "native")]
+    fn list_tree(
+        &self,
+        _client: &Client,
+        _location: &RepoLocation,
+        _reference: &str,
+        _token: Option<&str>,
+    ) -> Result<Vec<TreeEntry>> {
+        Err(GitripperError::Unsupported(format!(
+            "sparse rips are not supported for {}",
+            self.name()
+        )))
+    }
+
+    /// Contents of the blob `sha` of a [`TreeEntry`].
+    #[cfg(feature = "native")]
+    fn fetch_blob(
+        &self,
+        _client: &Client,
+        _location: &RepoLocation,
+        _sha: &str,
+        _token: Option<&str>,
+    ) -> Result<Vec<u8>> {
+        Err(GitripperError::Unsupported(format!(
+            "sparse rips are not supported for {}",
+            self.name()
+        )))
+    }
+
+    /// Contents of the single file at `path` in `reference`.
+    #[cfg(feature = "native")]
+    fn fetch_file(
+        &self,
+        _client: &Client,
+        _location: &RepoLocation,
+        _reference: &str,
+        _path: &str,
+        _token: Option<&str>,
+    ) -> Result<Vec<u8>> {
+        Err(GitripperError::Unsupported(format!(
+            "fetching single files is not supported for {}",
+            self.name()
+        )))
+    }
+
+    /// Archive of `location` already on disk, extracted instead of
+    /// downloading [`Provider::archive_url`].
+    fn local_archive(&self, _location: &RepoLocation) -> Option<PathBuf> {
+        None
+    }
+
+    /// Whether the provider serves archives at all. Snapshots of those that
+    /// don't are shallow clones of [`Provider::clone_url`].
+    fn serves_archives(&self) -> bool { true }
+
+    /// URL of the zip archive of `location` at `reference`.
+    fn archive_url(&self, location: &RepoLocation, reference: &str) -> String;
+
+    /// URL of the same archive outside the API, tried without a token when
+    /// the API can't be reached at all.
+    fn fallback_archive_url(
+        &self,
+        _location: &RepoLocation,
+        _reference: &str,
+    ) -> Option<String> {
+        None
+    }
+
+    /// HTTPS URL `git clone` would use for `location`.
+    fn clone_url(&self, location: &RepoLocation) -> String;
+
+    /// Username and password git authenticates with when given `token`.
+    fn git_credentials(&self, token: &str) -> (String, String) {
+        ("x-access-token".to_string(), token.to_string())
+    }
+
+    /// Header name and value used to send `token`.
+    fn auth_header(&self, token: &str) -> (&'static str, String) {
+        ("Authorization", format!("token {}", token))
+    }
+
+    /// `Accept` header for archive downloads, if the API wants one.
+    fn accept_header(&self) -> Option<&'static str> { None }
+
+    /// Environment variable the CLI reads a token from.
+    fn token_env(&self) -> Option<&'static str> { None }
+}
+
+/// The providers a [`crate::Ripper`] tries, in order, when none are
+/// configured.
+pub fn default_providers() -> Vec<Arc<dyn Provider>> {
+    vec![
+        Arc::new(DirectArchive),
+        Arc::new(GitHub::default()),
+        Arc::new(GitLab::default()),
+        Arc::new(Bitbucket),
+        Arc::new(AzureDevOps),
+        Arc::new(SourceHut::default()),
+        Arc::new(GitRemote),
+    ]
+}
+
+/// Finds the first of the default providers that recognizes `url`.
+pub fn detect_provider(url: &str) -> Option<(Arc<dyn Provider>, RepoLocation)> {
+    find_provider(&default_providers(), url)
+}
+
+pub(crate) fn find_provider(
+    providers: &[Arc<dyn Provider>],
+    url: &str,
+) -> Option<(Arc<dyn Provider>, RepoLocation)> {
+    providers
+        .iter()
+        .find_map(|p| p.parse_url(url).map(|loc| (p.clone(), loc)))
+        .or_else(|| expand_shorthand(providers, url))
+}
+
+/// Finds the provider of an `owner/repo` or `<prefix>:owner/repo`
+/// shorthand by its [`Provider::shorthand`], and parses its clone URL for
+/// the repository.
+fn expand_shorthand(
+    providers: &[Arc<dyn Provider>],
+    url: &str,
+) -> Option<(Arc<dyn Provider>, RepoLocation)> {
+    let url = url.trim();
+    if url.contains(char::is_whitespace) {
+        return None;
+    }
+    // Only prefixed shorthands may name nested groups.
+    let (prefix, path) = match url.split_once(':') {
+        Some((prefix, path)) => (prefix, path),
+        None if url.matches('/').count() == 1 => ("github", url),
+        None => return None,
+    };
+    let (owner, repo) = path.trim_end_matches('/').rsplit_once('/')?;
+    if owner.is_empty() || repo.is_empty() {
+        return None;
+    }
+
+    let provider = providers.iter().find(|p| {
+        p.shorthand().is_some_and(|s| s.eq_ignore_ascii_case(prefix))
+    })?;
+    let location = provider
+        .parse_url(&provider.clone_url(&RepoLocation::new(owner, repo)))?;
+    (location.owner == owner && location.repo == repo)
+        .then(|| (provider.clone(), location))
+}
+
+/// Whether `reference` already is a full commit SHA, so resolving it would
+/// be a wasted request.
+pub(crate) fn is_commit_sha(reference: &str) -> bool {
+    reference.len() == 40 && reference.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Strips a `https://`, `git@` or `ssh://` prefix for `host`, ignoring case.
+pub(crate) fn strip_host<'a>(url: &'a str, host: &str) -> Option<&'a str> {
+    let prefixes = [
+        format!("https://{}/", host),
+        format!("http://{}/", host),
+        format!("git@{}:", host),
+        format!("ssh://git@{}/", host),
+    ];
+
+    prefixes.iter().find_map(|p| {
+        url.get(..p.len())
+            .filter(|head| head.eq_ignore_ascii_case(p))
+            .map(|_| &url[p.len()..])
+    })
+}
+
+/// Parses the `owner/repo[/<marker><ref>/<path>]` part of a URL whose host
+/// has already been stripped.
+pub(crate) fn parse_repo_path(
+    rest: &str,
+    tree_marker: &str,
+) -> Option<RepoLocation> {
+    let mut parts = rest.trim_end_matches('/').splitn(3, '/');
+
+    let owner = parts.next().filter(|s| !s.is_empty())?;
+    let repo = parts.next().filter(|s| !s.is_empty())?;
+    let repo = repo.strip_suffix(".git").unwrap_or(repo);
+    let mut location = RepoLocation::new(owner, repo);
+
+    if let Some(tree) = parts.next().and_then(|e| e.strip_prefix(tree_marker)) {
+        let (reference, path) = match tree.split_once('/') {
+            Some((r, p)) => (r, Some(p)),
+            None => (tree, None),
+        };
+
+        if !reference.is_empty() {
+            location.reference = Some(reference.to_string());
+            location.path = path.filter(|p| !p.is_empty()).map(str::to_string);
+        }
+    }
+
+    Some(location)
+}
+
+/// GETs an API endpoint of `provider` and parses the JSON body.
+#[cfg(feature = "native")]
+pub(crate) fn fetch_json(
+    client: &Client,
+    provider: &dyn Provider,
+    url: &str,
+    token: Option<&str>,
+) -> Result<Value> {
+    let mut req = client.get(url);
+
+    if let Some(t) = token {
+        let (name, value) = provider.auth_header(t);
+        req = req.header(name, value);
+    }
+    let cache = conditional_cache();
+    let stored = cache.as_ref().and_then(|c| c.stored_response(url));
+    if let Some((etag, _)) = &stored {
+        req = req.header(IF_NONE_MATCH, etag);
+    }
+
+    let res = req.send()?;
+
+    if let Some(e) = rate_limit_error(res.status(), res.headers()) {
+        return Err(e);
+    }
+
+    match (res.status().as_u16(), stored) {
+        (304, Some((_, body))) => Ok(serde_json::from_slice(&fs::read(body)?)
+            .map_err(io::Error::from)?),
+        (200, _) => {
+            let etag = etag_of(&res);
+            let body = res.bytes()?;
+            if let (Some(cache), Some(etag)) = (&cache, etag) {
+                // Only the next request gets cheaper; not worth failing for.
+                let _ = cache.response_tempfile().and_then(|mut file| {
+                    file.write_all(&body)?;
+                    cache.store_response(url, &etag, file)
+                });
+            }
+            Ok(serde_json::from_slice(&body).map_err(io::Error::from)?)
+        },
+        (s, _) => {
+            let txt = res.text().unwrap_or_default();
+            Err(GitripperError::HttpStatus {
+                status:  s,
+                message: format!("Failed to get repo info: {} {}", s, txt),
+            })
+        },
+    }
+}
+
+/// GETs `url` from `provider` and returns the body as is. `what` names the
+/// download in errors.
+#[cfg(feature = "native")]
+pub(crate) fn fetch_raw(
+    client: &Client,
+    provider: &dyn Provider,
+    url: &str,
+    accept: Option<&str>,
+    token: Option<&str>,
+    what: &str,
+) -> Result<Vec<u8>> {
+    let mut req = client.get(url);
+    if let Some(accept) = accept {
+        req = req.header("Accept", accept);
+    }
+    if let Some(t) = token {
+        let (name, value) = provider.auth_header(t);
+        req = req.header(name, value);
+    }
+
+    let res = req.send()?;
+    if let Some(e) = rate_limit_error(res.status(), res.headers()) {
+        return Err(e);
+    }
+    let status = res.status();
+    if !status.is_success() {
+        return Err(GitripperError::HttpStatus {
+            status:  status.as_u16(),
+            message: format!("Failed to download {}: {}", what, status),
+        });
+    }
+    Ok(res.bytes()?.to_vec())
+}
+
+/// `base` with the `/`-separated `path` appended segment by segment, each
+/// percent-encoded.
+#[cfg(feature = "native")]
+pub(crate) fn join_url_path(base: &str, path: &str) -> Result<Url> {
+    let mut url = Url::parse(base)
+        .map_err(|_| GitripperError::InvalidUrl(base.to_string()))?;
+    url.path_segments_mut()
+        .map_err(|_| GitripperError::InvalidUrl(base.to_string()))?
+        .pop_if_empty()
+        .extend(path.split('/').filter(|s| !s.is_empty()));
+    Ok(url)
+}
+
+/// GETs every page of a paginated list endpoint that takes `per_page` and
+/// `page` parameters, as GitHub and GitLab do, and concatenates the items.
+#[cfg(feature = "native")]
+pub(crate) fn fetch_pages(
+    client: &Client,
+    provider: &dyn Provider,
+    url: &str,
+    token: Option<&str>,
+) -> Result<Vec<Value>> {
+    let separator = if url.contains('?') { '&' } else { '?' };
+    let mut items = Vec::new();
+
+    for page in 1.. {
+        let page_url = format!(
+            "{}{}per_page={}&page={}",
+            url, separator, ITEMS_PER_PAGE, page
+        );
+        let v = fetch_json(client, provider, &page_url, token)?;
+        let Value::Array(page_items) = v else {
+            break;
+        };
+
+        let last = page_items.len() < ITEMS_PER_PAGE;
+        items.extend(page_items);
+        if last {
+            break;
+        }
+    }
+
+    Ok(items)
+}
+
+/// The string at `pointer` in `v`, unless it is missing, null or empty.
+#[cfg(feature = "native")]
+pub(crate) fn non_empty_str(v: &Value, pointer: &str) -> Option<String> {
+    v.pointer(pointer)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+/// Every string in the array at `pointer` in `v`.
+#[cfg(feature = "native")]
+pub(crate) fn str_array(v: &Value, pointer: &str) -> Vec<String> {
+    v.pointer(pointer)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(str::to_string)
+        .collect()
+}
+
+/// `tag_name` of a release object, which GitHub and GitLab both use.
+#[cfg(feature = "native")]
+pub(crate) fn release_tag_name(release: &Value) -> Result<String> {
+    release
+        .get("tag_name")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| GitripperError::HttpStatus {
+            status:  200,
+            message: "Release has no tag_name.".to_string(),
+        })
+}
+
+/// Replaces the message of a 404 [`GitripperError::HttpStatus`].
+#[cfg(feature = "native")]
+pub(crate) fn not_found_as(
+    err: GitripperError,
+    message: impl FnOnce() -> String,
+) -> GitripperError {
+    match err {
+        GitripperError::HttpStatus { status: 404, .. } => {
+            GitripperError::HttpStatus {
+                status:  404,
+                message: message(),
+            }
+        },
+        e => e,
+    }
+}
+
+/// Downloads the archive of `location` at `reference` into `dest_dir`,
+/// returning the path of the saved file.
+#[cfg(feature = "native")]
+pub fn download_archive(
+    client: &Client,
+    provider: &dyn Provider,
+    location: &RepoLocation,
+    reference: &str,
+    token: Option<&str>,
+    dest_dir: &Path,
+    progress: &dyn ProgressSink,
+) -> Result<PathBuf> {
+    let path = archive_path(dest_dir);
+    let mut outfile = File::create(&path)?;
+    download_archive_to(
+        client,
+        provider,
+        location,
+        reference,
+        token,
+        &mut outfile,
+        progress,
+    )?;
+    Ok(path)
+}
+
+/// Like [`download_archive`], but splits the download across `connections`
+/// parallel range requests when the server reports the size and accepts
+/// ranges, which speeds up large archives on high-latency links. Falls back
+/// to a single request otherwise, or for archives too small to benefit.
+#[cfg(feature = "native")]
+#[allow(clippy::too_many_arguments)]
+pub fn download_archive_ranged(
+    client: &Client,
+    provider: &dyn Provider,
+    location: &RepoLocation,
+    reference: &str,
+    token: Option<&str>,
+    dest_dir: &Path,
+    progress: &dyn ProgressSink,
+    connections: usize,
+) -> Result<PathBuf> {
+    let single = || {
+        download_archive(
+            client, provider, location, reference, token, dest_dir, progress,
+        )
+    };
+    let url = provider.archive_url(location, reference);
+    // A conditional request may not download anything at all.
+    let conditional =
+        conditional_cache().is_some_and(|c| c.stored_response(&url).is_some());
+    if connections < 2 || conditional {
+        return single();
+    }
+
+    let probe = archive_request(client, provider, &url, token)
+        .header(RANGE, "bytes=0-0")
+        .send()?;
+    let status = probe.status();
+    if let Some(e) = rate_limit_error(status, probe.headers()) {
+        return Err(e);
+    }
+    if !status.is_success() {
+        return Err(archive_status_error(status, location, reference));
+    }
+    let total = probe
+        .headers()
+        .get(CONTENT_RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(content_range_total);
+    let total = match total {
+        Some(t) if status == StatusCode::PARTIAL_CONTENT => t,
+        _ => return single(),
+    };
+    if total < MIN_RANGED_BYTES {
+        return single();
+    }
+
+    // Hosts redirect archive downloads to a CDN with its own credentials in
+    // the URL; only send ours back to where they came from.
+    let final_url = probe.url().clone();
+    let same_host = reqwest::Url::parse(&url)
+        .is_ok_and(|u| u.host_str() == final_url.host_str());
+    let part_token = token.filter(|_| same_host);
+    drop(probe);
+
+    let path = archive_path(dest_dir);
+    File::create(&path)?.set_len(total)?;
+    progress.download_started(Some(total));
+
+    let results: Vec<Result<()>> = thread::scope(|scope| {
+        let handles: Vec<_> = split_ranges(total, connections)
+            .into_iter()
+            .map(|(start, end)| {
+                let (path, url) = (&path, final_url.as_str());
+                scope.spawn(move || {
+                    let resp =
+                        archive_request(client, provider, url, part_token)
+                            .header(RANGE, format!("bytes={}-{}", start, end))
+                            .send()?;
+                    let status = resp.status();
+                    if status != StatusCode::PARTIAL_CONTENT {
+                        return Err(archive_status_error(
+                            status, location, reference,
+                        ));
+                    }
+
+                    let mut file = File::options().write(true).open(path)?;
+                    file.seek(SeekFrom::Start(start))?;
+                    let mut part = ProgressReader::new(resp, progress)
+                        .take(end - start + 1);
+                    let written = io::copy(&mut part, &mut file)?;
+                    if written != end - start + 1 {
+                        return Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "archive download ended early",
+                        )
+                        .into());
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().expect("download thread panicked"))
+            .collect()
+    });
+    progress.download_finished();
+
+    if let Some(Err(e)) = results.into_iter().find(Result::is_err) {
+        let _ = fs::remove_file(&path);
+        return Err(e);
+    }
+    Ok(path)
+}
+
+/// Size of the archive of `location` at `reference` as the server reports
+/// it to a one-byte range request, without downloading it. `None` if it
+/// doesn't say, as for archives generated on the fly.
+#[cfg(feature = "native")]
+pub fn archive_size(
+    client: &Client,
+    provider: &dyn Provider,
+    location: &RepoLocation,
+    reference: &str,
+    token: Option<&str>,
+) -> Result<Option<u64>> {
+    let url = provider.archive_url(location, reference);
+    let probe = archive_request(client, provider, &url, token)
+        .header(RANGE, "bytes=0-0")
+        .send()?;
+    let status = probe.status();
+    if let Some(e) = rate_limit_error(status, probe.headers()) {
+        return Err(e);
+    }
+    if !status.is_success() {
+        return Err(archive_status_error(status, location, reference));
+    }
+
+    let header = |name| probe.headers().get(name)?.to_str().ok();
+    // A server that ignores the range sends the whole archive.
+    Ok(match status {
+        StatusCode::PARTIAL_CONTENT => {
+            header(CONTENT_RANGE).and_then(content_range_total)
+        },
+        _ => header(CONTENT_LENGTH).and_then(|v| v.parse().ok()),
+    })
+}
+
+/// GET of an archive URL with the headers `provider` wants.
+#[cfg(feature = "native")]
+fn archive_request(
+    client: &Client,
+    provider: &dyn Provider,
+    url: &str,
+    token: Option<&str>,
+) -> RequestBuilder {
+    let mut req = client.get(url);
+    if let Some(accept) = provider.accept_header() {
+        req = req.header("Accept", accept);
+    }
+    if let Some(t) = token {
+        let (name, value) = provider.auth_header(t);
+        req = req.header(name, value);
+    }
+    req
+}
+
+/// Total size in a `Content-Range: bytes 0-0/1234` header.
+#[cfg(feature = "native")]
+fn content_range_total(value: &str) -> Option<u64> {
+    value.strip_prefix("bytes ")?.split_once('/')?.1.parse().ok()
+}
+
+/// Splits `total` bytes into up to `parts` inclusive ranges.
+#[cfg(feature = "native")]
+fn split_ranges(total: u64, parts: usize) -> Vec<(u64, u64)> {
+    let parts = (parts as u64).clamp(1, total.max(1));
+    let size = total.div_ceil(parts);
+    (0..parts)
+        .map(|i| (i * size, ((i + 1) * size).min(total) - 1))
+        .filter(|(start, end)| start <= end)
+        .collect()
+}
+
+/// Downloads the archive of `location` at `reference` into `out`.
+#[cfg(feature = "native")]
+pub fn download_archive_to(
+    client: &Client,
+    provider: &dyn Provider,
+    location: &RepoLocation,
+    reference: &str,
+    token: Option<&str>,
+    out: &mut dyn Write,
+    progress: &dyn ProgressSink,
+) -> Result<()> {
+    let url = provider.archive_url(location, reference);
+    let req = archive_request(client, provider, &url, token);
+    download_url_to(req, &url, location, reference, out, progress)
+}
+
+/// Downloads the archive of `location` at `reference` from the
+/// [`Provider::fallback_archive_url`] `url` into `dest_dir`.
+#[cfg(feature = "native")]
+pub(crate) fn download_fallback_archive(
+    client: &Client,
+    url: &str,
+    location: &RepoLocation,
+    reference: &str,
+    dest_dir: &Path,
+    progress: &dyn ProgressSink,
+) -> Result<PathBuf> {
+    let path = archive_path(dest_dir);
+    let mut outfile = File::create(&path)?;
+    let req = client.get(url);
+    download_url_to(req, url, location, reference, &mut outfile, progress)?;
+    Ok(path)
+}
+
+/// Sends `req` for the archive at `url` and copies the body to `out`,
+/// revalidating a copy kept in the conditional cache.
+#[cfg(feature = "native")]
+fn download_url_to(
+    mut req: RequestBuilder,
+    url: &str,
+    location: &RepoLocation,
+    reference: &str,
+    out: &mut dyn Write,
+    progress: &dyn ProgressSink,
+) -> Result<()> {
+    let cache = conditional_cache();
+    let stored = cache.as_ref().and_then(|c| c.stored_response(url));
+    if let Some((etag, _)) = &stored {
+        req = req.header(IF_NONE_MATCH, etag);
+    }
+    let resp = req.send()?;
+    let status = resp.status();
+
+    if let Some(e) = rate_limit_error(status, resp.headers()) {
+        return Err(e);
+    }
+    if status == StatusCode::NOT_MODIFIED
+        && let Some((_, body)) = stored
+    {
+        io::copy(&mut File::open(body)?, out)?;
+        return Ok(());
+    }
+    if !status.is_success() {
+        return Err(archive_status_error(status, location, reference));
+    }
+
+    progress.download_started(resp.content_length());
+    let etag = etag_of(&resp);
+    let mut body = ProgressReader::new(resp, progress);
+    match (&cache, etag) {
+        (Some(cache), Some(etag)) => {
+            // Keep a copy for the next conditional request on the way.
+            let mut kept = cache.response_tempfile()?;
+            io::copy(&mut body, &mut kept)?;
+            kept.rewind()?;
+            io::copy(&mut kept, out)?;
+            cache.store_response(url, &etag, kept)?;
+        },
+        _ => {
+            io::copy(&mut body, out)?;
+        },
+    }
+    progress.download_finished();
+
+    Ok(())
+}
+
+/// Downloads `asset` to `dest`.
+#[cfg(feature = "native")]
+pub fn download_asset(
+    client: &Client,
+    provider: &dyn Provider,
+    asset: &ReleaseAsset,
+    token: Option<&str>,
+    dest: &Path,
+    progress: &dyn ProgressSink,
+) -> Result<()> {
+    // GitHub serves the metadata instead of the file without this.
+    let mut req =
+        client.get(&asset.url).header("Accept", "application/octet-stream");
+    if let Some(t) = token {
+        let (name, value) = provider.auth_header(t);
+        req = req.header(name, value);
+    }
+
+    let resp = req.send()?;
+    let status = resp.status();
+    if let Some(e) = rate_limit_error(status, resp.headers()) {
+        return Err(e);
+    }
+    if !status.is_success() {
+        return Err(GitripperError::HttpStatus {
+            status:  status.as_u16(),
+            message: format!(
+                "Failed to download asset '{}': {}",
+                asset.name, status
+            ),
+        });
+    }
+
+    let mut outfile = File::create(dest)?;
+    progress.download_started(resp.content_length());
+    io::copy(&mut ProgressReader::new(resp, progress), &mut outfile)?;
+    progress.download_finished();
+    Ok(())
+}
+
+/// Recognizes rate-limit rejections: a 403 or 429 with
+/// `X-RateLimit-Remaining: 0`, or a 429 with `Retry-After`.
+#[cfg(feature = "native")]
+pub(crate) fn rate_limit_error(
+    status: StatusCode,
+    headers: &HeaderMap,
+) -> Option<GitripperError> {
+    let header = |name: &str| -> Option<u64> {
+        headers.get(name)?.to_str().ok()?.trim().parse().ok()
+    };
+
+    if !matches!(status.as_u16(), 403 | 429) {
+        return None;
+    }
+
+    let exhausted = header("x-ratelimit-remaining") == Some(0);
+    let retry_after = header("retry-after").map(Duration::from_secs);
+
+    let throttled = status.as_u16() == 429 && retry_after.is_some();
+
+    if !exhausted && !throttled {
+        return None;
+    }
+
+    let reset_in = retry_after.or_else(|| {
+        let reset =
+            UNIX_EPOCH + Duration::from_secs(header("x-ratelimit-reset")?);
+        Some(reset.duration_since(SystemTime::now()).unwrap_or(Duration::ZERO))
+    });
+
+    Some(GitripperError::RateLimited { reset_in })
+}
+
+/// A unique file name under `dest_dir` for a downloaded archive.
+#[cfg(feature = "native")]
+pub(crate) fn archive_path(dest_dir: &Path) -> PathBuf {
+    let ts = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    dest_dir.join(format!("{}{}.zip", ARCHIVE_PREFIX, ts.as_nanos()))
+}
+
+#[cfg(feature = "native")]
+pub(crate) fn archive_status_error(
+    status: StatusCode,
+    location: &RepoLocation,
+    reference: &str,
+) -> GitripperError {
+    let message = if status.as_u16() == 404 {
+        format!("Archive for {}@{} not found (404).", location, reference)
+    } else if status.is_redirection() {
+        format!("Unexpected redirect: {}", status)
+    } else {
+        format!("Failed to download archive: {}", status)
+    };
+
+    GitripperError::HttpStatus {
+        status: status.as_u16(),
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_fetch_json_revalidates_with_etag() {
+        use std::{
+            io::{BufRead, BufReader},
+            net::TcpListener,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url =
+            format!("http://{}/repos/o/r", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let mut conditional = Vec::new();
+            for response in [
+                "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: \
+                 7\r\nConnection: close\r\n\r\n{\"a\":1}",
+                "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n",
+            ] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    if line.to_ascii_lowercase().starts_with("if-none-match") {
+                        conditional.push(line.clone());
+                    }
+                    line.clear();
+                }
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+            conditional
+        });
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new(temp_dir.path());
+        let client = Client::new();
+        let fetch = || {
+            with_conditional_cache(Some(&cache), || {
+                fetch_json(&client, &GitHub::default(), &url, None)
+            })
+            .unwrap()
+        };
+        assert_eq!(fetch()["a"], 1);
+        assert_eq!(fetch()["a"], 1);
+        assert_eq!(server.join().unwrap(), ["if-none-match: \"v1\"\r\n"]);
+    }
+
+    #[test]
+    fn test_rate_limit_error_from_headers() {
+        let reset = SystemTime::now().duration_since(UNIX_EPOCH).unwrap()
+            + Duration::from_secs(120);
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0"));
+        headers.insert(
+            "x-ratelimit-reset",
+            HeaderValue::from_str(&reset.as_secs().to_string()).unwrap(),
+        );
+
+        match rate_limit_error(StatusCode::FORBIDDEN, &headers) {
+            Some(GitripperError::RateLimited { reset_in: Some(d) }) => {
+                assert!(d <= Duration::from_secs(120));
+                assert!(d >= Duration::from_secs(100));
+            },
+            other => panic!("unexpected {:?}", other),
+        }
+
+        assert!(rate_limit_error(StatusCode::NOT_FOUND, &headers).is_none());
+        assert!(rate_limit_error(StatusCode::FORBIDDEN, &HeaderMap::new())
+            .is_none());
+    }
+
+    #[test]
+    fn test_join_url_path_encodes_segments() {
+        let url =
+            join_url_path("https://api.test/repos/o/r/contents", "a b/c#d")
+                .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://api.test/repos/o/r/contents/a%20b/c%23d"
+        );
+    }
+
+    #[test]
+    fn test_split_ranges_covers_archive() {
+        assert_eq!(split_ranges(10, 3), [(0, 3), (4, 7), (8, 9)]);
+        assert_eq!(split_ranges(2, 4), [(0, 0), (1, 1)]);
+        assert_eq!(content_range_total("bytes 0-0/1234"), Some(1234));
+        assert_eq!(content_range_total("bytes 0-0/*"), None);
+    }
+
+    #[test]
+    fn test_is_commit_sha() {
+        assert!(is_commit_sha("0123456789abcdef0123456789abcdef01234567"));
+        assert!(!is_commit_sha("main"));
+    }
+
+    #[test]
+    fn test_releases_unsupported_by_default() {
+        let err = Bitbucket
+            .release_tag(
+                &Client::new(),
+                &RepoLocation::new("o", "r"),
+                "v1",
+                None,
+            )
+            .unwrap_err();
+        assert!(matches!(err, GitripperError::Unsupported(_)));
+    }
+
+    #[test]
+    fn test_detect_provider_by_host() {
+        let (p, loc) = detect_provider("https://github.com/user/repo").unwrap();
+        assert_eq!(p.name(), "GitHub");
+        assert_eq!(loc, RepoLocation::new("user", "repo"));
+
+        let (p, loc) =
+            detect_provider("https://gitlab.com/group/sub/repo").unwrap();
+        assert_eq!(p.name(), "GitLab");
+        assert_eq!(loc, RepoLocation::new("group/sub", "repo"));
+
+        let (p, loc) = detect_provider("rust-lang/regex").unwrap();
+        assert_eq!(p.name(), "GitHub");
+        assert_eq!(loc, RepoLocation::new("rust-lang", "regex"));
+        let (p, loc) = detect_provider("gitlab:group/sub/proj").unwrap();
+        assert_eq!(p.name(), "GitLab");
+        assert_eq!(loc, RepoLocation::new("group/sub", "proj"));
+        assert!(detect_provider("a/b/c").is_none());
+        assert!(detect_provider("nowhere:a/b").is_none());
+
+        let (p, _) = detect_provider("https://example.com/user/repo").unwrap();
+        assert_eq!(p.name(), "git");
+        assert!(detect_provider("not a url").is_none());
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+/// Name of the receipt written at the top of a rip.
+pub const RECEIPT_FILE: &str = ".gitripper-receipt.json";
+
+/// Proof of exactly which archive a rip was extracted from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Receipt {
+    /// URL the archive was downloaded from.
+    pub url:           String,
+    pub reference:     String,
+    pub commit:        Option<String>,
+    /// Size of the archive in bytes.
+    pub size:          u64,
+    pub blake3:        String,
+    pub sha256:        String,
+    /// Seconds since the Unix epoch.
+    pub downloaded_at: u64,
+}
+
+impl Receipt {
+    /// Hashes the archive at `path`, reading it once for both digests.
+    pub fn for_archive(
+        path: &Path,
+        url: &str,
+        reference: &str,
+        commit: Option<&str>,
+    ) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let mut blake3 = blake3::Hasher::new();
+        let mut sha256 = Sha256::new();
+        let mut buf = vec![0u8; 64 * 1024];
+        let mut size = 0;
+
+        loop {
+            let n = file.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            blake3.update(&buf[..n]);
+            sha256.update(&buf[..n]);
+            size += n as u64;
+        }
+
+        Ok(Self {
+            url: url.to_string(),
+            reference: reference.to_string(),
+            commit: commit.map(str::to_string),
+            size,
+            blake3: blake3.finalize().to_hex().to_string(),
+            sha256: format!("{:x}", sha256.finalize()),
+            downloaded_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        })
+    }
+
+    /// Writes the receipt to [`RECEIPT_FILE`] in `dest`.
+    pub fn write(&self, dest: &Path) -> io::Result<PathBuf> {
+        let path = dest.join(RECEIPT_FILE);
+        let v = json!({
+            "url": self.url,
+            "reference": self.reference,
+            "commit": self.commit,
+            "size": self.size,
+            "blake3": self.blake3,
+            "sha256": self.sha256,
+            "downloaded_at": self.downloaded_at,
+        });
+        fs::write(&path, serde_json::to_string_pretty(&v)? + "\n")?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_receipt_hashes_archive() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let archive = temp_dir.path().join("a.zip");
+        fs::write(&archive, "").unwrap();
+
+        let receipt =
+            Receipt::for_archive(&archive, "https://x/a.zip", "main", None)
+                .unwrap();
+        assert_eq!(receipt.size, 0);
+        assert_eq!(
+            receipt.sha256,
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(receipt.blake3, blake3::hash(b"").to_hex().to_string());
+
+        let path = receipt.write(temp_dir.path()).unwrap();
+        let v: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(v["reference"], "main");
+        assert_eq!(v["sha256"], receipt.sha256);
+    }
+}
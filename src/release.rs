@@ -0,0 +1,194 @@
+use std::{
+    collections::HashMap,
+    fs::{self, create_dir_all},
+    io,
+    path::{Path, PathBuf},
+};
+
+use globset::{Glob, GlobSetBuilder};
+use tempfile::tempdir;
+
+use crate::{
+    compute_sha256_hex, provider::download_asset, GitripperError, ReleaseAsset,
+    Result, Ripper,
+};
+
+const CHECKSUM_SUFFIX: &str = ".sha256";
+
+/// Whether a downloaded asset could be checked against a published hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetCheck {
+    /// Matched a `*.sha256` file of the release.
+    Verified,
+    /// The release publishes no hash for the asset.
+    NoChecksum,
+}
+
+#[derive(Debug, Clone)]
+pub struct DownloadedAsset {
+    pub name:  String,
+    pub path:  PathBuf,
+    pub check: AssetCheck,
+}
+
+impl Ripper {
+    /// Tag and assets of `release` of the repository at `url`.
+    pub fn release_assets(
+        &self,
+        url: &str,
+        release: &str,
+    ) -> Result<(String, Vec<ReleaseAsset>)> {
+        let (provider, location) = self.locate(url)?;
+        self.with_retry(|| {
+            provider.release_assets(
+                self.client(),
+                &location,
+                release,
+                self.token(),
+            )
+        })
+    }
+
+    /// Downloads the assets of `release` whose names match any of the glob
+    /// `patterns` into `dest`, checking each against the release's
+    /// `*.sha256` files when one lists it.
+    pub fn download_release_assets(
+        &self,
+        url: &str,
+        release: &str,
+        patterns: &[String],
+        dest: &Path,
+    ) -> Result<Vec<DownloadedAsset>> {
+        let (provider, location) = self.locate(url)?;
+        let (tag, assets) = self.with_retry(|| {
+            provider.release_assets(
+                self.client(),
+                &location,
+                release,
+                self.token(),
+            )
+        })?;
+
+        let mut globs = GlobSetBuilder::new();
+        for pattern in patterns {
+            globs.add(Glob::new(pattern).map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidInput, e.to_string())
+            })?);
+        }
+        let globs = globs.build().map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidInput, e.to_string())
+        })?;
+
+        let selected: Vec<&ReleaseAsset> =
+            assets.iter().filter(|a| globs.is_match(&a.name)).collect();
+        if selected.is_empty() {
+            return Err(GitripperError::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "no asset of release {} matches {}",
+                    tag,
+                    patterns.join(", ")
+                ),
+            )));
+        }
+
+        let download = |asset: &ReleaseAsset, path: &Path| {
+            self.with_retry(|| {
+                download_asset(
+                    self.client(),
+                    provider.as_ref(),
+                    asset,
+                    self.token(),
+                    path,
+                    self.progress(),
+                )
+            })
+        };
+
+        let tmp = tempdir()?;
+        let mut checksums = HashMap::new();
+        for asset in assets.iter().filter(|a| a.name.ends_with(CHECKSUM_SUFFIX))
+        {
+            let path = tmp.path().join(&asset.name);
+            download(asset, &path)?;
+            parse_checksums(
+                &asset.name,
+                &fs::read_to_string(&path)?,
+                &mut checksums,
+            );
+        }
+
+        create_dir_all(dest)?;
+        let mut downloaded = Vec::new();
+        for asset in selected {
+            let path = dest.join(&asset.name);
+            download(asset, &path)?;
+
+            let check = match checksums.get(&asset.name) {
+                Some(expected) => {
+                    let actual = compute_sha256_hex(&path)?;
+                    if !actual.eq_ignore_ascii_case(expected) {
+                        let _ = fs::remove_file(&path);
+                        return Err(GitripperError::ChecksumMismatch {
+                            name: asset.name.clone(),
+                            expected: expected.clone(),
+                            actual,
+                        });
+                    }
+                    AssetCheck::Verified
+                },
+                None => AssetCheck::NoChecksum,
+            };
+            downloaded.push(DownloadedAsset {
+                name: asset.name.clone(),
+                path,
+                check,
+            });
+        }
+
+        Ok(downloaded)
+    }
+}
+
+/// Reads a checksum file, either `sha256sum` output (`<hex>  <name>` per
+/// line) or a lone hash for the file it is named after (`<name>.sha256`).
+fn parse_checksums(
+    file_name: &str,
+    text: &str,
+    out: &mut HashMap<String, String>,
+) {
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        match line.split_once(char::is_whitespace) {
+            Some((hash, name)) => {
+                // `sha256sum -b` marks binary files with a `*`.
+                let name = name.trim_start().trim_start_matches('*');
+                out.insert(name.to_string(), hash.to_lowercase());
+            },
+            None => {
+                if let Some(name) = file_name.strip_suffix(CHECKSUM_SUFFIX) {
+                    out.insert(name.to_string(), line.to_lowercase());
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_checksum_formats() {
+        let mut sums = HashMap::new();
+        parse_checksums("tool.tar.gz.sha256", "ABC123\n", &mut sums);
+        parse_checksums(
+            "SHA256SUMS.sha256",
+            "def456  tool.zip\n789abc *tool.exe\n",
+            &mut sums,
+        );
+
+        assert_eq!(sums["tool.tar.gz"], "abc123");
+        assert_eq!(sums["tool.zip"], "def456");
+        assert_eq!(sums["tool.exe"], "789abc");
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    io,
+    time::Duration,
+};
+
+use crate::{cancel::StopSignal, GitripperError, Result};
+
+pub const DEFAULT_RETRIES: u32 = 3;
+
+const DEFAULT_BASE_DELAY: Duration = Duration::from_millis(500);
+const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(10);
+
+/// How often and how patiently to retry HTTP calls that failed for transient
+/// reasons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; `0` disables retrying.
+    pub retries:             u32,
+    /// Delay before the first retry, doubled for each one after it.
+    pub base_delay:          Duration,
+    pub max_delay:           Duration,
+    /// Sleep until the rate limit resets instead of failing with
+    /// [`GitripperError::RateLimited`]. Such waits don't count as retries.
+    pub wait_for_rate_limit: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries:             DEFAULT_RETRIES,
+            base_delay:          DEFAULT_BASE_DELAY,
+            max_delay:           DEFAULT_MAX_DELAY,
+            wait_for_rate_limit: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        Self {
+            retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Backoff before retry number `attempt` (starting at 1), with "full
+    /// jitter": a random duration up to the exponential delay.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+            .min(self.max_delay);
+        exp.mul_f64(jitter())
+    }
+
+    /// Runs `f`, retrying transient failures. `on_retry` is told about each
+    /// failure that is about to be retried and the delay before it.
+    pub fn run<T>(
+        &self,
+        f: impl FnMut() -> Result<T>,
+        on_retry: impl FnMut(u32, Duration, &GitripperError),
+    ) -> Result<T> {
+        self.run_until(f, on_retry, &StopSignal::default())
+    }
+
+    /// Like [`RetryPolicy::run`], but gives up on `stop`, also while waiting
+    /// between attempts.
+    pub(crate) fn run_until<T>(
+        &self,
+        mut f: impl FnMut() -> Result<T>,
+        mut on_retry: impl FnMut(u32, Duration, &GitripperError),
+        stop: &StopSignal,
+    ) -> Result<T> {
+        let mut attempt = 0;
+
+        loop {
+            match f() {
+                Err(GitripperError::RateLimited {
+                    reset_in: Some(reset_in),
+                }) if self.wait_for_rate_limit => {
+                    // A second of slack for clock skew with the server.
+                    let delay = reset_in + Duration::from_secs(1);
+                    on_retry(
+                        attempt,
+                        delay,
+                        &GitripperError::RateLimited {
+                            reset_in: Some(reset_in),
+                        },
+                    );
+                    stop.sleep(delay)?;
+                },
+                Err(e) if attempt < self.retries && is_transient(&e) => {
+                    attempt += 1;
+                    let delay = self.delay(attempt);
+                    on_retry(attempt, delay, &e);
+                    stop.sleep(delay)?;
+                },
+                res => return res,
+            }
+        }
+    }
+}
+
+/// A random factor in `[0, 1)`, without pulling in an RNG crate.
+fn jitter() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Whether retrying the request that produced `err` might succeed.
+pub fn is_transient(err: &GitripperError) -> bool {
+    match err {
+        GitripperError::Http(e) => {
+            e.is_timeout() || e.is_connect() || e.is_body() || e.is_request()
+        },
+        GitripperError::HttpStatus { status, .. } => {
+            matches!(status, 408 | 429 | 500 | 502 | 503 | 504)
+        },
+        // Failures while streaming a response body surface as I/O errors.
+        GitripperError::Io(e) => matches!(
+            e.kind(),
+            io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::TimedOut
+                | io::ErrorKind::UnexpectedEof
+        ),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    fn status(status: u16) -> GitripperError {
+        GitripperError::HttpStatus {
+            status,
+            message: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_retries_transient_failures() {
+        let policy = RetryPolicy {
+            base_delay: Duration::ZERO,
+            ..Default::default()
+        };
+        let calls = Cell::new(0);
+        let mut retries = 0;
+
+        let res = policy.run(
+            || {
+                calls.set(calls.get() + 1);
+                if calls.get() < 3 {
+                    Err(status(503))
+                } else {
+                    Ok(())
+                }
+            },
+            |_, _, _| retries += 1,
+        );
+
+        assert!(res.is_ok());
+        assert_eq!(calls.get(), 3);
+        assert_eq!(retries, 2);
+    }
+
+    #[test]
+    fn test_does_not_retry_permanent_failures() {
+        let calls = Cell::new(0);
+        let res: Result<()> = RetryPolicy::default().run(
+            || {
+                calls.set(calls.get() + 1);
+                Err(status(404))
+            },
+            |_, _, _| {},
+        );
+
+        assert!(res.is_err());
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn test_delay_is_capped() {
+        let policy = RetryPolicy::default();
+        for attempt in 1..40 {
+            assert!(policy.delay(attempt) <= policy.max_delay);
+        }
+    }
+}
@@ -0,0 +1,3431 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{self, remove_dir_all, File},
+    io::{self, Cursor},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::anyhow;
+use globset::GlobSet;
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
+use reqwest::blocking::Client;
+use tempfile::tempdir;
+
+use crate::{
+    add_upstream_remote, archive_size,
+    cancel::{Cancellable, StopSignal},
+    check_git_installed, clone_with_history,
+    collision::CollisionGuard,
+    commit_branch, commit_snapshot, commit_vendored, dedupe_tree,
+    default_providers, detect_license, download_archive_ranged,
+    download_archive_to,
+    extract::{
+        archive_commit, exclude_set, extract_zip_into, is_symlink,
+        STRIP_META_PATTERNS,
+    },
+    extract_archive_with,
+    git::{enclosing_repo, has_upstream_remote, is_bare_repo},
+    hooks::run_hook,
+    initialize_bare_repo, initialize_repo_with,
+    lfs::{fetch_lfs_objects, find_lfs_pointers},
+    nested::extract_nested,
+    normalize_tree, provenance_statement,
+    provider::{
+        download_fallback_archive, find_provider, is_commit_sha, not_found_as,
+        with_conditional_cache,
+    },
+    remove_embedded_git, render_template, scan_secrets,
+    scheduler::Scheduler,
+    set_config, set_origin, set_upstream_base, sign_attestation, tag_head,
+    write_attestation, write_attribution, write_entry, write_manifest,
+    write_metadata, ArchiveFormat, ArchiveTarget, BatchLimits, BuilderInfo,
+    Cache, CancellationToken, CollisionPolicy, DedupeReport, DestLayout,
+    EntryAction, Eol, ExtractOptions, ExtractTarget, GitripperError,
+    HttpOptions, InitOptions, MemEntry, NotFoundCause, ProgressSink,
+    Provenance, Provider, Receipt, RefType, RemoteRef, RepoLocation,
+    RepoMetadata, Result, RetryPolicy, RipSource, SecretFinding, SecretPolicy,
+    SkippedFile, Stage, StripRoot, TemplateManifest, TemplateVariable,
+    TreeEntry, DEFAULT_BRANCH, MANIFEST_FILE, PROVENANCE_FILE_NO_GIT,
+};
+
+pub const DEFAULT_USER_AGENT: &str =
+    concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// Commit message of [`Ripper::update`] unless one was configured. Takes the
+/// same placeholders as [`RipperBuilder::message`].
+pub const DEFAULT_UPDATE_MESSAGE: &str = "Update to {ref} ({commit})";
+
+/// Commit message of a [`RipperBuilder::vendor`] rip unless one was
+/// configured.
+pub const DEFAULT_VENDOR_MESSAGE: &str = "Vendor {owner}/{repo} at {commit}";
+
+/// Summary of a completed rip.
+#[derive(Debug, Clone)]
+pub struct RipReport {
+    /// [`Provider::name`] of the host the repository came from.
+    pub provider:  &'static str,
+    pub owner:     String,
+    pub repo:      String,
+    pub reference: String,
+    /// Commit SHA the snapshot was taken from, when it was resolved.
+    pub commit:    Option<String>,
+    pub dest:      PathBuf,
+}
+
+/// What the reference given to a [`RipperBuilder`] names.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RefKind {
+    #[default]
+    Branch,
+    Tag,
+    /// A full or abbreviated commit SHA.
+    Commit,
+    /// A release, by tag name or [`crate::LATEST_RELEASE`].
+    Release,
+}
+
+impl fmt::Display for RefKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RefKind::Branch => "Branch",
+            RefKind::Tag => "Tag",
+            RefKind::Commit => "Commit",
+            RefKind::Release => "Release",
+        })
+    }
+}
+
+/// What to do when the destination of a rip is a non-empty directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Fail with [`GitripperError::DestinationExists`].
+    #[default]
+    Fail,
+    /// Remove the destination first.
+    Replace,
+    /// Extract over the destination, overwriting files in the archive and
+    /// keeping the rest.
+    Merge,
+    /// Extract over the destination, but keep files that already exist.
+    SkipExisting,
+    /// Move the destination aside to `<dest>.bak-<unix time>` first.
+    Backup,
+}
+
+/// Outcome of [`Ripper::update`].
+#[derive(Debug, Clone)]
+pub struct UpdateReport {
+    /// Commit the rip was at before updating.
+    pub previous: Option<String>,
+    pub report:   RipReport,
+    /// Whether a new snapshot was applied; `false` if upstream hadn't moved.
+    pub updated:  bool,
+}
+
+/// Notable steps of [`Ripper::rip`], reported through
+/// [`RipperBuilder::on_event`].
+#[derive(Debug, Clone)]
+pub enum RipEvent {
+    DefaultBranch(String),
+    /// Cloning from this URL because history is kept.
+    Cloning(String),
+    Downloaded(PathBuf),
+    /// The archive was taken from the cache instead of downloaded.
+    CacheHit(PathBuf),
+    RemovedEmbeddedGit(PathBuf),
+    /// The existing destination was moved here.
+    BackedUp(PathBuf),
+    /// A sparse rip downloaded this many files.
+    SparseFetched(usize),
+    /// This many Git LFS objects replaced their pointer files.
+    LfsObjects(usize),
+    /// An attribution was written, with the license if it was recognized.
+    Attributed(Option<String>),
+    /// Duplicate files were replaced with hardlinks.
+    Deduplicated(DedupeReport),
+    /// These archives in the rip were expanded next to themselves.
+    NestedExtracted(Vec<PathBuf>),
+    /// A provenance statement was written, and signed into `bundle` if
+    /// asked to.
+    Attested {
+        path:   PathBuf,
+        bundle: Option<PathBuf>,
+    },
+    /// Files left out by [`RipperBuilder::max_file_size`] or
+    /// [`RipperBuilder::skip_binaries`].
+    SkippedFiles(Vec<SkippedFile>),
+    /// A likely credential was found scanning the rip.
+    SecretFound(SecretFinding),
+    /// This many files changed instantiating a template.
+    TemplateRendered(usize),
+    /// Running a post-hook set with [`RipperBuilder::post_hook`].
+    RunningHook(String),
+    Initializing,
+    /// Committing this vendored directory to the repository it lies in.
+    Vendoring(PathBuf),
+    RemoteSet(String),
+    /// Registered this URL as the `upstream` remote.
+    UpstreamSet(String),
+    /// The snapshot commit was tagged with this name.
+    Tagged(String),
+    /// `dest` already holds a rip of `reference` at the upstream `commit`,
+    /// so it was left alone.
+    UpToDate {
+        dest:      PathBuf,
+        reference: String,
+        commit:    String,
+    },
+    Warning(String),
+    /// The repository is a fork of `parent`, both `owner/repo`; the parent
+    /// is ripped instead if `using_parent`.
+    Fork {
+        fork:         String,
+        parent:       String,
+        using_parent: bool,
+    },
+    /// The repository was renamed or transferred from `from` to `to`, both
+    /// `owner/repo`; the new names are used.
+    Moved {
+        from: String,
+        to:   String,
+    },
+    /// Ripping the head of a pull request, `owner/repo@ref`.
+    PullRequestHead {
+        number: u64,
+        head:   String,
+    },
+    /// A repository moved on in a batch run with
+    /// [`RipperBuilder::batch_limits`].
+    Stage {
+        repo:  String,
+        stage: Stage,
+    },
+    /// A transient HTTP failure; the request is retried after `delay`.
+    Retrying {
+        attempt: u32,
+        delay:   Duration,
+        reason:  String,
+    },
+}
+
+#[derive(Clone)]
+struct EventHandler(Arc<dyn Fn(&RipEvent) + Send + Sync>);
+
+impl fmt::Debug for EventHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EventHandler")
+    }
+}
+
+type PromptFn = dyn Fn(&TemplateVariable) -> Option<String> + Send + Sync;
+
+#[derive(Clone)]
+struct TemplatePrompt(Arc<PromptFn>);
+
+impl fmt::Debug for TemplatePrompt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TemplatePrompt")
+    }
+}
+
+type ConfirmFn = dyn Fn(&RepoLocation, u64) -> bool + Send + Sync;
+
+/// Size above which [`RipperBuilder::confirm_size_above`] asks first.
+#[derive(Clone)]
+struct SizeLimit {
+    bytes:   u64,
+    confirm: Arc<ConfirmFn>,
+}
+
+impl fmt::Debug for SizeLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SizeLimit").field("bytes", &self.bytes).finish()
+    }
+}
+
+/// Configures and builds a [`Ripper`].
+#[derive(Debug, Default)]
+pub struct RipperBuilder {
+    client:           Option<Client>,
+    user_agent:       Option<String>,
+    http:             HttpOptions,
+    providers:        Vec<Arc<dyn Provider>>,
+    token:            Option<String>,
+    reference:        Option<String>,
+    ref_kind:         RefKind,
+    pull:             Option<u64>,
+    subpath:          Option<PathBuf>,
+    dest:             Option<PathBuf>,
+    layout:           Option<DestLayout>,
+    init:             InitOptions,
+    overwrite:        OverwritePolicy,
+    extract_options:  ExtractOptions,
+    retry:            RetryPolicy,
+    cancel:           Option<CancellationToken>,
+    deadline:         Option<Instant>,
+    cache:            Option<Cache>,
+    refresh:          bool,
+    offline:          bool,
+    keep_history:     bool,
+    history_depth:    Option<u32>,
+    detach_remote:    bool,
+    no_init:          bool,
+    manifest:         bool,
+    receipt:          bool,
+    attribution:      bool,
+    attest:           Option<BuilderInfo>,
+    sign_attestation: bool,
+    empty_readme:     Option<String>,
+    verify_commit:    bool,
+    prefer_parent:    bool,
+    dedupe:           bool,
+    nested_depth:     usize,
+    scan_secrets:     SecretPolicy,
+    lfs:              bool,
+    post_hooks:       Vec<String>,
+    template:         Option<BTreeMap<String, String>>,
+    template_prompt:  Option<TemplatePrompt>,
+    size_limit:       Option<SizeLimit>,
+    exclude:          Vec<String>,
+    strip_meta:       bool,
+    sparse:           Vec<String>,
+    download_threads: usize,
+    batch_limits:     BatchLimits,
+    jobs:             Option<usize>,
+    vendor:           bool,
+    bare:             bool,
+    upstream_remote:  bool,
+    tag_as:           Option<String>,
+    copy_tag:         bool,
+    import_metadata:  bool,
+    on_event:         Option<EventHandler>,
+    #[cfg(feature = "async")]
+    overlap:          bool,
+}
+
+impl RipperBuilder {
+    /// Uses an existing HTTP client instead of building a new one.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// User agent for internally built clients. The client passed to
+    /// [`RipperBuilder::client`] keeps its own.
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Proxy, TLS and timeout settings for internally built clients,
+    /// including the async one of [`RipperBuilder::overlap_extraction`]. The
+    /// client passed to [`RipperBuilder::client`] keeps its own.
+    pub fn http_options(mut self, options: HttpOptions) -> Self {
+        self.http = options;
+        self
+    }
+
+    /// Adds a provider, tried before the built-in ones (see
+    /// [`default_providers`]) when matching URLs.
+    pub fn provider(mut self, provider: Arc<dyn Provider>) -> Self {
+        self.providers.push(provider);
+        self
+    }
+
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Branch to download. Defaults to the repository's default branch.
+    pub fn reference(mut self, reference: impl Into<String>) -> Self {
+        self.reference = Some(reference.into());
+        self.ref_kind = RefKind::Branch;
+        self
+    }
+
+    /// Download a tag instead of a branch.
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.reference = Some(tag.into());
+        self.ref_kind = RefKind::Tag;
+        self
+    }
+
+    /// Download a commit, given as a full or abbreviated SHA.
+    pub fn commit(mut self, sha: impl Into<String>) -> Self {
+        self.reference = Some(sha.into());
+        self.ref_kind = RefKind::Commit;
+        self
+    }
+
+    /// Download the tag of a release, or of the newest one for
+    /// [`crate::LATEST_RELEASE`].
+    pub fn release(mut self, release: impl Into<String>) -> Self {
+        self.reference = Some(release.into());
+        self.ref_kind = RefKind::Release;
+        self
+    }
+
+    /// Rip the head of this pull request, from the fork it was opened from
+    /// if there is one. Overrides a pull request taken from the URL.
+    pub fn pull_request(mut self, number: u64) -> Self {
+        self.pull = Some(number);
+        self
+    }
+
+    /// Only rip this directory of the repository. Overrides a path taken
+    /// from a `/tree/<ref>/<path>` URL.
+    pub fn subpath(mut self, path: impl Into<PathBuf>) -> Self {
+        self.subpath = Some(path.into());
+        self
+    }
+
+    /// Destination directory. Defaults to `<repo>-copy`.
+    pub fn dest(mut self, dest: impl Into<PathBuf>) -> Self {
+        self.dest = Some(dest.into());
+        self
+    }
+
+    /// Lay out destinations with `layout`: under the current directory for
+    /// [`Ripper::rip`] when no [`RipperBuilder::dest`] is set, and under the
+    /// root of [`Ripper::rip_batch`].
+    pub fn dest_layout(mut self, layout: DestLayout) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    pub fn author_name(mut self, name: impl Into<String>) -> Self {
+        self.init.author_name = Some(name.into());
+        self
+    }
+
+    pub fn author_email(mut self, email: impl Into<String>) -> Self {
+        self.init.author_email = Some(email.into());
+        self
+    }
+
+    /// URL to register as `origin` in the new repository.
+    pub fn remote(mut self, remote: impl Into<String>) -> Self {
+        self.init.remote = Some(remote.into());
+        self
+    }
+
+    /// Branch the new repository starts on, e.g. `trunk`.
+    pub fn initial_branch(mut self, branch: impl Into<String>) -> Self {
+        self.init.initial_branch = Some(branch.into());
+        self
+    }
+
+    /// Message of the import commit. `{owner}`, `{repo}`, `{ref}` and
+    /// `{commit}` are replaced with details of the ripped snapshot.
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.init.message = Some(message.into());
+        self
+    }
+
+    /// Replace a non-empty destination instead of failing. Shorthand for
+    /// [`OverwritePolicy::Replace`].
+    pub fn force(mut self, force: bool) -> Self {
+        self.overwrite = if force {
+            OverwritePolicy::Replace
+        } else {
+            OverwritePolicy::Fail
+        };
+        self
+    }
+
+    /// What to do with a non-empty destination.
+    pub fn overwrite(mut self, policy: OverwritePolicy) -> Self {
+        self.overwrite = policy;
+        self
+    }
+
+    /// Caps how much decompressed data extraction may hold in memory at
+    /// once. See [`ExtractOptions::memory_budget`].
+    pub fn memory_budget(mut self, bytes: u64) -> Self {
+        self.extract_options.memory_budget = bytes;
+        self
+    }
+
+    /// Which leading directories of archive paths to remove. See
+    /// [`ExtractOptions::strip_root`].
+    pub fn strip_root(mut self, strip: StripRoot) -> Self {
+        self.extract_options.strip_root = strip;
+        self
+    }
+
+    /// Leave out files bigger than `bytes`, as for test fixtures nobody
+    /// wants to review. See [`ExtractOptions::max_file_size`].
+    pub fn max_file_size(mut self, bytes: u64) -> Self {
+        self.extract_options.max_file_size = Some(bytes);
+        self
+    }
+
+    /// Give text files these line endings, so the working tree isn't dirty
+    /// right after the first commit on Windows. See [`ExtractOptions::eol`].
+    pub fn eol(mut self, eol: Eol) -> Self {
+        self.extract_options.eol = eol;
+        self
+    }
+
+    /// What to do with paths that are the same file on the destination
+    /// filesystem, like `README.md` and `Readme.md` on macOS. Fails by
+    /// default.
+    pub fn collisions(mut self, policy: CollisionPolicy) -> Self {
+        self.extract_options.collisions = policy;
+        self
+    }
+
+    /// Make the snapshot reproducible: entries are written in path order,
+    /// and modification times and permissions are normalized once the rip
+    /// is otherwise done, so two rips of the same commit match down to their
+    /// metadata. See [`crate::normalize_tree`].
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.extract_options.deterministic = deterministic;
+        self
+    }
+
+    /// Leave out files that look binary. See [`crate::looks_binary`].
+    pub fn skip_binaries(mut self, skip: bool) -> Self {
+        self.extract_options.skip_binaries = skip;
+        self
+    }
+
+    /// Run `hook` on every file before it is written, to rename, redact or
+    /// drop it. See [`ExtractOptions::entry_hook`].
+    pub fn entry_hook(
+        mut self,
+        hook: impl Fn(&mut MemEntry) -> EntryAction + Send + Sync + 'static,
+    ) -> Self {
+        self.extract_options.entry_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Leave out files and directories matching `glob`, relative to the
+    /// destination. Repeatable.
+    pub fn exclude(mut self, glob: impl Into<String>) -> Self {
+        self.exclude.push(glob.into());
+        self
+    }
+
+    /// Leave out CI configuration and hosting metadata such as `.github/`,
+    /// `.gitlab-ci.yml` and `CODEOWNERS`; see [`STRIP_META_PATTERNS`].
+    pub fn strip_meta(mut self, strip: bool) -> Self {
+        self.strip_meta = strip;
+        self
+    }
+
+    /// Only rip files matching `glob`, relative to the destination.
+    /// Repeatable. Instead of the archive, the tree is listed and just the
+    /// matching files are downloaded, in parallel, which pays off for a few
+    /// files out of a large repository. Only some providers support this.
+    pub fn sparse(mut self, glob: impl Into<String>) -> Self {
+        self.sparse.push(glob.into());
+        self
+    }
+
+    /// Bound the downloads, extractions and downloaded-but-unextracted bytes
+    /// of all the rips of this ripper together, for [`Ripper::rip_batch`].
+    /// Each repository reports its progress through them as
+    /// [`RipEvent::Stage`].
+    pub fn batch_limits(mut self, limits: BatchLimits) -> Self {
+        self.batch_limits = limits;
+        self
+    }
+
+    /// Download archives over up to `threads` parallel connections when the
+    /// server accepts range requests. Defaults to one.
+    pub fn download_threads(mut self, threads: usize) -> Self {
+        self.download_threads = threads;
+        self
+    }
+
+    /// Caps the threads used for parallel file writes and directory walks
+    /// at `jobs`. By default they use every core.
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = Some(jobs);
+        self
+    }
+
+    /// Receives download and extraction progress.
+    pub fn progress(mut self, sink: Arc<dyn ProgressSink>) -> Self {
+        self.extract_options.progress = sink;
+        self
+    }
+
+    /// Stop downloading and extracting once `token` is cancelled. The rip
+    /// then fails with [`GitripperError::Cancelled`], removing the
+    /// destination if it created it.
+    pub fn cancel_token(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Give up once `deadline` passes, also while waiting to retry. The rip
+    /// then fails with [`GitripperError::DeadlineExceeded`], cleaning up as
+    /// for [`RipperBuilder::cancel_token`].
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Extract the archive while it is still downloading. See
+    /// [`crate::async_pipeline`].
+    #[cfg(feature = "async")]
+    pub fn overlap_extraction(mut self, overlap: bool) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    /// Retry policy for API calls and downloads. Defaults to
+    /// [`RetryPolicy::default`].
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Reuses archives of already ripped commits from `cache`, and stores new
+    /// downloads in it.
+    pub fn cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Download even if the commit is cached, replacing the cached copy, and
+    /// make API requests without `If-None-Match`.
+    pub fn refresh(mut self, refresh: bool) -> Self {
+        self.refresh = refresh;
+        self
+    }
+
+    /// Never touch the network: refs are resolved and archives read from
+    /// the [`cache`](Self::cache) alone, failing with
+    /// [`GitripperError::NotCached`] for anything not ripped before.
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Clone the repository with git instead of downloading a snapshot, so
+    /// the result keeps its history.
+    pub fn keep_history(mut self, keep: bool) -> Self {
+        self.keep_history = keep;
+        self
+    }
+
+    /// Only fetch the last `depth` commits when keeping history.
+    pub fn history_depth(mut self, depth: u32) -> Self {
+        self.history_depth = Some(depth);
+        self
+    }
+
+    /// Drop the `origin` remote of a clone made with
+    /// [`RipperBuilder::keep_history`]. [`RipperBuilder::remote`] still wins.
+    pub fn detach_remote(mut self, detach: bool) -> Self {
+        self.detach_remote = detach;
+        self
+    }
+
+    /// Import into a subdirectory of an existing repository: the destination
+    /// is committed to the repository it lies in, with the rip metadata,
+    /// instead of getting a repository of its own. Can't be combined with
+    /// [`RipperBuilder::keep_history`].
+    pub fn vendor(mut self, vendor: bool) -> Self {
+        self.vendor = vendor;
+        self
+    }
+
+    /// Make the destination a bare repository holding the snapshot as its
+    /// only commit, without a working tree, e.g. to serve it from a git
+    /// server. Can't be combined with [`RipperBuilder::keep_history`],
+    /// [`RipperBuilder::vendor`] or [`RipperBuilder::no_init`].
+    pub fn bare(mut self, bare: bool) -> Self {
+        self.bare = bare;
+        self
+    }
+
+    /// Register the original repository as the `upstream` remote of the new
+    /// one, with a refspec that fetches the ripped commit into
+    /// [`crate::UPSTREAM_BASE_REF`]. [`Ripper::update`] moves that refspec
+    /// along. Ignored when history is kept, where upstream is `origin`.
+    pub fn upstream_remote(mut self, upstream: bool) -> Self {
+        self.upstream_remote = upstream;
+        self
+    }
+
+    /// Create an annotated tag `name` on the snapshot commit, recording the
+    /// upstream ref and commit SHA.
+    pub fn tag_as(mut self, name: impl Into<String>) -> Self {
+        self.tag_as = Some(name.into());
+        self
+    }
+
+    /// When ripping a tag or release, create an annotated tag of the same
+    /// name on the snapshot commit, as with [`RipperBuilder::tag_as`].
+    pub fn copy_tag(mut self, copy: bool) -> Self {
+        self.copy_tag = copy;
+        self
+    }
+
+    /// Fetch the upstream description, homepage and topics and store them
+    /// under [`crate::METADATA_SECTION`] in the git config of the new
+    /// repository, or in a [`crate::METADATA_FILE`] when there is none of
+    /// its own. A failed fetch is only a warning.
+    pub fn import_metadata(mut self, import: bool) -> Self {
+        self.import_metadata = import;
+        self
+    }
+
+    /// Only download and extract; don't create a repository or commit.
+    pub fn no_init(mut self, no_init: bool) -> Self {
+        self.no_init = no_init;
+        self
+    }
+
+    /// Write a [`MANIFEST_FILE`] with the hash of every extracted file, for
+    /// [`verify_manifest`] to check later.
+    pub fn manifest(mut self, manifest: bool) -> Self {
+        self.manifest = manifest;
+        self
+    }
+
+    /// Write a [`crate::RECEIPT_FILE`] with the URL, size, BLAKE3 and SHA-256
+    /// of the archive the rip was extracted from.
+    pub fn receipt(mut self, receipt: bool) -> Self {
+        self.receipt = receipt;
+        self
+    }
+
+    /// Detect the license of the rip and add an entry crediting the
+    /// repository to the [`crate::NOTICES_FILE`] at its top.
+    pub fn attribution(mut self, attribution: bool) -> Self {
+        self.attribution = attribution;
+        self
+    }
+
+    /// Write an in-toto statement with SLSA provenance for the rip to the
+    /// [`crate::ATTESTATION_FILE`] at its top, naming `builder` as what made
+    /// it. See [`provenance_statement`].
+    pub fn attest(mut self, builder: BuilderInfo) -> Self {
+        self.attest = Some(builder);
+        self
+    }
+
+    /// Sign the statement written by [`RipperBuilder::attest`] with cosign.
+    /// See [`sign_attestation`].
+    pub fn sign_attestation(mut self, sign: bool) -> Self {
+        self.sign_attestation = sign;
+        self
+    }
+
+    /// `README.md` for the rip of a repository without any commits, which
+    /// otherwise gets an empty initial commit.
+    pub fn empty_readme(mut self, contents: impl Into<String>) -> Self {
+        self.empty_readme = Some(contents.into());
+        self
+    }
+
+    /// Resolve the reference to a commit before downloading and fail with
+    /// [`GitripperError::CommitMismatch`] if the archive is of another one,
+    /// as happens when a branch moves mid-download.
+    pub fn verify_commit(mut self, verify: bool) -> Self {
+        self.verify_commit = verify;
+        self
+    }
+
+    /// When the repository is a fork, rip the repository it was forked from
+    /// instead, keeping the reference and path. Forks are reported with
+    /// [`RipEvent::Fork`] either way.
+    pub fn prefer_parent(mut self, prefer: bool) -> Self {
+        self.prefer_parent = prefer;
+        self
+    }
+
+    /// Replace files with identical contents by hardlinks to one copy once
+    /// the rip is in place. See [`dedupe_tree`].
+    pub fn dedupe(mut self, dedupe: bool) -> Self {
+        self.dedupe = dedupe;
+        self
+    }
+
+    /// Expand zip and tar archives found in the rip, and those found in
+    /// them, down to `depth` levels. See [`extract_nested`].
+    pub fn extract_nested(mut self, depth: usize) -> Self {
+        self.nested_depth = depth;
+        self
+    }
+
+    /// Scan the rip for credentials before anything is committed, and
+    /// report them or fail with [`GitripperError::SecretsFound`]. See
+    /// [`crate::scan_secrets`]. Rips that keep history only have their
+    /// checkout scanned, not the commits before it.
+    pub fn scan_secrets(mut self, policy: SecretPolicy) -> Self {
+        self.scan_secrets = policy;
+        self
+    }
+
+    /// Replace Git LFS pointer files with the objects they stand for,
+    /// fetched through the provider's LFS batch API with the same token.
+    pub fn lfs(mut self, lfs: bool) -> Self {
+        self.lfs = lfs;
+        self
+    }
+
+    /// Adds a shell command run in the destination after extraction and
+    /// before the import commit, in the order added, through `sh -c` (`cmd
+    /// /C` on Windows). `GITRIPPER_PROVIDER`, `GITRIPPER_OWNER`,
+    /// `GITRIPPER_REPO`, `GITRIPPER_REF`, `GITRIPPER_COMMIT` and
+    /// `GITRIPPER_DEST` describe the rip. A failing hook fails the rip. In
+    /// rips that keep history, hooks run on the checkout and their changes
+    /// are left uncommitted.
+    pub fn post_hook(mut self, command: impl Into<String>) -> Self {
+        self.post_hooks.push(command.into());
+        self
+    }
+
+    /// Treat the snapshot as a project template: fill `{{ name }}`
+    /// placeholders in file contents and paths before the import commit.
+    ///
+    /// Values come from `values`, then [`RipperBuilder::template_prompt`],
+    /// then the defaults of the [`crate::TEMPLATE_MANIFEST`]. `project_name`
+    /// defaults to the destination's directory name and `author` to
+    /// [`RipperBuilder::author_name`]. Rips that keep history are not
+    /// templated.
+    pub fn template(mut self, values: BTreeMap<String, String>) -> Self {
+        self.template = Some(values);
+        self
+    }
+
+    /// Asks for the value of a template variable that was not passed to
+    /// [`RipperBuilder::template`]. `None` falls back to its default.
+    pub fn template_prompt(
+        mut self,
+        f: impl Fn(&TemplateVariable) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.template_prompt = Some(TemplatePrompt(Arc::new(f)));
+        self
+    }
+
+    /// Before downloading a repository bigger than `bytes`, as its provider
+    /// or the archive's server reports it, asks `confirm` with its size
+    /// whether to go on, and fails with [`GitripperError::TooLarge`] if not.
+    /// Repositories of unknown size are ripped without asking.
+    pub fn confirm_size_above(
+        mut self,
+        bytes: u64,
+        confirm: impl Fn(&RepoLocation, u64) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.size_limit = Some(SizeLimit {
+            bytes,
+            confirm: Arc::new(confirm),
+        });
+        self
+    }
+
+    /// Sign the import commit. See [`InitOptions::sign`].
+    pub fn sign(mut self, sign: bool) -> Self {
+        self.init.sign = sign;
+        self
+    }
+
+    /// Leave files ignored by the snapshot's own `.gitignore` files out of
+    /// the import commit. See [`InitOptions::respect_gitignore`].
+    pub fn respect_gitignore(mut self, respect: bool) -> Self {
+        self.init.respect_gitignore = respect;
+        self
+    }
+
+    /// Registers a callback invoked for every [`RipEvent`].
+    pub fn on_event(
+        mut self,
+        f: impl Fn(&RipEvent) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_event = Some(EventHandler(Arc::new(f)));
+        self
+    }
+
+    pub fn build(self) -> Result<Ripper> {
+        if !self.sparse.is_empty() && self.keep_history {
+            return Err(GitripperError::Unsupported(
+                "a sparse rip downloads single files and can't keep history"
+                    .to_string(),
+            ));
+        }
+        if self.nested_depth > 0 && self.keep_history {
+            return Err(GitripperError::Unsupported(
+                "expanding nested archives of a rip that keeps history"
+                    .to_string(),
+            ));
+        }
+        if self.offline
+            && (self.cache.is_none()
+                || self.refresh
+                || self.keep_history
+                || self.lfs
+                || !self.sparse.is_empty())
+        {
+            return Err(GitripperError::Unsupported(
+                "offline mode needs the archive cache, and can't refresh it, \
+                 clone, fetch LFS objects or make sparse rips"
+                    .to_string(),
+            ));
+        }
+        if self.receipt && (self.keep_history || !self.sparse.is_empty()) {
+            return Err(GitripperError::Unsupported(
+                "a receipt records the downloaded archive, but clones and \
+                 sparse rips don't download one"
+                    .to_string(),
+            ));
+        }
+        if self.keep_history
+            && (self.extract_options.max_file_size.is_some()
+                || self.extract_options.skip_binaries
+                || self.extract_options.eol != Eol::Keep
+                || self.extract_options.entry_hook.is_some())
+        {
+            return Err(GitripperError::Unsupported(
+                "skipping, rewriting or converting the line endings of files \
+                 while keeping history"
+                    .to_string(),
+            ));
+        }
+        if (self.attest.is_some() || self.sign_attestation) && self.keep_history
+        {
+            return Err(GitripperError::Unsupported(
+                "attesting a clone, which is committed as it was fetched"
+                    .to_string(),
+            ));
+        }
+        if self.sign_attestation && self.attest.is_none() {
+            return Err(GitripperError::Unsupported(
+                "signing an attestation without writing one".to_string(),
+            ));
+        }
+        if self.extract_options.deterministic && self.keep_history {
+            return Err(GitripperError::Unsupported(
+                "a deterministic rip normalizes an extracted snapshot and \
+                 can't keep history"
+                    .to_string(),
+            ));
+        }
+        #[cfg(feature = "async")]
+        if self.extract_options.deterministic && self.overlap {
+            return Err(GitripperError::Unsupported(
+                "a deterministic rip writes entries in path order, which \
+                 overlapped extraction can't"
+                    .to_string(),
+            ));
+        }
+        if self.vendor && self.keep_history {
+            return Err(GitripperError::Unsupported(
+                "vendoring imports a snapshot and can't keep history"
+                    .to_string(),
+            ));
+        }
+        if (self.tag_as.is_some() || self.copy_tag)
+            && (self.keep_history || self.vendor || self.no_init)
+        {
+            return Err(GitripperError::Unsupported(
+                "tags go on the snapshot commit of a new repository and can't \
+                 be combined with keeping history, vendoring or skipping git"
+                    .to_string(),
+            ));
+        }
+        if self.copy_tag
+            && !matches!(self.ref_kind, RefKind::Tag | RefKind::Release)
+        {
+            return Err(GitripperError::Unsupported(
+                "copying the tag needs a tag or release to rip".to_string(),
+            ));
+        }
+        if self.bare && (self.keep_history || self.vendor || self.no_init) {
+            return Err(GitripperError::Unsupported(
+                "a bare repository holds a new snapshot commit and can't be \
+                 combined with keeping history, vendoring or skipping git"
+                    .to_string(),
+            ));
+        }
+        let user_agent =
+            self.user_agent.unwrap_or_else(|| DEFAULT_USER_AGENT.to_string());
+        let client = match self.client {
+            Some(c) => c,
+            None => self.http.build_client(&user_agent)?,
+        };
+        let mut providers = self.providers;
+        providers.extend(default_providers());
+
+        let mut extract_options = self.extract_options;
+        extract_options.skip_existing =
+            self.overwrite == OverwritePolicy::SkipExisting;
+        let stop = StopSignal {
+            token:    self.cancel,
+            deadline: self.deadline,
+        };
+        if stop.is_armed() {
+            extract_options.progress = Arc::new(Cancellable {
+                inner: extract_options.progress,
+                stop:  stop.clone(),
+            });
+        }
+        let mut exclude = self.exclude;
+        if self.strip_meta {
+            exclude.extend(STRIP_META_PATTERNS.iter().map(|p| p.to_string()));
+        }
+        if !exclude.is_empty() {
+            extract_options.exclude = Some(exclude_set(&exclude)?);
+        }
+        let sparse = (!self.sparse.is_empty())
+            .then(|| exclude_set(&self.sparse))
+            .transpose()?;
+        let pool = self
+            .jobs
+            .map(|jobs| {
+                ThreadPoolBuilder::new()
+                    .num_threads(jobs.max(1))
+                    .build()
+                    .map_err(|e| GitripperError::Io(io::Error::other(e)))
+            })
+            .transpose()?;
+
+        Ok(Ripper {
+            client,
+            user_agent,
+            #[cfg(feature = "async")]
+            http: self.http,
+            providers,
+            token: self.token,
+            reference: self.reference,
+            ref_kind: self.ref_kind,
+            pull: self.pull,
+            subpath: self.subpath,
+            dest: self.dest,
+            layout: self.layout,
+            init: self.init,
+            overwrite: self.overwrite,
+            extract_options,
+            retry: self.retry,
+            stop,
+            cache: self.cache,
+            refresh: self.refresh,
+            offline: self.offline,
+            keep_history: self.keep_history,
+            history_depth: self.history_depth,
+            detach_remote: self.detach_remote,
+            no_init: self.no_init,
+            manifest: self.manifest,
+            receipt: self.receipt,
+            attribution: self.attribution,
+            attest: self.attest,
+            sign_attestation: self.sign_attestation,
+            empty_readme: self.empty_readme,
+            verify_commit: self.verify_commit,
+            prefer_parent: self.prefer_parent,
+            dedupe: self.dedupe,
+            nested_depth: self.nested_depth,
+            scan_secrets: self.scan_secrets,
+            lfs: self.lfs,
+            sparse,
+            download_threads: self.download_threads,
+            scheduler: Scheduler::new(self.batch_limits),
+            post_hooks: self.post_hooks,
+            pool,
+            template: self.template,
+            template_prompt: self.template_prompt,
+            size_limit: self.size_limit,
+            vendor: self.vendor,
+            bare: self.bare,
+            upstream_remote: self.upstream_remote,
+            tag_as: self.tag_as,
+            copy_tag: self.copy_tag,
+            import_metadata: self.import_metadata,
+            on_event: self.on_event,
+            #[cfg(feature = "async")]
+            overlap: self.overlap,
+        })
+    }
+}
+
+/// Downloads a repository snapshot from GitHub, GitLab or another
+/// [`Provider`] and turns it into a fresh local git repository.
+///
+/// ```no_run
+/// use gitripper::Ripper;
+///
+/// let ripper = Ripper::builder().dest("regex-copy").build()?;
+/// let report = ripper.rip("https://github.com/rust-lang/regex")?;
+/// println!("ripped {} into {}", report.reference, report.dest.display());
+/// # Ok::<(), gitripper::GitripperError>(())
+/// ```
+#[derive(Debug)]
+pub struct Ripper {
+    client:           Client,
+    user_agent:       String,
+    #[cfg(feature = "async")]
+    http:             HttpOptions,
+    providers:        Vec<Arc<dyn Provider>>,
+    token:            Option<String>,
+    reference:        Option<String>,
+    ref_kind:         RefKind,
+    pull:             Option<u64>,
+    subpath:          Option<PathBuf>,
+    dest:             Option<PathBuf>,
+    layout:           Option<DestLayout>,
+    init:             InitOptions,
+    overwrite:        OverwritePolicy,
+    extract_options:  ExtractOptions,
+    retry:            RetryPolicy,
+    stop:             StopSignal,
+    cache:            Option<Cache>,
+    refresh:          bool,
+    offline:          bool,
+    keep_history:     bool,
+    history_depth:    Option<u32>,
+    detach_remote:    bool,
+    no_init:          bool,
+    manifest:         bool,
+    receipt:          bool,
+    attribution:      bool,
+    attest:           Option<BuilderInfo>,
+    sign_attestation: bool,
+    empty_readme:     Option<String>,
+    verify_commit:    bool,
+    prefer_parent:    bool,
+    dedupe:           bool,
+    nested_depth:     usize,
+    scan_secrets:     SecretPolicy,
+    lfs:              bool,
+    sparse:           Option<GlobSet>,
+    download_threads: usize,
+    scheduler:        Scheduler,
+    post_hooks:       Vec<String>,
+    pool:             Option<ThreadPool>,
+    template:         Option<BTreeMap<String, String>>,
+    template_prompt:  Option<TemplatePrompt>,
+    size_limit:       Option<SizeLimit>,
+    vendor:           bool,
+    bare:             bool,
+    upstream_remote:  bool,
+    tag_as:           Option<String>,
+    copy_tag:         bool,
+    import_metadata:  bool,
+    on_event:         Option<EventHandler>,
+    #[cfg(feature = "async")]
+    overlap:          bool,
+}
+
+impl Ripper {
+    pub fn builder() -> RipperBuilder { RipperBuilder::default() }
+
+    pub fn client(&self) -> &Client { &self.client }
+
+    pub fn user_agent(&self) -> &str { &self.user_agent }
+
+    pub(crate) fn token(&self) -> Option<&str> { self.token.as_deref() }
+
+    pub(crate) fn layout(&self) -> Option<&DestLayout> { self.layout.as_ref() }
+
+    pub(crate) fn progress(&self) -> &dyn ProgressSink {
+        self.extract_options.progress.as_ref()
+    }
+
+    fn emit(&self, event: RipEvent) {
+        if let Some(h) = &self.on_event {
+            (h.0)(&event);
+        }
+    }
+
+    /// Runs `f` on the pool set with [`RipperBuilder::jobs`], if any, so
+    /// parallel writes and directory walks inside it use that many threads.
+    fn in_pool<T: Send>(&self, f: impl FnOnce() -> T + Send) -> T {
+        match &self.pool {
+            Some(pool) => pool.install(f),
+            None => f(),
+        }
+    }
+
+    pub(crate) fn with_retry<T>(
+        &self,
+        mut f: impl FnMut() -> Result<T>,
+    ) -> Result<T> {
+        if self.offline {
+            return Err(GitripperError::Offline);
+        }
+        let attempt = || match self.stop.error() {
+            Some(e) => Err(e),
+            None => f(),
+        };
+        self.retry.run_until(
+            attempt,
+            |attempt, delay, e| {
+                self.emit(RipEvent::Retrying {
+                    attempt,
+                    delay,
+                    reason: e.to_string(),
+                })
+            },
+            &self.stop,
+        )
+    }
+
+    /// Whether the [`RipperBuilder::cancel_token`] was cancelled or the
+    /// [`RipperBuilder::deadline`] has passed.
+    pub fn is_cancelled(&self) -> bool { self.stop.error().is_some() }
+
+    /// Destination used for `repo` when none was configured.
+    pub fn destination_for(&self, repo: &str) -> PathBuf {
+        self.dest
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(format!("{}-copy", repo)))
+    }
+
+    /// Applies the [`OverwritePolicy`] to `dest` if it is a non-empty
+    /// directory.
+    pub fn prepare_destination(&self, dest: &Path) -> Result<()> {
+        if !dest.exists() {
+            return Ok(());
+        }
+
+        let not_empty =
+            dest.read_dir().map(|mut rd| rd.next().is_some()).unwrap_or(false);
+
+        match self.overwrite {
+            OverwritePolicy::Fail if not_empty => {
+                Err(GitripperError::DestinationExists(dest.to_path_buf()))
+            },
+            OverwritePolicy::Replace => {
+                remove_dir_all(dest).map_err(|e| GitripperError::Cleanup {
+                    path:   dest.to_path_buf(),
+                    source: e,
+                })
+            },
+            OverwritePolicy::Backup if not_empty => {
+                let secs = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                let mut backup = dest.as_os_str().to_owned();
+                backup.push(format!(".bak-{}", secs));
+                let backup = PathBuf::from(backup);
+                fs::rename(dest, &backup)?;
+                self.emit(RipEvent::BackedUp(backup));
+                Ok(())
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Picks the provider for `url` and parses it.
+    pub fn locate(
+        &self,
+        url: &str,
+    ) -> Result<(Arc<dyn Provider>, RepoLocation)> {
+        find_provider(&self.providers, url)
+            .ok_or_else(|| GitripperError::InvalidUrl(url.trim().to_string()))
+    }
+
+    pub fn default_branch(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+    ) -> Result<String> {
+        if self.offline {
+            return self
+                .cache
+                .as_ref()
+                .and_then(|c| c.default_branch(provider, location))
+                .ok_or_else(|| GitripperError::NotCached {
+                    location:  location.to_string(),
+                    reference: "HEAD".to_string(),
+                });
+        }
+        let branch = self.with_retry(|| {
+            with_conditional_cache(self.conditional_cache(), || {
+                provider.default_branch(
+                    &self.client,
+                    location,
+                    self.token.as_deref(),
+                )
+            })
+        })?;
+        if let Some(cache) = &self.cache {
+            let _ = cache.record_default_branch(provider, location, &branch);
+        }
+        Ok(branch)
+    }
+
+    /// `location` under the names its provider knows it by now, reporting
+    /// [`RipEvent::Moved`] if it moved. A failed lookup keeps the names as
+    /// given; whatever comes next reports the problem.
+    fn follow_move(
+        &self,
+        provider: &dyn Provider,
+        location: RepoLocation,
+    ) -> RepoLocation {
+        if self.offline {
+            return location;
+        }
+        let moved = self.with_retry(|| {
+            with_conditional_cache(self.conditional_cache(), || {
+                provider.moved_to(&self.client, &location, self.token())
+            })
+        });
+        match moved {
+            Ok(Some(to)) => {
+                self.emit(RipEvent::Moved {
+                    from: location.to_string(),
+                    to:   to.to_string(),
+                });
+                to
+            },
+            _ => location,
+        }
+    }
+
+    /// The repository `location` was forked from if it is a fork and
+    /// [`RipperBuilder::prefer_parent`] is set, reporting [`RipEvent::Fork`]
+    /// either way. A failed lookup keeps `location`.
+    fn follow_fork(
+        &self,
+        provider: &dyn Provider,
+        location: RepoLocation,
+    ) -> RepoLocation {
+        if self.offline {
+            return location;
+        }
+        let parent = self.with_retry(|| {
+            with_conditional_cache(self.conditional_cache(), || {
+                provider.fork_parent(&self.client, &location, self.token())
+            })
+        });
+        match parent {
+            Ok(Some(parent)) => {
+                self.emit(RipEvent::Fork {
+                    fork:         location.to_string(),
+                    parent:       parent.to_string(),
+                    using_parent: self.prefer_parent,
+                });
+                if self.prefer_parent {
+                    parent
+                } else {
+                    location
+                }
+            },
+            _ => location,
+        }
+    }
+
+    /// Why the repository at `url` may have come back as not found, to hint
+    /// at a fix. `None` if its provider can't tell, or asking failed too.
+    pub fn diagnose_not_found(&self, url: &str) -> Option<NotFoundCause> {
+        if self.offline {
+            return None;
+        }
+        let (provider, location) = self.locate(url).ok()?;
+        provider
+            .diagnose_not_found(&self.client, &location, self.token())
+            .ok()
+            .flatten()
+    }
+
+    /// Where the head of pull request `number` of `location` lives. The
+    /// returned location carries the head ref and the subpath of `location`.
+    pub fn pull_request_head(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        number: u64,
+    ) -> Result<RepoLocation> {
+        let head = self.with_retry(|| {
+            provider.pull_request_head(
+                &self.client,
+                location,
+                number,
+                self.token.as_deref(),
+            )
+        })?;
+        self.emit(RipEvent::PullRequestHead {
+            number,
+            head: format!(
+                "{}@{}",
+                head,
+                head.reference.as_deref().unwrap_or(DEFAULT_BRANCH)
+            ),
+        });
+        Ok(head)
+    }
+
+    /// Branches or tags of the repository at `url`. With `dates`, commit
+    /// dates the listing didn't include are looked up, one request each.
+    pub fn list_refs(
+        &self,
+        url: &str,
+        ref_type: RefType,
+        dates: bool,
+    ) -> Result<Vec<RemoteRef>> {
+        let (provider, location) = self.locate(url)?;
+        let mut refs = self.with_retry(|| {
+            provider.list_refs(
+                &self.client,
+                &location,
+                ref_type,
+                self.token.as_deref(),
+            )
+        })?;
+
+        if dates {
+            refs.par_iter_mut().filter(|r| r.date.is_none()).for_each(|r| {
+                match self.with_retry(|| {
+                    provider.commit_date(
+                        &self.client,
+                        &location,
+                        &r.commit,
+                        self.token.as_deref(),
+                    )
+                }) {
+                    Ok(date) => r.date = date,
+                    Err(e) => self.emit(RipEvent::Warning(format!(
+                        "could not get the date of '{}': {}",
+                        r.name, e
+                    ))),
+                }
+            });
+        }
+        Ok(refs)
+    }
+
+    /// The reference configured on the builder, with releases resolved to
+    /// their tag, and the commit it points at. Fails if the provider says
+    /// the reference doesn't exist.
+    pub fn explicit_reference(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+    ) -> Result<Option<(String, Option<String>)>> {
+        let Some(reference) = &self.reference else {
+            return Ok(None);
+        };
+
+        let reference = match self.ref_kind {
+            RefKind::Release => self.with_retry(|| {
+                provider.release_tag(
+                    &self.client,
+                    location,
+                    reference,
+                    self.token.as_deref(),
+                )
+            })?,
+            _ => reference.clone(),
+        };
+        let commit = if self.offline {
+            Some(self.cached_commit(provider, location, &reference)?)
+        } else {
+            self.with_retry(|| {
+                provider.resolve_commit(
+                    &self.client,
+                    location,
+                    &reference,
+                    self.token.as_deref(),
+                )
+            })
+            .map_err(|e| {
+                not_found_as(e, || {
+                    format!(
+                        "{} '{}' not found in {} (404).",
+                        self.ref_kind, reference, location
+                    )
+                })
+            })?
+        };
+
+        // Abbreviated SHAs are expanded so the download is unambiguous.
+        let reference = match (&commit, self.ref_kind) {
+            (Some(sha), RefKind::Commit) => sha.clone(),
+            _ => reference,
+        };
+        Ok(Some((reference, commit)))
+    }
+
+    /// The configured reference, or the repository's default branch, falling
+    /// back to [`DEFAULT_BRANCH`] if it can't be determined.
+    pub fn resolve_reference(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+    ) -> String {
+        if let Some(r) = &self.reference {
+            return r.clone();
+        }
+
+        match self.default_branch(provider, location) {
+            Ok(b) => {
+                self.emit(RipEvent::DefaultBranch(b.clone()));
+                b
+            },
+            Err(e) => {
+                self.emit(RipEvent::Warning(format!(
+                    "could not determine default branch: {}. Using '{}'.",
+                    e, DEFAULT_BRANCH
+                )));
+                DEFAULT_BRANCH.to_string()
+            },
+        }
+    }
+
+    pub fn download(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        reference: &str,
+        dest_dir: &Path,
+    ) -> Result<PathBuf> {
+        let path = self
+            .with_retry(|| {
+                download_archive_ranged(
+                    &self.client,
+                    provider,
+                    location,
+                    reference,
+                    self.token.as_deref(),
+                    dest_dir,
+                    self.extract_options.progress.as_ref(),
+                    self.download_threads,
+                )
+            })
+            .or_else(|e| {
+                self.download_fallback(
+                    provider, location, reference, dest_dir, e,
+                )
+            })?;
+        self.emit(RipEvent::Downloaded(path.clone()));
+        Ok(path)
+    }
+
+    /// Downloads the [`Provider::fallback_archive_url`] if `err` means the
+    /// API could not be reached at all, returning `err` otherwise.
+    fn download_fallback(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        reference: &str,
+        dest_dir: &Path,
+        err: GitripperError,
+    ) -> Result<PathBuf> {
+        let unreachable = matches!(
+            &err,
+            GitripperError::Http(e) if e.is_connect() || e.is_timeout()
+        );
+        let Some(url) = provider
+            .fallback_archive_url(location, reference)
+            .filter(|_| unreachable)
+        else {
+            return Err(err);
+        };
+        self.emit(RipEvent::Warning(format!(
+            "{}; downloading {} instead",
+            err, url
+        )));
+        self.with_retry(|| {
+            download_fallback_archive(
+                &self.client,
+                &url,
+                location,
+                reference,
+                dest_dir,
+                self.extract_options.progress.as_ref(),
+            )
+        })
+    }
+
+    /// The full commit SHA `reference` points at. Full SHAs are returned
+    /// as-is without a request.
+    pub fn resolve_commit(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        reference: &str,
+    ) -> Result<Option<String>> {
+        if is_commit_sha(reference) {
+            return Ok(Some(reference.to_string()));
+        }
+        // A file on disk belongs to no commit the provider knows of.
+        if provider.local_archive(location).is_some() {
+            return Ok(None);
+        }
+        if self.offline {
+            return self.cached_commit(provider, location, reference).map(Some);
+        }
+
+        self.with_retry(|| {
+            with_conditional_cache(self.conditional_cache(), || {
+                provider.resolve_commit(
+                    &self.client,
+                    location,
+                    reference,
+                    self.token.as_deref(),
+                )
+            })
+        })
+    }
+
+    /// Cache for conditional requests, unless [`RipperBuilder::refresh`]
+    /// asks to fetch everything anew.
+    fn conditional_cache(&self) -> Option<&Cache> {
+        self.cache.as_ref().filter(|_| !self.refresh)
+    }
+
+    /// Commit `reference` resolved to when it was last ripped, for offline
+    /// mode.
+    fn cached_commit(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        reference: &str,
+    ) -> Result<String> {
+        if is_commit_sha(reference) {
+            return Ok(reference.to_string());
+        }
+        self.cache
+            .as_ref()
+            .and_then(|c| c.lookup_ref(provider, location, reference))
+            .ok_or_else(|| GitripperError::NotCached {
+                location:  location.to_string(),
+                reference: reference.to_string(),
+            })
+    }
+
+    /// Like [`Ripper::resolve_commit`], but a failure only costs the cache
+    /// lookup.
+    fn cache_key(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        reference: &str,
+    ) -> Option<String> {
+        match self.resolve_commit(provider, location, reference) {
+            Ok(sha) => sha,
+            Err(e) => {
+                self.emit(RipEvent::Warning(format!(
+                    "could not resolve '{}' to a commit, skipping cache: {}",
+                    reference, e
+                )));
+                None
+            },
+        }
+    }
+
+    /// Downloads the archive into `work_dir` and extracts it into `dest`,
+    /// returning the path of the downloaded archive.
+    fn fetch_and_extract(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        reference: &str,
+        work_dir: &Path,
+        dest: &Path,
+        options: &ExtractOptions,
+    ) -> Result<PathBuf> {
+        if !provider.serves_archives() {
+            let path =
+                self.clone_snapshot(provider, location, reference, work_dir)?;
+            let _slot = self.scheduler.extraction();
+            self.stage(location, Stage::Extracting);
+            extract_archive_with(&path, dest, options)
+                .map_err(GitripperError::Extraction)?;
+            return Ok(path);
+        }
+        if let Some(path) = provider.local_archive(location) {
+            let _slot = self.scheduler.extraction();
+            self.stage(location, Stage::Extracting);
+            extract_archive_with(&path, dest, options)
+                .map_err(GitripperError::Extraction)?;
+            return Ok(path);
+        }
+        #[cfg(feature = "async")]
+        if self.overlap {
+            let streamed = self.with_retry(|| {
+                crate::async_pipeline::download_and_extract_blocking(
+                    &self.http,
+                    &self.user_agent,
+                    provider,
+                    location,
+                    reference,
+                    self.token.as_deref(),
+                    work_dir,
+                    dest,
+                    options,
+                )
+            });
+            let path = match streamed {
+                Ok(path) => path,
+                Err(e) => {
+                    let path = self.download_fallback(
+                        provider, location, reference, work_dir, e,
+                    )?;
+                    extract_archive_with(&path, dest, options)
+                        .map_err(GitripperError::Extraction)?;
+                    path
+                },
+            };
+            self.emit(RipEvent::Downloaded(path.clone()));
+            return Ok(path);
+        }
+
+        self.stage(location, Stage::Queued);
+        let zip_path = {
+            let _slot = self.scheduler.download();
+            self.stage(location, Stage::Downloading);
+            self.download(provider, location, reference, work_dir)?
+        };
+        let _held = self.scheduler.hold(fs::metadata(&zip_path)?.len());
+        self.stage(location, Stage::Downloaded);
+        let _slot = self.scheduler.extraction();
+        self.stage(location, Stage::Extracting);
+        extract_archive_with(&zip_path, dest, options)
+            .map_err(GitripperError::Extraction)?;
+        Ok(zip_path)
+    }
+
+    /// Shallow-clones `location` at `reference` into `work_dir` and packs
+    /// its files into a tarball there, as the archive of a provider that
+    /// doesn't serve any.
+    fn clone_snapshot(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        reference: &str,
+        work_dir: &Path,
+    ) -> Result<PathBuf> {
+        check_git_reference(reference)?;
+        let url = provider.clone_url(location);
+        let credentials =
+            self.token.as_deref().map(|t| provider.git_credentials(t));
+        self.emit(RipEvent::Cloning(url.clone()));
+        // libgit2 can't fetch shallowly from a local repository.
+        let depth = (!url.starts_with("file://")).then_some(1);
+
+        let checkout = work_dir.join("checkout");
+        clone_with_history(
+            &url,
+            &checkout,
+            reference,
+            depth,
+            credentials.as_ref().map(|(u, p)| (u.as_str(), p.as_str())),
+        )
+        .map_err(GitripperError::Clone)?;
+        remove_dir_all(checkout.join(".git"))?;
+
+        let path = work_dir.join("snapshot.tar");
+        let mut tar = tar::Builder::new(File::create(&path)?);
+        tar.follow_symlinks(false);
+        tar.append_dir_all(&location.repo, &checkout)?;
+        tar.into_inner()?.sync_all()?;
+        remove_dir_all(&checkout)?;
+        self.emit(RipEvent::Downloaded(path.clone()));
+        Ok(path)
+    }
+
+    /// Reports `stage` of `location` when [`RipperBuilder::batch_limits`]
+    /// can make it wait.
+    fn stage(&self, location: &RepoLocation, stage: Stage) {
+        if self.scheduler.is_limited() {
+            self.emit(RipEvent::Stage {
+                repo: location.to_string(),
+                stage,
+            });
+        }
+    }
+
+    /// Clones `location` at `reference` into `dest` with its history,
+    /// returning the checked out commit.
+    pub fn clone_history(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        reference: &str,
+        dest: &Path,
+    ) -> Result<String> {
+        if self.subpath.is_some() || location.path.is_some() {
+            self.emit(RipEvent::Warning(
+                "subdirectory selection is ignored when keeping history"
+                    .to_string(),
+            ));
+        }
+
+        check_git_reference(reference)?;
+        let url = provider.clone_url(location);
+        let credentials =
+            self.token.as_deref().map(|t| provider.git_credentials(t));
+        self.emit(RipEvent::Cloning(url.clone()));
+
+        let commit = clone_with_history(
+            &url,
+            dest,
+            reference,
+            self.history_depth,
+            credentials.as_ref().map(|(u, p)| (u.as_str(), p.as_str())),
+        )
+        .map_err(GitripperError::Clone)?;
+
+        if let Some(r) = &self.init.remote {
+            set_origin(dest, Some(r)).map_err(GitripperError::Clone)?;
+            self.emit(RipEvent::RemoteSet(r.clone()));
+        } else if self.detach_remote {
+            set_origin(dest, None).map_err(GitripperError::Clone)?;
+        }
+        Ok(commit.to_string())
+    }
+
+    pub fn initialize(&self, dest: &Path) -> Result<()> {
+        self.initialize_with(dest, &self.init)
+    }
+
+    fn initialize_with(
+        &self,
+        dest: &Path,
+        options: &InitOptions,
+    ) -> Result<()> {
+        self.emit(RipEvent::Initializing);
+        initialize_repo_with(dest, options).map_err(GitripperError::GitInit)?;
+
+        if let Some(r) = &options.remote {
+            self.emit(RipEvent::RemoteSet(r.clone()));
+        }
+        Ok(())
+    }
+
+    /// Runs the whole pipeline for `url`: download, extract, strip embedded
+    /// `.git` directories and commit the result into a new repository.
+    ///
+    /// A ref or path embedded in a `/tree/` URL is used unless one was set on
+    /// the builder.
+    pub fn rip(&self, url: &str) -> Result<RipReport> {
+        let (provider, location) = self.locate(url)?;
+        let dest = match (&self.dest, &self.layout) {
+            (None, Some(layout)) => Destination::Layout(Path::new("."), layout),
+            _ => Destination::Fixed(self.destination_for(&location.repo)),
+        };
+        self.in_pool(|| self.rip_located(url, provider, location, dest))
+    }
+
+    /// Like [`Ripper::rip`], but into `dest` regardless of the configured
+    /// destination.
+    pub fn rip_to(&self, url: &str, dest: &Path) -> Result<RipReport> {
+        let (provider, location) = self.locate(url)?;
+        self.in_pool(|| {
+            self.rip_located(
+                url,
+                provider,
+                location,
+                Destination::Fixed(dest.to_path_buf()),
+            )
+        })
+    }
+
+    /// Rips each of `refs` of `url` into one new repository, as a branch of
+    /// the same name holding a single snapshot commit. The branches share no
+    /// history; `HEAD` is left on the first. Returns a report per ref.
+    pub fn rip_refs(
+        &self,
+        url: &str,
+        refs: &[String],
+    ) -> Result<Vec<RipReport>> {
+        if self.reference.is_some()
+            || self.pull.is_some()
+            || self.keep_history
+            || self.no_init
+            || self.vendor
+            || self.bare
+        {
+            return Err(GitripperError::Unsupported(
+                "ripping several refs only makes a new repository of snapshots"
+                    .to_string(),
+            ));
+        }
+        let Some((first, rest)) = refs.split_first() else {
+            return Err(GitripperError::Unsupported(
+                "no refs to rip".to_string(),
+            ));
+        };
+        let (provider, location) = self.locate(url)?;
+        let dest = self.destination_for(&location.repo);
+        let at = |reference: &String| RepoLocation {
+            reference: Some(reference.clone()),
+            ..location.clone()
+        };
+
+        self.in_pool(|| {
+            let report = self.rip_located(
+                url,
+                provider.clone(),
+                at(first),
+                Destination::Branch(dest.clone()),
+            )?;
+            let mut reports = vec![report];
+            let staging = tempfile::tempdir()?;
+            for (i, reference) in rest.iter().enumerate() {
+                let report = self.rip_located(
+                    url,
+                    provider.clone(),
+                    at(reference),
+                    Destination::Staged(staging.path().join(i.to_string())),
+                )?;
+                let mut init = self.init.clone();
+                init.message =
+                    init.message.map(|m| expand_message(&m, &report));
+                commit_branch(&dest, &report.reference, &report.dest, &init)
+                    .map_err(GitripperError::GitInit)?;
+                reports.push(RipReport {
+                    dest: dest.clone(),
+                    ..report
+                });
+            }
+            Ok(reports)
+        })
+    }
+
+    /// Rips `url` into `target` instead of a directory. The archive is
+    /// downloaded into memory and extracted entry by entry, without creating
+    /// a repository or running hooks. Returns how many entries were stored.
+    pub fn rip_into(
+        &self,
+        url: &str,
+        target: &mut dyn ExtractTarget,
+    ) -> Result<u64> {
+        let (provider, location) = self.locate(url)?;
+        let (location, reference, _) =
+            self.snapshot_of(provider.as_ref(), location)?;
+
+        let mut options = self.extract_options.clone();
+        options.subpath = self
+            .subpath
+            .clone()
+            .or_else(|| location.path.as_ref().map(PathBuf::from));
+        let skipped = SkipRecorder::install(&mut options);
+
+        let archive = self.with_retry(|| {
+            let mut archive = Vec::new();
+            download_archive_to(
+                &self.client,
+                provider.as_ref(),
+                &location,
+                &reference,
+                self.token(),
+                &mut archive,
+                options.progress.as_ref(),
+            )?;
+            Ok(archive)
+        })?;
+        let written = extract_zip_into(Cursor::new(archive), target, &options)
+            .map_err(GitripperError::Extraction)?;
+        self.report_skipped(&skipped);
+        Ok(written)
+    }
+
+    /// Contents of the file at `path` in the snapshot `url` names, fetched
+    /// on its own without downloading the archive. `path` is relative to
+    /// the subdirectory of `url`, if it has one.
+    pub fn cat(&self, url: &str, path: &str) -> Result<Vec<u8>> {
+        let (provider, location) = self.locate(url)?;
+        let (location, reference, _) =
+            self.snapshot_of(provider.as_ref(), location)?;
+        let dir = self
+            .subpath
+            .as_ref()
+            .map(|p| p.to_string_lossy().into_owned())
+            .or_else(|| location.path.clone());
+        let path = match dir {
+            Some(dir) => format!("{}/{}", dir.trim_end_matches('/'), path),
+            None => path.to_string(),
+        };
+        self.with_retry(|| {
+            provider.fetch_file(
+                &self.client,
+                &location,
+                &reference,
+                &path,
+                self.token(),
+            )
+        })
+    }
+
+    /// Rips `url` into a new archive at `path` rather than a directory, in
+    /// the [`ArchiveFormat`] its extension names. Entries are filtered and
+    /// root-stripped as for a normal rip. Returns how many were written.
+    pub fn rip_to_archive(&self, url: &str, path: &Path) -> Result<u64> {
+        let format = ArchiveFormat::from_path(path).ok_or_else(|| {
+            GitripperError::Unsupported(format!(
+                "unknown archive format for '{}'; use .zip, .tar, .tar.gz or \
+                 .tar.zst",
+                path.display()
+            ))
+        })?;
+        if path.exists() && self.overwrite == OverwritePolicy::Fail {
+            return Err(GitripperError::DestinationExists(path.to_path_buf()));
+        }
+        let mut target = ArchiveTarget::create(path, format)?;
+        let written = self.rip_into(url, &mut target).and_then(|n| {
+            target.finish().map_err(GitripperError::Extraction)?;
+            Ok(n)
+        });
+        if written.is_err() {
+            let _ = fs::remove_file(path);
+        }
+        written
+    }
+
+    /// The location to rip for `location`, following a pull request, and
+    /// the reference to take a snapshot of, with its commit if that is
+    /// already known.
+    fn snapshot_of(
+        &self,
+        provider: &dyn Provider,
+        location: RepoLocation,
+    ) -> Result<(RepoLocation, String, Option<String>)> {
+        let location = self.follow_move(provider, location);
+        let location = match self.pull.or(location.pull) {
+            Some(number) => {
+                self.pull_request_head(provider, &location, number)?
+            },
+            None => self.follow_fork(provider, location),
+        };
+
+        let (reference, commit) =
+            match self.explicit_reference(provider, &location)? {
+                Some(explicit) => explicit,
+                None => match &location.reference {
+                    Some(r) => (r.clone(), None),
+                    None => (self.resolve_reference(provider, &location), None),
+                },
+            };
+        Ok((location, reference, commit))
+    }
+
+    /// Rips `url` into `root` joined with `layout` rendered for it.
+    pub(crate) fn rip_under(
+        &self,
+        url: &str,
+        root: &Path,
+        layout: &DestLayout,
+    ) -> Result<RipReport> {
+        let (provider, location) = self.locate(url)?;
+        self.in_pool(|| {
+            self.rip_located(
+                url,
+                provider,
+                location,
+                Destination::Layout(root, layout),
+            )
+        })
+    }
+
+    /// Description, homepage and topics of the repository at `url`.
+    pub fn metadata(&self, url: &str) -> Result<RepoMetadata> {
+        let (provider, location) = self.locate(url)?;
+        let location = self.follow_move(provider.as_ref(), location);
+        self.with_retry(|| {
+            provider.metadata(&self.client, &location, self.token.as_deref())
+        })
+    }
+
+    /// Brings a snapshot rip at `dest` up to date with its upstream ref,
+    /// committing the new snapshot on top of the existing history.
+    ///
+    /// What to fetch is read from the [`Provenance`] stored by the original
+    /// rip; a reference or subpath set on the builder overrides it.
+    pub fn update(&self, dest: &Path) -> Result<UpdateReport> {
+        // The staging directory goes with the failed update.
+        self.in_pool(|| self.update_in_place(dest))
+            .map_err(|e| self.stop.error().unwrap_or(e))
+    }
+
+    fn update_in_place(&self, dest: &Path) -> Result<UpdateReport> {
+        let started = SystemTime::now();
+        let previous = Provenance::read(dest)?
+            .ok_or_else(|| GitripperError::NotARip(dest.to_path_buf()))?;
+        if is_bare_repo(dest) {
+            return Err(GitripperError::Unsupported(
+                "updating a bare rip".to_string(),
+            ));
+        }
+        let (provider, location, report) = self.upstream_of(&previous, dest)?;
+
+        if report.commit.is_some() && report.commit == previous.commit {
+            return Ok(UpdateReport {
+                previous: previous.commit,
+                report,
+                updated: false,
+            });
+        }
+
+        // Stage next to `dest` so the swap below is a cheap rename, and a
+        // failed download leaves the rip untouched.
+        let staging = tempfile::Builder::new()
+            .prefix(".gitripper-update-")
+            .tempdir_in(parent_dir(dest))?;
+        let subpath = self.fetch_upstream(
+            provider.as_ref(),
+            &location,
+            &report,
+            &previous,
+            staging.path(),
+        )?;
+        self.expand_nested(staging.path())?;
+        let had_manifest = dest.join(MANIFEST_FILE).exists();
+        replace_contents(dest, staging.path())?;
+        self.run_hooks(&report)?;
+        self.attribute(&report)?;
+        self.check_secrets(dest)?;
+        self.dedupe(dest)?;
+        self.attest(
+            &report,
+            &RipSource {
+                url: &previous.url,
+                subpath: subpath.as_deref(),
+                started,
+            },
+        )?;
+        if self.manifest || had_manifest {
+            write_manifest(dest)?;
+        }
+        self.normalize(dest)?;
+
+        if dest.join(".git").is_dir() {
+            let mut init = self.init.clone();
+            let template = init
+                .message
+                .take()
+                .unwrap_or_else(|| DEFAULT_UPDATE_MESSAGE.to_string());
+            init.message = Some(expand_message(&template, &report));
+            commit_snapshot(dest, &init).map_err(GitripperError::GitInit)?;
+
+            if let Some(sha) = &report.commit
+                && has_upstream_remote(dest)
+            {
+                set_upstream_base(dest, sha)
+                    .map_err(GitripperError::GitInit)?;
+            }
+        }
+        self.record_provenance(&previous.url, &report, subpath.as_deref());
+
+        Ok(UpdateReport {
+            previous: previous.commit,
+            report,
+            updated: true,
+        })
+    }
+
+    /// Where the rip described by `previous` now points upstream. The
+    /// returned report has `dest` as destination and the current commit of
+    /// the tracked ref, or of the reference set on the builder.
+    pub(crate) fn upstream_of(
+        &self,
+        previous: &Provenance,
+        dest: &Path,
+    ) -> Result<(Arc<dyn Provider>, RepoLocation, RipReport)> {
+        let (provider, location) = self.locate(&previous.url)?;
+        let location = self.follow_move(provider.as_ref(), location);
+        let location = match self.pull.or(location.pull) {
+            Some(number) => {
+                self.pull_request_head(provider.as_ref(), &location, number)?
+            },
+            None => location,
+        };
+        let (reference, commit) = match self
+            .explicit_reference(provider.as_ref(), &location)?
+        {
+            Some(explicit) => explicit,
+            None => {
+                let reference = previous.reference.clone();
+                let commit = self
+                    .resolve_commit(provider.as_ref(), &location, &reference)
+                    .unwrap_or_else(|e| {
+                        self.emit(RipEvent::Warning(format!(
+                            "could not resolve '{}' to a commit: {}",
+                            reference, e
+                        )));
+                        None
+                    });
+                (reference, commit)
+            },
+        };
+        let report = RipReport {
+            provider: provider.name(),
+            owner: location.owner.clone(),
+            repo: location.repo.clone(),
+            reference,
+            commit,
+            dest: dest.to_path_buf(),
+        };
+        Ok((provider, location, report))
+    }
+
+    /// Extracts the upstream snapshot found by [`Ripper::upstream_of`] into
+    /// `dir`, limited to the same subdirectory as the original rip unless the
+    /// builder sets another. Returns that subdirectory.
+    pub(crate) fn fetch_upstream(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        report: &RipReport,
+        previous: &Provenance,
+        dir: &Path,
+    ) -> Result<Option<PathBuf>> {
+        let mut options = self.extract_options.clone();
+        options.subpath = self
+            .subpath
+            .clone()
+            .or_else(|| previous.path.as_ref().map(PathBuf::from));
+
+        self.fetch_snapshot(
+            provider,
+            location,
+            &report.reference,
+            report.commit.as_deref(),
+            dir,
+            &options,
+        )?;
+        Ok(options.subpath)
+    }
+
+    /// Downloads the files of `location` at `reference` that match
+    /// `patterns` one by one into `dest`, honoring the subpath and filters of
+    /// `options`.
+    fn fetch_sparse(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        reference: &str,
+        patterns: &GlobSet,
+        dest: &Path,
+        options: &ExtractOptions,
+    ) -> Result<()> {
+        let tree = self.with_retry(|| {
+            provider.list_tree(&self.client, location, reference, self.token())
+        })?;
+
+        let wanted: Vec<(PathBuf, TreeEntry)> = tree
+            .into_iter()
+            .filter_map(|entry| {
+                let path = Path::new(&entry.path);
+                let rel_path = match &options.subpath {
+                    Some(sub) => path.strip_prefix(sub).ok()?,
+                    None => path,
+                }
+                .to_path_buf();
+                if let Some(exclude) = &options.exclude
+                    && rel_path.ancestors().any(|p| exclude.is_match(p))
+                {
+                    return None;
+                }
+                if options.skip_existing
+                    && dest.join(&rel_path).symlink_metadata().is_ok()
+                {
+                    return None;
+                }
+                patterns.is_match(&rel_path).then_some((rel_path, entry))
+            })
+            .collect();
+        if wanted.is_empty() {
+            return Err(GitripperError::Extraction(anyhow!(
+                "No files match the sparse patterns."
+            )));
+        }
+
+        let count = wanted.len();
+        let progress = options.progress.as_ref();
+        progress.extract_started(count as u64);
+        fs::create_dir_all(dest)?;
+        let wanted = match CollisionGuard::for_dir(dest, options.collisions)? {
+            Some(mut guard) => wanted
+                .into_iter()
+                .map(|(rel_path, entry)| {
+                    let rel_path = guard
+                        .check(&rel_path)
+                        .map_err(GitripperError::Extraction)?;
+                    Ok((rel_path, entry))
+                })
+                .collect::<Result<Vec<_>>>()?,
+            None => wanted,
+        };
+        wanted.into_par_iter().try_for_each(
+            |(rel_path, entry)| -> Result<()> {
+                let size = entry.size.unwrap_or_default();
+                if options.skips(&rel_path, size, &[]) {
+                    progress.extract_progress(1);
+                    return Ok(());
+                }
+                let data = self.with_retry(|| {
+                    provider.fetch_blob(
+                        &self.client,
+                        location,
+                        &entry.sha,
+                        self.token(),
+                    )
+                })?;
+                let regular = !is_symlink(Some(entry.mode));
+                if regular && options.skips(&rel_path, data.len() as u64, &data)
+                {
+                    progress.extract_progress(1);
+                    return Ok(());
+                }
+                let data = if regular { options.with_eol(data) } else { data };
+                let entry = options
+                    .hook(MemEntry {
+                        rel_path,
+                        is_dir: false,
+                        _data_size: data.len() as u64,
+                        unix_mode: Some(entry.mode),
+                        _file_idx: 0,
+                        data,
+                    })
+                    .map_err(GitripperError::Extraction)?;
+                let Some(entry) = entry else {
+                    progress.extract_progress(1);
+                    return Ok(());
+                };
+                write_entry(&entry, dest)
+                    .map_err(GitripperError::Extraction)?;
+                progress.extract_progress(1);
+                Ok(())
+            },
+        )?;
+        progress.extract_finished();
+
+        self.emit(RipEvent::SparseFetched(count));
+        Ok(())
+    }
+
+    /// Extracts the snapshot of `location` at `reference` into `dest`, from
+    /// the cache when `commit` is cached, strips embedded `.git`
+    /// directories and, if enabled, resolves LFS pointers.
+    fn fetch_snapshot(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        reference: &str,
+        commit: Option<&str>,
+        dest: &Path,
+        options: &ExtractOptions,
+    ) -> Result<()> {
+        let mut options = options.clone();
+        let skipped = SkipRecorder::install(&mut options);
+        let options = &options;
+        let cached = match (&self.cache, commit) {
+            (Some(cache), Some(sha)) if !self.refresh => {
+                cache.get(provider, location, sha)
+            },
+            _ => None,
+        };
+
+        let tmp = tempdir()?;
+        if let Some(patterns) = &self.sparse {
+            self.fetch_sparse(
+                provider, location, reference, patterns, dest, options,
+            )?;
+        } else if let Some(path) = cached {
+            self.emit(RipEvent::CacheHit(path.clone()));
+            let _slot = self.scheduler.extraction();
+            self.stage(location, Stage::Extracting);
+            extract_archive_with(&path, dest, options)
+                .map_err(GitripperError::Extraction)?;
+            self.write_receipt(
+                provider, location, reference, commit, &path, dest,
+            )?;
+            self.remember_ref(provider, location, reference, commit);
+        } else if self.offline && provider.local_archive(location).is_none() {
+            return Err(GitripperError::NotCached {
+                location:  location.to_string(),
+                reference: reference.to_string(),
+            });
+        } else {
+            // Archives of known commits are cached by SHA instead.
+            let conditional =
+                self.conditional_cache().filter(|_| commit.is_none());
+            let archive = with_conditional_cache(conditional, || {
+                self.fetch_and_extract(
+                    provider,
+                    location,
+                    reference,
+                    tmp.path(),
+                    dest,
+                    options,
+                )
+            })?;
+            if self.verify_commit {
+                self.check_archive_commit(&archive, reference, commit)?;
+            }
+            self.write_receipt(
+                provider, location, reference, commit, &archive, dest,
+            )?;
+
+            if let (Some(cache), Some(sha)) = (&self.cache, commit)
+                && let Err(e) = cache.put(provider, location, sha, &archive)
+            {
+                self.emit(RipEvent::Warning(format!(
+                    "could not cache archive: {}",
+                    e
+                )));
+            }
+            self.remember_ref(provider, location, reference, commit);
+        }
+        self.report_skipped(&skipped);
+
+        // Leftover `.git` directories only cost disk space, so failing to
+        // remove one is not fatal.
+        for res in remove_embedded_git(dest) {
+            match res {
+                Ok(p) => self.emit(RipEvent::RemovedEmbeddedGit(p)),
+                Err(e) => self.emit(RipEvent::Warning(e.to_string())),
+            }
+        }
+
+        if self.lfs {
+            let pointers = find_lfs_pointers(dest)?;
+            if !pointers.is_empty() {
+                let fetched = self.with_retry(|| {
+                    fetch_lfs_objects(
+                        &self.client,
+                        provider,
+                        location,
+                        self.token(),
+                        &pointers,
+                    )
+                })?;
+                self.emit(RipEvent::LfsObjects(fetched));
+            }
+        }
+        Ok(())
+    }
+
+    /// Records in the cache that `reference` resolved to `commit`, so an
+    /// [offline](RipperBuilder::offline) rip of it can find the archive.
+    fn remember_ref(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        reference: &str,
+        commit: Option<&str>,
+    ) {
+        if let (Some(cache), Some(sha)) = (&self.cache, commit)
+            && !is_commit_sha(reference)
+            && !self.offline
+        {
+            let _ = cache.record_ref(provider, location, reference, sha);
+        }
+    }
+
+    /// Writes the [`Receipt`] of `archive` into `dest` for
+    /// [`RipperBuilder::receipt`].
+    fn write_receipt(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        reference: &str,
+        commit: Option<&str>,
+        archive: &Path,
+        dest: &Path,
+    ) -> Result<()> {
+        if self.receipt {
+            let url = provider.archive_url(location, reference);
+            Receipt::for_archive(archive, &url, reference, commit)?
+                .write(dest)?;
+        }
+        Ok(())
+    }
+
+    /// Fails unless the archive at `path` was made from `expected`, for
+    /// [`RipperBuilder::verify_commit`]. An archive that doesn't name its
+    /// commit can't be checked and only warns.
+    fn check_archive_commit(
+        &self,
+        path: &Path,
+        reference: &str,
+        expected: Option<&str>,
+    ) -> Result<()> {
+        let expected = expected.ok_or_else(|| {
+            GitripperError::Unsupported(format!(
+                "could not resolve '{}' to a commit to verify the archive",
+                reference
+            ))
+        })?;
+        match archive_commit(path).map_err(GitripperError::Extraction)? {
+            Some(actual)
+                if !expected.to_ascii_lowercase().starts_with(&actual) =>
+            {
+                Err(GitripperError::CommitMismatch {
+                    reference: reference.to_string(),
+                    expected: expected.to_string(),
+                    actual,
+                })
+            },
+            Some(_) => Ok(()),
+            None => {
+                self.emit(RipEvent::Warning(format!(
+                    "the archive of '{}' doesn't name its commit; not verified",
+                    reference
+                )));
+                Ok(())
+            },
+        }
+    }
+
+    /// Adds the `upstream` remote for [`RipperBuilder::upstream_remote`],
+    /// resolving the ripped commit if it isn't known yet.
+    fn add_upstream(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        report: &RipReport,
+    ) -> Result<()> {
+        let commit = match &report.commit {
+            Some(sha) => Some(sha.clone()),
+            None => self
+                .resolve_commit(provider, location, &report.reference)
+                .unwrap_or_else(|e| {
+                    self.emit(RipEvent::Warning(format!(
+                        "could not resolve '{}' to a commit: {}",
+                        report.reference, e
+                    )));
+                    None
+                }),
+        };
+
+        let url = provider.clone_url(location);
+        add_upstream_remote(&report.dest, &url, commit.as_deref())
+            .map_err(GitripperError::GitInit)?;
+        self.emit(RipEvent::UpstreamSet(url));
+        Ok(())
+    }
+
+    /// Turns the snapshot at `report.dest` into a bare repository there.
+    fn make_bare(&self, report: &RipReport) -> Result<()> {
+        let staging = tempfile::Builder::new()
+            .prefix(".gitripper-bare-")
+            .tempdir_in(parent_dir(&report.dest))?;
+        let tree = staging.path().join("tree");
+        fs::rename(&report.dest, &tree)?;
+
+        let mut init = self.init.clone();
+        init.message = init.message.map(|m| expand_message(&m, report));
+        self.emit(RipEvent::Initializing);
+        initialize_bare_repo(&report.dest, &tree, &init)
+            .map_err(GitripperError::GitInit)?;
+        if let Some(r) = &init.remote {
+            self.emit(RipEvent::RemoteSet(r.clone()));
+        }
+        Ok(())
+    }
+
+    /// Stores the upstream [`RepoMetadata`] of `location` in the git config
+    /// of the repository at `dest`, or else in a file in it, if
+    /// [`RipperBuilder::import_metadata`] is set.
+    fn import_metadata(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        dest: &Path,
+        in_repo: bool,
+    ) -> Result<()> {
+        if !self.import_metadata || self.offline {
+            return Ok(());
+        }
+        let metadata = match self.with_retry(|| {
+            provider.metadata(&self.client, location, self.token.as_deref())
+        }) {
+            Ok(metadata) if metadata.is_empty() => return Ok(()),
+            Ok(metadata) => metadata,
+            Err(e) => {
+                self.emit(RipEvent::Warning(format!(
+                    "could not fetch repository metadata: {}",
+                    e
+                )));
+                return Ok(());
+            },
+        };
+        if in_repo {
+            set_config(dest, &metadata.config_entries())
+                .map_err(GitripperError::GitInit)?;
+        } else {
+            write_metadata(dest, &metadata)?;
+        }
+        Ok(())
+    }
+
+    /// Tags the snapshot commit at `report.dest` as set with
+    /// [`RipperBuilder::tag_as`] or [`RipperBuilder::copy_tag`].
+    fn tag_snapshot(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        report: &RipReport,
+    ) -> Result<()> {
+        let name = match &self.tag_as {
+            Some(name) => name.clone(),
+            None if self.copy_tag => report.reference.clone(),
+            None => return Ok(()),
+        };
+        let commit = match &report.commit {
+            Some(sha) => Some(sha.clone()),
+            None => {
+                self.resolve_commit(provider, location, &report.reference)?
+            },
+        };
+        let mut message = format!(
+            "Upstream {} {} of {}",
+            self.ref_kind.to_string().to_lowercase(),
+            report.reference,
+            location
+        );
+        if let Some(sha) = commit {
+            message.push_str(&format!(" at {}", sha));
+        }
+        tag_head(&report.dest, &name, &message, &self.init)
+            .map_err(GitripperError::GitInit)?;
+        self.emit(RipEvent::Tagged(name));
+        Ok(())
+    }
+
+    fn commit_vendored(&self, report: &RipReport) -> Result<()> {
+        let mut init = self.init.clone();
+        let template = init
+            .message
+            .take()
+            .unwrap_or_else(|| DEFAULT_VENDOR_MESSAGE.to_string());
+        init.message = Some(expand_message(&template, report));
+
+        self.emit(RipEvent::Vendoring(report.dest.clone()));
+        commit_vendored(&report.dest, &init)
+            .map_err(GitripperError::GitInit)?;
+        Ok(())
+    }
+
+    /// Instantiates the snapshot at `report.dest` as a template, if enabled.
+    fn instantiate_template(&self, report: &RipReport) -> Result<()> {
+        let Some(defined) = &self.template else {
+            return Ok(());
+        };
+        let manifest =
+            TemplateManifest::read(&report.dest)?.unwrap_or_default();
+
+        let mut values = BTreeMap::new();
+        if let Some(name) = report.dest.file_name().and_then(|n| n.to_str()) {
+            values.insert("project_name".to_string(), name.to_string());
+        }
+        if let Some(author) = &self.init.author_name {
+            values.insert("author".to_string(), author.clone());
+        }
+        values.extend(defined.clone());
+
+        for var in &manifest.variables {
+            if defined.contains_key(&var.name) {
+                continue;
+            }
+            let builtin = values.get(&var.name).cloned();
+            let var = TemplateVariable {
+                default: builtin.or_else(|| var.default.clone()),
+                ..var.clone()
+            };
+            let value = self
+                .template_prompt
+                .as_ref()
+                .and_then(|p| (p.0)(&var))
+                .or(var.default)
+                .ok_or_else(|| {
+                    GitripperError::Template(format!(
+                        "no value for variable '{}'",
+                        var.name
+                    ))
+                })?;
+            values.insert(var.name, value);
+        }
+
+        let changed = render_template(&report.dest, &manifest, &values)?;
+        self.emit(RipEvent::TemplateRendered(changed));
+        Ok(())
+    }
+
+    /// Records the license of the rip in its [`crate::NOTICES_FILE`] for
+    /// [`RipperBuilder::attribution`].
+    fn attribute(&self, report: &RipReport) -> Result<()> {
+        if self.attribution {
+            let license = detect_license(&report.dest)?;
+            write_attribution(&report.dest, report, &license)?;
+            self.emit(RipEvent::Attributed(license.spdx));
+        }
+        Ok(())
+    }
+
+    /// Writes the provenance statement of the rip for
+    /// [`RipperBuilder::attest`] and signs it if asked to.
+    fn attest(&self, report: &RipReport, source: &RipSource) -> Result<()> {
+        let Some(builder) = &self.attest else {
+            return Ok(());
+        };
+        let statement = provenance_statement(report, source, builder)?;
+        let path = write_attestation(&report.dest, &statement)?;
+        let bundle = if self.sign_attestation {
+            Some(sign_attestation(&path)?)
+        } else {
+            None
+        };
+        self.emit(RipEvent::Attested { path, bundle });
+        Ok(())
+    }
+
+    /// Scans `dest` for [`RipperBuilder::scan_secrets`]. Runs after the hooks
+    /// so that files they add are scanned too.
+    fn check_secrets(&self, dest: &Path) -> Result<()> {
+        if self.scan_secrets == SecretPolicy::Off {
+            return Ok(());
+        }
+        let findings = scan_secrets(dest)?;
+        let count = findings.len();
+        for finding in findings {
+            self.emit(RipEvent::SecretFound(finding));
+        }
+        if count > 0 && self.scan_secrets == SecretPolicy::Abort {
+            return Err(GitripperError::SecretsFound {
+                count,
+                dest: dest.to_path_buf(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Reports the files extraction left out, if any.
+    fn report_skipped(&self, recorder: &SkipRecorder) {
+        let skipped = std::mem::take(&mut *recorder.skipped.lock().unwrap());
+        if !skipped.is_empty() {
+            self.emit(RipEvent::SkippedFiles(skipped));
+        }
+    }
+
+    /// Expands the archives under `dest` for
+    /// [`RipperBuilder::extract_nested`]. Those that fail are only warned
+    /// about.
+    fn expand_nested(&self, dest: &Path) -> Result<()> {
+        if self.nested_depth == 0 {
+            return Ok(());
+        }
+        let report =
+            extract_nested(dest, self.nested_depth, &self.extract_options)?;
+        for (archive, reason) in report.failed {
+            self.emit(RipEvent::Warning(format!(
+                "could not expand {}: {}",
+                archive.display(),
+                reason
+            )));
+        }
+        if !report.extracted.is_empty() {
+            self.emit(RipEvent::NestedExtracted(report.extracted));
+        }
+        Ok(())
+    }
+
+    /// Hardlinks duplicate files under `dest` for [`RipperBuilder::dedupe`].
+    fn dedupe(&self, dest: &Path) -> Result<()> {
+        if self.dedupe {
+            let report = dedupe_tree(dest)?;
+            if report.files > 0 {
+                self.emit(RipEvent::Deduplicated(report));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `err` is down to `location` having no commits yet. GitHub
+    /// serves no archive for such a repository, and only says why when asked
+    /// for a commit.
+    fn is_empty_upstream(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        reference: &str,
+        err: &GitripperError,
+    ) -> bool {
+        match err {
+            GitripperError::EmptyRepository(_) => true,
+            GitripperError::HttpStatus { status: 404, .. } => matches!(
+                self.resolve_commit(provider, location, reference),
+                Err(GitripperError::EmptyRepository(_))
+            ),
+            _ => false,
+        }
+    }
+
+    /// Starts the rip of a repository without commits in `dest`, with just
+    /// the [`RipperBuilder::empty_readme`] if there is one.
+    fn create_empty(&self, location: &RepoLocation, dest: &Path) -> Result<()> {
+        self.emit(RipEvent::Warning(format!(
+            "{} is empty; there is nothing to download",
+            location
+        )));
+        fs::create_dir_all(dest)?;
+        if let Some(readme) = &self.empty_readme {
+            fs::write(dest.join("README.md"), readme)?;
+        }
+        Ok(())
+    }
+
+    /// Normalizes metadata for [`RipperBuilder::deterministic`].
+    fn normalize(&self, dest: &Path) -> Result<()> {
+        if self.extract_options.deterministic {
+            normalize_tree(dest)?;
+        }
+        Ok(())
+    }
+
+    fn run_hooks(&self, report: &RipReport) -> Result<()> {
+        for hook in &self.post_hooks {
+            self.emit(RipEvent::RunningHook(hook.clone()));
+            run_hook(hook, report)?;
+        }
+        Ok(())
+    }
+
+    /// Asks [`RipperBuilder::confirm_size_above`] whether to rip `location`
+    /// if it is over the limit. The provider's figure is preferred, since
+    /// archives generated on the fly have no known size.
+    fn check_size(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        reference: &str,
+    ) -> Result<()> {
+        let Some(limit) = &self.size_limit else {
+            return Ok(());
+        };
+        if self.offline || provider.local_archive(location).is_some() {
+            return Ok(());
+        }
+
+        let token = self.token();
+        let size = match provider.repo_size(&self.client, location, token) {
+            Ok(Some(size)) => Some(size),
+            _ if provider.serves_archives() => {
+                archive_size(&self.client, provider, location, reference, token)
+                    .ok()
+                    .flatten()
+            },
+            _ => None,
+        };
+        match size {
+            Some(size)
+                if size > limit.bytes && !(limit.confirm)(location, size) =>
+            {
+                Err(GitripperError::TooLarge {
+                    location: location.to_string(),
+                    size,
+                    limit: limit.bytes,
+                })
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// The commit of an earlier rip at `dest` of the same repository,
+    /// reference and subdirectory, if upstream is still at it. `commit` is
+    /// the upstream commit when already known.
+    fn unchanged_commit(
+        &self,
+        provider: &dyn Provider,
+        location: &RepoLocation,
+        reference: &str,
+        commit: Option<&str>,
+        dest: &Path,
+    ) -> Option<String> {
+        let previous = Provenance::read(dest).ok().flatten()?;
+        let subpath =
+            self.subpath.as_deref().or(location.path.as_deref().map(Path::new));
+        if previous.provider != provider.name()
+            || previous.owner != location.owner
+            || previous.repo != location.repo
+            || previous.reference != reference
+            || previous.path.as_deref().map(Path::new) != subpath
+        {
+            return None;
+        }
+
+        let upstream = match commit {
+            Some(sha) => sha.to_string(),
+            None => {
+                self.resolve_commit(provider, location, reference).ok()??
+            },
+        };
+        (previous.commit? == upstream).then_some(upstream)
+    }
+
+    /// Stores the [`Provenance`] of a finished rip. Only [`Ripper::update`]
+    /// needs it, so failing is just a warning.
+    fn record_provenance(
+        &self,
+        url: &str,
+        report: &RipReport,
+        subpath: Option<&Path>,
+    ) {
+        let provenance = Provenance::from_report(url, report, subpath);
+        if let Err(e) = provenance.write(&report.dest) {
+            self.emit(RipEvent::Warning(format!(
+                "could not record rip metadata: {}",
+                e
+            )));
+        }
+    }
+
+    fn rip_located(
+        &self,
+        url: &str,
+        provider: Arc<dyn Provider>,
+        location: RepoLocation,
+        dest: Destination,
+    ) -> Result<RipReport> {
+        let mut created = None;
+        let result =
+            self.rip_located_into(url, provider, location, dest, &mut created);
+        let Err(e) = result else { return result };
+        let Some(stop) = self.stop.error() else {
+            return Err(e);
+        };
+        // Only what this rip made; an existing destination may hold work of
+        // its own.
+        if let Some(dest) = created {
+            let _ = remove_dir_all(dest);
+        }
+        Err(stop)
+    }
+
+    /// Does the work of [`Ripper::rip_located`], setting `created` to the
+    /// destination once it is known if it didn't exist before.
+    fn rip_located_into(
+        &self,
+        url: &str,
+        provider: Arc<dyn Provider>,
+        location: RepoLocation,
+        dest: Destination,
+        created: &mut Option<PathBuf>,
+    ) -> Result<RipReport> {
+        let started = SystemTime::now();
+        let branch = matches!(dest, Destination::Branch(_));
+        let staged = matches!(dest, Destination::Staged(_));
+        // Check a known destination before any network traffic; a layout
+        // may need the reference first.
+        if let Destination::Fixed(dest) | Destination::Branch(dest) = &dest {
+            self.prepare_destination(dest)?;
+        }
+        // libgit2 does the work itself unless it was left out of the build.
+        if !self.no_init && cfg!(not(feature = "git2")) {
+            check_git_installed()?;
+        }
+
+        let (location, reference, commit) =
+            self.snapshot_of(provider.as_ref(), location)?;
+        let dest = match dest {
+            Destination::Fixed(dest)
+            | Destination::Branch(dest)
+            | Destination::Staged(dest) => dest,
+            Destination::Layout(root, layout) => {
+                let dest = root.join(layout.render(
+                    provider.name(),
+                    &location,
+                    &reference,
+                ));
+                if let Some(commit) = self.unchanged_commit(
+                    provider.as_ref(),
+                    &location,
+                    &reference,
+                    commit.as_deref(),
+                    &dest,
+                ) {
+                    self.emit(RipEvent::UpToDate {
+                        dest:      dest.clone(),
+                        reference: reference.clone(),
+                        commit:    commit.clone(),
+                    });
+                    return Ok(RipReport {
+                        provider: provider.name(),
+                        owner: location.owner,
+                        repo: location.repo,
+                        reference,
+                        commit: Some(commit),
+                        dest,
+                    });
+                }
+                self.prepare_destination(&dest)?;
+                dest
+            },
+        };
+        self.check_size(provider.as_ref(), &location, &reference)?;
+        if !dest.exists() {
+            *created = Some(dest.clone());
+        }
+        if self.vendor && !self.no_init {
+            enclosing_repo(&dest).map_err(GitripperError::GitInit)?;
+        }
+        if self.keep_history {
+            let commit = self.clone_history(
+                provider.as_ref(),
+                &location,
+                &reference,
+                &dest,
+            )?;
+            let report = RipReport {
+                provider: provider.name(),
+                owner: location.owner.clone(),
+                repo: location.repo.clone(),
+                reference,
+                commit: Some(commit),
+                dest,
+            };
+            self.run_hooks(&report)?;
+            self.import_metadata(
+                provider.as_ref(),
+                &location,
+                &report.dest,
+                true,
+            )?;
+            self.check_secrets(&report.dest)?;
+            return Ok(report);
+        }
+
+        let mut options = self.extract_options.clone();
+        options.subpath = self
+            .subpath
+            .clone()
+            .or_else(|| location.path.as_ref().map(PathBuf::from));
+
+        let commit = match (&self.cache, commit) {
+            (_, Some(sha)) => Ok(Some(sha)),
+            _ if self.verify_commit || self.offline => {
+                self.resolve_commit(provider.as_ref(), &location, &reference)
+            },
+            (Some(_), None) => {
+                Ok(self.cache_key(provider.as_ref(), &location, &reference))
+            },
+            (None, None) => Ok(None),
+        };
+        let fetched = commit.and_then(|commit| {
+            self.fetch_snapshot(
+                provider.as_ref(),
+                &location,
+                &reference,
+                commit.as_deref(),
+                &dest,
+                &options,
+            )?;
+            Ok(commit)
+        });
+        let commit = match fetched {
+            Err(e)
+                if self.is_empty_upstream(
+                    provider.as_ref(),
+                    &location,
+                    &reference,
+                    &e,
+                ) =>
+            {
+                self.create_empty(&location, &dest)?;
+                None
+            },
+            fetched => fetched?,
+        };
+        self.expand_nested(&dest)?;
+
+        let report = RipReport {
+            provider: provider.name(),
+            owner: location.owner.clone(),
+            repo: location.repo.clone(),
+            reference,
+            commit,
+            dest,
+        };
+        self.instantiate_template(&report)?;
+        self.run_hooks(&report)?;
+        self.attribute(&report)?;
+        if self.no_init || self.vendor {
+            self.import_metadata(
+                provider.as_ref(),
+                &location,
+                &report.dest,
+                false,
+            )?;
+        }
+        self.check_secrets(&report.dest)?;
+        self.dedupe(&report.dest)?;
+        self.attest(
+            &report,
+            &RipSource {
+                url,
+                subpath: options.subpath.as_deref(),
+                started,
+            },
+        )?;
+        if self.manifest {
+            write_manifest(&report.dest)?;
+        }
+        self.normalize(&report.dest)?;
+        if staged {
+            return Ok(report);
+        }
+
+        if self.bare {
+            self.make_bare(&report)?;
+            self.tag_snapshot(provider.as_ref(), &location, &report)?;
+            self.import_metadata(
+                provider.as_ref(),
+                &location,
+                &report.dest,
+                true,
+            )?;
+            if self.upstream_remote {
+                self.add_upstream(provider.as_ref(), &location, &report)?;
+            }
+            self.record_provenance(url, &report, options.subpath.as_deref());
+            return Ok(report);
+        }
+
+        if self.vendor {
+            // The metadata lives in the vendored directory, so it has to be
+            // there before the commit.
+            self.record_provenance(url, &report, options.subpath.as_deref());
+            if !self.no_init {
+                self.commit_vendored(&report)?;
+            }
+            return Ok(report);
+        }
+
+        if !self.no_init {
+            let mut init = self.init.clone();
+            init.message = init.message.map(|m| expand_message(&m, &report));
+            if branch {
+                init.initial_branch = Some(report.reference.clone());
+            }
+            if report.dest.join(".git").is_dir() {
+                // Merged into an existing repository; commit on top of it.
+                commit_snapshot(&report.dest, &init)
+                    .map_err(GitripperError::GitInit)?;
+            } else {
+                self.initialize_with(&report.dest, &init)?;
+            }
+            self.tag_snapshot(provider.as_ref(), &location, &report)?;
+            self.import_metadata(
+                provider.as_ref(),
+                &location,
+                &report.dest,
+                true,
+            )?;
+            if self.upstream_remote {
+                self.add_upstream(provider.as_ref(), &location, &report)?;
+            }
+        }
+        self.record_provenance(url, &report, options.subpath.as_deref());
+
+        Ok(report)
+    }
+}
+
+/// Where [`Ripper::rip_located`] puts a rip.
+enum Destination<'a> {
+    Fixed(PathBuf),
+    /// A [`DestLayout`] under a root, rendered once the reference is known.
+    Layout(&'a Path, &'a DestLayout),
+    /// Like `Fixed`, on a branch named after the reference.
+    Branch(PathBuf),
+    /// Only the snapshot, without a repository or rip metadata, for
+    /// [`Ripper::rip_refs`] to commit elsewhere.
+    Staged(PathBuf),
+}
+
+/// Passes progress on to the configured sink and keeps the files extraction
+/// left out, for [`Ripper::report_skipped`].
+struct SkipRecorder {
+    inner:   Arc<dyn ProgressSink>,
+    skipped: Mutex<Vec<SkippedFile>>,
+}
+
+impl SkipRecorder {
+    /// Puts a recorder in front of the progress sink of `options`.
+    fn install(options: &mut ExtractOptions) -> Arc<Self> {
+        let recorder = Arc::new(Self {
+            inner:   options.progress.clone(),
+            skipped: Mutex::new(Vec::new()),
+        });
+        options.progress = recorder.clone();
+        recorder
+    }
+}
+
+impl ProgressSink for SkipRecorder {
+    fn download_started(&self, total_bytes: Option<u64>) {
+        self.inner.download_started(total_bytes);
+    }
+
+    fn download_progress(&self, bytes: u64) {
+        self.inner.download_progress(bytes);
+    }
+
+    fn download_finished(&self) { self.inner.download_finished(); }
+
+    fn extract_started(&self, total_entries: u64) {
+        self.inner.extract_started(total_entries);
+    }
+
+    fn extract_progress(&self, entries: u64) {
+        self.inner.extract_progress(entries);
+    }
+
+    fn extract_finished(&self) { self.inner.extract_finished(); }
+
+    fn file_skipped(&self, file: &SkippedFile) {
+        self.skipped.lock().unwrap().push(file.clone());
+        self.inner.file_skipped(file);
+    }
+
+    fn is_cancelled(&self) -> bool { self.inner.is_cancelled() }
+}
+
+/// Replaces everything in `dest` except the repository and the rip metadata
+/// with the contents of `staging`.
+fn replace_contents(dest: &Path, staging: &Path) -> Result<()> {
+    for entry in fs::read_dir(dest)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if name == ".git" || name == PROVENANCE_FILE_NO_GIT {
+            continue;
+        }
+
+        let path = entry.path();
+        let removed = if entry.file_type()?.is_dir() {
+            remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        removed.map_err(|e| GitripperError::Cleanup { path, source: e })?;
+    }
+
+    for entry in fs::read_dir(staging)? {
+        let entry = entry?;
+        fs::rename(entry.path(), dest.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+/// Directory `path` is in, `.` for a bare file name.
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    }
+}
+
+/// Refuses a `reference` git could take for an option, such as
+/// `--upload-pack=<command>`, before it is fetched.
+fn check_git_reference(reference: &str) -> Result<()> {
+    if reference.starts_with('-') {
+        return Err(GitripperError::Clone(anyhow!(
+            "'{}' isn't a valid ref",
+            reference
+        )));
+    }
+    Ok(())
+}
+
+/// Fills the placeholders documented on [`RipperBuilder::message`].
+fn expand_message(template: &str, report: &RipReport) -> String {
+    let commit = report.commit.as_deref().unwrap_or(&report.reference);
+    template
+        .replace("{owner}", &report.owner)
+        .replace("{repo}", &report.repo)
+        .replace("{ref}", &report.reference)
+        .replace("{commit}", commit)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, write};
+
+    use super::*;
+    use crate::GitHub;
+
+    #[test]
+    fn test_destination_defaults_to_repo_copy() {
+        let ripper = Ripper::builder().build().unwrap();
+        assert_eq!(ripper.destination_for("foo"), PathBuf::from("foo-copy"));
+
+        let ripper = Ripper::builder().dest("elsewhere").build().unwrap();
+        assert_eq!(ripper.destination_for("foo"), PathBuf::from("elsewhere"));
+    }
+
+    #[test]
+    fn test_prepare_destination_respects_force() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dest = temp_dir.path().join("dest");
+        create_dir_all(&dest).unwrap();
+        write(dest.join("file.txt"), "x").unwrap();
+
+        let ripper = Ripper::builder().build().unwrap();
+        assert!(matches!(
+            ripper.prepare_destination(&dest),
+            Err(GitripperError::DestinationExists(_))
+        ));
+
+        let ripper = Ripper::builder().force(true).build().unwrap();
+        ripper.prepare_destination(&dest).unwrap();
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn test_prepare_destination_backup_moves_dest_aside() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dest = temp_dir.path().join("dest");
+        create_dir_all(&dest).unwrap();
+        write(dest.join("file.txt"), "x").unwrap();
+
+        let ripper = Ripper::builder()
+            .overwrite(OverwritePolicy::Merge)
+            .build()
+            .unwrap();
+        ripper.prepare_destination(&dest).unwrap();
+        assert!(dest.join("file.txt").exists());
+
+        let ripper = Ripper::builder()
+            .overwrite(OverwritePolicy::Backup)
+            .build()
+            .unwrap();
+        ripper.prepare_destination(&dest).unwrap();
+        assert!(!dest.exists());
+        let backup = fs::read_dir(temp_dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .find(|n| n.starts_with("dest.bak-"))
+            .unwrap();
+        assert!(temp_dir.path().join(backup).join("file.txt").exists());
+    }
+
+    #[test]
+    fn test_jobs_bounds_pool() {
+        let ripper = Ripper::builder().jobs(2).build().unwrap();
+        assert_eq!(ripper.in_pool(rayon::current_num_threads), 2);
+
+        let ripper = Ripper::builder().jobs(0).build().unwrap();
+        assert_eq!(ripper.in_pool(rayon::current_num_threads), 1);
+    }
+
+    #[test]
+    fn test_offline_rips_from_cache() {
+        use std::io::Write;
+
+        use zip::{write::SimpleFileOptions, ZipWriter};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let archive = temp_dir.path().join("a.zip");
+        let mut zip = ZipWriter::new(fs::File::create(&archive).unwrap());
+        zip.start_file("o-r-abc1234/README.md", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"cached").unwrap();
+        zip.finish().unwrap();
+
+        let cache = Cache::new(temp_dir.path().join("cache"));
+        let github = GitHub::default();
+        let location = RepoLocation::new("o", "r");
+        let sha = "abc1234abc1234abc1234abc1234abc1234abc12";
+        cache.put(&github, &location, sha, &archive).unwrap();
+        cache.record_ref(&github, &location, "main", sha).unwrap();
+        cache.record_default_branch(&github, &location, "main").unwrap();
+
+        let ripper = Ripper::builder()
+            .cache(cache)
+            .offline(true)
+            .no_init(true)
+            .build()
+            .unwrap();
+        let dest = temp_dir.path().join("out");
+        let report = ripper.rip_to("https://github.com/o/r", &dest).unwrap();
+        assert_eq!(report.reference, "main");
+        assert_eq!(report.commit.as_deref(), Some(sha));
+        assert_eq!(
+            fs::read_to_string(dest.join("README.md")).unwrap(),
+            "cached"
+        );
+
+        assert!(matches!(
+            ripper.rip_to(
+                "https://github.com/o/r/tree/dev",
+                &temp_dir.path().join("dev")
+            ),
+            Err(GitripperError::NotCached { .. })
+        ));
+    }
+
+    #[test]
+    fn test_explicit_reference_skips_lookup() {
+        let ripper = Ripper::builder().reference("dev").build().unwrap();
+        let location = RepoLocation::new("o", "r");
+        let github = GitHub::default();
+        assert_eq!(ripper.resolve_reference(&github, &location), "dev");
+    }
+
+    #[test]
+    fn test_locate_picks_provider_by_host() {
+        let ripper = Ripper::builder().build().unwrap();
+        let (provider, location) =
+            ripper.locate("https://gitlab.com/group/project").unwrap();
+        assert_eq!(provider.name(), "GitLab");
+        assert_eq!(location.owner, "group");
+    }
+
+    #[test]
+    fn test_expand_message_placeholders() {
+        let report = RipReport {
+            provider:  "GitHub",
+            owner:     "o".to_string(),
+            repo:      "foo".to_string(),
+            reference: "main".to_string(),
+            commit:    Some("abc123".to_string()),
+            dest:      PathBuf::from("foo-copy"),
+        };
+        assert_eq!(
+            expand_message("Import of {repo}@{commit} ({ref})", &report),
+            "Import of foo@abc123 (main)"
+        );
+    }
+
+    #[test]
+    fn test_replace_contents_keeps_repository() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dest = temp_dir.path().join("dest");
+        let staging = temp_dir.path().join("staging");
+        create_dir_all(dest.join(".git")).unwrap();
+        create_dir_all(dest.join("old")).unwrap();
+        write(dest.join("old/file.txt"), "x").unwrap();
+        create_dir_all(staging.join("new")).unwrap();
+        write(staging.join("new/file.txt"), "y").unwrap();
+
+        replace_contents(&dest, &staging).unwrap();
+
+        assert!(dest.join(".git").is_dir());
+        assert!(!dest.join("old").exists());
+        assert!(dest.join("new/file.txt").exists());
+    }
+
+    #[test]
+    fn test_update_requires_metadata() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let ripper = Ripper::builder().build().unwrap();
+        assert!(matches!(
+            ripper.update(temp_dir.path()),
+            Err(GitripperError::NotARip(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_url_is_reported() {
+        let ripper = Ripper::builder().build().unwrap();
+        assert!(matches!(
+            ripper.rip("example.com/user/repo"),
+            Err(GitripperError::InvalidUrl(_))
+        ));
+    }
+}
@@ -0,0 +1,191 @@
+use std::{
+    fmt,
+    sync::{Condvar, Mutex},
+};
+
+/// Limits on the work [`crate::Ripper::rip_batch`] does at once, across all
+/// the repositories in flight. `None` leaves a resource unbounded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchLimits {
+    /// Archives downloaded at the same time.
+    pub downloads:       Option<usize>,
+    /// Archives extracted at the same time.
+    pub extractions:     Option<usize>,
+    /// Bytes of downloaded archives not yet extracted. New downloads wait
+    /// while the total is over it.
+    pub in_flight_bytes: Option<u64>,
+}
+
+/// Where a repository of a batch is, as reported by
+/// [`crate::RipEvent::Stage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Waiting for a download slot or for in-flight bytes to drop.
+    Queued,
+    Downloading,
+    /// Downloaded and waiting for an extraction slot.
+    Downloaded,
+    Extracting,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Queued => "queued",
+            Self::Downloading => "downloading",
+            Self::Downloaded => "waiting to extract",
+            Self::Extracting => "extracting",
+        })
+    }
+}
+
+/// Hands out download and extraction slots and in-flight bytes within
+/// [`BatchLimits`], blocking until they are free.
+#[derive(Debug, Default)]
+pub(crate) struct Scheduler {
+    limits:  BatchLimits,
+    state:   Mutex<Usage>,
+    changed: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct Usage {
+    downloads:   usize,
+    extractions: usize,
+    bytes:       u64,
+}
+
+/// What a [`Permit`] holds until it is dropped.
+#[derive(Debug, Clone, Copy)]
+enum Held {
+    Download,
+    Extraction,
+    Bytes(u64),
+}
+
+/// A slot or a share of the in-flight bytes, given back on drop.
+#[must_use]
+#[derive(Debug)]
+pub(crate) struct Permit<'a> {
+    scheduler: &'a Scheduler,
+    held:      Held,
+}
+
+impl Scheduler {
+    pub(crate) fn new(limits: BatchLimits) -> Self {
+        Self {
+            limits,
+            ..Default::default()
+        }
+    }
+
+    /// Whether anything is limited, so waiting is possible at all.
+    pub(crate) fn is_limited(&self) -> bool {
+        self.limits != BatchLimits::default()
+    }
+
+    /// Waits for a download slot, and for the in-flight bytes to drop under
+    /// their limit.
+    pub(crate) fn download(&self) -> Permit<'_> {
+        let limits = self.limits;
+        self.acquire(Held::Download, |u| {
+            limits.downloads.is_none_or(|max| u.downloads < max)
+                && limits.in_flight_bytes.is_none_or(|max| u.bytes < max)
+        })
+    }
+
+    /// Counts `bytes` of a downloaded archive as in flight. Never waits; the
+    /// limit holds back the next download instead.
+    pub(crate) fn hold(&self, bytes: u64) -> Permit<'_> {
+        self.acquire(Held::Bytes(bytes), |_| true)
+    }
+
+    /// Waits for an extraction slot.
+    pub(crate) fn extraction(&self) -> Permit<'_> {
+        let limits = self.limits;
+        self.acquire(Held::Extraction, |u| {
+            limits.extractions.is_none_or(|max| u.extractions < max)
+        })
+    }
+
+    fn acquire(
+        &self,
+        held: Held,
+        ready: impl Fn(&Usage) -> bool,
+    ) -> Permit<'_> {
+        let mut usage = self
+            .changed
+            .wait_while(self.state.lock().unwrap(), |u| !ready(u))
+            .unwrap();
+        match held {
+            Held::Download => usage.downloads += 1,
+            Held::Extraction => usage.extractions += 1,
+            Held::Bytes(n) => usage.bytes += n,
+        }
+        Permit {
+            scheduler: self,
+            held,
+        }
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        let mut usage = self.scheduler.state.lock().unwrap();
+        match self.held {
+            Held::Download => usage.downloads -= 1,
+            Held::Extraction => usage.extractions -= 1,
+            Held::Bytes(n) => usage.bytes -= n,
+        }
+        self.scheduler.changed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+        time::Duration,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_limits_concurrent_downloads() {
+        let scheduler = Scheduler::new(BatchLimits {
+            downloads: Some(2),
+            ..Default::default()
+        });
+        let (active, peak) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        thread::scope(|s| {
+            for _ in 0..6 {
+                s.spawn(|| {
+                    let _slot = scheduler.download();
+                    let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(10));
+                    active.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_in_flight_bytes_hold_back_downloads() {
+        let scheduler = Scheduler::new(BatchLimits {
+            in_flight_bytes: Some(100),
+            ..Default::default()
+        });
+        let held = scheduler.hold(150);
+        thread::scope(|s| {
+            let waiter = s.spawn(|| drop(scheduler.download()));
+            thread::sleep(Duration::from_millis(20));
+            assert!(!waiter.is_finished());
+            drop(held);
+            waiter.join().unwrap();
+        });
+        assert_eq!(scheduler.state.lock().unwrap().bytes, 0);
+    }
+}
@@ -0,0 +1,364 @@
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, ResponseBox};
+use url::Url;
+
+use crate::{
+    ArchiveFormat, CancellationToken, DirectArchive, GitRemote, GitripperError,
+    Job, JobQueue, JobStatus, ProgressSink, Provider, Result, RipperBuilder,
+};
+
+/// Largest request body [`Server`] reads.
+const MAX_BODY_BYTES: u64 = 64 * 1024;
+/// How often [`Server::run`] checks whether it should stop.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const FORMATS: [ArchiveFormat; 4] = [
+    ArchiveFormat::Zip,
+    ArchiveFormat::Tar,
+    ArchiveFormat::TarGz,
+    ArchiveFormat::TarZst,
+];
+
+/// Makes the builder of the ripper for a job's URL. The server adds the
+/// job's branch and progress before building it.
+pub type BuilderFactory = dyn Fn(&str) -> Result<RipperBuilder> + Send + Sync;
+
+/// Records the progress of one job.
+struct JobProgress {
+    queue: Arc<JobQueue>,
+    id:    u64,
+}
+
+impl ProgressSink for JobProgress {
+    fn download_started(&self, total_bytes: Option<u64>) {
+        self.queue.update(self.id, |job| job.total_bytes = total_bytes);
+    }
+
+    fn download_progress(&self, bytes: u64) {
+        self.queue.update(self.id, |job| job.downloaded_bytes += bytes);
+    }
+
+    fn extract_started(&self, total_entries: u64) {
+        self.queue
+            .update(self.id, |job| job.total_entries = Some(total_entries));
+    }
+
+    fn extract_progress(&self, entries: u64) {
+        self.queue.update(self.id, |job| job.extracted_entries += entries);
+    }
+}
+
+/// The archive job `job` rips into, named after its id.
+fn archive_path(root: &Path, job: &Job) -> PathBuf {
+    job.dest.clone().unwrap_or_else(|| {
+        root.join(format!("{}.{}", job.id, ArchiveFormat::TarGz.extension()))
+    })
+}
+
+fn job_json(job: &Job) -> Value {
+    let format = job.dest.as_deref().and_then(ArchiveFormat::from_path);
+    json!({
+        "id": job.id,
+        "url": job.url,
+        "branch": job.branch,
+        "format": format.unwrap_or(ArchiveFormat::TarGz).extension(),
+        "status": job.status.name(),
+        "progress": {
+            "downloaded_bytes": job.downloaded_bytes,
+            "total_bytes": job.total_bytes,
+            "extracted_entries": job.extracted_entries,
+            "total_entries": job.total_entries,
+        },
+        "error": job.error,
+        "archive": (job.status == JobStatus::Done)
+            .then(|| format!("/rips/{}/archive", job.id)),
+    })
+}
+
+/// An HTTP API that queues rips into archives, reports on them and serves
+/// the archives:
+///
+/// - `POST /rips` with `{"url": ..., "branch": ..., "format": ...}` queues a
+///   rip, answering `202` with the job. Only `url` is required; `format` is
+///   `zip`, `tar`, `tar.gz` (the default) or `tar.zst`.
+/// - `GET /rips` lists the jobs and `GET /rips/<id>` reports one: its `status`
+///   (`queued`, `running`, `done` or `failed`), `progress` and any `error`.
+/// - `GET /rips/<id>/archive` downloads the archive of a finished job.
+///
+/// Local paths, `file://` URLs and, unless [allowed](Server::allow_any_host),
+/// direct links to archives and plain git remotes are refused with a `400`,
+/// so clients can't have the server package its own files, fetch whatever
+/// it can reach or connect over ssh with its keys. There is no authentication,
+/// so only listen where every client may rip.
+pub struct Server {
+    http:           tiny_http::Server,
+    root:           PathBuf,
+    queue:          Arc<JobQueue>,
+    factory:        Arc<BuilderFactory>,
+    workers:        Vec<JoinHandle<()>>,
+    allow_any_host: bool,
+}
+
+impl Server {
+    /// Listens on `addr` and starts `workers` threads ripping the jobs of
+    /// `queue`, and those queued through the API, into `root` as
+    /// `<id>.<format>`, with rippers from `factory`.
+    pub fn bind(
+        addr: &str,
+        root: impl Into<PathBuf>,
+        queue: JobQueue,
+        workers: usize,
+        factory: impl Fn(&str) -> Result<RipperBuilder> + Send + Sync + 'static,
+    ) -> Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(&root)?;
+        let http = tiny_http::Server::http(addr)
+            .map_err(|e| GitripperError::Io(io::Error::other(e)))?;
+
+        let queue = Arc::new(queue);
+        let factory: Arc<BuilderFactory> = Arc::new(factory);
+        let workers = (0..workers.max(1))
+            .map(|_| {
+                let (queue, factory) = (queue.clone(), factory.clone());
+                let root = root.clone();
+                thread::spawn(move || work(&queue, &root, factory.as_ref()))
+            })
+            .collect();
+        Ok(Self {
+            http,
+            root,
+            queue,
+            factory,
+            workers,
+            allow_any_host: false,
+        })
+    }
+
+    /// Lets clients queue direct links to archives and plain git remotes,
+    /// on any host the server can reach over `http(s)`, `git` or `ssh`.
+    pub fn allow_any_host(mut self, allow: bool) -> Self {
+        self.allow_any_host = allow;
+        self
+    }
+
+    /// Address the server listens on, useful after binding port `0`.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.http.server_addr().to_ip()
+    }
+
+    /// Answers requests until `cancel` is cancelled. Running jobs are left
+    /// to the rippers' own cancellation.
+    pub fn run(&self, cancel: &CancellationToken) -> Result<()> {
+        while !cancel.is_cancelled() {
+            if let Some(request) = self.http.recv_timeout(POLL_INTERVAL)? {
+                // A client that went away isn't the server's problem.
+                let _ = self.handle(request);
+            }
+        }
+        Ok(())
+    }
+
+    fn handle(&self, mut request: Request) -> io::Result<()> {
+        let path = request.url().split('?').next().unwrap_or_default();
+        let segments: Vec<String> = path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect();
+        let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+        let response = match (request.method(), segments.as_slice()) {
+            (Method::Post, ["rips"]) => self.enqueue(&mut request),
+            (Method::Get, ["rips"]) => {
+                let jobs: Vec<Value> =
+                    self.queue.jobs().iter().map(job_json).collect();
+                json_response(200, &Value::from(jobs))
+            },
+            (Method::Get, ["rips", id]) => match self.job(id) {
+                Ok(job) => json_response(200, &job_json(&job)),
+                Err(response) => response,
+            },
+            (Method::Get, ["rips", id, "archive"]) => self.archive(id),
+            (_, ["rips", ..]) => error_response(405, "Method not allowed."),
+            _ => error_response(404, "Not found."),
+        };
+        request.respond(response)
+    }
+
+    fn enqueue(&self, request: &mut Request) -> ResponseBox {
+        let mut body = Vec::new();
+        let fields = match request
+            .as_reader()
+            .take(MAX_BODY_BYTES)
+            .read_to_end(&mut body)
+            .ok()
+            .and_then(|_| serde_json::from_slice(&body).ok())
+        {
+            Some(Value::Object(fields)) => fields,
+            _ => return error_response(400, "Expected a JSON object."),
+        };
+        let string = |name: &str| fields.get(name).and_then(Value::as_str);
+
+        let Some(url) = string("url").map(str::trim).filter(|u| !u.is_empty())
+        else {
+            return error_response(400, "'url' is required.");
+        };
+        if let Some(response) = self.refuse(url) {
+            return response;
+        }
+        let format = match string("format") {
+            None => ArchiveFormat::TarGz,
+            Some(name) => {
+                match FORMATS.into_iter().find(|f| f.extension() == name) {
+                    Some(format) => format,
+                    None => {
+                        return error_response(
+                            400,
+                            &format!("Unknown archive format '{}'.", name),
+                        );
+                    },
+                }
+            },
+        };
+
+        let branch = string("branch").map(str::to_string);
+        let pushed = self.queue.push(url, branch, |id| {
+            Some(self.root.join(format!("{}.{}", id, format.extension())))
+        });
+        match pushed {
+            Ok(job) => {
+                let location = format!("/rips/{}", job.id);
+                let response = json_response(202, &job_json(&job));
+                with_header(response, "Location", &location)
+            },
+            Err(e) => error_response(500, &e.to_string()),
+        }
+    }
+
+    fn archive(&self, id: &str) -> ResponseBox {
+        let job = match self.job(id) {
+            Ok(job) => job,
+            Err(response) => return response,
+        };
+        match job.status {
+            JobStatus::Done => {},
+            JobStatus::Failed => {
+                return error_response(409, &format!("Rip {} failed.", id));
+            },
+            JobStatus::Queued | JobStatus::Running => {
+                let message = format!("Rip {} hasn't finished.", id);
+                return error_response(409, &message);
+            },
+        }
+
+        let path = archive_path(&self.root, &job);
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        match File::open(&path) {
+            Ok(file) => {
+                let response = Response::from_file(file).boxed();
+                let disposition = format!("attachment; filename=\"{}\"", name);
+                with_header(response, "Content-Disposition", &disposition)
+            },
+            Err(e) => error_response(500, &e.to_string()),
+        }
+    }
+
+    /// The response refusing a rip of `url` if it isn't a repository the
+    /// rippers know, or one clients may not ask for.
+    fn refuse(&self, url: &str) -> Option<ResponseBox> {
+        let ripper = match (self.factory)(url).and_then(RipperBuilder::build) {
+            Ok(ripper) => ripper,
+            Err(e) => return Some(error_response(500, &e.to_string())),
+        };
+        let (provider, location) = match ripper.locate(url) {
+            Ok(found) => found,
+            Err(e) => return Some(error_response(400, &e.to_string())),
+        };
+        let on_server = Url::parse(url).is_ok_and(|u| u.scheme() == "file")
+            || provider.local_archive(&location).is_some();
+        let message = if on_server {
+            format!("'{}' is a path on the server.", url)
+        } else if provider.name() == DirectArchive.name()
+            && !self.allow_any_host
+        {
+            format!("Direct archive links like '{}' aren't allowed.", url)
+        } else if provider.name() == GitRemote.name() && !self.allow_any_host {
+            format!("Plain git remotes like '{}' aren't allowed.", url)
+        } else {
+            return None;
+        };
+        Some(error_response(400, &message))
+    }
+
+    /// The job `id`, or a `404` if there is no such job.
+    fn job(&self, id: &str) -> std::result::Result<Job, ResponseBox> {
+        id.parse()
+            .ok()
+            .and_then(|id| self.queue.get(id))
+            .ok_or_else(|| error_response(404, &format!("No rip {}.", id)))
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.queue.stop();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Rips the jobs of `queue` until it is stopped.
+fn work(queue: &Arc<JobQueue>, root: &Path, factory: &BuilderFactory) {
+    while let Some(job) = queue.next() {
+        let path = archive_path(root, &job);
+        let progress = Arc::new(JobProgress {
+            queue: queue.clone(),
+            id:    job.id,
+        });
+        let result = factory(&job.url).and_then(|builder| {
+            let builder = match &job.branch {
+                Some(branch) => builder.reference(branch),
+                None => builder,
+            };
+            builder.progress(progress).build()?.rip_to_archive(&job.url, &path)
+        });
+
+        let outcome = match result {
+            Ok(_) => Ok(Some(path)),
+            Err(e) => {
+                let _ = fs::remove_file(&path);
+                Err(e.to_string())
+            },
+        };
+        // The job's status is right in memory even if the state file
+        // couldn't be written.
+        let _ = queue.finish(job.id, outcome);
+    }
+}
+
+fn with_header(response: ResponseBox, name: &str, value: &str) -> ResponseBox {
+    match Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+        Ok(header) => response.with_header(header),
+        Err(()) => response,
+    }
+}
+
+fn json_response(status: u16, body: &Value) -> ResponseBox {
+    let response =
+        Response::from_string(body.to_string()).with_status_code(status);
+    with_header(response.boxed(), "Content-Type", "application/json")
+}
+
+fn error_response(status: u16, message: &str) -> ResponseBox {
+    json_response(status, &json!({ "error": message }))
+}
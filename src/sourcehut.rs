@@ -0,0 +1,352 @@
+#[cfg(feature = "native")]
+use reqwest::blocking::Client;
+#[cfg(feature = "native")]
+use serde_json::{json, Value};
+
+use crate::provider::{parse_repo_path, strip_host, Provider, RepoLocation};
+#[cfg(feature = "native")]
+use crate::{
+    provider::{not_found_as, rate_limit_error, RefType, RemoteRef},
+    GitripperError, Result, DEFAULT_BRANCH,
+};
+
+pub const SOURCEHUT_HOST: &str = "git.sr.ht";
+#[cfg(feature = "native")]
+const HEAD_QUERY: &str = "query($owner: String!, $name: String!) { \
+                          user(username: $owner) { repository(name: $name) { \
+                          HEAD { name } } } }";
+
+/// sourcehut's git service, whose archives are `.tar.gz` tarballs.
+///
+/// The default branch comes from the GraphQL API, which needs a personal
+/// access token; without one, and for commits and refs, gitripper reads the
+/// refs the repository advertises over git's smart HTTP protocol.
+#[derive(Debug, Clone)]
+pub struct SourceHut {
+    url:  String,
+    host: String,
+}
+
+impl Default for SourceHut {
+    fn default() -> Self {
+        Self::with_url(&format!("https://{}", SOURCEHUT_HOST))
+    }
+}
+
+impl SourceHut {
+    /// The git service of another sourcehut instance, at `url` such as
+    /// `https://git.example.org`.
+    pub fn with_url(url: &str) -> Self {
+        let url = url.trim().trim_end_matches('/');
+        let host = url.split_once("://").map_or(url, |(_, rest)| rest);
+        Self {
+            url:  url.to_string(),
+            host: host.to_string(),
+        }
+    }
+
+    /// Host repository URLs are matched against, e.g. `git.sr.ht`.
+    pub fn host(&self) -> &str { &self.host }
+}
+
+/// Parses `git.sr.ht/~user/repo` URLs, including
+/// `/tree/<ref>/item/<path>` links.
+pub fn parse_sourcehut_url(url: &str) -> Option<RepoLocation> {
+    parse_url_on(url, SOURCEHUT_HOST)
+}
+
+fn parse_url_on(url: &str, host: &str) -> Option<RepoLocation> {
+    let rest = strip_host(url.trim(), host)?;
+    let mut location = parse_repo_path(rest, "tree/")?;
+    if !location.owner.starts_with('~') {
+        return None;
+    }
+    location.path = location
+        .path
+        .as_deref()
+        .and_then(|p| p.strip_prefix("item"))
+        .map(|p| p.trim_matches('/').to_string())
+        .filter(|p| !p.is_empty());
+    Some(location)
+}
+
+/// What a repository advertises to `git fetch` over smart HTTP.
+#[cfg(feature = "native")]
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Advertisement {
+    /// Ref `HEAD` points at, such as `refs/heads/master`.
+    head: Option<String>,
+    /// Full ref names and their objects, with peeled tags as `<tag>^{}`.
+    refs: Vec<(String, String)>,
+}
+
+#[cfg(feature = "native")]
+impl Advertisement {
+    fn parse(body: &[u8]) -> Self {
+        let mut advertisement = Self::default();
+        let mut rest = body;
+        while let Some(len) = rest
+            .get(..4)
+            .and_then(|len| std::str::from_utf8(len).ok())
+            .and_then(|len| usize::from_str_radix(len, 16).ok())
+        {
+            // A flush packet.
+            if len < 4 {
+                rest = &rest[4..];
+                continue;
+            }
+            let Some(line) = rest.get(4..len) else {
+                break;
+            };
+            rest = &rest[len..];
+
+            let line = String::from_utf8_lossy(line);
+            let line = line.trim_end_matches('\n');
+            if line.starts_with('#') {
+                continue;
+            }
+            let (line, capabilities) =
+                line.split_once('\0').unwrap_or((line, ""));
+            if let Some(head) = capabilities
+                .split(' ')
+                .find_map(|c| c.strip_prefix("symref=HEAD:"))
+            {
+                advertisement.head = Some(head.to_string());
+            }
+            if let Some((object, name)) = line.split_once(' ') {
+                advertisement.refs.push((name.to_string(), object.to_string()));
+            }
+        }
+        advertisement
+    }
+
+    /// Commit of `name`, peeling annotated tags.
+    fn commit_of(&self, name: &str) -> Option<&str> {
+        let peeled = format!("{}^{{}}", name);
+        let find = |wanted: &str| {
+            self.refs.iter().find(|(n, _)| n == wanted).map(|(_, o)| o.as_str())
+        };
+        find(&peeled).or_else(|| find(name))
+    }
+}
+
+#[cfg(feature = "native")]
+impl SourceHut {
+    fn advertisement(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        token: Option<&str>,
+    ) -> Result<Advertisement> {
+        let url = format!(
+            "{}/info/refs?service=git-upload-pack",
+            self.clone_url(location)
+        );
+        let mut req = client.get(&url);
+        if let Some(t) = token {
+            let (name, value) = self.auth_header(t);
+            req = req.header(name, value);
+        }
+        let res = req.send()?;
+        let status = res.status();
+        if !status.is_success() {
+            return Err(not_found_as(
+                GitripperError::HttpStatus {
+                    status:  status.as_u16(),
+                    message: format!(
+                        "Failed to list refs of {}: {}",
+                        location, status
+                    ),
+                },
+                || format!("Repository {} not found (404).", location),
+            ));
+        }
+        Ok(Advertisement::parse(&res.bytes()?))
+    }
+
+    /// `HEAD` of `location` as the GraphQL API reports it.
+    fn graphql_head(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        token: &str,
+    ) -> Result<Option<String>> {
+        let (header, value) = self.auth_header(token);
+        let body = json!({
+            "query": HEAD_QUERY,
+            "variables": {
+                "owner": location.owner.trim_start_matches('~'),
+                "name": location.repo,
+            },
+        });
+        let res = client
+            .post(format!("{}/query", self.url))
+            .header(header, value)
+            .json(&body)
+            .send()?;
+        if let Some(e) = rate_limit_error(res.status(), res.headers()) {
+            return Err(e);
+        }
+        let status = res.status();
+        if !status.is_success() {
+            let txt = res.text().unwrap_or_default();
+            return Err(GitripperError::HttpStatus {
+                status:  status.as_u16(),
+                message: format!("Failed to get repo info: {} {}", status, txt),
+            });
+        }
+
+        let v: Value = res.json()?;
+        let repository = v.pointer("/data/user/repository");
+        if repository.is_none_or(Value::is_null) {
+            return Err(GitripperError::HttpStatus {
+                status:  404,
+                message: format!("Repository {} not found (404).", location),
+            });
+        }
+        Ok(v.pointer("/data/user/repository/HEAD/name")
+            .and_then(Value::as_str)
+            .map(str::to_string))
+    }
+}
+
+impl Provider for SourceHut {
+    fn name(&self) -> &'static str { "sourcehut" }
+
+    fn shorthand(&self) -> Option<&'static str> { Some("sourcehut") }
+
+    fn parse_url(&self, url: &str) -> Option<RepoLocation> {
+        parse_url_on(url, &self.host)
+    }
+
+    #[cfg(feature = "native")]
+    fn default_branch(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        token: Option<&str>,
+    ) -> Result<String> {
+        let head = match token {
+            Some(token) => self.graphql_head(client, location, token)?,
+            None => self.advertisement(client, location, None)?.head,
+        };
+        Ok(head
+            .as_deref()
+            .map(|h| h.strip_prefix("refs/heads/").unwrap_or(h))
+            .unwrap_or(DEFAULT_BRANCH)
+            .to_string())
+    }
+
+    #[cfg(feature = "native")]
+    fn resolve_commit(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        reference: &str,
+        token: Option<&str>,
+    ) -> Result<Option<String>> {
+        let advertisement = self.advertisement(client, location, token)?;
+        Ok(["refs/heads/", "refs/tags/"]
+            .iter()
+            .find_map(|prefix| {
+                advertisement.commit_of(&format!("{}{}", prefix, reference))
+            })
+            .map(str::to_string))
+    }
+
+    #[cfg(feature = "native")]
+    fn list_refs(
+        &self,
+        client: &Client,
+        location: &RepoLocation,
+        ref_type: RefType,
+        token: Option<&str>,
+    ) -> Result<Vec<RemoteRef>> {
+        let prefix = match ref_type {
+            RefType::Branch => "refs/heads/",
+            RefType::Tag => "refs/tags/",
+        };
+        let advertisement = self.advertisement(client, location, token)?;
+        Ok(advertisement
+            .refs
+            .iter()
+            .filter(|(name, _)| !name.ends_with("^{}"))
+            .filter_map(|(name, _)| {
+                Some(RemoteRef {
+                    name:   name.strip_prefix(prefix)?.to_string(),
+                    commit: advertisement.commit_of(name)?.to_string(),
+                    date:   None,
+                })
+            })
+            .collect())
+    }
+
+    fn archive_url(&self, location: &RepoLocation, reference: &str) -> String {
+        format!("{}/archive/{}.tar.gz", self.clone_url(location), reference)
+    }
+
+    fn clone_url(&self, location: &RepoLocation) -> String {
+        format!("{}/{}", self.url, location)
+    }
+
+    fn auth_header(&self, token: &str) -> (&'static str, String) {
+        ("Authorization", format!("Bearer {}", token))
+    }
+
+    fn token_env(&self) -> Option<&'static str> { Some("SOURCEHUT_TOKEN") }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sourcehut_url_forms() {
+        for url in [
+            "https://git.sr.ht/~user/repo",
+            "https://git.sr.ht/~user/repo/",
+            "git@git.sr.ht:~user/repo",
+        ] {
+            let loc = parse_sourcehut_url(url).unwrap();
+            assert_eq!(loc, RepoLocation::new("~user", "repo"), "{}", url);
+        }
+        assert!(parse_sourcehut_url("https://git.sr.ht/user/repo").is_none());
+
+        let url = "https://git.sr.ht/~user/repo/tree/dev/item/src/lib";
+        let loc = parse_sourcehut_url(url).unwrap();
+        assert_eq!(loc.reference.as_deref(), Some("dev"));
+        assert_eq!(loc.path.as_deref(), Some("src/lib"));
+        assert_eq!(
+            SourceHut::default().archive_url(&loc, "dev"),
+            "https://git.sr.ht/~user/repo/archive/dev.tar.gz"
+        );
+    }
+
+    #[test]
+    fn test_parse_advertised_refs() {
+        let lines = [
+            "# service=git-upload-pack\n",
+            "",
+            "aaaa HEAD\0multi_ack symref=HEAD:refs/heads/trunk agent=git\n",
+            "aaaa refs/heads/trunk\n",
+            "bbbb refs/tags/v1\n",
+            "cccc refs/tags/v1^{}\n",
+        ];
+        let mut body = String::new();
+        for line in lines {
+            match line {
+                "" => body.push_str("0000"),
+                line => {
+                    body.push_str(&format!("{:04x}{}", line.len() + 4, line))
+                },
+            }
+        }
+        body.push_str("0000");
+
+        let advertisement = Advertisement::parse(body.as_bytes());
+        assert_eq!(advertisement.head.as_deref(), Some("refs/heads/trunk"));
+        assert_eq!(advertisement.commit_of("refs/heads/trunk"), Some("aaaa"));
+        assert_eq!(advertisement.commit_of("refs/tags/v1"), Some("cccc"));
+        assert_eq!(advertisement.commit_of("refs/tags/v2"), None);
+    }
+}
@@ -0,0 +1,135 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde_json::{json, Value};
+
+use crate::{language::detect_kind, manifest::collect_files};
+
+/// Files of unknown kind are grouped under this name.
+const OTHER_LANGUAGE: &str = "Other";
+
+/// Summary of the files in a rip, from [`rip_stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RipStats {
+    pub files:     u64,
+    pub bytes:     u64,
+    /// Biggest files, largest first.
+    pub largest:   Vec<(PathBuf, u64)>,
+    /// Files per language, most bytes first.
+    pub languages: Vec<LanguageStats>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanguageStats {
+    pub language: String,
+    pub files:    u64,
+    pub bytes:    u64,
+}
+
+/// Counts the files under `dest`, keeping the `top` largest. The repository
+/// and rip metadata at the top are left out.
+pub fn rip_stats(dest: &Path, top: usize) -> io::Result<RipStats> {
+    let mut files = Vec::new();
+    collect_files(dest, Path::new(""), &mut files)?;
+
+    let mut stats = RipStats::default();
+    let mut by_language: HashMap<&'static str, (u64, u64)> = HashMap::new();
+    let mut sizes = Vec::new();
+    for rel in files {
+        let meta = fs::symlink_metadata(dest.join(&rel))?;
+        if !meta.is_file() {
+            continue;
+        }
+        let size = meta.len();
+        stats.files += 1;
+        stats.bytes += size;
+
+        let language = detect_kind(&rel).map_or(OTHER_LANGUAGE, |k| k.language);
+        let entry = by_language.entry(language).or_default();
+        entry.0 += 1;
+        entry.1 += size;
+        sizes.push((rel, size));
+    }
+
+    sizes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    sizes.truncate(top);
+    stats.largest = sizes;
+
+    stats.languages = by_language
+        .into_iter()
+        .map(|(language, (files, bytes))| LanguageStats {
+            language: language.to_string(),
+            files,
+            bytes,
+        })
+        .collect();
+    stats.languages.sort_by(|a, b| {
+        b.bytes.cmp(&a.bytes).then_with(|| a.language.cmp(&b.language))
+    });
+    Ok(stats)
+}
+
+impl RipStats {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "files": self.files,
+            "bytes": self.bytes,
+            "largest": self
+                .largest
+                .iter()
+                .map(|(path, bytes)| json!({ "path": path, "bytes": bytes }))
+                .collect::<Vec<_>>(),
+            "languages": self
+                .languages
+                .iter()
+                .map(|l| json!({
+                    "language": l.language,
+                    "files": l.files,
+                    "bytes": l.bytes,
+                }))
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, write};
+
+    use super::*;
+
+    #[test]
+    fn test_rip_stats_groups_by_language() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dest = temp_dir.path();
+        create_dir_all(dest.join("src")).unwrap();
+        create_dir_all(dest.join(".git")).unwrap();
+        write(dest.join(".git/HEAD"), "ref: refs/heads/main").unwrap();
+        write(dest.join("src/lib.rs"), "fn a() {}").unwrap();
+        write(dest.join("src/main.rs"), "fn main() {}").unwrap();
+        write(dest.join("README.md"), "# r").unwrap();
+        write(dest.join("LICENSE"), "MIT").unwrap();
+
+        let stats = rip_stats(dest, 2).unwrap();
+        assert_eq!((stats.files, stats.bytes), (4, 27));
+        assert_eq!(
+            stats.largest,
+            vec![
+                (PathBuf::from("src/main.rs"), 12),
+                (PathBuf::from("src/lib.rs"), 9),
+            ]
+        );
+        assert_eq!(
+            stats.languages[0],
+            LanguageStats {
+                language: "Rust".to_string(),
+                files:    2,
+                bytes:    21,
+            }
+        );
+        assert_eq!(stats.languages.len(), 3);
+    }
+}
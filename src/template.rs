@@ -0,0 +1,272 @@
+use std::{borrow::Cow, collections::BTreeMap, fs, io, path::Path};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use toml::{Table, Value};
+
+use crate::{GitripperError, Result};
+
+/// Manifest at the root of a template repository declaring its variables.
+/// It is removed from the instantiated project.
+pub const TEMPLATE_MANIFEST: &str = "gitripper.template.toml";
+
+static PLACEHOLDER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_-]*)\s*\}\}").unwrap()
+});
+
+/// A variable declared in a [`TEMPLATE_MANIFEST`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateVariable {
+    pub name:    String,
+    /// Question to ask for the value. Defaults to the name.
+    pub prompt:  Option<String>,
+    pub default: Option<String>,
+}
+
+/// Parsed [`TEMPLATE_MANIFEST`]:
+///
+/// ```toml
+/// [variables]
+/// license = "MIT"  # just a default
+///
+/// [variables.description]
+/// prompt = "Short description"
+/// default = "A new project"
+///
+/// [template]
+/// exclude = ["assets/**"]  # copied without substitution
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TemplateManifest {
+    /// In declaration order.
+    pub variables: Vec<TemplateVariable>,
+    /// Globs of files, relative to the root, left untouched.
+    pub exclude:   Vec<String>,
+}
+
+impl TemplateManifest {
+    /// Reads the manifest in `dir`, `None` if there is none.
+    pub fn read(dir: &Path) -> Result<Option<Self>> {
+        match fs::read_to_string(dir.join(TEMPLATE_MANIFEST)) {
+            Ok(text) => text.parse().map(Some),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl std::str::FromStr for TemplateManifest {
+    type Err = GitripperError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let table: Table = s.parse().map_err(|e| {
+            GitripperError::Template(format!(
+                "invalid {}: {}",
+                TEMPLATE_MANIFEST, e
+            ))
+        })?;
+
+        let mut manifest = Self::default();
+        if let Some(vars) = table.get("variables").and_then(Value::as_table) {
+            for (name, spec) in vars {
+                let field = |key: &str| {
+                    spec.get(key).and_then(Value::as_str).map(str::to_string)
+                };
+                manifest.variables.push(TemplateVariable {
+                    name:    name.clone(),
+                    prompt:  field("prompt"),
+                    default: match spec {
+                        Value::Table(_) => field("default"),
+                        Value::String(s) => Some(s.clone()),
+                        v => Some(v.to_string()),
+                    },
+                });
+            }
+        }
+        if let Some(exclude) =
+            table.get("template").and_then(|t| t.get("exclude"))
+        {
+            manifest.exclude = exclude
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect();
+        }
+        Ok(manifest)
+    }
+}
+
+/// Replaces `{{ name }}` with `values[name]` in the paths and UTF-8 file
+/// contents under `dir`, except for files matching `manifest.exclude` and
+/// the repository at the top. Placeholders without a value are left alone.
+/// Removes the [`TEMPLATE_MANIFEST`]. Returns how many files changed.
+pub fn render_template(
+    dir: &Path,
+    manifest: &TemplateManifest,
+    values: &BTreeMap<String, String>,
+) -> Result<usize> {
+    let mut excluded = GlobSetBuilder::new();
+    for pattern in &manifest.exclude {
+        excluded.add(Glob::new(pattern).map_err(|e| {
+            GitripperError::Template(format!("invalid exclude glob: {}", e))
+        })?);
+    }
+    let excluded = excluded.build().map_err(|e| {
+        GitripperError::Template(format!("invalid exclude glob: {}", e))
+    })?;
+
+    match fs::remove_file(dir.join(TEMPLATE_MANIFEST)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {},
+    }
+    render_dir(dir, dir, &excluded, values)
+}
+
+fn render_dir(
+    root: &Path,
+    dir: &Path,
+    excluded: &GlobSet,
+    values: &BTreeMap<String, String>,
+) -> Result<usize> {
+    let mut changed = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if dir == root && entry.file_name() == ".git" {
+            continue;
+        }
+
+        let mut path = entry.path();
+        let relative = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+        if excluded.is_match(&relative) {
+            continue;
+        }
+
+        if let Some(name) = entry.file_name().to_str()
+            && let Cow::Owned(renamed) = substitute(name, values)
+        {
+            let target = dir.join(renamed);
+            fs::rename(&path, &target)?;
+            path = target;
+            changed += 1;
+        }
+
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            changed += render_dir(root, &path, excluded, values)?;
+        } else if file_type.is_file() && render_file(&path, values)? {
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+/// Substitutes placeholders in `path` if it is UTF-8 text.
+fn render_file(path: &Path, values: &BTreeMap<String, String>) -> Result<bool> {
+    let Ok(text) = String::from_utf8(fs::read(path)?) else {
+        return Ok(false);
+    };
+    match substitute(&text, values) {
+        Cow::Owned(rendered) => {
+            fs::write(path, rendered)?;
+            Ok(true)
+        },
+        Cow::Borrowed(_) => Ok(false),
+    }
+}
+
+fn substitute<'a>(
+    text: &'a str,
+    values: &BTreeMap<String, String>,
+) -> Cow<'a, str> {
+    if !PLACEHOLDER.captures_iter(text).any(|c| values.contains_key(&c[1])) {
+        return Cow::Borrowed(text);
+    }
+    PLACEHOLDER.replace_all(text, |c: &Captures| match values.get(&c[1]) {
+        Some(v) => v.clone(),
+        None => c[0].to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, read_to_string, write};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest: TemplateManifest = r#"
+            [variables]
+            license = "MIT"
+
+            [variables.description]
+            prompt = "Describe it"
+
+            [template]
+            exclude = ["assets/**"]
+        "#
+        .parse()
+        .unwrap();
+
+        assert_eq!(
+            manifest.variables,
+            [
+                TemplateVariable {
+                    name:    "license".to_string(),
+                    prompt:  None,
+                    default: Some("MIT".to_string()),
+                },
+                TemplateVariable {
+                    name:    "description".to_string(),
+                    prompt:  Some("Describe it".to_string()),
+                    default: None,
+                },
+            ]
+        );
+        assert_eq!(manifest.exclude, ["assets/**"]);
+    }
+
+    #[test]
+    fn test_render_template_substitutes_contents_and_paths() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        create_dir_all(root.join("src/{{project_name}}")).unwrap();
+        create_dir_all(root.join("assets")).unwrap();
+        write(
+            root.join("src/{{project_name}}/lib.rs"),
+            "// {{ author }}\n",
+        )
+        .unwrap();
+        write(root.join("README.md"), "# {{project_name}} {{unknown}}\n")
+            .unwrap();
+        write(root.join("assets/logo.svg"), "{{project_name}}").unwrap();
+        write(root.join(TEMPLATE_MANIFEST), "").unwrap();
+
+        let manifest = TemplateManifest {
+            exclude: vec!["assets/**".to_string()],
+            ..Default::default()
+        };
+        let values = BTreeMap::from([
+            ("project_name".to_string(), "demo".to_string()),
+            ("author".to_string(), "Ann".to_string()),
+        ]);
+        render_template(root, &manifest, &values).unwrap();
+
+        assert_eq!(
+            read_to_string(root.join("src/demo/lib.rs")).unwrap(),
+            "// Ann\n"
+        );
+        assert_eq!(
+            read_to_string(root.join("README.md")).unwrap(),
+            "# demo {{unknown}}\n"
+        );
+        assert_eq!(
+            read_to_string(root.join("assets/logo.svg")).unwrap(),
+            "{{project_name}}"
+        );
+        assert!(!root.join(TEMPLATE_MANIFEST).exists());
+    }
+}